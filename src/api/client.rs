@@ -1,7 +1,10 @@
 //! HTTP client for Cloudflare API
 
+use std::time::Duration;
+
 use anyhow::{anyhow, Result};
-use reqwest::{Client, Method, RequestBuilder};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Client, Method, RequestBuilder, StatusCode};
 use serde::de::DeserializeOwned;
 use serde_json::{json, Value};
 use tracing::{debug, trace};
@@ -12,6 +15,48 @@ use crate::config::Config;
 const CF_API_BASE: &str = "https://api.cloudflare.com/client/v4";
 const CF_GRAPHQL_URL: &str = "https://api.cloudflare.com/client/v4/graphql";
 
+/// Non-success HTTP response from the API
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    /// Delay requested by a `Retry-After` header, if any
+    pub retry_after: Option<Duration>,
+    pub body: String,
+}
+
+impl ApiError {
+    /// Whether `err` is an API response with the given status
+    pub fn has_status(err: &anyhow::Error, status: StatusCode) -> bool {
+        err.downcast_ref::<ApiError>()
+            .is_some_and(|e| e.status == status)
+    }
+
+    /// The `Retry-After` delay carried by `err`, if it is an API response
+    pub fn retry_after_of(err: &anyhow::Error) -> Option<Duration> {
+        err.downcast_ref::<ApiError>().and_then(|e| e.retry_after)
+    }
+}
+
+/// Parse a `Retry-After` header given in seconds (HTTP dates are ignored)
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "API error ({}): {}", self.status, self.body)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
 /// Cloudflare API client
 pub struct CloudflareClient {
     client: Client,
@@ -47,11 +92,18 @@ impl CloudflareClient {
         let response = self.build_request(Method::GET, &url).send().await?;
 
         let status = response.status();
+
+        let retry_after = retry_after(response.headers());
         let text = response.text().await?;
         trace!("Response: {}", text);
 
         if !status.is_success() {
-            return Err(anyhow!("API error ({}): {}", status, text));
+            return Err(ApiError {
+                status,
+                retry_after,
+                body: text,
+            }
+            .into());
         }
 
         let api_response: ApiResponse<T> = serde_json::from_str(&text)?;
@@ -85,11 +137,18 @@ impl CloudflareClient {
             .await?;
 
         let status = response.status();
+
+        let retry_after = retry_after(response.headers());
         let text = response.text().await?;
         trace!("Response: {}", text);
 
         if !status.is_success() {
-            return Err(anyhow!("API error ({}): {}", status, text));
+            return Err(ApiError {
+                status,
+                retry_after,
+                body: text,
+            }
+            .into());
         }
 
         let api_response: ApiResponse<T> = serde_json::from_str(&text)?;
@@ -123,11 +182,18 @@ impl CloudflareClient {
             .await?;
 
         let status = response.status();
+
+        let retry_after = retry_after(response.headers());
         let text = response.text().await?;
         trace!("Response: {}", text);
 
         if !status.is_success() {
-            return Err(anyhow!("API error ({}): {}", status, text));
+            return Err(ApiError {
+                status,
+                retry_after,
+                body: text,
+            }
+            .into());
         }
 
         let api_response: ApiResponse<T> = serde_json::from_str(&text)?;
@@ -161,11 +227,18 @@ impl CloudflareClient {
             .await?;
 
         let status = response.status();
+
+        let retry_after = retry_after(response.headers());
         let text = response.text().await?;
         trace!("Response: {}", text);
 
         if !status.is_success() {
-            return Err(anyhow!("API error ({}): {}", status, text));
+            return Err(ApiError {
+                status,
+                retry_after,
+                body: text,
+            }
+            .into());
         }
 
         let api_response: ApiResponse<T> = serde_json::from_str(&text)?;
@@ -191,11 +264,18 @@ impl CloudflareClient {
         let response = self.build_request(Method::DELETE, &url).send().await?;
 
         let status = response.status();
+
+        let retry_after = retry_after(response.headers());
         let text = response.text().await?;
         trace!("Response: {}", text);
 
         if !status.is_success() {
-            return Err(anyhow!("API error ({}): {}", status, text));
+            return Err(ApiError {
+                status,
+                retry_after,
+                body: text,
+            }
+            .into());
         }
 
         let api_response: ApiResponse<T> = serde_json::from_str(&text)?;
@@ -220,10 +300,17 @@ impl CloudflareClient {
         let response = self.build_request(Method::GET, &url).send().await?;
 
         let status = response.status();
+
+        let retry_after = retry_after(response.headers());
         let text = response.text().await?;
 
         if !status.is_success() {
-            return Err(anyhow!("API error ({}): {}", status, text));
+            return Err(ApiError {
+                status,
+                retry_after,
+                body: text,
+            }
+            .into());
         }
 
         let value: Value = serde_json::from_str(&text)?;
@@ -242,10 +329,17 @@ impl CloudflareClient {
             .await?;
 
         let status = response.status();
+
+        let retry_after = retry_after(response.headers());
         let text = response.text().await?;
 
         if !status.is_success() {
-            return Err(anyhow!("API error ({}): {}", status, text));
+            return Err(ApiError {
+                status,
+                retry_after,
+                body: text,
+            }
+            .into());
         }
 
         let value: Value = serde_json::from_str(&text)?;
@@ -264,10 +358,17 @@ impl CloudflareClient {
             .await?;
 
         let status = response.status();
+
+        let retry_after = retry_after(response.headers());
         let text = response.text().await?;
 
         if !status.is_success() {
-            return Err(anyhow!("API error ({}): {}", status, text));
+            return Err(ApiError {
+                status,
+                retry_after,
+                body: text,
+            }
+            .into());
         }
 
         let value: Value = serde_json::from_str(&text)?;
@@ -286,10 +387,17 @@ impl CloudflareClient {
             .await?;
 
         let status = response.status();
+
+        let retry_after = retry_after(response.headers());
         let text = response.text().await?;
 
         if !status.is_success() {
-            return Err(anyhow!("API error ({}): {}", status, text));
+            return Err(ApiError {
+                status,
+                retry_after,
+                body: text,
+            }
+            .into());
         }
 
         let value: Value = serde_json::from_str(&text)?;
@@ -304,10 +412,46 @@ impl CloudflareClient {
         let response = self.build_request(Method::DELETE, &url).send().await?;
 
         let status = response.status();
+
+        let retry_after = retry_after(response.headers());
         let text = response.text().await?;
 
         if !status.is_success() {
-            return Err(anyhow!("API error ({}): {}", status, text));
+            return Err(ApiError {
+                status,
+                retry_after,
+                body: text,
+            }
+            .into());
+        }
+
+        let value: Value = serde_json::from_str(&text)?;
+        Ok(value)
+    }
+
+    /// Make a raw DELETE request with a JSON body (returns Value)
+    pub async fn delete_raw_with_body(&self, path: &str, body: Value) -> Result<Value> {
+        let url = format!("{}{}", CF_API_BASE, path);
+        debug!("DELETE (raw) {} with body: {}", url, body);
+
+        let response = self
+            .build_request(Method::DELETE, &url)
+            .json(&body)
+            .send()
+            .await?;
+
+        let status = response.status();
+
+        let retry_after = retry_after(response.headers());
+        let text = response.text().await?;
+
+        if !status.is_success() {
+            return Err(ApiError {
+                status,
+                retry_after,
+                body: text,
+            }
+            .into());
         }
 
         let value: Value = serde_json::from_str(&text)?;
@@ -360,10 +504,16 @@ impl CloudflareClient {
 
         let response = req.send().await?;
         let status = response.status();
+        let retry_after = retry_after(response.headers());
         let text = response.text().await?;
 
         if !status.is_success() {
-            return Err(anyhow!("API error ({}): {}", status, text));
+            return Err(ApiError {
+                status,
+                retry_after,
+                body: text,
+            }
+            .into());
         }
 
         let value: Value = serde_json::from_str(&text)?;
@@ -1,176 +1,951 @@
 //! GraphQL query helpers for Cloudflare Analytics API
+//!
+//! Queries are built with [`GroupQuery`]: names (datasets, fields, orderBy
+//! values) are checked against the GraphQL name grammar and values are always
+//! escaped, so user input never ends up in a query as raw text.
+
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, Result};
+use chrono::Duration;
+use serde_json::Value;
+
+/// A GraphQL input value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    List(Vec<Arg>),
+}
+
+impl Arg {
+    /// Interpret a command-line value: numbers and booleans as such, anything else
+    /// (or anything in quotes) as a string
+    pub fn parse(raw: &str) -> Arg {
+        let raw = raw.trim();
+        for quote in ['"', '\''] {
+            if let Some(inner) = raw.strip_prefix(quote).and_then(|r| r.strip_suffix(quote)) {
+                return Arg::Str(inner.to_string());
+            }
+        }
+
+        if let Ok(value) = raw.parse::<i64>() {
+            Arg::Int(value)
+        } else if let Ok(value) = raw.parse::<bool>() {
+            Arg::Bool(value)
+        } else if let Some(value) = raw
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite() && raw.contains('.'))
+        {
+            Arg::Float(value)
+        } else {
+            Arg::Str(raw.to_string())
+        }
+    }
+
+    fn render(&self) -> String {
+        match self {
+            // JSON string escaping is valid GraphQL string escaping
+            Arg::Str(value) => Value::from(value.as_str()).to_string(),
+            Arg::Int(value) => value.to_string(),
+            Arg::Float(value) => value.to_string(),
+            Arg::Bool(value) => value.to_string(),
+            Arg::List(values) => format!(
+                "[{}]",
+                values
+                    .iter()
+                    .map(Arg::render)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// An aggregate to select: `count` or `<block>.<field>` (e.g. `sum.edgeResponseBytes`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Metric {
+    Count,
+    Field {
+        block: String,
+        field: String,
+    },
+    /// A list-valued aggregate with its own selection, e.g.
+    /// `sum.responseStatusMap { edgeResponseStatus requests }`
+    Nested {
+        block: String,
+        field: String,
+        subfields: Vec<String>,
+    },
+}
+
+impl Metric {
+    pub fn parse(raw: &str) -> Result<Metric> {
+        let raw = raw.trim();
+        if raw == "count" {
+            return Ok(Metric::Count);
+        }
+        let (block, field) = raw.split_once('.').ok_or_else(|| {
+            anyhow!(
+                "Invalid metric '{}': use count or <block>.<field>, e.g. sum.edgeResponseBytes",
+                raw
+            )
+        })?;
+        Ok(Metric::Field {
+            block: check_name(block)?.to_string(),
+            field: check_name(field)?.to_string(),
+        })
+    }
+
+    /// Name as written on the command line and in table headers
+    pub fn label(&self) -> String {
+        match self {
+            Metric::Count => "count".to_string(),
+            Metric::Field { block, field } | Metric::Nested { block, field, .. } => {
+                format!("{}.{}", block, field)
+            }
+        }
+    }
+
+    /// JSON pointer to the value in a result group
+    pub fn pointer(&self) -> String {
+        match self {
+            Metric::Count => "/count".to_string(),
+            Metric::Field { block, field } | Metric::Nested { block, field, .. } => {
+                format!("/{}/{}", block, field)
+            }
+        }
+    }
+
+    /// The orderBy value sorting by this metric, e.g. `sum_edgeResponseBytes_DESC`
+    pub fn order(&self, descending: bool) -> String {
+        let direction = if descending { "DESC" } else { "ASC" };
+        match self {
+            Metric::Count => format!("count_{}", direction),
+            Metric::Field { block, field } | Metric::Nested { block, field, .. } => {
+                format!("{}_{}_{}", block, field, direction)
+            }
+        }
+    }
+}
+
+/// Where a query reads from
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scope {
+    Zone(String),
+    /// Several zones in one request (`zoneTag_in`), one result entry per zone
+    Zones(Vec<String>),
+    Account(String),
+}
+
+impl Scope {
+    /// The `viewer` field results are under
+    pub fn key(&self) -> &'static str {
+        match self {
+            Scope::Zone(_) | Scope::Zones(_) => "zones",
+            Scope::Account(_) => "accounts",
+        }
+    }
+}
+
+/// A grouped query on one dataset, e.g. `httpRequestsAdaptiveGroups`
+#[derive(Debug, Clone)]
+pub struct GroupQuery {
+    pub scope: Scope,
+    pub dataset: String,
+    pub limit: u32,
+    pub filter: Vec<(String, Arg)>,
+    pub order_by: Vec<String>,
+    pub dimensions: Vec<String>,
+    pub metrics: Vec<Metric>,
+}
+
+impl GroupQuery {
+    /// A query counting all groups, without dimensions, filters or ordering
+    pub fn new(scope: Scope, dataset: &str) -> Self {
+        Self {
+            scope,
+            dataset: dataset.to_string(),
+            limit: 100,
+            filter: Vec::new(),
+            order_by: Vec::new(),
+            dimensions: Vec::new(),
+            metrics: vec![Metric::Count],
+        }
+    }
+
+    /// Restrict to `since` (inclusive) up to `until` (exclusive), both RFC 3339
+    ///
+    /// Daily datasets filter on `date`, everything else on `datetime`.
+    pub fn window(&mut self, since: &str, until: &str) -> &mut Self {
+        if self.dataset.ends_with("1dGroups") {
+            let date = |time: &str| time.get(..10).unwrap_or(time).to_string();
+            self.filter.push(("date_geq".into(), Arg::Str(date(since))));
+            self.filter.push(("date_lt".into(), Arg::Str(date(until))));
+        } else {
+            self.filter
+                .push(("datetime_geq".into(), Arg::Str(since.to_string())));
+            self.filter
+                .push(("datetime_lt".into(), Arg::Str(until.to_string())));
+        }
+        self
+    }
+
+    /// Render the query text, checking every name that goes into it
+    pub fn render(&self) -> Result<String> {
+        check_name(&self.dataset)?;
+        for name in self
+            .filter
+            .iter()
+            .map(|(name, _)| name)
+            .chain(&self.order_by)
+            .chain(&self.dimensions)
+        {
+            check_name(name)?;
+        }
+        for metric in &self.metrics {
+            match metric {
+                Metric::Count => {}
+                Metric::Field { block, field } => {
+                    check_name(block)?;
+                    check_name(field)?;
+                }
+                Metric::Nested {
+                    block,
+                    field,
+                    subfields,
+                } => {
+                    check_name(block)?;
+                    check_name(field)?;
+                    for name in subfields {
+                        check_name(name)?;
+                    }
+                }
+            }
+        }
+
+        let scope = match &self.scope {
+            Scope::Zone(zone_id) => format!(
+                "zones(filter: {{zoneTag: {}}}) {{",
+                Arg::Str(zone_id.clone()).render()
+            ),
+            Scope::Zones(zone_ids) => format!(
+                "zones(filter: {{zoneTag_in: {}}}, limit: {}) {{\n      zoneTag",
+                Arg::List(zone_ids.iter().cloned().map(Arg::Str).collect()).render(),
+                zone_ids.len()
+            ),
+            Scope::Account(account_id) => format!(
+                "accounts(filter: {{accountTag: {}}}) {{",
+                Arg::Str(account_id.clone()).render()
+            ),
+        };
+
+        let mut args = vec![format!("limit: {}", self.limit)];
+        if !self.filter.is_empty() {
+            let fields: Vec<String> = self
+                .filter
+                .iter()
+                .map(|(name, value)| format!("{}: {}", name, value.render()))
+                .collect();
+            args.push(format!("filter: {{{}}}", fields.join(", ")));
+        }
+        if !self.order_by.is_empty() {
+            args.push(format!("orderBy: [{}]", self.order_by.join(", ")));
+        }
+
+        let mut selection = Vec::new();
+        let mut blocks: Vec<(&str, Vec<String>)> = Vec::new();
+        for metric in &self.metrics {
+            let (block, field) = match metric {
+                Metric::Count => {
+                    selection.push("count".to_string());
+                    continue;
+                }
+                Metric::Field { block, field } => (block, field.clone()),
+                Metric::Nested {
+                    block,
+                    field,
+                    subfields,
+                } => (block, nested(field, subfields)),
+            };
+            match blocks.iter_mut().find(|(name, _)| name == block) {
+                Some((_, fields)) => fields.push(field),
+                None => blocks.push((block, vec![field])),
+            }
+        }
+        for (block, fields) in blocks {
+            selection.push(nested(block, &fields));
+        }
+        if !self.dimensions.is_empty() {
+            selection.push(nested("dimensions", &self.dimensions));
+        }
+
+        Ok(format!(
+            "{{\n  viewer {{\n    {}\n      {}(\n        {}\n      ) {{\n{}\n      }}\n    }}\n  }}\n}}",
+            scope,
+            self.dataset,
+            args.join(",\n        "),
+            selection
+                .iter()
+                .map(|s| indent(s, 8))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ))
+    }
+}
+
+fn nested(name: &str, fields: &[String]) -> String {
+    let fields: Vec<String> = fields.iter().map(|f| indent(f, 2)).collect();
+    format!("{} {{\n{}\n}}", name, fields.join("\n"))
+}
+
+fn indent(text: &str, width: usize) -> String {
+    text.lines()
+        .map(|line| format!("{}{}", " ".repeat(width), line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Check a GraphQL name (dataset, field, argument or enum value)
+pub fn check_name(name: &str) -> Result<&str> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if valid {
+        Ok(name)
+    } else {
+        Err(anyhow!("Invalid GraphQL name: '{}'", name))
+    }
+}
 
 /// Build a GraphQL query for HTTP requests analytics
 pub fn http_requests_query(
     zone_id: &str,
     since: &str,
+    until: &str,
     limit: u32,
     dimensions: &[&str],
     order_by: &str,
-    filter: Option<&str>,
-) -> String {
-    let dims = dimensions.join(" ");
-    let filter_clause = filter.map(|f| format!(", {}", f)).unwrap_or_default();
-
-    format!(
-        r#"{{
-  viewer {{
-    zones(filter: {{zoneTag: "{zone_id}"}}) {{
-      httpRequestsAdaptiveGroups(
-        limit: {limit},
-        filter: {{datetime_geq: "{since}"{filter_clause}}},
-        orderBy: [{order_by}]
-      ) {{
-        count
-        dimensions {{
-          {dims}
-        }}
-      }}
-    }}
-  }}
-}}"#
-    )
+    filter: &[(&str, Arg)],
+) -> Result<String> {
+    let mut query = GroupQuery::new(
+        Scope::Zone(zone_id.to_string()),
+        "httpRequestsAdaptiveGroups",
+    );
+    query.limit = limit;
+    query.window(since, until);
+    query.filter.extend(
+        filter
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone())),
+    );
+    query.order_by = vec![order_by.to_string()];
+    query.dimensions = dimensions.iter().map(|d| d.to_string()).collect();
+    query.render()
 }
 
 /// Build a GraphQL query for firewall events
-pub fn firewall_events_query(zone_id: &str, since: &str, limit: u32) -> String {
-    format!(
-        r#"{{
-  viewer {{
-    zones(filter: {{zoneTag: "{zone_id}"}}) {{
-      firewallEventsAdaptiveGroups(
-        limit: {limit},
-        filter: {{datetime_geq: "{since}"}},
-        orderBy: [count_DESC]
-      ) {{
-        count
-        dimensions {{
-          action
-          clientIP
-          clientCountryName
-          clientRequestPath
-          ruleId
-          source
-        }}
-      }}
-    }}
-  }}
-}}"#
-    )
+pub fn firewall_events_query(
+    zone_id: &str,
+    since: &str,
+    until: &str,
+    limit: u32,
+) -> Result<String> {
+    let mut query = GroupQuery::new(
+        Scope::Zone(zone_id.to_string()),
+        "firewallEventsAdaptiveGroups",
+    );
+    query.limit = limit;
+    query.window(since, until);
+    query.order_by = vec!["count_DESC".to_string()];
+    query.dimensions = [
+        "action",
+        "clientIP",
+        "clientCountryName",
+        "clientRequestPath",
+        "ruleId",
+        "source",
+    ]
+    .map(String::from)
+    .to_vec();
+    query.render()
 }
 
 /// Build query for top URLs
-pub fn top_urls_query(zone_id: &str, since: &str, limit: u32) -> String {
+pub fn top_urls_query(zone_id: &str, since: &str, until: &str, limit: u32) -> Result<String> {
     http_requests_query(
         zone_id,
         since,
+        until,
         limit,
         &["clientRequestPath"],
         "count_DESC",
-        None,
+        &[],
     )
 }
 
 /// Build query for top IPs (Free plan compatible)
-pub fn top_ips_query(zone_id: &str, since: &str, limit: u32) -> String {
+pub fn top_ips_query(zone_id: &str, since: &str, until: &str, limit: u32) -> Result<String> {
     http_requests_query(
         zone_id,
         since,
+        until,
         limit,
         &["clientIP", "clientCountryName"],
         "count_DESC",
-        None,
+        &[],
     )
 }
 
 /// Build query for top countries
-pub fn top_countries_query(zone_id: &str, since: &str, limit: u32) -> String {
+pub fn top_countries_query(zone_id: &str, since: &str, until: &str, limit: u32) -> Result<String> {
     http_requests_query(
         zone_id,
         since,
+        until,
         limit,
         &["clientCountryName"],
         "count_DESC",
-        None,
+        &[],
     )
 }
 
 /// Build query for error responses (4xx, 5xx) - Free plan compatible
-pub fn errors_query(zone_id: &str, since: &str, limit: u32) -> String {
+pub fn errors_query(zone_id: &str, since: &str, until: &str, limit: u32) -> Result<String> {
     http_requests_query(
         zone_id,
         since,
+        until,
         limit,
         &["edgeResponseStatus", "clientRequestPath"],
         "count_DESC",
-        Some("edgeResponseStatus_geq: 400"),
+        &[("edgeResponseStatus_geq", Arg::Int(400))],
     )
 }
 
 /// Build query for cache status
-pub fn cache_status_query(zone_id: &str, since: &str, limit: u32) -> String {
-    http_requests_query(zone_id, since, limit, &["cacheStatus"], "count_DESC", None)
+pub fn cache_status_query(zone_id: &str, since: &str, until: &str, limit: u32) -> Result<String> {
+    http_requests_query(
+        zone_id,
+        since,
+        until,
+        limit,
+        &["cacheStatus"],
+        "count_DESC",
+        &[],
+    )
 }
 
-/// Build query for bandwidth by status code (Free plan compatible)
-pub fn bandwidth_query(zone_id: &str, since: &str, limit: u32) -> String {
-    format!(
-        r#"{{
-  viewer {{
-    zones(filter: {{zoneTag: "{zone_id}"}}) {{
-      httpRequestsAdaptiveGroups(
-        limit: {limit},
-        filter: {{datetime_geq: "{since}"}},
-        orderBy: [sum_edgeResponseBytes_DESC]
-      ) {{
-        sum {{
-          edgeResponseBytes
-        }}
-        dimensions {{
-          edgeResponseStatus
-        }}
-      }}
-    }}
-  }}
-}}"#
+/// Build query for requests by status code
+pub fn status_codes_query(zone_id: &str, since: &str, until: &str, limit: u32) -> Result<String> {
+    http_requests_query(
+        zone_id,
+        since,
+        until,
+        limit,
+        &["edgeResponseStatus"],
+        "count_DESC",
+        &[],
     )
 }
 
+/// Build query for bandwidth by status code (Free plan compatible)
+pub fn bandwidth_query(zone_id: &str, since: &str, until: &str, limit: u32) -> Result<String> {
+    let mut query = GroupQuery::new(
+        Scope::Zone(zone_id.to_string()),
+        "httpRequestsAdaptiveGroups",
+    );
+    query.limit = limit;
+    query.window(since, until);
+    query.order_by = vec!["sum_edgeResponseBytes_DESC".to_string()];
+    query.dimensions = vec!["edgeResponseStatus".to_string()];
+    query.metrics = vec![Metric::Field {
+        block: "sum".to_string(),
+        field: "edgeResponseBytes".to_string(),
+    }];
+    query.render()
+}
+
 /// Build query for bots/device types (Free plan compatible)
-pub fn bots_query(zone_id: &str, since: &str, limit: u32) -> String {
+pub fn bots_query(zone_id: &str, since: &str, until: &str, limit: u32) -> Result<String> {
     http_requests_query(
         zone_id,
         since,
+        until,
         limit,
         &["clientDeviceType", "userAgent"],
         "count_DESC",
-        None,
+        &[],
     )
 }
 
 /// Build query for hourly traffic
-pub fn hourly_traffic_query(zone_id: &str, since: &str) -> String {
-    format!(
-        r#"{{
-  viewer {{
-    zones(filter: {{zoneTag: "{zone_id}"}}) {{
-      httpRequests1hGroups(
-        limit: 168,
-        filter: {{datetime_geq: "{since}"}},
-        orderBy: [datetime_ASC]
-      ) {{
-        sum {{
-          requests
-          bytes
-          cachedBytes
-          threats
-        }}
-        dimensions {{
-          datetime
-        }}
-      }}
-    }}
-  }}
-}}"#
-    )
+pub fn hourly_traffic_query(zone_id: &str, since: &str, until: &str) -> Result<String> {
+    let mut query = GroupQuery::new(Scope::Zone(zone_id.to_string()), "httpRequests1hGroups");
+    query.limit = 168;
+    query.window(since, until);
+    query.order_by = vec!["datetime_ASC".to_string()];
+    query.dimensions = vec!["datetime".to_string()];
+    query.metrics = ["requests", "bytes", "cachedBytes", "threats"]
+        .map(|field| Metric::Field {
+            block: "sum".to_string(),
+            field: field.to_string(),
+        })
+        .to_vec();
+    query.render()
+}
+
+/// Build a query for a zone's traffic over time, one group per minute, hour or day
+///
+/// The time dimension follows the dataset: `datetimeMinute` for `httpRequests1mGroups`,
+/// `datetime` for `httpRequests1hGroups` and `date` for `httpRequests1dGroups`.
+pub fn traffic_query(zone_id: &str, dataset: &str, since: &str, until: &str) -> Result<String> {
+    let dimension = time_dimension(dataset);
+    let mut query = GroupQuery::new(Scope::Zone(zone_id.to_string()), dataset);
+    query.limit = 10000;
+    query.window(since, until);
+    query.order_by = vec![format!("{}_ASC", dimension)];
+    query.dimensions = vec![dimension.to_string()];
+    query.metrics = [
+        "requests",
+        "bytes",
+        "cachedRequests",
+        "cachedBytes",
+        "threats",
+    ]
+    .map(|field| Metric::Field {
+        block: "sum".to_string(),
+        field: field.to_string(),
+    })
+    .to_vec();
+    query.metrics.push(Metric::Nested {
+        block: "sum".to_string(),
+        field: "responseStatusMap".to_string(),
+        subfields: vec!["edgeResponseStatus".to_string(), "requests".to_string()],
+    });
+    query.render()
+}
+
+/// The time dimension of an `httpRequests1mGroups`/`1hGroups`/`1dGroups` style dataset
+pub fn time_dimension(dataset: &str) -> &'static str {
+    if dataset.ends_with("1mGroups") {
+        "datetimeMinute"
+    } else if dataset.ends_with("1dGroups") {
+        "date"
+    } else {
+        "datetime"
+    }
+}
+
+/// Build a query for traffic totals of several zones at once, grouped by hour or day
+pub fn zones_traffic_query(
+    zone_ids: &[String],
+    dataset: &str,
+    since: &str,
+    until: &str,
+) -> Result<String> {
+    let mut query = GroupQuery::new(Scope::Zones(zone_ids.to_vec()), dataset);
+    query.limit = 10000;
+    query.window(since, until);
+    query.dimensions = vec![if dataset.ends_with("1dGroups") {
+        "date".to_string()
+    } else {
+        "datetime".to_string()
+    }];
+    query.metrics = [
+        "requests",
+        "bytes",
+        "cachedRequests",
+        "threats",
+        "pageViews",
+    ]
+    .map(|field| Metric::Field {
+        block: "sum".to_string(),
+        field: field.to_string(),
+    })
+    .to_vec();
+    query.render()
+}
+
+/// Longest time range a single query on a dataset may cover
+pub fn max_range(dataset: &str) -> Duration {
+    if dataset.ends_with("1dGroups") {
+        Duration::days(365)
+    } else if dataset.ends_with("1hGroups") {
+        Duration::days(7)
+    } else if dataset.ends_with("1mGroups") {
+        Duration::hours(6)
+    } else {
+        // Adaptive and raw event datasets
+        Duration::days(1)
+    }
+}
+
+/// Merge responses of the same query over consecutive time windows
+///
+/// Zones and accounts are matched by their tag when it was selected, otherwise
+/// by position. Groups with identical dimensions are combined: `count` and `sum` values are
+/// added, `max`/`min` keep the extreme, anything else keeps the first value.
+/// Every dataset in every zone or account is then sorted by `order_by`
+/// (e.g. `count_DESC`, `sum_edgeResponseBytes_DESC`, `datetime_ASC`) and cut
+/// to `limit`. Top-N lists are therefore only exact for groups that made the
+/// top N in at least one window.
+pub fn merge_responses(responses: &[Value], order_by: &str, limit: Option<usize>) -> Value {
+    let Some(first) = responses.first() else {
+        return Value::Null;
+    };
+    let mut merged = first.clone();
+
+    for scope in ["zones", "accounts"] {
+        let Some(Value::Array(targets)) = merged.pointer_mut(&format!("/data/viewer/{}", scope))
+        else {
+            continue;
+        };
+
+        for response in &responses[1..] {
+            let more = response
+                .pointer(&format!("/data/viewer/{}", scope))
+                .and_then(|t| t.as_array());
+            for (index, entry) in more.into_iter().flatten().enumerate() {
+                match counterpart(targets, index, tag(entry)) {
+                    Some(target) => add_entry(target, entry),
+                    None => targets.push(entry.clone()),
+                }
+            }
+        }
+
+        for target in targets.iter_mut() {
+            let datasets = target
+                .as_object_mut()
+                .into_iter()
+                .flat_map(|t| t.values_mut());
+            for groups in datasets.filter_map(|g| g.as_array_mut()) {
+                sort_groups(groups, order_by);
+                if let Some(limit) = limit {
+                    groups.truncate(limit);
+                }
+            }
+        }
+    }
+
+    merged
+}
+
+fn tag(entry: &Value) -> Option<&Value> {
+    entry.get("zoneTag").or_else(|| entry.get("accountTag"))
+}
+
+/// The merged entry of a zone or account: by tag if selected, else by position
+fn counterpart<'a>(
+    targets: &'a mut [Value],
+    index: usize,
+    wanted: Option<&Value>,
+) -> Option<&'a mut Value> {
+    match wanted {
+        Some(wanted) => targets.iter_mut().find(|t| tag(t) == Some(wanted)),
+        None => targets.get_mut(index),
+    }
+}
+
+/// Add the groups of every dataset in `entry` to `target`
+fn add_entry(target: &mut Value, entry: &Value) {
+    let (Some(target), Some(entry)) = (target.as_object_mut(), entry.as_object()) else {
+        return;
+    };
+    for (dataset, groups) in entry {
+        let Some(groups) = groups.as_array() else {
+            continue;
+        };
+        match target.get_mut(dataset).and_then(|g| g.as_array_mut()) {
+            Some(existing) => {
+                for group in groups {
+                    add_group(existing, group);
+                }
+            }
+            None => {
+                target.insert(dataset.clone(), Value::Array(groups.clone()));
+            }
+        }
+    }
+}
+
+fn add_group(groups: &mut Vec<Value>, group: &Value) {
+    let dimensions = group.get("dimensions");
+    match groups
+        .iter_mut()
+        .find(|g| g.get("dimensions") == dimensions)
+    {
+        Some(existing) => combine(existing, group, None),
+        None => groups.push(group.clone()),
+    }
+}
+
+/// Combine `other` into `target`; `block` is the enclosing aggregate (sum, max, ...)
+fn combine(target: &mut Value, other: &Value, block: Option<&str>) {
+    let (Some(target), Some(other)) = (target.as_object_mut(), other.as_object()) else {
+        return;
+    };
+
+    for (key, value) in other {
+        let Some(existing) = target.get_mut(key) else {
+            target.insert(key.clone(), value.clone());
+            continue;
+        };
+        if value.is_object() {
+            if key != "dimensions" {
+                combine(existing, value, Some(key));
+            }
+            continue;
+        }
+
+        let op = if block.is_none() && key == "count" {
+            Some("sum")
+        } else {
+            block
+        };
+        if let (Some(a), Some(b)) = (existing.as_f64(), value.as_f64()) {
+            let combined = match op {
+                Some("sum") => a + b,
+                Some("max") => a.max(b),
+                Some("min") => a.min(b),
+                _ => continue,
+            };
+            *existing = number(combined, existing.is_u64() && value.is_u64());
+        }
+    }
+}
+
+fn number(value: f64, integer: bool) -> Value {
+    if integer {
+        Value::from(value as u64)
+    } else {
+        Value::from(value)
+    }
+}
+
+/// Sort groups by a GraphQL orderBy value like `count_DESC` or `sum_bytes_ASC`
+pub fn sort_groups(groups: &mut [Value], order_by: &str) {
+    let (field, descending) = match order_by.rsplit_once('_') {
+        Some((field, "DESC")) => (field, true),
+        Some((field, "ASC")) => (field, false),
+        _ => (order_by, false),
+    };
+    let path = order_path(field);
+
+    groups.sort_by(|a, b| {
+        let ordering = compare(a.pointer(&path), b.pointer(&path));
+        if descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+/// JSON pointer to the value a group is ordered by
+fn order_path(field: &str) -> String {
+    if field == "count" {
+        return "/count".to_string();
+    }
+    for block in ["sum", "avg", "max", "min", "uniq"] {
+        if let Some(name) = field.strip_prefix(block).and_then(|f| f.strip_prefix('_')) {
+            return format!("/{}/{}", block, name);
+        }
+    }
+    format!("/dimensions/{}", field)
+}
+
+fn compare(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => match (a.as_f64(), b.as_f64()) {
+            (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            _ => a.as_str().unwrap_or("").cmp(b.as_str().unwrap_or("")),
+        },
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use graphql_parser::query::{Definition, OperationDefinition, Selection, Value as GqlValue};
+
+    const HOSTILE: &str = "a\"b\\c\nd\r\t}) { __schema { types { name } } } #";
+
+    /// The `filter` argument of the dataset field in a rendered query
+    fn filter_of(query: &str) -> Vec<(String, GqlValue<'_, String>)> {
+        let document = graphql_parser::parse_query::<String>(query).expect("valid GraphQL");
+        assert_eq!(document.definitions.len(), 1, "{}", query);
+        let Definition::Operation(OperationDefinition::SelectionSet(set)) =
+            &document.definitions[0]
+        else {
+            panic!("expected an anonymous query");
+        };
+        // { viewer { zones(...) { dataset(...) { ... } } } }
+        let mut fields = Vec::new();
+        let mut current = &set.items;
+        for _ in 0..3 {
+            assert_eq!(current.len(), 1, "{}", query);
+            let Selection::Field(field) = &current[0] else {
+                panic!("expected a field");
+            };
+            fields.push(field);
+            current = &field.selection_set.items;
+        }
+        let dataset = fields[2];
+        dataset
+            .arguments
+            .iter()
+            .find(|(name, _)| name == "filter")
+            .and_then(|(_, value)| match value {
+                GqlValue::Object(map) => Some(
+                    map.iter()
+                        .map(|(k, v)| (k.clone(), v.clone()))
+                        .collect::<Vec<_>>(),
+                ),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn values_stay_inside_one_string_literal() {
+        let query = http_requests_query(
+            HOSTILE,
+            "2024-05-01T00:00:00Z",
+            "2024-05-02T00:00:00Z",
+            10,
+            &["clientRequestPath"],
+            "count_DESC",
+            &[
+                ("clientRequestPath", Arg::Str(HOSTILE.to_string())),
+                ("clientIP_in", Arg::List(vec![Arg::parse(HOSTILE)])),
+            ],
+        )
+        .unwrap();
+
+        let filter = filter_of(&query);
+        let names: Vec<&str> = filter.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "clientIP_in",
+                "clientRequestPath",
+                "datetime_geq",
+                "datetime_lt"
+            ]
+        );
+        assert_eq!(filter[1].1, GqlValue::String(HOSTILE.to_string()));
+        assert_eq!(
+            filter[0].1,
+            GqlValue::List(vec![GqlValue::String(HOSTILE.trim().to_string())])
+        );
+    }
+
+    #[test]
+    fn parses_command_line_values() {
+        assert_eq!(Arg::parse("404"), Arg::Int(404));
+        assert_eq!(Arg::parse("-1"), Arg::Int(-1));
+        assert_eq!(Arg::parse("true"), Arg::Bool(true));
+        assert_eq!(Arg::parse("0.5"), Arg::Float(0.5));
+        assert_eq!(Arg::parse("\"404\""), Arg::Str("404".into()));
+        assert_eq!(Arg::parse("'true'"), Arg::Str("true".into()));
+        assert_eq!(Arg::parse("inf"), Arg::Str("inf".into()));
+        assert_eq!(Arg::parse("NaN"), Arg::Str("NaN".into()));
+        assert_eq!(Arg::parse("/api"), Arg::Str("/api".into()));
+    }
+
+    #[test]
+    fn rejects_injected_names() {
+        for name in ["sum", "_private", "edgeResponseStatus_geq", "a1"] {
+            assert!(check_name(name).is_ok(), "{}", name);
+        }
+        for name in [
+            "",
+            "1abc",
+            "count_DESC, datetime_ASC",
+            "a b",
+            "x) { __schema { types { name } } } y(",
+            "ok\"",
+            "name#",
+            "é",
+        ] {
+            assert!(check_name(name).is_err(), "{:?}", name);
+        }
+
+        let valid = || {
+            let mut query = GroupQuery::new(Scope::Zone("z".into()), "httpRequestsAdaptiveGroups");
+            query.dimensions = vec!["clientIP".into()];
+            query
+        };
+        assert!(valid().render().is_ok());
+
+        let mut query = valid();
+        query.dataset = "httpRequestsAdaptiveGroups(limit: 1) { count } x".into();
+        assert!(query.render().is_err());
+
+        let mut query = valid();
+        query.filter = vec![("clientIP: \"1\"} ) {".into(), Arg::Int(1))];
+        assert!(query.render().is_err());
+
+        let mut query = valid();
+        query.order_by = vec!["count_DESC]) { __typename } x([".into()];
+        assert!(query.render().is_err());
+
+        let mut query = valid();
+        query.dimensions = vec!["clientIP } __schema { types { name }".into()];
+        assert!(query.render().is_err());
+
+        let mut query = valid();
+        query.metrics = vec![Metric::Nested {
+            block: "sum".into(),
+            field: "responseStatusMap".into(),
+            subfields: vec!["requests } __schema {".into()],
+        }];
+        assert!(query.render().is_err());
+
+        assert!(Metric::parse("sum.bytes } __schema {").is_err());
+        assert!(Metric::parse("bytes").is_err());
+    }
+
+    #[test]
+    fn renders_http_requests_query() {
+        let query = http_requests_query(
+            "023e105f4ecef8ad9ca31a8372d0c353",
+            "2024-05-01T00:00:00Z",
+            "2024-05-02T00:00:00Z",
+            25,
+            &["clientRequestPath", "edgeResponseStatus"],
+            "count_DESC",
+            &[("edgeResponseStatus_geq", Arg::Int(400))],
+        )
+        .unwrap();
+        assert_eq!(query, GOLDEN_HTTP_REQUESTS);
+    }
+
+    const GOLDEN_HTTP_REQUESTS: &str = r#"{
+  viewer {
+    zones(filter: {zoneTag: "023e105f4ecef8ad9ca31a8372d0c353"}) {
+      httpRequestsAdaptiveGroups(
+        limit: 25,
+        filter: {datetime_geq: "2024-05-01T00:00:00Z", datetime_lt: "2024-05-02T00:00:00Z", edgeResponseStatus_geq: 400},
+        orderBy: [count_DESC]
+      ) {
+        count
+        dimensions {
+          clientRequestPath
+          edgeResponseStatus
+        }
+      }
+    }
+  }
+}"#;
 }
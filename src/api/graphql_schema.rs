@@ -0,0 +1,862 @@
+//! Cached GraphQL schema of the Analytics API: dataset discovery and offline query validation
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use graphql_parser::query::{
+    self as ast, Definition, OperationDefinition, Selection, SelectionSet, TypeCondition,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::api::CloudflareClient;
+use crate::config::Config;
+
+const CACHE_FILE: &str = "graphql-schema.json";
+
+/// Introspection query, with type references deep enough for `[Type!]!` and lists of them
+pub const INTROSPECTION_QUERY: &str = r#"query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    types {
+      kind
+      name
+      description
+      fields(includeDeprecated: true) {
+        name
+        description
+        args { name description type { ...TypeRef } }
+        type { ...TypeRef }
+      }
+      inputFields { name description type { ...TypeRef } }
+      enumValues(includeDeprecated: true) { name }
+    }
+  }
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType { kind name ofType { kind name ofType { kind name ofType { kind name } } } }
+}"#;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeRef {
+    pub kind: String,
+    pub name: Option<String>,
+    pub of_type: Option<Box<TypeRef>>,
+}
+
+impl TypeRef {
+    /// The named type inside any list/non-null wrappers
+    pub fn named(&self) -> &str {
+        match (&self.name, &self.of_type) {
+            (Some(name), _) => name,
+            (None, Some(inner)) => inner.named(),
+            (None, None) => "",
+        }
+    }
+
+    /// The type of list elements (or the type itself when it is not a list)
+    fn element(&self) -> &TypeRef {
+        match (self.kind.as_str(), &self.of_type) {
+            ("NON_NULL", Some(inner)) => inner.element(),
+            ("LIST", Some(inner)) => inner,
+            _ => self,
+        }
+    }
+
+    /// GraphQL notation, e.g. `[String!]!`
+    pub fn display(&self) -> String {
+        match (self.kind.as_str(), &self.of_type) {
+            ("NON_NULL", Some(inner)) => format!("{}!", inner.display()),
+            ("LIST", Some(inner)) => format!("[{}]", inner.display()),
+            _ => self.name.clone().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputValue {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub type_ref: TypeRef,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Field {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub args: Vec<InputValue>,
+    #[serde(rename = "type")]
+    pub type_ref: TypeRef,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeDef {
+    pub kind: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub fields: Option<Vec<Field>>,
+    pub input_fields: Option<Vec<InputValue>>,
+    pub enum_values: Option<Vec<EnumValue>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnumValue {
+    pub name: String,
+}
+
+impl TypeDef {
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().flatten().find(|f| f.name == name)
+    }
+
+    fn input_field(&self, name: &str) -> Option<&InputValue> {
+        self.input_fields.iter().flatten().find(|f| f.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RawSchema {
+    #[serde(rename = "queryType")]
+    query_type: NameRef,
+    types: Vec<TypeDef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NameRef {
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheFile {
+    fetched_at: DateTime<Utc>,
+    schema: RawSchema,
+}
+
+/// The Analytics API schema, as returned by introspection
+pub struct Schema {
+    pub fetched_at: DateTime<Utc>,
+    query_type: String,
+    types: HashMap<String, TypeDef>,
+}
+
+/// Datasets live on these fields of `viewer`
+pub const SCOPES: &[&str] = &["zones", "accounts"];
+
+/// Fetch the schema by introspection and store it in the cache
+pub async fn refresh(client: &CloudflareClient) -> Result<Schema> {
+    let response = client.graphql(INTROSPECTION_QUERY, None).await?;
+    let raw: RawSchema = serde_json::from_value(
+        response
+            .pointer("/data/__schema")
+            .cloned()
+            .ok_or_else(|| anyhow!("Introspection returned no schema"))?,
+    )?;
+
+    let cache = CacheFile {
+        fetched_at: Utc::now(),
+        schema: raw,
+    };
+    std::fs::write(cache_path()?, serde_json::to_string(&cache)?)?;
+    Ok(Schema::from_cache(cache))
+}
+
+/// The cached schema, if `analytics schema` has been run
+pub fn load_cached() -> Result<Option<Schema>> {
+    let path = cache_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let cache: CacheFile = serde_json::from_str(&std::fs::read_to_string(&path)?)
+        .map_err(|e| anyhow!("Invalid schema cache {}: {}", path.display(), e))?;
+    Ok(Some(Schema::from_cache(cache)))
+}
+
+/// The cached schema, or an error telling how to fetch it
+pub fn require_cached() -> Result<Schema> {
+    load_cached()?.ok_or_else(|| anyhow!("No cached schema; run `cli5 analytics schema` first"))
+}
+
+pub fn cache_path() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join(CACHE_FILE))
+}
+
+impl Schema {
+    fn from_cache(cache: CacheFile) -> Self {
+        Self {
+            fetched_at: cache.fetched_at,
+            query_type: cache.schema.query_type.name,
+            types: cache
+                .schema
+                .types
+                .into_iter()
+                .map(|t| (t.name.clone(), t))
+                .collect(),
+        }
+    }
+
+    pub fn type_count(&self) -> usize {
+        self.types.len()
+    }
+
+    pub fn get_type(&self, name: &str) -> Option<&TypeDef> {
+        self.types.get(name)
+    }
+
+    /// The type holding the datasets of a scope (`zones` or `accounts`)
+    fn scope_type(&self, scope: &str) -> Option<&TypeDef> {
+        let viewer = self.get_type(&self.query_type)?.field("viewer")?;
+        let scope = self.get_type(viewer.type_ref.named())?.field(scope)?;
+        self.get_type(scope.type_ref.named())
+    }
+
+    /// Datasets of a scope: fields returning groups or events
+    pub fn datasets(&self, scope: &str) -> Vec<&Field> {
+        let mut datasets: Vec<&Field> = self
+            .scope_type(scope)
+            .into_iter()
+            .flat_map(|t| t.fields.iter().flatten())
+            .filter(|f| {
+                self.get_type(f.type_ref.named())
+                    .is_some_and(|t| t.fields.is_some())
+            })
+            .collect();
+        datasets.sort_by(|a, b| a.name.cmp(&b.name));
+        datasets
+    }
+
+    /// Find a dataset in any scope, returning the scope with it
+    pub fn dataset(&self, name: &str) -> Option<(&'static str, &Field)> {
+        SCOPES.iter().find_map(|scope| {
+            self.datasets(scope)
+                .into_iter()
+                .find(|f| f.name == name)
+                .map(|f| (*scope, f))
+        })
+    }
+
+    /// Input fields of a dataset's `filter` argument
+    pub fn filter_fields(&self, dataset: &Field) -> Vec<&InputValue> {
+        dataset
+            .args
+            .iter()
+            .find(|a| a.name == "filter")
+            .and_then(|a| self.get_type(a.type_ref.named()))
+            .map(|t| t.input_fields.iter().flatten().collect())
+            .unwrap_or_default()
+    }
+
+    /// Validate a query: syntax, fields, arguments, filter fields, enum values and variables
+    ///
+    /// Returns one message per problem, prefixed with the line and column.
+    pub fn validate(&self, query: &str, variables: &Map<String, Value>) -> Vec<String> {
+        let document = match graphql_parser::parse_query::<&str>(query) {
+            Ok(document) => document,
+            Err(e) => return vec![e.to_string().trim().to_string()],
+        };
+
+        let fragments: HashMap<&str, &ast::FragmentDefinition<&str>> = document
+            .definitions
+            .iter()
+            .filter_map(|d| match d {
+                Definition::Fragment(f) => Some((f.name, f)),
+                _ => None,
+            })
+            .collect();
+
+        let mut validator = Validator {
+            schema: self,
+            fragments,
+            declared: HashSet::new(),
+            errors: Vec::new(),
+        };
+
+        for definition in &document.definitions {
+            let Definition::Operation(operation) = definition else {
+                continue;
+            };
+            let (declared, selection) = match operation {
+                OperationDefinition::SelectionSet(set) => (Vec::new(), set),
+                OperationDefinition::Query(q) => {
+                    (q.variable_definitions.iter().collect(), &q.selection_set)
+                }
+                OperationDefinition::Mutation(m) => {
+                    validator.error(m.position, "The Analytics API does not support mutations");
+                    continue;
+                }
+                OperationDefinition::Subscription(s) => {
+                    validator.error(
+                        s.position,
+                        "The Analytics API does not support subscriptions",
+                    );
+                    continue;
+                }
+            };
+
+            validator.declared = declared.iter().map(|v| v.name.to_string()).collect();
+            for variable in &declared {
+                let required = matches!(variable.var_type, ast::Type::NonNullType(_))
+                    && variable.default_value.is_none();
+                if required && !variables.contains_key(variable.name) {
+                    validator.error(
+                        variable.position,
+                        &format!(
+                            "Variable ${} is required but not given (use --var)",
+                            variable.name
+                        ),
+                    );
+                }
+            }
+            validator.selection(&self.query_type, selection, 0);
+        }
+
+        validator.errors
+    }
+
+    /// Selectable fields of a dataset by block (`dimensions`, `sum`, ...); plain fields under ""
+    pub fn dataset_fields(&self, dataset: &Field) -> BTreeMap<String, Vec<&Field>> {
+        let mut blocks = BTreeMap::new();
+        let Some(item) = self.get_type(dataset.type_ref.named()) else {
+            return blocks;
+        };
+
+        for field in item.fields.iter().flatten() {
+            let nested = self
+                .get_type(field.type_ref.named())
+                .and_then(|t| t.fields.as_ref());
+            match nested {
+                Some(fields) => {
+                    blocks.insert(field.name.clone(), fields.iter().collect());
+                }
+                None => blocks
+                    .entry(String::new())
+                    .or_insert_with(Vec::new)
+                    .push(field),
+            }
+        }
+        blocks
+    }
+}
+
+struct Validator<'s, 'q> {
+    schema: &'s Schema,
+    fragments: HashMap<&'q str, &'q ast::FragmentDefinition<'q, &'q str>>,
+    declared: HashSet<String>,
+    errors: Vec<String>,
+}
+
+impl<'q> Validator<'_, 'q> {
+    fn error(&mut self, position: graphql_parser::Pos, message: &str) {
+        self.errors.push(format!(
+            "{}:{}: {}",
+            position.line, position.column, message
+        ));
+    }
+
+    fn selection(&mut self, type_name: &str, set: &SelectionSet<'q, &'q str>, depth: usize) {
+        // Guard against fragment cycles
+        if depth > 32 {
+            return;
+        }
+        let Some(parent) = self.schema.get_type(type_name) else {
+            return;
+        };
+
+        for item in &set.items {
+            match item {
+                Selection::Field(field) => self.field(parent, field, depth),
+                Selection::FragmentSpread(spread) => {
+                    match self.fragments.get(spread.fragment_name).copied() {
+                        Some(fragment) => {
+                            let TypeCondition::On(on) = fragment.type_condition;
+                            self.selection(on, &fragment.selection_set, depth + 1);
+                        }
+                        None => self.error(
+                            spread.position,
+                            &format!("Unknown fragment '{}'", spread.fragment_name),
+                        ),
+                    }
+                }
+                Selection::InlineFragment(inline) => {
+                    let on = match &inline.type_condition {
+                        Some(TypeCondition::On(on)) => *on,
+                        None => type_name,
+                    };
+                    self.selection(on, &inline.selection_set, depth + 1);
+                }
+            }
+        }
+    }
+
+    fn field(&mut self, parent: &TypeDef, field: &ast::Field<'q, &'q str>, depth: usize) {
+        if field.name.starts_with("__") {
+            return;
+        }
+        let Some(definition) = parent.field(field.name) else {
+            let hint = suggestion(
+                field.name,
+                parent.fields.iter().flatten().map(|f| f.name.as_str()),
+            );
+            self.error(
+                field.position,
+                &format!(
+                    "Unknown field '{}' on type '{}'{}",
+                    field.name, parent.name, hint
+                ),
+            );
+            return;
+        };
+
+        for (name, value) in &field.arguments {
+            match definition.args.iter().find(|a| a.name == *name) {
+                Some(arg) => self.value(field.position, &arg.type_ref, value, name),
+                None => {
+                    let hint = suggestion(name, definition.args.iter().map(|a| a.name.as_str()));
+                    self.error(
+                        field.position,
+                        &format!(
+                            "Unknown argument '{}' on field '{}'{}",
+                            name, field.name, hint
+                        ),
+                    );
+                }
+            }
+        }
+
+        let target = definition.type_ref.named();
+        let is_object = self
+            .schema
+            .get_type(target)
+            .is_some_and(|t| t.fields.is_some());
+        match (is_object, field.selection_set.items.is_empty()) {
+            (true, true) => self.error(
+                field.position,
+                &format!(
+                    "Field '{}' of type '{}' needs a selection of subfields",
+                    field.name, target
+                ),
+            ),
+            (false, false) => self.error(
+                field.position,
+                &format!(
+                    "Field '{}' is a scalar and cannot have subfields",
+                    field.name
+                ),
+            ),
+            (true, false) => self.selection(target, &field.selection_set, depth + 1),
+            (false, true) => {}
+        }
+    }
+
+    fn value(
+        &mut self,
+        position: graphql_parser::Pos,
+        type_ref: &TypeRef,
+        value: &ast::Value<'q, &'q str>,
+        path: &str,
+    ) {
+        let target = self.schema.get_type(type_ref.named());
+        match value {
+            ast::Value::Variable(name) if !self.declared.contains(*name) => {
+                self.error(position, &format!("Variable ${} is not declared", name));
+            }
+            ast::Value::List(items) => {
+                for item in items {
+                    self.value(position, type_ref.element(), item, path);
+                }
+            }
+            ast::Value::Object(fields) => {
+                let Some(input) = target.filter(|t| t.input_fields.is_some()) else {
+                    return;
+                };
+                for (name, inner) in fields {
+                    let nested = format!("{}.{}", path, name);
+                    match input.input_field(name) {
+                        Some(field) => self.value(position, &field.type_ref, inner, &nested),
+                        None => {
+                            let hint = suggestion(
+                                name,
+                                input.input_fields.iter().flatten().map(|f| f.name.as_str()),
+                            );
+                            self.error(
+                                position,
+                                &format!("Unknown filter field '{}' in '{}'{}", name, path, hint),
+                            );
+                        }
+                    }
+                }
+            }
+            ast::Value::Enum(name) => {
+                let Some(values) = target.and_then(|t| t.enum_values.as_ref()) else {
+                    return;
+                };
+                if !values.iter().any(|v| v.name == *name) {
+                    let hint = suggestion(name, values.iter().map(|v| v.name.as_str()));
+                    self.error(
+                        position,
+                        &format!("Invalid value '{}' for '{}'{}", name, path, hint),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// ` (did you mean 'x'?)` for a close match, ignoring case and underscores
+fn suggestion<'a>(wanted: &str, candidates: impl Iterator<Item = &'a str>) -> String {
+    let normalize = |s: &str| s.to_lowercase().replace('_', "");
+    let wanted = normalize(wanted);
+
+    candidates
+        .filter(|c| {
+            let candidate = normalize(c);
+            candidate == wanted
+                || (wanted.len() >= 4
+                    && (candidate.starts_with(&wanted) || wanted.starts_with(&candidate)))
+        })
+        .min_by_key(|c| c.len())
+        .map(|c| format!(" (did you mean '{}'?)", c))
+        .unwrap_or_default()
+}
+
+/// Parse `--var name=value` pairs: JSON values as such, anything else as a string
+///
+/// Variables the query declares as a string type keep the raw text, so
+/// `--var zoneTag=123` stays `"123"`.
+pub fn parse_variables(pairs: &[String], query: &str) -> Result<Map<String, Value>> {
+    let strings = string_variables(query);
+    let mut variables = Map::new();
+    for pair in pairs {
+        let (name, raw) = pair
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid variable '{}': use name=value", pair))?;
+        let name = name.trim().trim_start_matches('$').to_string();
+        let value = if strings.contains(&name) {
+            json!(raw)
+        } else {
+            serde_json::from_str(raw).unwrap_or_else(|_| json!(raw))
+        };
+        variables.insert(name, value);
+    }
+    Ok(variables)
+}
+
+/// Scalar types whose values are plain text
+const STRING_TYPES: &[&str] = &["String", "string", "ID"];
+
+/// Variables declared with a (non-list) string type
+fn string_variables(query: &str) -> HashSet<String> {
+    let Ok(document) = graphql_parser::parse_query::<&str>(query) else {
+        return HashSet::new();
+    };
+    document
+        .definitions
+        .iter()
+        .filter_map(|d| match d {
+            Definition::Operation(OperationDefinition::Query(q)) => Some(&q.variable_definitions),
+            _ => None,
+        })
+        .flatten()
+        .filter(|v| {
+            let named = match &v.var_type {
+                ast::Type::NonNullType(inner) => inner.as_ref(),
+                other => other,
+            };
+            matches!(named, ast::Type::NamedType(name) if STRING_TYPES.contains(name))
+        })
+        .map(|v| v.name.to_string())
+        .collect()
+}
+
+/// Names of the variables a query declares (empty when it does not parse)
+pub fn declared_variables(query: &str) -> Vec<String> {
+    let Ok(document) = graphql_parser::parse_query::<&str>(query) else {
+        return Vec::new();
+    };
+    document
+        .definitions
+        .iter()
+        .filter_map(|d| match d {
+            Definition::Operation(OperationDefinition::Query(q)) => Some(&q.variable_definitions),
+            _ => None,
+        })
+        .flatten()
+        .map(|v| v.name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(kind: &str, name: &str) -> Value {
+        json!({"kind": kind, "name": name, "ofType": null})
+    }
+
+    fn list(inner: Value) -> Value {
+        json!({"kind": "LIST", "name": null, "ofType": inner})
+    }
+
+    fn non_null(inner: Value) -> Value {
+        json!({"kind": "NON_NULL", "name": null, "ofType": inner})
+    }
+
+    fn object(name: &str, fields: Value) -> Value {
+        json!({"kind": "OBJECT", "name": name, "fields": fields})
+    }
+
+    fn field(name: &str, args: Value, type_ref: Value) -> Value {
+        json!({"name": name, "args": args, "type": type_ref})
+    }
+
+    fn arg(name: &str, type_ref: Value) -> Value {
+        json!({"name": name, "type": type_ref})
+    }
+
+    /// A cut-down Analytics API: viewer.zones(filter).httpRequests1hGroups
+    fn fixture() -> Schema {
+        let types = json!([
+            object(
+                "Query",
+                json!([field("viewer", json!([]), named("OBJECT", "Viewer"))])
+            ),
+            object(
+                "Viewer",
+                json!([field(
+                    "zones",
+                    json!([arg("filter", named("INPUT_OBJECT", "ZoneFilter"))]),
+                    list(named("OBJECT", "Zone"))
+                )])
+            ),
+            object(
+                "Zone",
+                json!([field(
+                    "httpRequests1hGroups",
+                    json!([
+                        arg("limit", non_null(named("SCALAR", "uint64"))),
+                        arg("filter", named("INPUT_OBJECT", "GroupFilter")),
+                        arg(
+                            "orderBy",
+                            list(non_null(named("ENUM", "GroupOrderBy")))
+                        )
+                    ]),
+                    list(named("OBJECT", "Group"))
+                )])
+            ),
+            object(
+                "Group",
+                json!([
+                    field("dimensions", json!([]), named("OBJECT", "Dimensions")),
+                    field("sum", json!([]), named("OBJECT", "Sum"))
+                ])
+            ),
+            object(
+                "Dimensions",
+                json!([field("datetime", json!([]), named("SCALAR", "Time"))])
+            ),
+            object(
+                "Sum",
+                json!([
+                    field("requests", json!([]), named("SCALAR", "uint64")),
+                    field("bytes", json!([]), named("SCALAR", "uint64"))
+                ])
+            ),
+            {
+                "kind": "INPUT_OBJECT",
+                "name": "ZoneFilter",
+                "inputFields": [arg("zoneTag", named("SCALAR", "string"))]
+            },
+            {
+                "kind": "INPUT_OBJECT",
+                "name": "GroupFilter",
+                "inputFields": [
+                    arg("datetime_geq", named("SCALAR", "Time")),
+                    arg("clientCountryName", named("SCALAR", "string")),
+                    arg("AND", list(non_null(named("INPUT_OBJECT", "GroupFilter"))))
+                ]
+            },
+            {
+                "kind": "ENUM",
+                "name": "GroupOrderBy",
+                "enumValues": [{"name": "datetime_ASC"}, {"name": "datetime_DESC"}]
+            },
+            {"kind": "SCALAR", "name": "string"},
+            {"kind": "SCALAR", "name": "uint64"},
+            {"kind": "SCALAR", "name": "Time"}
+        ]);
+
+        Schema::from_cache(CacheFile {
+            fetched_at: Utc::now(),
+            schema: serde_json::from_value(json!({
+                "queryType": {"name": "Query"},
+                "types": types
+            }))
+            .unwrap(),
+        })
+    }
+
+    fn zone_var() -> Map<String, Value> {
+        let mut variables = Map::new();
+        variables.insert("zoneTag".into(), json!("abc"));
+        variables
+    }
+
+    /// Errors for a query with the given `httpRequests1hGroups` arguments and selection
+    fn check(arguments: &str, selection: &str) -> Vec<String> {
+        let query = format!(
+            "query ($zoneTag: string!) {{ viewer {{ zones(filter: {{zoneTag: $zoneTag}}) {{ httpRequests1hGroups({}) {{ {} }} }} }} }}",
+            arguments, selection
+        );
+        fixture().validate(&query, &zone_var())
+    }
+
+    #[test]
+    fn fixture_datasets() {
+        let schema = fixture();
+        let names: Vec<&str> = schema
+            .datasets("zones")
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["httpRequests1hGroups"]);
+        let (scope, dataset) = schema.dataset("httpRequests1hGroups").unwrap();
+        assert_eq!(scope, "zones");
+        assert_eq!(schema.filter_fields(dataset).len(), 3);
+    }
+
+    #[test]
+    fn valid_query_has_no_errors() {
+        let errors = check(
+            "limit: 10, filter: {datetime_geq: \"2026-01-01T00:00:00Z\", AND: [{clientCountryName: \"DE\"}]}, orderBy: [datetime_ASC]",
+            "dimensions { datetime } sum { requests bytes } __typename",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn unknown_field_suggests_a_close_match() {
+        let errors = check("limit: 10", "sum { request }");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0].ends_with("Unknown field 'request' on type 'Sum' (did you mean 'requests'?)"),
+            "{}",
+            errors[0]
+        );
+
+        let errors = check("limit: 10", "sum { visits }");
+        assert!(errors[0].ends_with("Unknown field 'visits' on type 'Sum'"));
+    }
+
+    #[test]
+    fn unknown_filter_field_and_nested_filters() {
+        let errors = check("limit: 10, filter: {datetimeGeq: \"x\"}", "sum { bytes }");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].ends_with(
+            "Unknown filter field 'datetimeGeq' in 'filter' (did you mean 'datetime_geq'?)"
+        ));
+
+        let errors = check(
+            "limit: 10, filter: {AND: [{country: \"DE\"}]}",
+            "sum { bytes }",
+        );
+        assert!(errors[0].contains("Unknown filter field 'country' in 'filter.AND'"));
+    }
+
+    #[test]
+    fn bad_enum_value() {
+        let errors = check("limit: 10, orderBy: [datetime_ASCENDING]", "sum { bytes }");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].ends_with(
+            "Invalid value 'datetime_ASCENDING' for 'orderBy' (did you mean 'datetime_ASC'?)"
+        ));
+    }
+
+    #[test]
+    fn variables_must_be_declared_and_given() {
+        let errors = check("limit: $limit", "sum { bytes }");
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].ends_with("Variable $limit is not declared"));
+
+        let query = "query ($zoneTag: string!, $limit: uint64 = 10) { viewer { zones(filter: {zoneTag: $zoneTag}) { httpRequests1hGroups(limit: $limit) { sum { bytes } } } } }";
+        let errors = fixture().validate(query, &Map::new());
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].ends_with("Variable $zoneTag is required but not given (use --var)"));
+        assert!(fixture().validate(query, &zone_var()).is_empty());
+    }
+
+    #[test]
+    fn mutations_are_rejected() {
+        let errors = fixture().validate("mutation { purge }", &Map::new());
+        assert_eq!(
+            errors,
+            vec!["1:1: The Analytics API does not support mutations"]
+        );
+    }
+
+    #[test]
+    fn fragments_are_followed() {
+        let schema = fixture();
+        let query = |fragment: &str| {
+            format!(
+                "query ($zoneTag: string!) {{ viewer {{ zones(filter: {{zoneTag: $zoneTag}}) {{ httpRequests1hGroups(limit: 1) {{ ...Totals ... on Group {{ dimensions {{ datetime }} }} }} }} }} }}\n{}",
+                fragment
+            )
+        };
+
+        let ok = schema.validate(
+            &query("fragment Totals on Group { sum { requests } }"),
+            &zone_var(),
+        );
+        assert!(ok.is_empty(), "{:?}", ok);
+
+        let bad = schema.validate(
+            &query("fragment Totals on Group { sum { requests hits } }"),
+            &zone_var(),
+        );
+        assert_eq!(bad.len(), 1, "{:?}", bad);
+        assert!(bad[0].contains("Unknown field 'hits' on type 'Sum'"));
+
+        let missing = schema.validate(&query(""), &zone_var());
+        assert_eq!(missing.len(), 1, "{:?}", missing);
+        assert!(missing[0].ends_with("Unknown fragment 'Totals'"));
+    }
+
+    #[test]
+    fn string_variables_stay_strings() {
+        let query = "query ($zoneTag: string!, $name: String, $id: ID!, $limit: uint64, $tags: [String!]) { viewer { __typename } }";
+        let pairs: Vec<String> = [
+            "zoneTag=123",
+            "$name=true",
+            "id=null",
+            "limit=5",
+            "tags=[\"a\",\"b\"]",
+            "other={\"x\":1}",
+            "word=hello",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let variables = parse_variables(&pairs, query).unwrap();
+
+        assert_eq!(variables["zoneTag"], json!("123"));
+        assert_eq!(variables["name"], json!("true"));
+        assert_eq!(variables["id"], json!("null"));
+        assert_eq!(variables["limit"], json!(5));
+        assert_eq!(variables["tags"], json!(["a", "b"]));
+        assert_eq!(variables["other"], json!({"x": 1}));
+        assert_eq!(variables["word"], json!("hello"));
+
+        assert!(parse_variables(&["novalue".to_string()], query).is_err());
+    }
+}
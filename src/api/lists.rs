@@ -0,0 +1,206 @@
+//! Account-level lists (IP, hostname, ASN and redirect lists)
+//!
+//! Item changes are asynchronous on Cloudflare's side: add/remove calls
+//! return an operation ID that has to be polled until it completes.
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use crate::api::CloudflareClient;
+
+/// Maximum number of items sent in one add/remove request
+pub const ITEMS_PER_REQUEST: usize = 1000;
+
+/// List all lists in the account
+pub async fn list(client: &CloudflareClient, account_id: &str) -> Result<Vec<Value>> {
+    let response = client
+        .get_raw(&format!("/accounts/{}/rules/lists", account_id))
+        .await?;
+
+    Ok(response
+        .get("result")
+        .and_then(|r| r.as_array())
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Find a list by ID or name
+pub async fn find(client: &CloudflareClient, account_id: &str, target: &str) -> Result<Value> {
+    list(client, account_id)
+        .await?
+        .into_iter()
+        .find(|l| {
+            l.get("id").and_then(|v| v.as_str()) == Some(target)
+                || l.get("name").and_then(|v| v.as_str()) == Some(target)
+        })
+        .ok_or_else(|| anyhow!("List not found: {}", target))
+}
+
+/// Create a new list
+pub async fn create(
+    client: &CloudflareClient,
+    account_id: &str,
+    name: &str,
+    kind: &str,
+    description: Option<&str>,
+) -> Result<Value> {
+    let body = json!({
+        "name": name,
+        "kind": kind,
+        "description": description.unwrap_or_default()
+    });
+
+    let response = client
+        .post_raw(&format!("/accounts/{}/rules/lists", account_id), body)
+        .await?;
+
+    response
+        .get("result")
+        .cloned()
+        .ok_or_else(|| anyhow!("Unexpected response when creating list"))
+}
+
+/// Fetch all items of a list, following the pagination cursor
+pub async fn items(
+    client: &CloudflareClient,
+    account_id: &str,
+    list_id: &str,
+) -> Result<Vec<Value>> {
+    let mut all = Vec::new();
+    let mut cursor: Option<String> = None;
+
+    loop {
+        let mut path = format!(
+            "/accounts/{}/rules/lists/{}/items?per_page=500",
+            account_id, list_id
+        );
+        if let Some(c) = &cursor {
+            path.push_str(&format!("&cursor={}", c));
+        }
+
+        let response = client.get_raw(&path).await?;
+        if let Some(items) = response.get("result").and_then(|r| r.as_array()) {
+            all.extend(items.iter().cloned());
+        }
+
+        cursor = response
+            .pointer("/result_info/cursors/after")
+            .and_then(|v| v.as_str())
+            .filter(|c| !c.is_empty())
+            .map(String::from);
+
+        if cursor.is_none() {
+            break;
+        }
+    }
+
+    Ok(all)
+}
+
+/// Append items to a list and return the bulk operation ID
+pub async fn add_items(
+    client: &CloudflareClient,
+    account_id: &str,
+    list_id: &str,
+    items: Vec<Value>,
+) -> Result<String> {
+    let response = client
+        .post_raw(
+            &format!("/accounts/{}/rules/lists/{}/items", account_id, list_id),
+            Value::Array(items),
+        )
+        .await?;
+
+    operation_id(&response)
+}
+
+/// Remove items (by item ID) from a list and return the bulk operation ID
+pub async fn remove_items(
+    client: &CloudflareClient,
+    account_id: &str,
+    list_id: &str,
+    item_ids: &[String],
+) -> Result<String> {
+    let body = json!({
+        "items": item_ids.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>()
+    });
+
+    let response = client
+        .delete_raw_with_body(
+            &format!("/accounts/{}/rules/lists/{}/items", account_id, list_id),
+            body,
+        )
+        .await?;
+
+    operation_id(&response)
+}
+
+/// Poll a bulk operation until it completes or fails
+pub async fn wait_operation(
+    client: &CloudflareClient,
+    account_id: &str,
+    operation_id: &str,
+) -> Result<()> {
+    let path = format!(
+        "/accounts/{}/rules/lists/bulk_operations/{}",
+        account_id, operation_id
+    );
+
+    for _ in 0..120 {
+        let response = client.get_raw(&path).await?;
+        let status = response
+            .pointer("/result/status")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+
+        match status {
+            "completed" => return Ok(()),
+            "failed" => {
+                let error = response
+                    .pointer("/result/error")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("unknown error");
+                return Err(anyhow!("Bulk operation failed: {}", error));
+            }
+            _ => tokio::time::sleep(Duration::from_millis(500)).await,
+        }
+    }
+
+    Err(anyhow!(
+        "Timed out waiting for bulk operation {}",
+        operation_id
+    ))
+}
+
+/// Value an item is keyed by (ip, hostname, asn or redirect source)
+pub fn item_value(item: &Value) -> String {
+    if let Some(ip) = item.get("ip").and_then(|v| v.as_str()) {
+        return ip.to_string();
+    }
+    if let Some(asn) = item.get("asn").and_then(|v| v.as_u64()) {
+        return asn.to_string();
+    }
+    if let Some(host) = item
+        .pointer("/hostname/url_hostname")
+        .and_then(|v| v.as_str())
+    {
+        return host.to_string();
+    }
+    if let Some(source) = item
+        .pointer("/redirect/source_url")
+        .and_then(|v| v.as_str())
+    {
+        return source.to_string();
+    }
+    String::new()
+}
+
+fn operation_id(response: &Value) -> Result<String> {
+    response
+        .pointer("/result/operation_id")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .ok_or_else(|| anyhow!("Response did not include an operation ID"))
+}
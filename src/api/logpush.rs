@@ -0,0 +1,87 @@
+//! Logpush jobs and dataset fields
+//!
+//! Jobs exist on zones (`/zones/{id}`) and accounts (`/accounts/{id}`); every
+//! call takes that prefix as `base`.
+
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+
+use crate::api::CloudflareClient;
+
+/// List the Logpush jobs under `base`
+pub async fn list(client: &CloudflareClient, base: &str) -> Result<Vec<Value>> {
+    let response = client.get_raw(&format!("{}/logpush/jobs", base)).await?;
+    Ok(response
+        .get("result")
+        .and_then(|r| r.as_array())
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Fetch a job by ID
+pub async fn get(client: &CloudflareClient, base: &str, id: u64) -> Result<Value> {
+    let response = client
+        .get_raw(&format!("{}/logpush/jobs/{}", base, id))
+        .await?;
+    result(response, "fetching")
+}
+
+/// Create a job
+pub async fn create(client: &CloudflareClient, base: &str, body: Value) -> Result<Value> {
+    let response = client
+        .post_raw(&format!("{}/logpush/jobs", base), body)
+        .await?;
+    result(response, "creating")
+}
+
+/// Update a job; only the fields in `body` change
+pub async fn update(client: &CloudflareClient, base: &str, id: u64, body: Value) -> Result<Value> {
+    let response = client
+        .put_raw(&format!("{}/logpush/jobs/{}", base, id), body)
+        .await?;
+    result(response, "updating")
+}
+
+/// Delete a job
+pub async fn delete(client: &CloudflareClient, base: &str, id: u64) -> Result<()> {
+    client
+        .delete_raw(&format!("{}/logpush/jobs/{}", base, id))
+        .await?;
+    Ok(())
+}
+
+/// Fields of a dataset with their descriptions
+pub async fn fields(
+    client: &CloudflareClient,
+    base: &str,
+    dataset: &str,
+) -> Result<Map<String, Value>> {
+    let response = client
+        .get_raw(&format!("{}/logpush/datasets/{}/fields", base, dataset))
+        .await?;
+    response
+        .get("result")
+        .and_then(|r| r.as_object())
+        .cloned()
+        .ok_or_else(|| anyhow!("No fields found for dataset '{}'", dataset))
+}
+
+/// Start an Instant Logs session for a zone; `destination_conf` holds its WebSocket URL
+pub async fn instant_logs(client: &CloudflareClient, zone_id: &str, body: Value) -> Result<Value> {
+    let response = client
+        .post_raw(&format!("/zones/{}/logpush/edge/jobs", zone_id), body)
+        .await?;
+    response
+        .get("result")
+        .filter(|r| !r.is_null())
+        .cloned()
+        .ok_or_else(|| anyhow!("Unexpected response when starting Instant Logs"))
+}
+
+fn result(response: Value, action: &str) -> Result<Value> {
+    response
+        .get("result")
+        .filter(|r| !r.is_null())
+        .cloned()
+        .ok_or_else(|| anyhow!("Unexpected response when {} Logpush job", action))
+}
@@ -3,6 +3,11 @@
 pub mod client;
 pub mod endpoints;
 pub mod graphql;
+pub mod graphql_schema;
+pub mod lists;
+pub mod logpush;
 pub mod response;
+pub mod rulesets;
+pub mod settings;
 
 pub use client::CloudflareClient;
@@ -0,0 +1,212 @@
+//! Rulesets engine helpers (zone and account level)
+//!
+//! All helpers take a `base` path, either `/zones/{zone_id}` or
+//! `/accounts/{account_id}`, so the same code serves both scopes.
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use crate::api::CloudflareClient;
+
+/// Short aliases for commonly used phases
+pub const PHASE_ALIASES: &[(&str, &str)] = &[
+    ("custom", "http_request_firewall_custom"),
+    ("waf", "http_request_firewall_custom"),
+    ("managed", "http_request_firewall_managed"),
+    ("ratelimit", "http_ratelimit"),
+    ("transform", "http_request_transform"),
+    ("response-headers", "http_response_headers_transform"),
+    ("request-headers", "http_request_late_transform"),
+    ("cache", "http_request_cache_settings"),
+    ("redirect", "http_request_dynamic_redirect"),
+    ("redirects", "http_request_dynamic_redirect"),
+    ("origin", "http_request_origin"),
+    ("config", "http_config_settings"),
+];
+
+/// Resolve a phase alias (e.g. "custom") to the full phase name
+pub fn resolve_phase(name: &str) -> String {
+    PHASE_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == name)
+        .map(|(_, phase)| phase.to_string())
+        .unwrap_or_else(|| name.to_string())
+}
+
+/// Check whether a string looks like a ruleset/rule ID (32 hex chars)
+pub fn is_id(value: &str) -> bool {
+    value.len() == 32 && value.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// List all rulesets visible at the given scope
+pub async fn list(client: &CloudflareClient, base: &str) -> Result<Vec<Value>> {
+    let response = client.get_raw(&format!("{}/rulesets", base)).await?;
+
+    Ok(response
+        .get("result")
+        .and_then(|r| r.as_array())
+        .cloned()
+        .unwrap_or_default())
+}
+
+/// Get a ruleset (including its rules) by ID
+pub async fn get(client: &CloudflareClient, base: &str, ruleset_id: &str) -> Result<Value> {
+    let response = client
+        .get_raw(&format!("{}/rulesets/{}", base, ruleset_id))
+        .await?;
+
+    response
+        .get("result")
+        .cloned()
+        .ok_or_else(|| anyhow!("Ruleset not found: {}", ruleset_id))
+}
+
+/// Get the entrypoint ruleset for a phase, if one has been created
+pub async fn get_entrypoint(
+    client: &CloudflareClient,
+    base: &str,
+    phase: &str,
+) -> Result<Option<Value>> {
+    // Entrypoints are the zone/root kind rulesets; look them up via the list
+    // instead of relying on a 404 from the entrypoint endpoint.
+    let entry = list(client, base).await?.into_iter().find(|r| {
+        let kind = r.get("kind").and_then(|v| v.as_str()).unwrap_or("");
+        let rphase = r.get("phase").and_then(|v| v.as_str()).unwrap_or("");
+        (kind == "zone" || kind == "root") && rphase == phase
+    });
+
+    match entry {
+        Some(r) => {
+            let id = r.get("id").and_then(|v| v.as_str()).unwrap_or_default();
+            Ok(Some(get(client, base, id).await?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Get the entrypoint ruleset for a phase, creating an empty one if missing
+pub async fn ensure_entrypoint(
+    client: &CloudflareClient,
+    base: &str,
+    phase: &str,
+) -> Result<Value> {
+    if let Some(ruleset) = get_entrypoint(client, base, phase).await? {
+        return Ok(ruleset);
+    }
+
+    let body = json!({
+        "description": format!("Entrypoint for {} (created by cli5)", phase),
+        "rules": []
+    });
+    let response = client
+        .put_raw(
+            &format!("{}/rulesets/phases/{}/entrypoint", base, phase),
+            body,
+        )
+        .await?;
+
+    response
+        .get("result")
+        .cloned()
+        .ok_or_else(|| anyhow!("Failed to create entrypoint for phase {}", phase))
+}
+
+/// Resolve a ruleset target: a ruleset ID, or a phase name/alias (entrypoint)
+pub async fn resolve(client: &CloudflareClient, base: &str, target: &str) -> Result<Value> {
+    if is_id(target) {
+        return get(client, base, target).await;
+    }
+
+    let phase = resolve_phase(target);
+    get_entrypoint(client, base, &phase)
+        .await?
+        .ok_or_else(|| anyhow!("No entrypoint ruleset for phase {}", phase))
+}
+
+/// Get the rules of a ruleset
+pub fn rules(ruleset: &Value) -> Vec<Value> {
+    ruleset
+        .get("rules")
+        .and_then(|r| r.as_array())
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Find a rule in a ruleset by ID
+pub fn find_rule(ruleset: &Value, rule_id: &str) -> Result<Value> {
+    rules(ruleset)
+        .into_iter()
+        .find(|r| r.get("id").and_then(|v| v.as_str()) == Some(rule_id))
+        .ok_or_else(|| anyhow!("Rule not found: {}", rule_id))
+}
+
+/// Get the ID of a ruleset
+pub fn id(ruleset: &Value) -> Result<String> {
+    ruleset
+        .get("id")
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string())
+        .ok_or_else(|| anyhow!("Ruleset has no ID"))
+}
+
+/// Add a rule to a ruleset, returns the updated ruleset
+pub async fn add_rule(
+    client: &CloudflareClient,
+    base: &str,
+    ruleset_id: &str,
+    rule: Value,
+) -> Result<Value> {
+    let response = client
+        .post_raw(&format!("{}/rulesets/{}/rules", base, ruleset_id), rule)
+        .await?;
+    Ok(response.get("result").cloned().unwrap_or_default())
+}
+
+/// Update a rule by merging `changes` into its current definition
+///
+/// The API replaces the whole rule on PATCH, so the existing fields are sent
+/// along with the changes.
+pub async fn update_rule(
+    client: &CloudflareClient,
+    base: &str,
+    ruleset: &Value,
+    rule_id: &str,
+    changes: Value,
+) -> Result<Value> {
+    let mut rule = find_rule(ruleset, rule_id)?;
+
+    if let Some(obj) = rule.as_object_mut() {
+        for key in ["id", "version", "last_updated", "ref"] {
+            obj.remove(key);
+        }
+        if let Some(changes) = changes.as_object() {
+            for (key, value) in changes {
+                obj.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    let response = client
+        .patch_raw(
+            &format!("{}/rulesets/{}/rules/{}", base, id(ruleset)?, rule_id),
+            rule,
+        )
+        .await?;
+    Ok(response.get("result").cloned().unwrap_or_default())
+}
+
+/// Delete a rule from a ruleset
+pub async fn delete_rule(
+    client: &CloudflareClient,
+    base: &str,
+    ruleset_id: &str,
+    rule_id: &str,
+) -> Result<()> {
+    client
+        .delete_raw(&format!(
+            "{}/rulesets/{}/rules/{}",
+            base, ruleset_id, rule_id
+        ))
+        .await?;
+    Ok(())
+}
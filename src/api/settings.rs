@@ -0,0 +1,520 @@
+//! Built-in schema of zone settings
+//!
+//! Used to validate values before they are sent, to generate shell
+//! completions and to explain settings (`settings describe`). Settings that
+//! are not listed here can still be set with raw JSON.
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+/// Shape of a setting's value
+#[derive(Debug, Clone, Copy)]
+pub enum Kind {
+    /// "on" / "off"
+    OnOff,
+    /// One of a fixed set of strings
+    Choice(&'static [&'static str]),
+    /// One of a fixed set of numbers
+    Number(&'static [u64]),
+    /// A number in an inclusive range
+    Range(u64, u64),
+    /// An object whose listed keys are each "on" / "off"
+    Switches(&'static [&'static str]),
+    /// A list of strings
+    StringList,
+    /// Any JSON value (objects with nested structure)
+    Json,
+    /// Reported by the API but not changeable
+    ReadOnly,
+}
+
+/// A known zone setting
+#[derive(Debug)]
+pub struct Spec {
+    pub name: &'static str,
+    pub kind: Kind,
+    pub description: &'static str,
+    /// Lowest plan the setting can be changed on
+    pub plan: Option<&'static str>,
+}
+
+const fn spec(name: &'static str, kind: Kind, description: &'static str) -> Spec {
+    Spec {
+        name,
+        kind,
+        description,
+        plan: None,
+    }
+}
+
+const fn plan_spec(
+    name: &'static str,
+    kind: Kind,
+    description: &'static str,
+    plan: &'static str,
+) -> Spec {
+    Spec {
+        name,
+        kind,
+        description,
+        plan: Some(plan),
+    }
+}
+
+const TTLS: &[u64] = &[
+    0, 30, 60, 120, 300, 1200, 1800, 3600, 7200, 10800, 14400, 18000, 28800, 43200, 57600, 72000,
+    86400, 172800, 259200, 345600, 432000, 691200, 1382400, 2073600, 2678400, 5356800, 16070400,
+    31536000,
+];
+
+const CHALLENGE_TTLS: &[u64] = &[
+    300, 900, 1800, 2700, 3600, 7200, 10800, 14400, 28800, 57600, 86400, 604800, 2592000, 31536000,
+];
+
+/// All known zone settings, sorted by name
+pub const SCHEMA: &[Spec] = &[
+    spec(
+        "0rtt",
+        Kind::OnOff,
+        "TLS 1.3 0-RTT resumption for returning visitors",
+    ),
+    spec(
+        "advanced_ddos",
+        Kind::ReadOnly,
+        "Advanced DDoS protection (always on for Business and Enterprise)",
+    ),
+    spec(
+        "always_online",
+        Kind::OnOff,
+        "Serve pages from the Internet Archive when the origin is down",
+    ),
+    spec(
+        "always_use_https",
+        Kind::OnOff,
+        "Redirect all HTTP requests to HTTPS",
+    ),
+    spec(
+        "automatic_https_rewrites",
+        Kind::OnOff,
+        "Rewrite http:// links to https:// where possible",
+    ),
+    spec(
+        "automatic_platform_optimization",
+        Kind::Json,
+        "Automatic Platform Optimization for WordPress",
+    ),
+    spec(
+        "brotli",
+        Kind::OnOff,
+        "Brotli compression to visitors that support it",
+    ),
+    spec(
+        "browser_cache_ttl",
+        Kind::Number(TTLS),
+        "Browser cache TTL in seconds (0 respects origin headers)",
+    ),
+    spec(
+        "browser_check",
+        Kind::OnOff,
+        "Challenge requests with suspicious HTTP headers",
+    ),
+    spec(
+        "cache_level",
+        Kind::Choice(&["aggressive", "basic", "simplified"]),
+        "Which query strings are part of the cache key",
+    ),
+    spec(
+        "challenge_ttl",
+        Kind::Number(CHALLENGE_TTLS),
+        "How long a passed challenge is valid, in seconds",
+    ),
+    plan_spec(
+        "ciphers",
+        Kind::StringList,
+        "Allowed TLS cipher suites (empty list for defaults)",
+        "Advanced Certificate Manager",
+    ),
+    plan_spec(
+        "cname_flattening",
+        Kind::Choice(&["flatten_at_root", "flatten_all"]),
+        "Which CNAME records are flattened",
+        "Enterprise for flatten_all",
+    ),
+    spec(
+        "development_mode",
+        Kind::OnOff,
+        "Bypass the cache for three hours",
+    ),
+    spec(
+        "early_hints",
+        Kind::OnOff,
+        "Send 103 Early Hints for preload links",
+    ),
+    spec(
+        "email_obfuscation",
+        Kind::OnOff,
+        "Hide email addresses in pages from bots",
+    ),
+    plan_spec(
+        "h2_prioritization",
+        Kind::Choice(&["on", "off", "custom"]),
+        "Enhanced HTTP/2 prioritization",
+        "Pro",
+    ),
+    spec(
+        "hotlink_protection",
+        Kind::OnOff,
+        "Block images from being embedded on other sites",
+    ),
+    spec("http2", Kind::OnOff, "HTTP/2 to visitors"),
+    spec("http3", Kind::OnOff, "HTTP/3 (QUIC) to visitors"),
+    plan_spec(
+        "image_resizing",
+        Kind::Choice(&["on", "off", "open"]),
+        "Resize images on the fly (open allows any origin)",
+        "Pro",
+    ),
+    spec(
+        "ip_geolocation",
+        Kind::OnOff,
+        "Add the CF-IPCountry header to requests",
+    ),
+    spec("ipv6", Kind::OnOff, "Serve the zone over IPv6"),
+    plan_spec(
+        "max_upload",
+        Kind::Range(100, 500),
+        "Maximum upload size in MB (above 100 needs Business)",
+        "Business",
+    ),
+    spec(
+        "min_tls_version",
+        Kind::Choice(&["1.0", "1.1", "1.2", "1.3"]),
+        "Minimum TLS version accepted from visitors",
+    ),
+    spec(
+        "minify",
+        Kind::Switches(&["css", "html", "js"]),
+        "Auto Minify of CSS, HTML and JavaScript",
+    ),
+    plan_spec(
+        "mirage",
+        Kind::OnOff,
+        "Optimize images for mobile visitors",
+        "Pro",
+    ),
+    spec(
+        "opportunistic_encryption",
+        Kind::OnOff,
+        "Advertise HTTPS to HTTP/2 capable browsers",
+    ),
+    spec(
+        "opportunistic_onion",
+        Kind::OnOff,
+        "Serve Tor visitors through an onion service",
+    ),
+    spec(
+        "orange_to_orange",
+        Kind::OnOff,
+        "Route to other Cloudflare zones through their proxy",
+    ),
+    plan_spec(
+        "origin_error_page_pass_thru",
+        Kind::OnOff,
+        "Show origin error pages instead of Cloudflare's",
+        "Enterprise",
+    ),
+    spec(
+        "origin_max_http_version",
+        Kind::Choice(&["1", "2"]),
+        "Highest HTTP version used to the origin",
+    ),
+    plan_spec(
+        "polish",
+        Kind::Choice(&["off", "lossless", "lossy"]),
+        "Image compression",
+        "Pro",
+    ),
+    plan_spec(
+        "prefetch_preload",
+        Kind::OnOff,
+        "Prefetch URLs listed in the prefetch header",
+        "Enterprise",
+    ),
+    spec("privacy_pass", Kind::OnOff, "Privacy Pass challenge tokens"),
+    plan_spec(
+        "proxy_read_timeout",
+        Kind::Range(1, 6000),
+        "Seconds to wait for the origin's response",
+        "Enterprise",
+    ),
+    spec(
+        "pseudo_ipv4",
+        Kind::Choice(&["off", "add_header", "overwrite_header"]),
+        "Send an IPv4 address for IPv6 visitors",
+    ),
+    plan_spec(
+        "response_buffering",
+        Kind::OnOff,
+        "Buffer the whole origin response before sending",
+        "Enterprise",
+    ),
+    spec("rocket_loader", Kind::OnOff, "Defer loading of JavaScript"),
+    spec(
+        "security_header",
+        Kind::Json,
+        "HSTS (strict_transport_security) settings",
+    ),
+    spec(
+        "security_level",
+        Kind::Choice(&[
+            "off",
+            "essentially_off",
+            "low",
+            "medium",
+            "high",
+            "under_attack",
+        ]),
+        "How aggressively visitors are challenged",
+    ),
+    spec(
+        "server_side_exclude",
+        Kind::OnOff,
+        "Hide content in <!--sse--> tags from suspicious visitors",
+    ),
+    plan_spec(
+        "sort_query_string_for_cache",
+        Kind::OnOff,
+        "Treat reordered query strings as the same cache key",
+        "Enterprise",
+    ),
+    spec(
+        "ssl",
+        Kind::Choice(&["off", "flexible", "full", "strict"]),
+        "SSL/TLS encryption mode to the origin",
+    ),
+    spec(
+        "tls_1_3",
+        Kind::Choice(&["on", "off", "zrt"]),
+        "TLS 1.3 (zrt enables 0-RTT as well)",
+    ),
+    spec(
+        "tls_client_auth",
+        Kind::OnOff,
+        "Authenticated origin pulls with Cloudflare's certificate",
+    ),
+    plan_spec(
+        "true_client_ip_header",
+        Kind::OnOff,
+        "Add the True-Client-IP header",
+        "Enterprise",
+    ),
+    plan_spec("waf", Kind::OnOff, "Legacy WAF managed rules", "Pro"),
+    plan_spec("webp", Kind::OnOff, "Serve WebP images with Polish", "Pro"),
+    spec("websockets", Kind::OnOff, "Proxy WebSocket connections"),
+];
+
+/// Look up a setting by name
+pub fn find(name: &str) -> Option<&'static Spec> {
+    SCHEMA.iter().find(|s| s.name == name)
+}
+
+impl Kind {
+    /// Values offered for completion and in error messages
+    pub fn values(&self) -> Vec<String> {
+        match self {
+            Kind::OnOff => vec!["on".to_string(), "off".to_string()],
+            Kind::Choice(choices) => choices.iter().map(|c| c.to_string()).collect(),
+            Kind::Number(numbers) => numbers.iter().map(|n| n.to_string()).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Human readable description of the accepted values
+    pub fn describe(&self) -> String {
+        match self {
+            Kind::OnOff => "on | off".to_string(),
+            Kind::Choice(choices) => choices.join(" | "),
+            Kind::Number(numbers) => format!(
+                "one of {}",
+                numbers
+                    .iter()
+                    .map(|n| n.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Kind::Range(min, max) => format!("number from {} to {}", min, max),
+            Kind::Switches(keys) => format!("object with {} set to on | off", keys.join(", ")),
+            Kind::StringList => "list of strings".to_string(),
+            Kind::Json => "JSON object".to_string(),
+            Kind::ReadOnly => "read-only".to_string(),
+        }
+    }
+}
+
+impl Spec {
+    /// Parse a command-line value into the setting's JSON value
+    ///
+    /// Plain words are accepted for string settings, on/off also as
+    /// true/false, and switches as `css=on,js=off`. Anything else has to be
+    /// valid JSON.
+    pub fn parse(&self, raw: &str) -> Result<Value> {
+        let value = match self.kind {
+            Kind::ReadOnly => return Err(anyhow!("{} is read-only", self.name)),
+            Kind::OnOff => match raw.to_lowercase().as_str() {
+                "on" | "true" | "1" => json!("on"),
+                "off" | "false" | "0" => json!("off"),
+                _ => parse_json(self.name, raw)?,
+            },
+            Kind::Choice(_) => json!(raw.trim_matches('"')),
+            Kind::Number(_) | Kind::Range(..) => match raw.parse::<u64>() {
+                Ok(n) => json!(n),
+                Err(_) => parse_json(self.name, raw)?,
+            },
+            Kind::Switches(_) if !raw.trim_start().starts_with('{') => {
+                let mut object = serde_json::Map::new();
+                for pair in raw.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+                    let (key, state) = pair
+                        .split_once('=')
+                        .ok_or_else(|| anyhow!("Expected key=on|off, got: {}", pair))?;
+                    object.insert(key.trim().to_string(), json!(state.trim()));
+                }
+                Value::Object(object)
+            }
+            Kind::StringList if !raw.trim_start().starts_with('[') => json!(raw
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()),
+            _ => parse_json(self.name, raw)?,
+        };
+
+        self.validate(&value)?;
+        Ok(value)
+    }
+
+    /// Check a JSON value against the setting's schema
+    pub fn validate(&self, value: &Value) -> Result<()> {
+        let ok = match self.kind {
+            Kind::ReadOnly => return Err(anyhow!("{} is read-only", self.name)),
+            Kind::OnOff => matches!(value.as_str(), Some("on" | "off")),
+            Kind::Choice(choices) => value.as_str().is_some_and(|v| choices.contains(&v)),
+            Kind::Number(numbers) => value.as_u64().is_some_and(|n| numbers.contains(&n)),
+            Kind::Range(min, max) => value.as_u64().is_some_and(|n| (min..=max).contains(&n)),
+            Kind::Switches(keys) => value.as_object().is_some_and(|object| {
+                object.iter().all(|(k, v)| {
+                    keys.contains(&k.as_str()) && matches!(v.as_str(), Some("on" | "off"))
+                })
+            }),
+            Kind::StringList => value
+                .as_array()
+                .is_some_and(|items| items.iter().all(|i| i.is_string())),
+            Kind::Json => value.is_object(),
+        };
+
+        if ok {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Invalid value for {}: {} (expected {})",
+                self.name,
+                value,
+                self.kind.describe()
+            ))
+        }
+    }
+}
+
+/// Parse raw JSON for a setting, without falling back to a string
+pub fn parse_json(name: &str, raw: &str) -> Result<Value> {
+    serde_json::from_str(raw).map_err(|e| {
+        anyhow!(
+            "Invalid JSON value for {}: {} (quote strings, e.g. '\"{}\"')",
+            name,
+            e,
+            raw
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec_of(name: &str) -> &'static Spec {
+        find(name).unwrap()
+    }
+
+    #[test]
+    fn schema_is_sorted_and_unique() {
+        assert!(SCHEMA.windows(2).all(|w| w[0].name < w[1].name));
+    }
+
+    #[test]
+    fn choices_accept_only_listed_values() {
+        let cache_level = spec_of("cache_level");
+        assert_eq!(
+            cache_level.parse("aggressive").unwrap(),
+            json!("aggressive")
+        );
+        assert_eq!(cache_level.parse("\"basic\"").unwrap(), json!("basic"));
+        assert!(cache_level.parse("bypass").is_err());
+        assert!(cache_level.validate(&json!(1)).is_err());
+
+        let ssl = spec_of("ssl");
+        assert!(ssl.parse("strict").is_ok());
+        assert!(ssl.parse("full_strict").is_err());
+    }
+
+    #[test]
+    fn numbers_are_checked_against_their_set_or_range() {
+        let ttl = spec_of("browser_cache_ttl");
+        assert_eq!(ttl.parse("14400").unwrap(), json!(14400));
+        assert!(ttl.parse("14401").is_err());
+        assert!(ttl.parse("-1").is_err());
+
+        let upload = spec_of("max_upload");
+        assert_eq!(upload.parse("100").unwrap(), json!(100));
+        assert_eq!(upload.parse("500").unwrap(), json!(500));
+        assert!(upload.parse("99").is_err());
+        assert!(upload.parse("501").is_err());
+        assert!(upload.parse("lots").is_err());
+    }
+
+    #[test]
+    fn on_off_accepts_booleans() {
+        let https = spec_of("always_use_https");
+        assert_eq!(https.parse("on").unwrap(), json!("on"));
+        assert_eq!(https.parse("TRUE").unwrap(), json!("on"));
+        assert_eq!(https.parse("0").unwrap(), json!("off"));
+        assert!(https.parse("yes").is_err());
+        assert!(https.validate(&json!(true)).is_err());
+    }
+
+    #[test]
+    fn switches_and_lists() {
+        let minify = spec_of("minify");
+        assert_eq!(
+            minify.parse("css=on, js=off").unwrap(),
+            json!({"css": "on", "js": "off"})
+        );
+        assert!(minify.parse("css=yes").is_err());
+        assert!(minify.parse("svg=on").is_err());
+        assert!(minify.parse("css").is_err());
+
+        let ciphers = spec_of("ciphers");
+        assert_eq!(
+            ciphers.parse("AES128-SHA, AES256-SHA").unwrap(),
+            json!(["AES128-SHA", "AES256-SHA"])
+        );
+        assert_eq!(ciphers.parse("[]").unwrap(), json!([]));
+    }
+
+    #[test]
+    fn read_only_settings_cannot_be_set() {
+        let ddos = spec_of("advanced_ddos");
+        let err = ddos.parse("on").unwrap_err().to_string();
+        assert!(err.contains("read-only"), "{}", err);
+        assert!(ddos.validate(&json!("on")).is_err());
+        assert!(ddos.kind.values().is_empty());
+    }
+}
@@ -0,0 +1,563 @@
+//! Threshold rules on zone traffic for `analytics check`
+
+use std::process::Stdio;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use super::analytics::{auto_granularity, fetch_series, format_bytes, Point};
+use super::timerange::TimeRange;
+use super::ExitStatus;
+use crate::api::CloudflareClient;
+use crate::config::OutputFormat;
+use crate::output;
+use crate::output::chart;
+
+/// Metric names a rule can use, with aliases
+const METRICS: &[(&str, Metric)] = &[
+    ("requests", Metric::Requests),
+    ("bytes", Metric::Bytes),
+    ("bandwidth", Metric::Bytes),
+    ("cached_requests", Metric::CachedRequests),
+    ("threats", Metric::Threats),
+    ("errors", Metric::Errors),
+    ("5xx", Metric::Errors),
+    ("error_rate", Metric::ErrorRate),
+    ("cache_ratio", Metric::CacheRatio),
+    ("cache_hit", Metric::CacheRatio),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Metric {
+    Requests,
+    Bytes,
+    CachedRequests,
+    Threats,
+    /// Requests answered with a 5xx status
+    Errors,
+    /// 5xx share of requests, in percent
+    ErrorRate,
+    /// Cached share of requests, in percent
+    CacheRatio,
+}
+
+impl Metric {
+    fn is_percentage(self) -> bool {
+        matches!(self, Metric::ErrorRate | Metric::CacheRatio)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+/// A condition that counts as a violation when it holds, e.g. `error_rate > 5%`
+#[derive(Debug, Clone)]
+pub struct Rule {
+    text: String,
+    metric: Metric,
+    op: Op,
+    threshold: f64,
+    /// Compare a rate per this many seconds instead of the window total (`100/h`)
+    per: Option<i64>,
+}
+
+impl Rule {
+    /// Parse `<metric> <op> <value>`: ops >, >=, <, <=, ==, !=; values like 5%, 100/h,
+    /// 1.5K, 2M or 10GB
+    pub fn parse(raw: &str) -> Result<Rule> {
+        const OPERATORS: &[(&str, Op)] = &[
+            (">=", Op::Ge),
+            ("<=", Op::Le),
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            (">", Op::Gt),
+            ("<", Op::Lt),
+            ("=", Op::Eq),
+        ];
+        let invalid = || {
+            anyhow!(
+                "Invalid rule '{}': use <metric> <op> <value>, e.g. 'error_rate > 5%' or 'requests < 100/h'",
+                raw
+            )
+        };
+
+        let (metric, op, value) = OPERATORS
+            .iter()
+            .find_map(|(symbol, op)| {
+                raw.split_once(symbol)
+                    .map(|(metric, value)| (metric.trim(), *op, value.trim()))
+            })
+            .ok_or_else(invalid)?;
+
+        let metric = METRICS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(metric))
+            .map(|(_, metric)| *metric)
+            .ok_or_else(|| {
+                let names: Vec<&str> = METRICS.iter().map(|(name, _)| *name).collect();
+                anyhow!(
+                    "Unknown metric '{}' in rule '{}'; available: {}",
+                    metric,
+                    raw,
+                    names.join(", ")
+                )
+            })?;
+
+        let (value, per) = match value.rsplit_once('/') {
+            Some((value, unit)) => {
+                let seconds = match unit.trim() {
+                    "s" => 1,
+                    "m" | "min" => 60,
+                    "h" => 3600,
+                    "d" => 86400,
+                    _ => {
+                        return Err(anyhow!(
+                            "Invalid rate unit '/{}' in rule '{}': use /s, /m, /h or /d",
+                            unit,
+                            raw
+                        ))
+                    }
+                };
+                (value.trim(), Some(seconds))
+            }
+            None => (value, None),
+        };
+        if per.is_some() && metric.is_percentage() {
+            return Err(anyhow!(
+                "Rule '{}': percentages cannot be compared per time unit",
+                raw
+            ));
+        }
+
+        let threshold = if let Some(percent) = value.strip_suffix('%') {
+            if !metric.is_percentage() {
+                return Err(anyhow!(
+                    "Rule '{}': only error_rate and cache_ratio are percentages",
+                    raw
+                ));
+            }
+            percent.trim().parse::<f64>().ok()
+        } else {
+            parse_amount(value)
+        }
+        .filter(|v| v.is_finite())
+        .ok_or_else(invalid)?;
+
+        Ok(Rule {
+            text: raw.trim().to_string(),
+            metric,
+            op,
+            threshold,
+            per,
+        })
+    }
+
+    /// The observed value over the window
+    fn value(&self, totals: &Point, window: Duration) -> f64 {
+        let total = match self.metric {
+            Metric::Requests => totals.requests as f64,
+            Metric::Bytes => totals.bytes as f64,
+            Metric::CachedRequests => totals.cached_requests as f64,
+            Metric::Threats => totals.threats as f64,
+            Metric::Errors => totals.errors as f64,
+            Metric::ErrorRate => return totals.error_rate(),
+            Metric::CacheRatio => return totals.cache_ratio(),
+        };
+        match self.per {
+            Some(per) => total / window.num_seconds().max(1) as f64 * per as f64,
+            None => total,
+        }
+    }
+
+    fn holds(&self, value: f64) -> bool {
+        match self.op {
+            Op::Gt => value > self.threshold,
+            Op::Ge => value >= self.threshold,
+            Op::Lt => value < self.threshold,
+            Op::Le => value <= self.threshold,
+            Op::Eq => value == self.threshold,
+            Op::Ne => value != self.threshold,
+        }
+    }
+
+    fn display(&self, value: f64) -> String {
+        let amount = if self.metric.is_percentage() {
+            format!("{:.2}%", value)
+        } else if self.metric == Metric::Bytes {
+            format_bytes(value as u64)
+        } else if self.per.is_some() {
+            format!("{:.1}", value)
+        } else {
+            chart::human_count(value)
+        };
+        match self.per {
+            Some(60) => format!("{}/m", amount),
+            Some(3600) => format!("{}/h", amount),
+            Some(86400) => format!("{}/d", amount),
+            Some(_) => format!("{}/s", amount),
+            None => amount,
+        }
+    }
+}
+
+/// Parse a count or size: 1500, 1.5K, 2M, 3G, 512KB, 10GB
+fn parse_amount(raw: &str) -> Option<f64> {
+    const SUFFIXES: &[(&str, f64)] = &[
+        ("TB", 1024.0 * 1024.0 * 1024.0 * 1024.0),
+        ("GB", 1024.0 * 1024.0 * 1024.0),
+        ("MB", 1024.0 * 1024.0),
+        ("KB", 1024.0),
+        ("B", 1.0),
+        ("K", 1e3),
+        ("M", 1e6),
+        ("G", 1e9),
+    ];
+    let upper = raw.trim().to_ascii_uppercase();
+    for (suffix, factor) in SUFFIXES {
+        if let Some(number) = upper.strip_suffix(suffix) {
+            return number.trim().parse::<f64>().ok().map(|n| n * factor);
+        }
+    }
+    upper.parse().ok()
+}
+
+/// Result of one rule in one evaluation
+#[derive(Debug, Clone, Serialize)]
+struct Outcome {
+    rule: String,
+    value: f64,
+    display: String,
+    violated: bool,
+}
+
+/// Shortest `--every`; analytics are per minute, so polling faster only costs API quota
+pub const MIN_INTERVAL: Duration = Duration::seconds(60);
+
+/// How far behind real time the window ends: the newest minutes of analytics
+/// are still being ingested and would read low
+const INGESTION_LAG: Duration = Duration::minutes(3);
+
+/// How long a webhook request or notify command may take before it is abandoned
+const NOTIFY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Where to send alerts and recoveries
+pub struct Notify {
+    webhook: Option<String>,
+    command: Option<String>,
+    http: reqwest::Client,
+}
+
+impl Notify {
+    pub fn new(webhook: Option<String>, command: Option<String>) -> Result<Self> {
+        let http = reqwest::Client::builder().timeout(NOTIFY_TIMEOUT).build()?;
+        Ok(Self {
+            webhook,
+            command,
+            http,
+        })
+    }
+}
+
+/// Evaluate the rules over the last `window`, once or every `every` until interrupted
+///
+/// A single check exits with status 2 when any rule is violated. In a loop,
+/// notifications go out when a rule starts or stops being violated.
+pub async fn run(
+    client: &CloudflareClient,
+    zone: (&str, &str),
+    rules: &[Rule],
+    window: Duration,
+    every: Option<Duration>,
+    notify: &Notify,
+    format: &OutputFormat,
+) -> Result<()> {
+    let (zone_name, zone_id) = zone;
+
+    let Some(every) = every else {
+        let (range, outcomes) = evaluate(client, zone_id, rules, window).await?;
+        let violated: Vec<&Outcome> = outcomes.iter().filter(|o| o.violated).collect();
+        if matches!(format, OutputFormat::Json) {
+            output::print_json_pretty(&outcomes)?;
+        } else {
+            print_outcomes(&outcomes, &range);
+        }
+        for outcome in &violated {
+            send(notify, zone_name, outcome, "alert", &range).await;
+        }
+        if !violated.is_empty() {
+            output::error(&format!(
+                "{} of {} rule(s) violated",
+                violated.len(),
+                rules.len()
+            ));
+            return Err(ExitStatus(2).into());
+        }
+        output::success(&format!("All {} rule(s) passed", rules.len()));
+        return Ok(());
+    };
+
+    output::info(&format!(
+        "Checking {} rule(s) on {} every {}s over the last {}s (Ctrl-C to stop)",
+        rules.len(),
+        zone_name,
+        every.num_seconds(),
+        window.num_seconds()
+    ));
+    let mut violated = vec![false; rules.len()];
+    let interval = every
+        .to_std()
+        .map_err(|e| anyhow!("Invalid interval: {}", e))?;
+    loop {
+        match evaluate(client, zone_id, rules, window).await {
+            Ok((range, outcomes)) => {
+                for (outcome, was) in outcomes.iter().zip(violated.iter_mut()) {
+                    if outcome.violated == *was {
+                        continue;
+                    }
+                    *was = outcome.violated;
+                    let status = if outcome.violated {
+                        "alert"
+                    } else {
+                        "resolved"
+                    };
+                    let line = format!(
+                        "{} {}: {} ({})",
+                        Utc::now().format("%Y-%m-%d %H:%M:%S"),
+                        status.to_uppercase(),
+                        outcome.rule,
+                        outcome.display
+                    );
+                    if outcome.violated {
+                        output::error(&line);
+                    } else {
+                        output::success(&line);
+                    }
+                    send(notify, zone_name, outcome, status, &range).await;
+                }
+            }
+            Err(e) => output::warning(&format!("Check failed: {}", e)),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn evaluate(
+    client: &CloudflareClient,
+    zone_id: &str,
+    rules: &[Rule],
+    window: Duration,
+) -> Result<(TimeRange, Vec<Outcome>)> {
+    let range = lagged_window(Utc::now(), window);
+    let points = fetch_series(client, zone_id, &range, auto_granularity(&range)).await?;
+    let totals = Point::total(&points);
+
+    let outcomes = rules
+        .iter()
+        .map(|rule| {
+            let value = rule.value(&totals, window);
+            Outcome {
+                rule: rule.text.clone(),
+                value,
+                display: rule.display(value),
+                violated: rule.holds(value),
+            }
+        })
+        .collect();
+    Ok((range, outcomes))
+}
+
+/// The `window` ending INGESTION_LAG before `now`, on a whole minute
+fn lagged_window(now: DateTime<Utc>, window: Duration) -> TimeRange {
+    let end = now - INGESTION_LAG;
+    let until = end
+        - Duration::seconds(end.timestamp() % 60)
+        - Duration::nanoseconds(end.timestamp_subsec_nanos() as i64);
+    TimeRange {
+        since: until - window,
+        until,
+    }
+}
+
+fn print_outcomes(outcomes: &[Outcome], range: &TimeRange) {
+    output::table_header(&["STATUS", "VALUE", "RULE"]);
+    for outcome in outcomes {
+        let status = if outcome.violated {
+            "ALERT".red().bold().to_string()
+        } else {
+            "OK".green().to_string()
+        };
+        println!("{}\t{}\t{}", status, outcome.display, outcome.rule);
+    }
+    output::info(&format!(
+        "Window {} to {}",
+        range.since_str(),
+        range.until_str()
+    ));
+}
+
+/// Post to the webhook and run the command; failures are reported but not fatal
+async fn send(notify: &Notify, zone: &str, outcome: &Outcome, status: &str, range: &TimeRange) {
+    let text = format!(
+        "[{}] {}: {} (value {})",
+        status.to_uppercase(),
+        zone,
+        outcome.rule,
+        outcome.display
+    );
+    let payload = json!({
+        "text": text,
+        "status": status,
+        "zone": zone,
+        "rule": outcome.rule,
+        "value": outcome.value,
+        "since": range.since_str(),
+        "until": range.until_str(),
+    });
+
+    if let Some(url) = &notify.webhook {
+        let result = notify
+            .http
+            .post(url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+        if let Err(e) = result {
+            output::warning(&format!("Webhook failed: {}", e));
+        }
+    }
+
+    if let Some(command) = &notify.command {
+        // The payload goes to stdin, the main fields to the environment
+        let result = async {
+            let mut child = Command::new("sh")
+                .arg("-c")
+                .arg(command)
+                .env("CLI5_STATUS", status)
+                .env("CLI5_ZONE", zone)
+                .env("CLI5_RULE", &outcome.rule)
+                .env("CLI5_VALUE", &outcome.display)
+                .env("CLI5_TEXT", &text)
+                .stdin(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()?;
+            if let Some(mut stdin) = child.stdin.take() {
+                // The command may not read its input
+                let _ = stdin.write_all(payload.to_string().as_bytes()).await;
+            }
+            child.wait().await
+        };
+        let result = match tokio::time::timeout(NOTIFY_TIMEOUT, result).await {
+            Ok(result) => result,
+            Err(_) => {
+                output::warning(&format!(
+                    "Notify command killed after {}s",
+                    NOTIFY_TIMEOUT.as_secs()
+                ));
+                return;
+            }
+        };
+        match result {
+            Ok(status) if status.success() => {}
+            Ok(status) => output::warning(&format!("Notify command exited with {}", status)),
+            Err(e) => output::warning(&format!("Notify command failed: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(raw: &str) -> Rule {
+        Rule::parse(raw).unwrap()
+    }
+
+    #[test]
+    fn two_character_operators_win() {
+        let r = rule("requests >= 100");
+        assert_eq!(
+            (r.metric, r.op, r.threshold),
+            (Metric::Requests, Op::Ge, 100.0)
+        );
+        assert_eq!(rule("requests<=100").op, Op::Le);
+        assert_eq!(rule("threats != 0").op, Op::Ne);
+        assert_eq!(rule("threats == 0").op, Op::Eq);
+        assert_eq!(rule("threats = 0").op, Op::Eq);
+        assert_eq!(rule("requests > 100").op, Op::Gt);
+        assert_eq!(rule("5xx < 1").metric, Metric::Errors);
+
+        assert!(rule("requests >= 100").holds(100.0));
+        assert!(!rule("requests > 100").holds(100.0));
+        assert!(Rule::parse("requests 100").is_err());
+        assert!(Rule::parse("visits > 100").is_err());
+    }
+
+    #[test]
+    fn percentages_only_on_ratio_metrics() {
+        let r = rule("error_rate > 5%");
+        assert_eq!((r.metric, r.threshold), (Metric::ErrorRate, 5.0));
+        assert_eq!(rule("cache_hit < 80.5 %").threshold, 80.5);
+        assert!(Rule::parse("requests > 5%").is_err());
+        assert!(Rule::parse("error_rate > 5%/h").is_err());
+    }
+
+    #[test]
+    fn rates_compare_per_time_unit() {
+        let r = rule("requests < 100/h");
+        assert_eq!((r.threshold, r.per), (100.0, Some(3600)));
+        assert_eq!(rule("bytes > 1GB/d").per, Some(86400));
+        assert_eq!(rule("errors > 2/min").per, Some(60));
+        assert!(Rule::parse("requests > 1/w").is_err());
+
+        // 50 requests in half an hour is 100 per hour
+        let totals = Point {
+            requests: 50,
+            ..Point::default()
+        };
+        let value = r.value(&totals, Duration::minutes(30));
+        assert_eq!(value, 100.0);
+        assert_eq!(r.display(value), "100.0/h");
+        assert!(!r.holds(value));
+        assert_eq!(
+            rule("requests < 100").value(&totals, Duration::minutes(30)),
+            50.0
+        );
+    }
+
+    #[test]
+    fn counts_are_decimal_and_sizes_binary() {
+        assert_eq!(parse_amount("1500"), Some(1500.0));
+        assert_eq!(parse_amount("1.5K"), Some(1500.0));
+        assert_eq!(parse_amount("2m"), Some(2e6));
+        assert_eq!(parse_amount("3G"), Some(3e9));
+        assert_eq!(parse_amount("512KB"), Some(524288.0));
+        assert_eq!(parse_amount("10GB"), Some(10.0 * 1024.0 * 1024.0 * 1024.0));
+        assert_eq!(parse_amount("1 TB"), Some(1024f64.powi(4)));
+        assert_eq!(parse_amount("100B"), Some(100.0));
+        assert_eq!(parse_amount("lots"), None);
+        assert_eq!(rule("bytes > 10GB").threshold, 10.0 * 1024f64.powi(3));
+    }
+
+    #[test]
+    fn window_ends_before_the_incomplete_minutes() {
+        let now = DateTime::parse_from_rfc3339("2026-10-18T12:00:42.5Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let range = lagged_window(now, Duration::minutes(15));
+        assert_eq!(range.until.to_rfc3339(), "2026-10-18T11:57:00+00:00");
+        assert_eq!(range.since.to_rfc3339(), "2026-10-18T11:42:00+00:00");
+    }
+}
@@ -1,26 +1,45 @@
 //! Analytics command (GraphQL)
 
-use anyhow::Result;
-use chrono::{Duration, Utc};
+use std::path::PathBuf;
+
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use clap::{Args, Subcommand};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use serde_json::{json, Value};
 
-use crate::api::graphql;
+use super::alerts;
+use super::dashboard;
+use super::multizone::ZoneSelection;
+use super::report;
+use super::timerange::{parse_duration, TimeRange, TimeRangeArgs};
+use crate::api::graphql::{self, Arg, GroupQuery, Metric, Scope};
+use crate::api::graphql_schema;
 use crate::api::CloudflareClient;
-use crate::config::Config;
+use crate::config::{Config, OutputFormat};
 use crate::output;
+use crate::output::chart;
+
+pub(crate) const HTTP_ADAPTIVE: &str = "httpRequestsAdaptiveGroups";
+pub(crate) const FIREWALL_ADAPTIVE: &str = "firewallEventsAdaptiveGroups";
+const HTTP_HOURLY: &str = "httpRequests1hGroups";
+const HTTP_DAILY: &str = "httpRequests1dGroups";
+const HTTP_MINUTE: &str = "httpRequests1mGroups";
 
 #[derive(Args, Debug)]
 pub struct AnalyticsArgs {
     /// Zone name or ID
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     pub zone: Option<String>,
 
-    /// Time range: 1h, 6h, 24h, 7d, 30d
-    #[arg(short, long, default_value = "24h")]
-    pub since: String,
+    #[command(flatten)]
+    pub time: TimeRangeArgs,
 
     /// Number of results
-    #[arg(short, long, default_value = "20")]
+    #[arg(short, long, default_value = "20", global = true)]
     pub limit: u32,
 
     #[command(subcommand)]
@@ -56,98 +75,1386 @@ pub enum AnalyticsCommand {
     /// Hourly traffic summary
     Hourly,
 
+    /// Requests, bandwidth, cache hit ratio, threats and 5xx rate over time, as charts
+    Traffic {
+        /// Bucket size (default: 1m up to 3 hours, 1h up to 7 days, 1d beyond)
+        #[arg(long, value_parser = ["1m", "1h", "1d"])]
+        granularity: Option<String>,
+
+        /// Chart style
+        #[arg(long, default_value = "sparkline", value_parser = ["sparkline", "bars", "table"])]
+        chart: String,
+
+        /// Series drawn by --chart bars
+        #[arg(long, default_value = "requests", value_parser = ["requests", "bytes", "threats", "errors", "cache"])]
+        series: String,
+    },
+
+    /// Compare a window with an earlier one: traffic totals or a top-N report, with changes
+    Compare {
+        /// What to compare
+        #[arg(default_value = "traffic", value_parser = ["traffic", "top-urls", "top-ips", "top-countries", "errors", "cache", "bots", "firewall"])]
+        report: String,
+
+        /// Baseline: previous (the window right before), a duration to move the window
+        /// back by (7d: same window a week earlier), or a start time
+        #[arg(long, default_value = "previous")]
+        vs: String,
+    },
+
+    /// Summary of a period (traffic, cache, errors, top-N) as Markdown or HTML
+    Report {
+        /// HTML instead of Markdown
+        #[arg(long)]
+        html: bool,
+
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Check threshold rules on recent traffic; exit status 2 when any rule is violated
+    Check {
+        /// Violated when it holds: <metric> <op> <value>, e.g. 'error_rate > 5%', 'requests < 100/h';
+        /// repeatable. Metrics: requests, bytes, cached_requests, threats, errors, error_rate, cache_ratio
+        #[arg(long, required = true)]
+        rule: Vec<String>,
+
+        /// Evaluate the traffic of this long before now (90m, 1h, 1d)
+        #[arg(long, default_value = "1h")]
+        window: String,
+
+        /// Keep checking at this interval (at least 1m, e.g. 5m), notifying when a rule starts or stops failing
+        #[arg(long)]
+        every: Option<String>,
+
+        /// POST each alert as JSON to this URL (includes a Slack-compatible text field)
+        #[arg(long)]
+        webhook: Option<String>,
+
+        /// Run this shell command per alert, with the JSON payload on stdin and
+        /// CLI5_STATUS, CLI5_ZONE, CLI5_RULE, CLI5_VALUE and CLI5_TEXT set
+        #[arg(long)]
+        exec: Option<String>,
+    },
+
+    /// Full-screen live dashboard: traffic, top paths, countries, status codes, firewall events
+    Dashboard {
+        /// Seconds between refreshes
+        #[arg(long, default_value_t = 30, value_parser = clap::value_parser!(u64).range(5..))]
+        interval: u64,
+
+        /// Zones to switch between with z/Z (comma-separated names or IDs; default: all zones)
+        #[arg(long, value_delimiter = ',')]
+        zones: Vec<String>,
+    },
+
+    /// Group any adaptive dataset by arbitrary dimensions, with filters and metrics
+    Http {
+        #[command(flatten)]
+        group: GroupArgs,
+
+        /// Dataset to query
+        #[arg(long, default_value = HTTP_ADAPTIVE)]
+        dataset: String,
+    },
+
+    /// Account-level analytics: workers, r2, d1, tunnels, ai, or any account dataset
+    Account {
+        /// Preset (workers, r2, d1, tunnels, ai) or account dataset name
+        dataset: String,
+
+        #[command(flatten)]
+        group: GroupArgs,
+    },
+
+    /// Compare traffic totals of many zones (queried several zones per request)
+    Zones {
+        #[command(flatten)]
+        selection: ZoneSelection,
+    },
+
     /// Run a custom GraphQL query
     Query {
         /// GraphQL query string
-        query: String,
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        query: Option<String>,
+
+        /// Read the query from a file
+        #[arg(long)]
+        file: Option<PathBuf>,
+
+        /// Query variable as name=value (JSON values allowed); repeatable.
+        /// Declared $zoneTag, $since and $until default to --zone, --since and --until
+        #[arg(long)]
+        var: Vec<String>,
+
+        /// Only validate against the cached schema, without sending (works offline)
+        #[arg(long)]
+        check: bool,
+
+        /// Send without validating against the cached schema
+        #[arg(long, conflicts_with = "check")]
+        no_validate: bool,
+    },
+
+    /// Fetch the GraphQL schema and cache it for validation and discovery
+    Schema,
+
+    /// List datasets in the cached schema
+    Datasets {
+        /// Account-level datasets instead of zone datasets
+        #[arg(long)]
+        account: bool,
+
+        /// Only datasets whose name contains this text
+        #[arg(long)]
+        search: Option<String>,
+    },
+
+    /// List the dimensions, metrics and filters of a dataset (cached schema)
+    Fields {
+        /// Dataset name, e.g. httpRequestsAdaptiveGroups
+        dataset: String,
     },
 }
 
-fn parse_since(since: &str) -> String {
-    let duration = match since {
-        "1h" => Duration::hours(1),
-        "6h" => Duration::hours(6),
-        "24h" => Duration::hours(24),
-        "7d" => Duration::days(7),
-        "30d" => Duration::days(30),
-        _ => Duration::hours(24),
-    };
+/// A top-N report: dataset, ordering, query builder and the dimension it is about
+pub(crate) struct Preset {
+    pub name: &'static str,
+    pub dataset: &'static str,
+    pub order_by: &'static str,
+    pub build: fn(&str, &str, &str, u32) -> Result<String>,
+    pub main_dim: &'static str,
+}
+
+pub(crate) const PRESETS: &[Preset] = &[
+    Preset {
+        name: "top-urls",
+        dataset: HTTP_ADAPTIVE,
+        order_by: "count_DESC",
+        build: graphql::top_urls_query,
+        main_dim: "clientRequestPath",
+    },
+    Preset {
+        name: "top-ips",
+        dataset: HTTP_ADAPTIVE,
+        order_by: "count_DESC",
+        build: graphql::top_ips_query,
+        main_dim: "clientIP",
+    },
+    Preset {
+        name: "top-countries",
+        dataset: HTTP_ADAPTIVE,
+        order_by: "count_DESC",
+        build: graphql::top_countries_query,
+        main_dim: "clientCountryName",
+    },
+    Preset {
+        name: "errors",
+        dataset: HTTP_ADAPTIVE,
+        order_by: "count_DESC",
+        build: graphql::errors_query,
+        main_dim: "edgeResponseStatus",
+    },
+    Preset {
+        name: "cache",
+        dataset: HTTP_ADAPTIVE,
+        order_by: "count_DESC",
+        build: graphql::cache_status_query,
+        main_dim: "cacheStatus",
+    },
+    Preset {
+        name: "bandwidth",
+        dataset: HTTP_ADAPTIVE,
+        order_by: "sum_edgeResponseBytes_DESC",
+        build: graphql::bandwidth_query,
+        main_dim: "edgeResponseStatus",
+    },
+    Preset {
+        name: "bots",
+        dataset: HTTP_ADAPTIVE,
+        order_by: "count_DESC",
+        build: graphql::bots_query,
+        main_dim: "clientDeviceType",
+    },
+    Preset {
+        name: "firewall",
+        dataset: FIREWALL_ADAPTIVE,
+        order_by: "count_DESC",
+        build: graphql::firewall_events_query,
+        main_dim: "action",
+    },
+];
+
+pub(crate) fn preset(name: &str) -> Result<&'static Preset> {
+    PRESETS
+        .iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| anyhow!("Unknown report: {}", name))
+}
+
+/// Grouping, filters and metrics of a composed query
+#[derive(Args, Debug)]
+pub struct GroupArgs {
+    /// Dimensions to group by (comma-separated), e.g. clientCountryName,edgeResponseStatus
+    #[arg(long, value_delimiter = ',')]
+    by: Vec<String>,
+
+    /// Filter: field=value, field!=value, field>=400, field~/api/% (like), field_in=a,b; repeatable
+    #[arg(long)]
+    filter: Vec<String>,
+
+    /// Metrics (comma-separated): count, sum.edgeResponseBytes, avg.sampleInterval, ... (default: count)
+    #[arg(long, value_delimiter = ',')]
+    metric: Vec<String>,
+
+    /// Sort order, e.g. count_DESC or sum_edgeResponseBytes_ASC (default: first metric, descending)
+    #[arg(long)]
+    order_by: Option<String>,
+
+    /// Print the generated GraphQL query instead of running it
+    #[arg(long)]
+    show_query: bool,
+}
+
+/// An account dataset with useful default dimensions, metrics and ordering
+struct AccountPreset {
+    name: &'static str,
+    dataset: &'static str,
+    dimensions: &'static [&'static str],
+    metrics: &'static [&'static str],
+}
+
+const ACCOUNT_PRESETS: &[AccountPreset] = &[
+    AccountPreset {
+        name: "workers",
+        dataset: "workersInvocationsAdaptive",
+        dimensions: &["scriptName", "status"],
+        metrics: &[
+            "sum.requests",
+            "sum.errors",
+            "sum.subrequests",
+            "quantiles.cpuTimeP50",
+        ],
+    },
+    AccountPreset {
+        name: "r2",
+        dataset: "r2OperationsAdaptiveGroups",
+        dimensions: &["bucketName", "actionType"],
+        metrics: &["sum.requests"],
+    },
+    AccountPreset {
+        name: "d1",
+        dataset: "d1AnalyticsAdaptiveGroups",
+        dimensions: &["databaseId"],
+        metrics: &[
+            "sum.readQueries",
+            "sum.writeQueries",
+            "sum.rowsRead",
+            "sum.rowsWritten",
+        ],
+    },
+    AccountPreset {
+        name: "tunnels",
+        dataset: "magicTransitTunnelTrafficAdaptiveGroups",
+        dimensions: &["tunnelName", "direction"],
+        metrics: &["sum.bits", "sum.packets"],
+    },
+    AccountPreset {
+        name: "ai",
+        dataset: "aiInferenceAdaptiveGroups",
+        dimensions: &["modelId"],
+        metrics: &["count", "sum.totalInputTokens", "sum.totalOutputTokens"],
+    },
+];
+
+/// How many zones one `zoneTag_in` query covers
+const MAX_ZONES_PER_QUERY: usize = 10;
 
-    (Utc::now() - duration)
-        .format("%Y-%m-%dT%H:%M:%SZ")
-        .to_string()
+impl AnalyticsCommand {
+    /// Commands that only read the cached schema
+    pub fn is_offline(&self) -> bool {
+        matches!(
+            self,
+            AnalyticsCommand::Datasets { .. }
+                | AnalyticsCommand::Fields { .. }
+                | AnalyticsCommand::Query { check: true, .. }
+        )
+    }
 }
 
 pub async fn execute(config: &Config, args: AnalyticsArgs) -> Result<()> {
+    match &args.command {
+        AnalyticsCommand::Schema => return refresh_schema(config).await,
+        AnalyticsCommand::Datasets { account, search } => {
+            return list_datasets(*account, search.as_deref())
+        }
+        AnalyticsCommand::Fields { dataset } => return list_fields(dataset),
+        AnalyticsCommand::Query {
+            query,
+            file,
+            var,
+            check,
+            no_validate,
+        } => {
+            let text = match (query, file) {
+                (Some(query), _) => query.clone(),
+                (None, Some(path)) => std::fs::read_to_string(path)
+                    .map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))?,
+                (None, None) => unreachable!(), // Enforced by clap
+            };
+            return run_query(config, &args, &text, var, *check, *no_validate).await;
+        }
+        AnalyticsCommand::Account { dataset, group } => {
+            let range = args.time.resolve()?;
+            let client = CloudflareClient::new(config.clone())?;
+            let account_id = get_account_id(&client).await?;
+
+            let preset = ACCOUNT_PRESETS.iter().find(|p| p.name == dataset);
+            let (dataset, dimensions, metrics) = match preset {
+                Some(preset) => (preset.dataset, preset.dimensions, preset.metrics),
+                None => (dataset.as_str(), &[][..], &["count"][..]),
+            };
+            let query = group_query(
+                Scope::Account(account_id),
+                dataset,
+                group,
+                (dimensions, metrics),
+                args.limit,
+            )?;
+            return run_groups(&client, config, &range, query, group.show_query).await;
+        }
+        AnalyticsCommand::Zones { selection } => {
+            let range = args.time.resolve()?;
+            let client = CloudflareClient::new(config.clone())?;
+            return compare_zones(&client, config, &range, selection).await;
+        }
+        AnalyticsCommand::Dashboard { interval, zones } => {
+            let client = CloudflareClient::new(config.clone())?;
+            let start = config.resolve_zone(args.zone.as_deref()).ok();
+            return dashboard::run(&client, start, zones, &args.time, *interval, args.limit).await;
+        }
+        _ => {}
+    }
+
+    let range = args.time.resolve()?;
     let client = CloudflareClient::new(config.clone())?;
     let zone = config.resolve_zone(args.zone.as_deref())?;
     let zone_id = client.resolve_zone_id(&zone).await?;
-    let since = parse_since(&args.since);
+    let limit = args.limit;
+    let bandwidth = matches!(args.command, AnalyticsCommand::Bandwidth);
+
+    let name = match args.command {
+        AnalyticsCommand::TopUrls => "top-urls",
+        AnalyticsCommand::TopIps => "top-ips",
+        AnalyticsCommand::TopCountries => "top-countries",
+        AnalyticsCommand::Errors => "errors",
+        AnalyticsCommand::Cache => "cache",
+        AnalyticsCommand::Bandwidth => "bandwidth",
+        AnalyticsCommand::Bots => "bots",
+        AnalyticsCommand::Firewall => "firewall",
 
-    match args.command {
-        AnalyticsCommand::TopUrls => {
-            let query = graphql::top_urls_query(&zone_id, &since, args.limit);
-            let response = client.graphql(&query, None).await?;
-            print_analytics_response(&response, "clientRequestPath")?;
+        AnalyticsCommand::Hourly => {
+            let response = query_range(
+                &client,
+                &range,
+                HTTP_HOURLY,
+                "datetime_ASC",
+                None,
+                |since, until| graphql::hourly_traffic_query(&zone_id, since, until),
+            )
+            .await?;
+            return print_hourly_response(&response);
         }
 
-        AnalyticsCommand::TopIps => {
-            let query = graphql::top_ips_query(&zone_id, &since, args.limit);
-            let response = client.graphql(&query, None).await?;
-            print_analytics_response(&response, "clientIP")?;
+        AnalyticsCommand::Compare { report, vs } => {
+            let baseline = range.baseline(&vs)?;
+            return report::compare(
+                &client,
+                &zone_id,
+                &report,
+                (&range, &baseline),
+                limit,
+                &config.output_format,
+            )
+            .await;
         }
 
-        AnalyticsCommand::TopCountries => {
-            let query = graphql::top_countries_query(&zone_id, &since, args.limit);
-            let response = client.graphql(&query, None).await?;
-            print_analytics_response(&response, "clientCountryName")?;
+        AnalyticsCommand::Report { html, output } => {
+            return report::report(
+                &client,
+                (&zone, &zone_id),
+                &range,
+                limit,
+                html,
+                output.as_deref(),
+            )
+            .await;
         }
 
-        AnalyticsCommand::Errors => {
-            let query = graphql::errors_query(&zone_id, &since, args.limit);
-            let response = client.graphql(&query, None).await?;
-            print_analytics_response(&response, "edgeResponseStatus")?;
+        AnalyticsCommand::Check {
+            rule,
+            window,
+            every,
+            webhook,
+            exec,
+        } => {
+            let rules = rule
+                .iter()
+                .map(|r| alerts::Rule::parse(r))
+                .collect::<Result<Vec<_>>>()?;
+            let window = parse_duration(&window)
+                .ok_or_else(|| anyhow!("Invalid --window '{}': use e.g. 90m, 1h or 1d", window))?;
+            let every = every
+                .map(|every| {
+                    let parsed = parse_duration(&every).ok_or_else(|| {
+                        anyhow!("Invalid --every '{}': use e.g. 1m, 5m or 1h", every)
+                    })?;
+                    if parsed < alerts::MIN_INTERVAL {
+                        return Err(anyhow!(
+                            "--every must be at least {}s",
+                            alerts::MIN_INTERVAL.num_seconds()
+                        ));
+                    }
+                    Ok(parsed)
+                })
+                .transpose()?;
+            let notify = alerts::Notify::new(webhook, exec)?;
+            return alerts::run(
+                &client,
+                (&zone, &zone_id),
+                &rules,
+                window,
+                every,
+                &notify,
+                &config.output_format,
+            )
+            .await;
         }
 
-        AnalyticsCommand::Cache => {
-            let query = graphql::cache_status_query(&zone_id, &since, args.limit);
-            let response = client.graphql(&query, None).await?;
-            print_analytics_response(&response, "cacheStatus")?;
+        AnalyticsCommand::Traffic {
+            granularity,
+            chart,
+            series,
+        } => {
+            let granularity = granularity.unwrap_or_else(|| auto_granularity(&range).to_string());
+            let points = fetch_series(&client, &zone_id, &range, &granularity).await?;
+            if matches!(config.output_format, OutputFormat::Json) {
+                return output::print_json_pretty(&points);
+            }
+            match chart.as_str() {
+                "bars" => print_traffic_bars(&points, &granularity, &series),
+                "table" => print_traffic_table(&points, &granularity),
+                _ => print_traffic_sparklines(&points, &range, &granularity),
+            }
+            return Ok(());
         }
 
-        AnalyticsCommand::Bandwidth => {
-            let query = graphql::bandwidth_query(&zone_id, &since, args.limit);
-            let response = client.graphql(&query, None).await?;
-            print_bandwidth_response(&response)?;
+        AnalyticsCommand::Http { group, dataset } => {
+            let query = group_query(
+                Scope::Zone(zone_id),
+                &dataset,
+                &group,
+                (&[], &["count"]),
+                limit,
+            )?;
+            return run_groups(&client, config, &range, query, group.show_query).await;
         }
 
-        AnalyticsCommand::Bots => {
-            let query = graphql::bots_query(&zone_id, &since, args.limit);
-            let response = client.graphql(&query, None).await?;
-            print_analytics_response(&response, "clientDeviceType")?;
+        AnalyticsCommand::Query { .. }
+        | AnalyticsCommand::Account { .. }
+        | AnalyticsCommand::Zones { .. }
+        | AnalyticsCommand::Dashboard { .. }
+        | AnalyticsCommand::Schema
+        | AnalyticsCommand::Datasets { .. }
+        | AnalyticsCommand::Fields { .. } => unreachable!(), // Handled above
+    };
+
+    let preset = preset(name)?;
+    let response = query_range(
+        &client,
+        &range,
+        preset.dataset,
+        preset.order_by,
+        Some(limit as usize),
+        |since, until| (preset.build)(&zone_id, since, until, limit),
+    )
+    .await?;
+
+    if preset.dataset == FIREWALL_ADAPTIVE {
+        print_firewall_response(&response)
+    } else if bandwidth {
+        print_bandwidth_response(&response)
+    } else {
+        print_analytics_response(&response, preset.main_dim)
+    }
+}
+
+/// Build a composed query; `defaults` are the dimensions and metrics used when none are given
+fn group_query(
+    scope: Scope,
+    dataset: &str,
+    group: &GroupArgs,
+    defaults: (&[&str], &[&str]),
+    limit: u32,
+) -> Result<GroupQuery> {
+    let mut query = GroupQuery::new(scope, dataset);
+    query.limit = limit;
+    query.dimensions = if group.by.is_empty() {
+        defaults.0.iter().map(|d| d.to_string()).collect()
+    } else {
+        group.by.clone()
+    };
+    let metrics: Vec<String> = if group.metric.is_empty() {
+        defaults.1.iter().map(|m| m.to_string()).collect()
+    } else {
+        group.metric.clone()
+    };
+    query.metrics = metrics
+        .iter()
+        .map(|m| Metric::parse(m))
+        .collect::<Result<_>>()?;
+    for raw in &group.filter {
+        query.filter.push(parse_filter(raw)?);
+    }
+    query.order_by = vec![match &group.order_by {
+        Some(order_by) => order_by.clone(),
+        None => query
+            .metrics
+            .first()
+            .ok_or_else(|| anyhow!("At least one --metric is required"))?
+            .order(true),
+    }];
+    Ok(query)
+}
+
+/// Run a composed query over the range (or only print it), checked against the cached schema
+async fn run_groups(
+    client: &CloudflareClient,
+    config: &Config,
+    range: &TimeRange,
+    query: GroupQuery,
+    show_query: bool,
+) -> Result<()> {
+    let mut first = query.clone();
+    first.window(&range.since_str(), &range.until_str());
+    let rendered = first.render()?;
+    if show_query {
+        println!("{}", rendered);
+        return Ok(());
+    }
+    if let Some(schema) = graphql_schema::load_cached()? {
+        let errors = schema.validate(&rendered, &Default::default());
+        if !errors.is_empty() {
+            for error in &errors {
+                output::error(error);
+            }
+            return Err(anyhow!(
+                "Query does not match the cached schema (see `cli5 analytics fields {}`)",
+                query.dataset
+            ));
         }
+    }
+
+    let response = query_range(
+        client,
+        range,
+        &query.dataset,
+        &query.order_by[0],
+        Some(query.limit as usize),
+        |since, until| {
+            let mut chunk = query.clone();
+            chunk.window(since, until);
+            chunk.render()
+        },
+    )
+    .await?;
+
+    print_groups(&response, &query, &config.output_format)
+}
+
+/// Traffic totals of one zone over the window
+#[derive(Debug, Default, Serialize)]
+struct ZoneTotals {
+    zone: String,
+    requests: u64,
+    bytes: u64,
+    cached_requests: u64,
+    threats: u64,
+    page_views: u64,
+}
+
+/// Print a per-zone comparison of traffic totals, with a total row
+async fn compare_zones(
+    client: &CloudflareClient,
+    config: &Config,
+    range: &TimeRange,
+    selection: &ZoneSelection,
+) -> Result<()> {
+    let zones = selection.resolve(client).await?;
+    // Hourly data for short windows, daily data (whole days) for longer ones
+    let (dataset, order_by) = if range.until - range.since <= chrono::Duration::days(3) {
+        (HTTP_HOURLY, "datetime_ASC")
+    } else {
+        (HTTP_DAILY, "date_ASC")
+    };
+
+    let mut totals: Vec<ZoneTotals> = Vec::with_capacity(zones.len());
+    let batches: Vec<_> = zones.chunks(MAX_ZONES_PER_QUERY).collect();
+    for (done, batch) in batches.iter().enumerate() {
+        let ids: Vec<String> = batch.iter().map(|z| z.id.clone()).collect();
+        let response = query_range(client, range, dataset, order_by, None, |since, until| {
+            graphql::zones_traffic_query(&ids, dataset, since, until)
+        })
+        .await?;
+
+        for zone in batch.iter() {
+            let groups = response
+                .pointer("/data/viewer/zones")
+                .and_then(|z| z.as_array())
+                .and_then(|entries| {
+                    entries
+                        .iter()
+                        .find(|e| e.get("zoneTag").and_then(|t| t.as_str()) == Some(&zone.id))
+                })
+                .and_then(|e| e.get(dataset))
+                .and_then(|g| g.as_array())
+                .cloned()
+                .unwrap_or_default();
 
-        AnalyticsCommand::Firewall => {
-            let query = graphql::firewall_events_query(&zone_id, &since, args.limit);
-            let response = client.graphql(&query, None).await?;
-            print_firewall_response(&response)?;
+            let sum = |field: &str| -> u64 {
+                groups
+                    .iter()
+                    .filter_map(|g| g.pointer(&format!("/sum/{}", field)))
+                    .filter_map(|v| v.as_u64())
+                    .sum()
+            };
+            totals.push(ZoneTotals {
+                zone: zone.name.clone(),
+                requests: sum("requests"),
+                bytes: sum("bytes"),
+                cached_requests: sum("cachedRequests"),
+                threats: sum("threats"),
+                page_views: sum("pageViews"),
+            });
         }
+        if batches.len() > 1 {
+            output::progress(done + 1, batches.len(), "zone batches");
+        }
+    }
+    totals.sort_by(|a, b| b.requests.cmp(&a.requests).then(a.zone.cmp(&b.zone)));
 
-        AnalyticsCommand::Hourly => {
-            let query = graphql::hourly_traffic_query(&zone_id, &since);
-            let response = client.graphql(&query, None).await?;
-            print_hourly_response(&response)?;
+    if matches!(config.output_format, OutputFormat::Json) {
+        return output::print_json_pretty(&totals);
+    }
+
+    let all = ZoneTotals {
+        zone: "TOTAL".to_string(),
+        requests: totals.iter().map(|t| t.requests).sum(),
+        bytes: totals.iter().map(|t| t.bytes).sum(),
+        cached_requests: totals.iter().map(|t| t.cached_requests).sum(),
+        threats: totals.iter().map(|t| t.threats).sum(),
+        page_views: totals.iter().map(|t| t.page_views).sum(),
+    };
+    let percent = |part: u64, whole: u64| {
+        if whole == 0 {
+            "-".to_string()
+        } else {
+            format!("{:.1}%", part as f64 * 100.0 / whole as f64)
+        }
+    };
+
+    output::table_header(&[
+        "ZONE",
+        "REQUESTS",
+        "SHARE",
+        "BANDWIDTH",
+        "CACHED",
+        "THREATS",
+        "PAGEVIEWS",
+    ]);
+    for row in totals.iter().chain(std::iter::once(&all)) {
+        let line = format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            row.zone,
+            row.requests,
+            percent(row.requests, all.requests),
+            format_bytes(row.bytes),
+            percent(row.cached_requests, row.requests),
+            row.threats,
+            row.page_views
+        );
+        if std::ptr::eq(row, &all) {
+            println!("{}", line.bold());
+        } else {
+            println!("{}", line);
+        }
+    }
+    output::info(&format!(
+        "{} zones, {} to {} ({})",
+        totals.len(),
+        range.since_str(),
+        range.until_str(),
+        dataset
+    ));
+    Ok(())
+}
+
+/// Run a custom query, filling in common variables and validating it first
+async fn run_query(
+    config: &Config,
+    args: &AnalyticsArgs,
+    query: &str,
+    pairs: &[String],
+    check: bool,
+    no_validate: bool,
+) -> Result<()> {
+    let mut variables = graphql_schema::parse_variables(pairs, query)?;
+    let missing: Vec<String> = graphql_schema::declared_variables(query)
+        .into_iter()
+        .filter(|name| !variables.contains_key(name))
+        .collect();
+    let wants = |name: &str| missing.iter().any(|m| m == name);
+
+    if wants("since") || wants("until") {
+        let range = args.time.resolve()?;
+        if wants("since") {
+            variables.insert("since".into(), json!(range.since_str()));
+        }
+        if wants("until") {
+            variables.insert("until".into(), json!(range.until_str()));
+        }
+    }
+    if wants("zoneTag") {
+        // Only a placeholder is needed to validate offline
+        let zone_tag = if check {
+            "<zone>".to_string()
+        } else {
+            let client = CloudflareClient::new(config.clone())?;
+            let zone = config.resolve_zone(args.zone.as_deref())?;
+            client.resolve_zone_id(&zone).await?
+        };
+        variables.insert("zoneTag".into(), json!(zone_tag));
+    }
+
+    if !no_validate {
+        match graphql_schema::load_cached()? {
+            Some(schema) => {
+                let errors = schema.validate(query, &variables);
+                if !errors.is_empty() {
+                    for error in &errors {
+                        output::error(error);
+                    }
+                    return Err(anyhow!(
+                        "Query failed validation against the cached schema ({} problem(s)); use --no-validate to send anyway",
+                        errors.len()
+                    ));
+                }
+                if check {
+                    output::success(&format!(
+                        "Query is valid (schema cached {})",
+                        schema.fetched_at.format("%Y-%m-%d")
+                    ));
+                    return Ok(());
+                }
+            }
+            None if check => {
+                graphql_schema::require_cached()?;
+            }
+            None => {}
+        }
+    }
+
+    let client = CloudflareClient::new(config.clone())?;
+    let response = client
+        .graphql(query, Some(Value::Object(variables)))
+        .await?;
+    output::print_output(&response, &config.output_format)
+}
+
+async fn refresh_schema(config: &Config) -> Result<()> {
+    let client = CloudflareClient::new(config.clone())?;
+    let schema = graphql_schema::refresh(&client).await?;
+
+    output::success(&format!(
+        "Cached GraphQL schema: {} types, {} zone datasets, {} account datasets",
+        schema.type_count(),
+        schema.datasets("zones").len(),
+        schema.datasets("accounts").len()
+    ));
+    output::info(&format!(
+        "Saved to {}",
+        graphql_schema::cache_path()?.display()
+    ));
+    Ok(())
+}
+
+fn list_datasets(account: bool, search: Option<&str>) -> Result<()> {
+    let schema = graphql_schema::require_cached()?;
+    let scope = if account { "accounts" } else { "zones" };
+    let search = search.map(|s| s.to_lowercase());
+
+    let datasets: Vec<_> = schema
+        .datasets(scope)
+        .into_iter()
+        .filter(|d| {
+            search
+                .as_ref()
+                .is_none_or(|s| d.name.to_lowercase().contains(s))
+        })
+        .collect();
+
+    output::table_header(&["DATASET", "DESCRIPTION"]);
+    for dataset in &datasets {
+        println!(
+            "{}\t{}",
+            dataset.name.cyan(),
+            summary(dataset.description.as_deref())
+        );
+    }
+    output::info(&format!("Total: {} datasets", datasets.len()));
+    Ok(())
+}
+
+fn list_fields(name: &str) -> Result<()> {
+    let schema = graphql_schema::require_cached()?;
+    let (scope, dataset) = schema
+        .dataset(name)
+        .ok_or_else(|| anyhow!("Unknown dataset: {} (see `cli5 analytics datasets`)", name))?;
+
+    println!("{} ({})", dataset.name.bold(), scope);
+    if let Some(description) = &dataset.description {
+        println!("{}", summary(Some(description)));
+    }
+
+    for (block, fields) in schema.dataset_fields(dataset) {
+        println!();
+        let title = match block.as_str() {
+            "" => "Fields".to_string(),
+            "dimensions" => "Dimensions (--by)".to_string(),
+            other => format!("Metrics: {} (--metric {}.<field>)", other, other),
+        };
+        println!("{}", title.cyan());
+        for field in fields {
+            println!("  {}\t{}", field.name, field.type_ref.display().dimmed());
+        }
+    }
+
+    let filters = schema.filter_fields(dataset);
+    if !filters.is_empty() {
+        println!();
+        println!("{}", "Filters (--filter)".cyan());
+        let names: Vec<&str> = filters.iter().map(|f| f.name.as_str()).collect();
+        for line in names.chunks(4) {
+            println!("  {}", line.join("  "));
         }
+    }
+    Ok(())
+}
+
+/// First line of a description, shortened for tables
+fn summary(description: Option<&str>) -> String {
+    let line = description
+        .and_then(|d| d.lines().map(str::trim).find(|l| !l.is_empty()))
+        .unwrap_or("-");
+    if line.chars().count() > 80 {
+        format!("{}…", line.chars().take(79).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// Traffic of one time bucket
+#[derive(Debug, Clone, Default, Serialize)]
+pub(crate) struct Point {
+    pub time: DateTime<Utc>,
+    pub requests: u64,
+    pub bytes: u64,
+    pub cached_requests: u64,
+    pub cached_bytes: u64,
+    pub threats: u64,
+    /// Requests answered with a 5xx status
+    pub errors: u64,
+}
+
+impl Point {
+    /// Sum of all points, timed at the first one
+    pub fn total(points: &[Point]) -> Point {
+        points.iter().fold(
+            Point {
+                time: points.first().map_or_else(Utc::now, |p| p.time),
+                ..Point::default()
+            },
+            |mut sum, p| {
+                sum.requests += p.requests;
+                sum.bytes += p.bytes;
+                sum.cached_requests += p.cached_requests;
+                sum.cached_bytes += p.cached_bytes;
+                sum.threats += p.threats;
+                sum.errors += p.errors;
+                sum
+            },
+        )
+    }
+
+    /// Share of requests served from cache, in percent
+    pub fn cache_ratio(&self) -> f64 {
+        percentage(self.cached_requests, self.requests)
+    }
+
+    /// Share of requests answered with a 5xx status, in percent
+    pub fn error_rate(&self) -> f64 {
+        percentage(self.errors, self.requests)
+    }
+}
+
+fn percentage(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
+
+/// The coarsest bucket size that still gives a useful number of points for the window
+pub(crate) fn auto_granularity(range: &TimeRange) -> &'static str {
+    let length = range.until - range.since;
+    if length <= Duration::hours(3) {
+        "1m"
+    } else if length <= Duration::days(7) {
+        "1h"
+    } else {
+        "1d"
+    }
+}
+
+/// Dataset and bucket length of a --granularity value
+fn granularity_dataset(granularity: &str) -> Result<(&'static str, Duration)> {
+    match granularity {
+        "1m" => Ok((HTTP_MINUTE, Duration::minutes(1))),
+        "1h" => Ok((HTTP_HOURLY, Duration::hours(1))),
+        "1d" => Ok((HTTP_DAILY, Duration::days(1))),
+        other => Err(anyhow!("Invalid granularity '{}': use 1m, 1h or 1d", other)),
+    }
+}
+
+/// Fetch a zone's traffic over the range, one point per bucket
+///
+/// Buckets without traffic are missing from the API response and are filled
+/// in with zeros, so the points are evenly spaced.
+pub(crate) async fn fetch_series(
+    client: &CloudflareClient,
+    zone_id: &str,
+    range: &TimeRange,
+    granularity: &str,
+) -> Result<Vec<Point>> {
+    let (dataset, step) = granularity_dataset(granularity)?;
+    let dimension = graphql::time_dimension(dataset);
+    let response = query_range(
+        client,
+        range,
+        dataset,
+        &format!("{}_ASC", dimension),
+        None,
+        |since, until| graphql::traffic_query(zone_id, dataset, since, until),
+    )
+    .await?;
+
+    let groups = response
+        .pointer(&format!("/data/viewer/zones/0/{}", dataset))
+        .and_then(|g| g.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut buckets: BTreeMap<DateTime<Utc>, Point> = BTreeMap::new();
+    for group in &groups {
+        let Some(time) = group
+            .pointer(&format!("/dimensions/{}", dimension))
+            .and_then(|t| t.as_str())
+            .and_then(parse_bucket_time)
+        else {
+            continue;
+        };
+        let sum = |field: &str| {
+            group
+                .pointer(&format!("/sum/{}", field))
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0)
+        };
+        let errors: u64 = group
+            .pointer("/sum/responseStatusMap")
+            .and_then(|m| m.as_array())
+            .into_iter()
+            .flatten()
+            .filter(|s| {
+                s.get("edgeResponseStatus")
+                    .and_then(|v| v.as_u64())
+                    .is_some_and(|status| (500..600).contains(&status))
+            })
+            .filter_map(|s| s.get("requests").and_then(|v| v.as_u64()))
+            .sum();
+
+        let point = buckets.entry(time).or_insert_with(|| Point {
+            time,
+            ..Point::default()
+        });
+        point.requests += sum("requests");
+        point.bytes += sum("bytes");
+        point.cached_requests += sum("cachedRequests");
+        point.cached_bytes += sum("cachedBytes");
+        point.threats += sum("threats");
+        point.errors += errors;
+    }
+
+    let step_seconds = step.num_seconds();
+    let first = range.since.timestamp();
+    let mut time =
+        DateTime::from_timestamp(first - first.rem_euclid(step_seconds), 0).unwrap_or(range.since);
+    let mut points = Vec::new();
+    while time < range.until {
+        points.push(buckets.remove(&time).unwrap_or(Point {
+            time,
+            ..Point::default()
+        }));
+        time += step;
+    }
+    // Anything the API returned outside the aligned buckets
+    points.extend(buckets.into_values());
+    points.sort_by_key(|p| p.time);
+    Ok(points)
+}
+
+/// Parse a `datetimeMinute`, `datetime` or `date` dimension value
+fn parse_bucket_time(raw: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(raw)
+        .map(|t| t.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .map(|t| t.and_utc())
+        })
+}
+
+/// Label of a bucket, as precise as the granularity
+fn bucket_label(time: DateTime<Utc>, granularity: &str) -> String {
+    let format = match granularity {
+        "1d" => "%Y-%m-%d",
+        "1h" => "%m-%d %H:00",
+        _ => "%m-%d %H:%M",
+    };
+    time.format(format).to_string()
+}
+
+fn print_traffic_sparklines(points: &[Point], range: &TimeRange, granularity: &str) {
+    if points.is_empty() {
+        output::info("No traffic in this time range");
+        return;
+    }
+    const LABEL: usize = 10;
+    const STATS: usize = 30;
+    let width = chart::terminal_width()
+        .saturating_sub(LABEL + STATS + 2)
+        .max(10);
+
+    let total = |value: fn(&Point) -> u64| points.iter().map(value).sum::<u64>();
+    let requests = total(|p| p.requests);
+    let peak = |values: &[f64]| values.iter().cloned().fold(0.0, f64::max);
+    let column = |values: Vec<f64>| chart::sparkline(&chart::resample(&values, width));
+
+    let series: Vec<(&str, Vec<f64>, String)> = vec![
+        {
+            let values: Vec<f64> = points.iter().map(|p| p.requests as f64).collect();
+            let stats = format!(
+                "total {}, peak {}",
+                chart::human_count(requests as f64),
+                chart::human_count(peak(&values))
+            );
+            ("requests", values, stats)
+        },
+        {
+            let values: Vec<f64> = points.iter().map(|p| p.bytes as f64).collect();
+            let stats = format!(
+                "total {}, peak {}",
+                format_bytes(total(|p| p.bytes)),
+                format_bytes(peak(&values) as u64)
+            );
+            ("bandwidth", values, stats)
+        },
+        {
+            let values: Vec<f64> = points.iter().map(Point::cache_ratio).collect();
+            let stats = format!(
+                "overall {:.1}%",
+                percentage(total(|p| p.cached_requests), requests)
+            );
+            ("cache hit", values, stats)
+        },
+        {
+            let values: Vec<f64> = points.iter().map(|p| p.threats as f64).collect();
+            let stats = format!(
+                "total {}, peak {}",
+                chart::human_count(total(|p| p.threats) as f64),
+                chart::human_count(peak(&values))
+            );
+            ("threats", values, stats)
+        },
+        {
+            let values: Vec<f64> = points.iter().map(Point::error_rate).collect();
+            let stats = format!(
+                "overall {:.2}%, peak {:.2}%",
+                percentage(total(|p| p.errors), requests),
+                peak(&values)
+            );
+            ("5xx rate", values, stats)
+        },
+    ];
+
+    for (label, values, stats) in series {
+        let line = column(values);
+        let padding = width.saturating_sub(line.chars().count());
+        println!(
+            "{:<LABEL$} {}{} {}",
+            label.bold(),
+            line.cyan(),
+            " ".repeat(padding),
+            stats.dimmed()
+        );
+    }
 
-        AnalyticsCommand::Query { query } => {
-            let response = client.graphql(&query, None).await?;
-            output::print_output(&response, &config.output_format)?;
+    let start = bucket_label(points[0].time, granularity);
+    let end = bucket_label(range.until, granularity);
+    let drawn = points.len().min(width);
+    println!(
+        "{:<LABEL$} {}{:>fill$}",
+        "",
+        start.dimmed(),
+        end.dimmed(),
+        fill = drawn.saturating_sub(start.len()).max(end.len() + 1)
+    );
+    output::info(&format!(
+        "{} points of {}{}",
+        points.len(),
+        granularity,
+        if points.len() > width {
+            ", averaged to fit the terminal"
+        } else {
+            ""
         }
+    ));
+}
+
+fn print_traffic_bars(points: &[Point], granularity: &str, series: &str) {
+    let value = |p: &Point| match series {
+        "bytes" => p.bytes as f64,
+        "threats" => p.threats as f64,
+        "errors" => p.error_rate(),
+        "cache" => p.cache_ratio(),
+        _ => p.requests as f64,
+    };
+    let display = |v: f64| match series {
+        "bytes" => format_bytes(v as u64),
+        "errors" | "cache" => format!("{:.1}%", v),
+        _ => chart::human_count(v),
+    };
+    let max = match series {
+        "errors" | "cache" => 100.0,
+        _ => points.iter().map(value).fold(0.0, f64::max),
+    };
+
+    let labels: Vec<String> = points
+        .iter()
+        .map(|p| bucket_label(p.time, granularity))
+        .collect();
+    let label_width = labels.iter().map(|l| l.len()).max().unwrap_or(0);
+    // Room for the label, the value and the cache hit ratio
+    let width = chart::terminal_width()
+        .saturating_sub(label_width + 24)
+        .max(10);
+
+    for (point, label) in points.iter().zip(&labels) {
+        let v = value(point);
+        let bar = chart::bar(v, max, width);
+        let padding = width.saturating_sub(bar.chars().count());
+        println!(
+            "{:<label_width$} {}{} {:>9} {}",
+            label,
+            bar.cyan(),
+            " ".repeat(padding),
+            display(v),
+            format!("{:5.1}% cached", point.cache_ratio()).dimmed()
+        );
+    }
+}
+
+fn print_traffic_table(points: &[Point], granularity: &str) {
+    output::table_header(&["TIME", "REQUESTS", "BANDWIDTH", "CACHED", "THREATS", "5XX"]);
+    for point in points {
+        println!(
+            "{}\t{}\t{}\t{:.1}%\t{}\t{:.2}%",
+            bucket_label(point.time, granularity),
+            point.requests,
+            format_bytes(point.bytes),
+            point.cache_ratio(),
+            point.threats,
+            point.error_rate()
+        );
     }
+}
 
+/// Count and dimension values of each group, the values joined into one label
+pub(crate) fn count_rows(response: &Value, dataset: &str) -> Vec<(String, u64)> {
+    response
+        .pointer(&format!("/data/viewer/zones/0/{}", dataset))
+        .and_then(|g| g.as_array())
+        .into_iter()
+        .flatten()
+        .map(|group| {
+            let label = group
+                .get("dimensions")
+                .and_then(|d| d.as_object())
+                .map(|dims| {
+                    dims.values()
+                        .map(|v| match v {
+                            Value::String(s) if s.is_empty() => "-".to_string(),
+                            Value::String(s) => s.clone(),
+                            other => other.to_string(),
+                        })
+                        .collect::<Vec<_>>()
+                        .join("  ")
+                })
+                .unwrap_or_default();
+            let count = group.get("count").and_then(|c| c.as_u64()).unwrap_or(0);
+            (label, count)
+        })
+        .collect()
+}
+
+/// Run a query over the range, split into windows the dataset accepts, and merge the results
+///
+/// `build` gets the since/until of each window; `order_by` and `limit` are
+/// re-applied to the merged groups.
+pub(crate) async fn query_range<F>(
+    client: &CloudflareClient,
+    range: &TimeRange,
+    dataset: &str,
+    order_by: &str,
+    limit: Option<usize>,
+    build: F,
+) -> Result<Value>
+where
+    F: Fn(&str, &str) -> Result<String>,
+{
+    let chunks = range.chunks(graphql::max_range(dataset));
+    if chunks.len() == 1 {
+        return client
+            .graphql(&build(&range.since_str(), &range.until_str())?, None)
+            .await;
+    }
+
+    let mut responses = Vec::with_capacity(chunks.len());
+    for chunk in &chunks {
+        let query = build(&chunk.since_str(), &chunk.until_str())?;
+        responses.push(client.graphql(&query, None).await?);
+        output::progress(responses.len(), chunks.len(), dataset);
+    }
+
+    Ok(graphql::merge_responses(&responses, order_by, limit))
+}
+
+/// Parse a --filter value into a GraphQL filter field and value
+///
+/// `field=value` passes the field through as written (so `clientRequestPath_like=/api/%`
+/// works), the other operators add the matching suffix. `_in` fields take a
+/// comma-separated list.
+fn parse_filter(raw: &str) -> Result<(String, Arg)> {
+    const OPERATORS: &[(&str, &str)] = &[
+        (">=", "_geq"),
+        ("<=", "_leq"),
+        ("!=", "_neq"),
+        (">", "_gt"),
+        ("<", "_lt"),
+        ("~", "_like"),
+        ("=", ""),
+    ];
+
+    let (position, operator, suffix) = OPERATORS
+        .iter()
+        .filter_map(|(op, suffix)| raw.find(op).map(|pos| (pos, *op, *suffix)))
+        .min_by_key(|(pos, op, _)| (*pos, std::cmp::Reverse(op.len())))
+        .ok_or_else(|| {
+            anyhow!(
+                "Invalid filter '{}': use field=value, field!=value, field>=value or field~pattern",
+                raw
+            )
+        })?;
+
+    let field = format!("{}{}", raw[..position].trim(), suffix);
+    let value = &raw[position + operator.len()..];
+    graphql::check_name(&field)?;
+
+    let value = if field.ends_with("_in") || field.ends_with("_notin") {
+        Arg::List(value.split(',').map(Arg::parse).collect())
+    } else {
+        Arg::parse(value)
+    };
+    Ok((field, value))
+}
+
+/// Print the groups of a query as a table of metrics and dimensions
+fn print_groups(response: &Value, query: &GroupQuery, format: &OutputFormat) -> Result<()> {
+    let groups = response
+        .pointer(&format!(
+            "/data/viewer/{}/0/{}",
+            query.scope.key(),
+            query.dataset
+        ))
+        .and_then(|g| g.as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    if matches!(format, OutputFormat::Json) {
+        return output::print_json_pretty(&groups);
+    }
+    if groups.is_empty() {
+        output::warning("No data found");
+        return Ok(());
+    }
+
+    let mut columns: Vec<String> = query.metrics.iter().map(|m| m.label()).collect();
+    columns.extend(query.dimensions.iter().cloned());
+    let header: Vec<String> = columns.iter().map(|c| c.to_uppercase()).collect();
+    output::table_header(&header.iter().map(String::as_str).collect::<Vec<_>>());
+
+    for group in &groups {
+        let mut row: Vec<String> = query
+            .metrics
+            .iter()
+            .map(|m| display_value(group.pointer(&m.pointer())))
+            .collect();
+        row.extend(
+            query
+                .dimensions
+                .iter()
+                .map(|d| display_value(group.get("dimensions").and_then(|dims| dims.get(d)))),
+        );
+        println!("{}", row.join("\t"));
+    }
+
+    output::info(&format!("Total groups: {}", groups.len()));
     Ok(())
 }
 
+fn display_value(value: Option<&Value>) -> String {
+    match value {
+        Some(Value::String(s)) if !s.is_empty() => s.clone(),
+        Some(Value::Null) | None => "-".to_string(),
+        Some(Value::String(_)) => "-".to_string(),
+        Some(other) => other.to_string(),
+    }
+}
+
 fn get_analytics_data(response: &serde_json::Value) -> Option<&Vec<serde_json::Value>> {
     response
         .get("data")
@@ -305,7 +1612,7 @@ fn print_hourly_response(response: &serde_json::Value) -> Result<()> {
     Ok(())
 }
 
-fn format_bytes(bytes: u64) -> String {
+pub(crate) fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
     const GB: u64 = MB * 1024;
@@ -320,3 +1627,17 @@ fn format_bytes(bytes: u64) -> String {
         format!("{} B", bytes)
     }
 }
+
+async fn get_account_id(client: &CloudflareClient) -> Result<String> {
+    let response = client.get_raw("/zones?per_page=1").await?;
+    if let Some(zones) = response.get("result").and_then(|r| r.as_array()) {
+        if let Some(zone) = zones.first() {
+            if let Some(account) = zone.get("account") {
+                if let Some(id) = account.get("id").and_then(|i| i.as_str()) {
+                    return Ok(id.to_string());
+                }
+            }
+        }
+    }
+    Err(anyhow!("Could not determine account ID"))
+}
@@ -1,13 +1,41 @@
 //! Cache command
 
-use anyhow::Result;
+use std::collections::{BTreeMap, HashSet};
+use std::io::Read;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
 use clap::{Args, Subcommand};
-use serde_json::json;
+use owo_colors::OwoColorize;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
 
+use crate::api::client::ApiError;
+use crate::api::rulesets;
 use crate::api::CloudflareClient;
 use crate::config::Config;
 use crate::output;
 
+/// Retries for a batch that hit the API rate limit
+const MAX_RETRIES: u32 = 5;
+
+/// Longest `Retry-After` wait honoured before retrying a batch
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(120);
+
+/// Nested sitemap indexes followed at most this deep
+const MAX_SITEMAP_DEPTH: usize = 3;
+
+const PHASE_CACHE: &str = "http_request_cache_settings";
+
+/// Zone settings shown by `cache settings`
+const CACHE_ZONE_SETTINGS: &[&str] = &[
+    "cache_level",
+    "browser_cache_ttl",
+    "development_mode",
+    "always_online",
+    "sort_query_string_for_cache",
+];
+
 #[derive(Args, Debug)]
 pub struct CacheArgs {
     /// Zone name or ID
@@ -31,33 +59,244 @@ pub enum CacheCommand {
     /// Purge specific URLs
     PurgeUrls {
         /// URLs to purge (comma-separated or multiple arguments)
-        #[arg(required = true)]
+        #[arg(required_unless_present_any = ["from_file", "sitemap"])]
         urls: Vec<String>,
+
+        /// Purge every URL listed in a sitemap (local file or URL; sitemap indexes are followed)
+        #[arg(long)]
+        sitemap: Option<String>,
+
+        /// Cache key header, e.g. "CF-Device-Type: mobile" (repeat a name to purge each value)
+        #[arg(short = 'H', long = "header")]
+        headers: Vec<String>,
+
+        #[command(flatten)]
+        input: PurgeInput,
     },
 
     /// Purge by cache tags (Enterprise)
     PurgeTags {
         /// Cache tags to purge
-        #[arg(required = true)]
+        #[arg(required_unless_present = "from_file")]
         tags: Vec<String>,
+
+        #[command(flatten)]
+        input: PurgeInput,
     },
 
     /// Purge by prefix (Enterprise)
     PurgePrefixes {
         /// URL prefixes to purge
-        #[arg(required = true)]
+        #[arg(required_unless_present = "from_file")]
         prefixes: Vec<String>,
+
+        #[command(flatten)]
+        input: PurgeInput,
     },
 
     /// Purge by hostname
     PurgeHosts {
         /// Hostnames to purge
-        #[arg(required = true)]
+        #[arg(required_unless_present = "from_file")]
         hosts: Vec<String>,
+
+        #[command(flatten)]
+        input: PurgeInput,
+    },
+
+    /// Show all cache settings at a glance, export or apply them from a file
+    Settings {
+        #[command(subcommand)]
+        cmd: Option<CacheSettingsCommand>,
+    },
+
+    /// Cache Rules (http_request_cache_settings phase)
+    Rules {
+        #[command(subcommand)]
+        cmd: CacheRulesCommand,
+    },
+
+    /// Show or set Tiered Cache topology
+    Tiered {
+        /// off, generic (regional tiers) or smart (Cloudflare-selected upper tier)
+        #[arg(value_parser = ["off", "generic", "smart"])]
+        mode: Option<String>,
+    },
+
+    /// Show or toggle Cache Reserve
+    Reserve {
+        /// on or off
+        #[arg(value_parser = ["on", "off"])]
+        value: Option<String>,
+    },
+
+    /// Fetch URLs and report how Cloudflare cached them
+    Probe {
+        /// URLs to probe
+        #[arg(required = true)]
+        urls: Vec<String>,
+
+        /// Extra request header, e.g. "Accept-Encoding: br" (repeatable)
+        #[arg(short = 'H', long = "header")]
+        headers: Vec<String>,
+
+        /// Send requests for HOST to IP, like curl (host:ip or host:port:ip)
+        #[arg(long)]
+        resolve: Vec<String>,
+
+        /// Request each URL twice to confirm a HIT after a MISS
+        #[arg(long)]
+        twice: bool,
+
+        /// Skip fetching the zone's cache level, browser cache TTL and cache rules
+        #[arg(long)]
+        no_zone: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheSettingsCommand {
+    /// Print current settings as JSON (a starting point for `apply`)
+    Export,
+
+    /// Apply settings from a JSON file; only differences are changed
+    Apply {
+        /// File with setting names and values (see `cache settings export`)
+        file: String,
+
+        /// Show the changes without applying them
+        #[arg(long)]
+        dry_run: bool,
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum CacheRulesCommand {
+    /// List cache rules
+    List,
+
+    /// Create a cache rule
+    Add {
+        #[command(flatten)]
+        rule: CacheRuleArgs,
+    },
+
+    /// Update a cache rule (only given fields change)
+    Update {
+        /// Rule ID
+        rule_id: String,
+
+        #[command(flatten)]
+        rule: CacheRuleArgs,
+    },
+
+    /// Delete a cache rule
+    Delete {
+        /// Rule ID
+        rule_id: String,
+
+        /// Skip confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct CacheRuleArgs {
+    /// Requests matching this expression use the rule
+    #[arg(short, long)]
+    pub expression: Option<String>,
+
+    /// Rule description
+    #[arg(short, long)]
+    pub description: Option<String>,
+
+    /// Bypass the cache for matching requests
+    #[arg(long, conflicts_with = "cache")]
+    pub bypass: bool,
+
+    /// Make matching requests eligible for caching
+    #[arg(long)]
+    pub cache: bool,
+
+    /// Edge TTL in seconds (overrides origin headers), or "respect" to use origin headers
+    #[arg(long)]
+    pub edge_ttl: Option<String>,
+
+    /// Browser TTL in seconds, "respect" (origin headers) or "bypass"
+    #[arg(long)]
+    pub browser_ttl: Option<String>,
+
+    /// Query string in the cache key: all, ignore, include:a,b or exclude:a,b
+    #[arg(long)]
+    pub key_query: Option<String>,
+
+    /// Include this request header in the cache key (repeatable)
+    #[arg(long)]
+    pub key_header: Vec<String>,
+
+    /// Include this cookie in the cache key (repeatable)
+    #[arg(long)]
+    pub key_cookie: Vec<String>,
+
+    /// Cache separately by device type (mobile, tablet, desktop)
+    #[arg(long)]
+    pub key_device_type: bool,
+
+    /// Treat query strings with the same parameters in any order as one
+    #[arg(long)]
+    pub ignore_query_order: bool,
+
+    /// Send the expression without validating it locally first
+    #[arg(long)]
+    pub no_check: bool,
+}
+
+/// Input and batching options shared by the purge commands
+#[derive(Args, Debug)]
+pub struct PurgeInput {
+    /// Read targets from a file, one per line ('-' for stdin)
+    #[arg(long)]
+    pub from_file: Option<String>,
+
+    /// Targets per API call [default: 30 URLs, 100 tags/prefixes/hosts; at most 500 (Enterprise)]
+    #[arg(long)]
+    pub batch_size: Option<usize>,
+
+    /// Pause between batches in milliseconds
+    #[arg(long, default_value = "0")]
+    pub delay: u64,
+
+    /// Show the batches without purging
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+impl CacheCommand {
+    /// Commands that work without API credentials
+    pub fn is_offline(&self) -> bool {
+        matches!(self, CacheCommand::Probe { .. })
+    }
+}
+
 pub async fn execute(config: &Config, args: CacheArgs) -> Result<()> {
+    if let CacheCommand::Probe {
+        urls,
+        headers,
+        resolve,
+        twice,
+        no_zone,
+    } = args.command
+    {
+        // Zone settings improve the verdict but are optional
+        let zone = if no_zone || config.auth_headers().is_empty() {
+            None
+        } else {
+            zone_cache_settings(config, args.zone.as_deref()).await
+        };
+        return probe(&urls, &headers, &resolve, twice, zone.as_ref()).await;
+    }
+
     let client = CloudflareClient::new(config.clone())?;
     let zone = config.resolve_zone(args.zone.as_deref())?;
     let zone_id = client.resolve_zone_id(&zone).await?;
@@ -77,44 +316,1546 @@ pub async fn execute(config: &Config, args: CacheArgs) -> Result<()> {
             output::success("Cache purged successfully!");
         }
 
-        CacheCommand::PurgeUrls { urls } => {
-            // Flatten comma-separated URLs
-            let all_urls: Vec<String> = urls
+        CacheCommand::PurgeUrls {
+            urls,
+            sitemap,
+            headers,
+            input,
+        } => {
+            let mut all_urls = collect_targets(&urls, &input)?;
+            if let Some(sitemap) = sitemap {
+                let found = sitemap_urls(&sitemap).await?;
+                output::info(&format!("Sitemap: {} URLs", found.len()));
+                all_urls.extend(found);
+            }
+            let all_urls = dedupe(all_urls);
+
+            let invalid: Vec<&String> = all_urls
                 .iter()
-                .flat_map(|u| u.split(',').map(|s| s.trim().to_string()))
+                .filter(|u| !u.starts_with("http://") && !u.starts_with("https://"))
                 .collect();
+            if !invalid.is_empty() {
+                for url in &invalid {
+                    output::error(&format!("Not a URL (missing http:// or https://): {}", url));
+                }
+                return Err(anyhow!("{} invalid URL(s)", invalid.len()));
+            }
 
-            let body = json!({"files": all_urls});
-            client
-                .post_raw(&format!("/zones/{}/purge_cache", zone_id), body)
-                .await?;
-            output::success(&format!("Purged {} URLs", all_urls.len()));
+            let files = with_cache_key_headers(all_urls, &headers)?;
+            purge_batched(&client, &zone_id, "files", "URLs", files, &input).await?;
+        }
+
+        CacheCommand::PurgeTags { tags, input } => {
+            let tags = dedupe(collect_targets(&tags, &input)?);
+            let items = tags.into_iter().map(Value::String).collect();
+            purge_batched(&client, &zone_id, "tags", "tags", items, &input).await?;
         }
 
-        CacheCommand::PurgeTags { tags } => {
-            let body = json!({"tags": tags});
+        CacheCommand::PurgePrefixes { prefixes, input } => {
+            // Prefixes are given without the scheme
+            let prefixes = collect_targets(&prefixes, &input)?
+                .into_iter()
+                .map(|p| {
+                    p.trim_start_matches("https://")
+                        .trim_start_matches("http://")
+                        .to_string()
+                })
+                .collect();
+            let items = dedupe(prefixes).into_iter().map(Value::String).collect();
+            purge_batched(&client, &zone_id, "prefixes", "prefixes", items, &input).await?;
+        }
+
+        CacheCommand::PurgeHosts { hosts, input } => {
+            let hosts = dedupe(collect_targets(&hosts, &input)?);
+            let items = hosts.into_iter().map(Value::String).collect();
+            purge_batched(&client, &zone_id, "hosts", "hosts", items, &input).await?;
+        }
+
+        CacheCommand::Settings { cmd } => match cmd {
+            None => {
+                let state = cache_state(&client, &zone_id).await?;
+
+                output::table_header(&["SETTING", "VALUE"]);
+                for (name, value) in &state {
+                    println!("{}\t{}", name.cyan(), display_value(value));
+                }
+
+                let rules = match rulesets::get_entrypoint(
+                    &client,
+                    &format!("/zones/{}", zone_id),
+                    PHASE_CACHE,
+                )
+                .await
+                {
+                    Ok(Some(ruleset)) => rulesets::rules(&ruleset).len().to_string(),
+                    Ok(None) => "0".to_string(),
+                    Err(_) => "unavailable".dimmed().to_string(),
+                };
+                println!("{}\t{}", "cache_rules".cyan(), rules);
+            }
+
+            Some(CacheSettingsCommand::Export) => {
+                let state: serde_json::Map<String, Value> = cache_state(&client, &zone_id)
+                    .await?
+                    .into_iter()
+                    .filter(|(_, v)| !v.is_null())
+                    .collect();
+                output::print_json_pretty(&state)?;
+            }
+
+            Some(CacheSettingsCommand::Apply { file, dry_run }) => {
+                let content = std::fs::read_to_string(&file)
+                    .map_err(|e| anyhow!("Cannot read {}: {}", file, e))?;
+                let desired: BTreeMap<String, Value> = serde_json::from_str(&content)
+                    .map_err(|e| anyhow!("Invalid JSON in {}: {}", file, e))?;
+
+                let current = cache_state(&client, &zone_id).await?;
+                let mut changes = Vec::new();
+                for (name, value) in &desired {
+                    match current.get(name) {
+                        None => return Err(anyhow!("Unknown cache setting: {}", name)),
+                        Some(Value::Null) => {
+                            return Err(anyhow!("{} is not available on this zone", name))
+                        }
+                        Some(cur) if cur == value => {}
+                        Some(cur) => changes.push((name, cur, value)),
+                    }
+                }
+
+                if changes.is_empty() {
+                    output::success("Cache settings already match the file");
+                    return Ok(());
+                }
+
+                for (name, cur, value) in &changes {
+                    println!(
+                        "{}: {} -> {}",
+                        name.cyan(),
+                        display_value(cur).red(),
+                        display_value(value).green()
+                    );
+                }
+
+                if dry_run {
+                    output::info(&format!("Dry run: {} change(s)", changes.len()));
+                    return Ok(());
+                }
+
+                for (name, _, value) in &changes {
+                    set_cache_value(&client, &zone_id, name, value).await?;
+                }
+                output::success(&format!("Applied {} change(s)", changes.len()));
+            }
+        },
+
+        CacheCommand::Rules { cmd } => {
+            execute_rules(&client, &format!("/zones/{}", zone_id), cmd).await?;
+        }
+
+        CacheCommand::Tiered { mode } => match mode {
+            None => {
+                let state = cache_state(&client, &zone_id).await?;
+                output::info(&format!(
+                    "Tiered Cache: {}",
+                    display_value(&state["tiered_cache"])
+                ));
+            }
+            Some(mode) => {
+                set_cache_value(&client, &zone_id, "tiered_cache", &json!(mode)).await?;
+                output::success(&format!("Tiered Cache set to: {}", mode));
+            }
+        },
+
+        CacheCommand::Probe { .. } => unreachable!(), // Handled above
+
+        CacheCommand::Reserve { value } => match value {
+            None => {
+                let state = cache_state(&client, &zone_id).await?;
+                output::info(&format!(
+                    "Cache Reserve: {}",
+                    display_value(&state["cache_reserve"])
+                ));
+            }
+            Some(value) => {
+                set_cache_value(&client, &zone_id, "cache_reserve", &json!(value)).await?;
+                output::success(&format!("Cache Reserve set to: {}", value));
+            }
+        },
+    }
+
+    Ok(())
+}
+
+/// Current value of every cache setting; null when not available on the plan
+async fn cache_state(client: &CloudflareClient, zone_id: &str) -> Result<BTreeMap<String, Value>> {
+    let mut state = BTreeMap::new();
+
+    let response = client
+        .get_raw(&format!("/zones/{}/settings", zone_id))
+        .await?;
+    let settings = response
+        .get("result")
+        .and_then(|r| r.as_array())
+        .cloned()
+        .unwrap_or_default();
+    for name in CACHE_ZONE_SETTINGS {
+        let value = settings
+            .iter()
+            .find(|s| s.get("id").and_then(|v| v.as_str()) == Some(name))
+            .and_then(|s| s.get("value"))
+            .cloned()
+            .unwrap_or(Value::Null);
+        state.insert(name.to_string(), value);
+    }
+
+    let tiered = get_value(client, &format!("/zones/{}/argo/tiered_caching", zone_id)).await;
+    let smart = get_value(
+        client,
+        &format!(
+            "/zones/{}/cache/tiered_cache_smart_topology_enable",
+            zone_id
+        ),
+    )
+    .await;
+    let tiered = match (tiered.as_str(), smart.as_str()) {
+        (Some("on"), Some("on")) => json!("smart"),
+        (Some("on"), _) => json!("generic"),
+        (Some(_), _) => json!("off"),
+        _ => Value::Null,
+    };
+    state.insert("tiered_cache".to_string(), tiered);
+
+    state.insert(
+        "cache_reserve".to_string(),
+        get_value(client, &format!("/zones/{}/cache/cache_reserve", zone_id)).await,
+    );
+
+    Ok(state)
+}
+
+/// `result.value` of a settings-style endpoint, or null if unavailable
+async fn get_value(client: &CloudflareClient, path: &str) -> Value {
+    match client.get_raw(path).await {
+        Ok(response) => response
+            .pointer("/result/value")
+            .cloned()
+            .unwrap_or(Value::Null),
+        Err(_) => Value::Null,
+    }
+}
+
+/// Change a single cache setting (zone setting, tiered cache or cache reserve)
+async fn set_cache_value(
+    client: &CloudflareClient,
+    zone_id: &str,
+    name: &str,
+    value: &Value,
+) -> Result<()> {
+    match name {
+        "tiered_cache" => {
+            let (tiered, smart) = match value.as_str() {
+                Some("off") => ("off", "off"),
+                Some("generic") => ("on", "off"),
+                Some("smart") => ("on", "on"),
+                _ => return Err(anyhow!("tiered_cache must be off, generic or smart")),
+            };
+            // Smart topology requires tiered caching, so order the calls accordingly
+            if tiered == "on" {
+                client
+                    .patch_raw(
+                        &format!("/zones/{}/argo/tiered_caching", zone_id),
+                        json!({ "value": tiered }),
+                    )
+                    .await?;
+            }
             client
-                .post_raw(&format!("/zones/{}/purge_cache", zone_id), body)
+                .patch_raw(
+                    &format!(
+                        "/zones/{}/cache/tiered_cache_smart_topology_enable",
+                        zone_id
+                    ),
+                    json!({ "value": smart }),
+                )
                 .await?;
-            output::success(&format!("Purged cache tags: {}", tags.join(", ")));
+            if tiered == "off" {
+                client
+                    .patch_raw(
+                        &format!("/zones/{}/argo/tiered_caching", zone_id),
+                        json!({ "value": tiered }),
+                    )
+                    .await?;
+            }
         }
-
-        CacheCommand::PurgePrefixes { prefixes } => {
-            let body = json!({"prefixes": prefixes});
+        "cache_reserve" => {
             client
-                .post_raw(&format!("/zones/{}/purge_cache", zone_id), body)
+                .patch_raw(
+                    &format!("/zones/{}/cache/cache_reserve", zone_id),
+                    json!({ "value": value }),
+                )
                 .await?;
-            output::success(&format!("Purged prefixes: {}", prefixes.join(", ")));
         }
-
-        CacheCommand::PurgeHosts { hosts } => {
-            let body = json!({"hosts": hosts});
+        _ => {
             client
-                .post_raw(&format!("/zones/{}/purge_cache", zone_id), body)
+                .patch_raw(
+                    &format!("/zones/{}/settings/{}", zone_id, name),
+                    json!({ "value": value }),
+                )
                 .await?;
-            output::success(&format!("Purged hosts: {}", hosts.join(", ")));
         }
     }
 
     Ok(())
 }
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Null => "unavailable".to_string(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+async fn execute_rules(
+    client: &CloudflareClient,
+    base: &str,
+    cmd: CacheRulesCommand,
+) -> Result<()> {
+    match cmd {
+        CacheRulesCommand::List => {
+            let rules = match rulesets::get_entrypoint(client, base, PHASE_CACHE).await? {
+                Some(ruleset) => rulesets::rules(&ruleset),
+                None => Vec::new(),
+            };
+
+            output::table_header(&["#", "STATE", "ACTION", "DESCRIPTION", "ID"]);
+            for (i, rule) in rules.iter().enumerate() {
+                output::print_cache_rule(i + 1, rule);
+            }
+            output::info(&format!("Total: {} cache rules", rules.len()));
+        }
+
+        CacheRulesCommand::Add { rule } => {
+            let mut body = json!({
+                "action": "set_cache_settings",
+                "action_parameters": {}
+            });
+            apply_cache_rule_args(&mut body, &rule)?;
+
+            if body.get("expression").is_none() {
+                return Err(anyhow!("--expression is required"));
+            }
+            if body["action_parameters"]
+                .as_object()
+                .is_none_or(|p| p.is_empty())
+            {
+                return Err(anyhow!(
+                    "Nothing to do; use --cache, --bypass, TTL or cache key options"
+                ));
+            }
+
+            let ruleset = rulesets::ensure_entrypoint(client, base, PHASE_CACHE).await?;
+            let updated = rulesets::add_rule(client, base, &rulesets::id(&ruleset)?, body).await?;
+            output::success("Created cache rule");
+
+            let rules = rulesets::rules(&updated);
+            if let Some(created) = rules.last() {
+                output::print_cache_rule(rules.len(), created);
+            }
+        }
+
+        CacheRulesCommand::Update { rule_id, rule } => {
+            let ruleset = rulesets::resolve(client, base, PHASE_CACHE).await?;
+            let current = rulesets::find_rule(&ruleset, &rule_id)?;
+
+            // Start from the current parameters so unchanged settings are kept
+            let mut changes = json!({
+                "action_parameters": current
+                    .get("action_parameters")
+                    .cloned()
+                    .unwrap_or_else(|| json!({}))
+            });
+            apply_cache_rule_args(&mut changes, &rule)?;
+
+            let updated = rulesets::update_rule(client, base, &ruleset, &rule_id, changes).await?;
+            output::success(&format!("Updated cache rule: {}", rule_id));
+
+            let rules = rulesets::rules(&updated);
+            if let Some(i) = rules
+                .iter()
+                .position(|r| r.get("id").and_then(|v| v.as_str()) == Some(rule_id.as_str()))
+            {
+                output::print_cache_rule(i + 1, &rules[i]);
+            }
+        }
+
+        CacheRulesCommand::Delete { rule_id, yes } => {
+            if !yes {
+                output::warning(&format!(
+                    "Are you sure you want to delete cache rule {}?",
+                    rule_id
+                ));
+                output::info("Use -y to skip this confirmation");
+                return Ok(());
+            }
+
+            let ruleset = rulesets::resolve(client, base, PHASE_CACHE).await?;
+            rulesets::delete_rule(client, base, &rulesets::id(&ruleset)?, &rule_id).await?;
+            output::success(&format!("Deleted cache rule: {}", rule_id));
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply cache rule flags on top of a rule body
+fn apply_cache_rule_args(body: &mut Value, args: &CacheRuleArgs) -> Result<()> {
+    if let Some(ref expression) = args.expression {
+        if !args.no_check {
+            super::rulesets::check_expression(expression)?;
+        }
+        body["expression"] = json!(expression);
+    }
+    if let Some(ref description) = args.description {
+        body["description"] = json!(description);
+    }
+
+    if !body["action_parameters"].is_object() {
+        body["action_parameters"] = json!({});
+    }
+    let params = &mut body["action_parameters"];
+
+    if args.bypass {
+        params["cache"] = json!(false);
+    }
+    if args.cache {
+        params["cache"] = json!(true);
+    }
+    if let Some(ref ttl) = args.edge_ttl {
+        params["edge_ttl"] = match ttl.as_str() {
+            "respect" => json!({ "mode": "respect_origin" }),
+            secs => json!({ "mode": "override_origin", "default": parse_seconds(secs)? }),
+        };
+    }
+    if let Some(ref ttl) = args.browser_ttl {
+        params["browser_ttl"] = match ttl.as_str() {
+            "respect" => json!({ "mode": "respect_origin" }),
+            "bypass" => json!({ "mode": "bypass" }),
+            secs => json!({ "mode": "override_origin", "default": parse_seconds(secs)? }),
+        };
+    }
+
+    if let Some(ref query) = args.key_query {
+        params["cache_key"]["custom_key"]["query_string"] = match query.split_once(':') {
+            None if query == "all" => json!({ "include": { "all": true } }),
+            None if query == "ignore" => json!({ "exclude": { "all": true } }),
+            Some(("include", names)) => json!({ "include": { "list": split_names(names) } }),
+            Some(("exclude", names)) => json!({ "exclude": { "list": split_names(names) } }),
+            _ => {
+                return Err(anyhow!(
+                    "Invalid --key-query (use all, ignore, include:a,b or exclude:a,b)"
+                ))
+            }
+        };
+    }
+    if !args.key_header.is_empty() {
+        params["cache_key"]["custom_key"]["header"]["include"] = json!(args.key_header);
+    }
+    if !args.key_cookie.is_empty() {
+        params["cache_key"]["custom_key"]["cookie"]["include"] = json!(args.key_cookie);
+    }
+    if args.key_device_type {
+        params["cache_key"]["cache_by_device_type"] = json!(true);
+    }
+    if args.ignore_query_order {
+        params["cache_key"]["ignore_query_strings_order"] = json!(true);
+    }
+
+    Ok(())
+}
+
+fn parse_seconds(value: &str) -> Result<u64> {
+    value
+        .parse()
+        .map_err(|_| anyhow!("Invalid TTL: {} (use seconds or \"respect\")", value))
+}
+
+fn split_names(names: &str) -> Vec<String> {
+    names
+        .split(',')
+        .map(|n| n.trim().to_string())
+        .filter(|n| !n.is_empty())
+        .collect()
+}
+
+/// Purge `items` in batches, retrying rate-limited calls with backoff
+async fn purge_batched(
+    client: &CloudflareClient,
+    zone_id: &str,
+    key: &str,
+    label: &str,
+    items: Vec<Value>,
+    input: &PurgeInput,
+) -> Result<()> {
+    if items.is_empty() {
+        return Err(anyhow!("Nothing to purge"));
+    }
+    let batch_size = batch_size(key, input.batch_size)?;
+    let batches: Vec<&[Value]> = items.chunks(batch_size).collect();
+    let path = format!("/zones/{}/purge_cache", zone_id);
+
+    if input.dry_run {
+        for (i, batch) in batches.iter().enumerate() {
+            output::info(&format!(
+                "Batch {}/{}: {} {}",
+                i + 1,
+                batches.len(),
+                batch.len(),
+                label
+            ));
+            for item in batch.iter() {
+                println!("  {}", describe_item(item));
+            }
+        }
+        output::info(&format!(
+            "Dry run: {} {} in {} batch(es)",
+            items.len(),
+            label,
+            batches.len()
+        ));
+        return Ok(());
+    }
+
+    let mut purged = 0;
+    let mut failed_batches = 0;
+
+    for (i, batch) in batches.iter().enumerate() {
+        let body = json!({ key: batch });
+        let mut attempt = 0;
+
+        loop {
+            match client.post_raw(&path, body.clone()).await {
+                Ok(_) => {
+                    purged += batch.len();
+                    output::success(&format!(
+                        "Batch {}/{}: purged {} {}",
+                        i + 1,
+                        batches.len(),
+                        batch.len(),
+                        label
+                    ));
+                    break;
+                }
+                Err(e) if is_rate_limited(&e) && attempt < MAX_RETRIES => {
+                    attempt += 1;
+                    let wait = retry_wait(&e, attempt);
+                    output::warning(&format!(
+                        "Batch {}/{}: rate limited, retrying in {}s",
+                        i + 1,
+                        batches.len(),
+                        wait.as_secs()
+                    ));
+                    tokio::time::sleep(wait).await;
+                }
+                Err(e) => {
+                    failed_batches += 1;
+                    output::error(&format!(
+                        "Batch {}/{}: failed ({} {}): {}",
+                        i + 1,
+                        batches.len(),
+                        batch.len(),
+                        label,
+                        e
+                    ));
+                    break;
+                }
+            }
+        }
+
+        if input.delay > 0 && i + 1 < batches.len() {
+            tokio::time::sleep(Duration::from_millis(input.delay)).await;
+        }
+    }
+
+    output::info(&format!(
+        "Purged {} of {} {} in {} batch(es)",
+        purged,
+        items.len(),
+        label,
+        batches.len()
+    ));
+
+    if failed_batches > 0 {
+        return Err(anyhow!(
+            "{} of {} batch(es) failed",
+            failed_batches,
+            batches.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Per-call batch size for a purge key: the plan default, or `requested`
+/// checked against the largest batch any plan accepts
+fn batch_size(key: &str, requested: Option<usize>) -> Result<usize> {
+    // Single-file purges are capped at 30 per call below Enterprise;
+    // tags, prefixes and hosts at 100. Enterprise takes up to 500 of each.
+    let (default, max) = match key {
+        "files" => (30, 500),
+        _ => (100, 500),
+    };
+    match requested {
+        None => Ok(default),
+        Some(0) => Err(anyhow!("--batch-size must be at least 1")),
+        Some(n) if n > max => Err(anyhow!(
+            "--batch-size {} is above the limit of {} {} per call",
+            n,
+            max,
+            key
+        )),
+        Some(n) => Ok(n),
+    }
+}
+
+fn is_rate_limited(e: &anyhow::Error) -> bool {
+    ApiError::has_status(e, StatusCode::TOO_MANY_REQUESTS)
+}
+
+/// Wait before retry `attempt`: the API's `Retry-After` if given, else 2^attempt seconds
+fn retry_wait(e: &anyhow::Error, attempt: u32) -> Duration {
+    ApiError::retry_after_of(e)
+        .map(|d| d.min(MAX_RETRY_WAIT))
+        .unwrap_or_else(|| Duration::from_secs(2u64.pow(attempt)))
+}
+
+fn describe_item(item: &Value) -> String {
+    match item {
+        Value::String(s) => s.clone(),
+        _ => {
+            let url = item.get("url").and_then(|v| v.as_str()).unwrap_or("-");
+            let headers = item
+                .get("headers")
+                .and_then(|h| h.as_object())
+                .map(|h| {
+                    h.iter()
+                        .map(|(k, v)| format!("{}: {}", k, v.as_str().unwrap_or("")))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .unwrap_or_default();
+            format!("{} [{}]", url, headers)
+        }
+    }
+}
+
+/// Targets from arguments (comma-separated allowed) plus --from-file
+fn collect_targets(args: &[String], input: &PurgeInput) -> Result<Vec<String>> {
+    let mut targets: Vec<String> = args
+        .iter()
+        .flat_map(|a| a.split(',').map(|s| s.trim().to_string()))
+        .filter(|s| !s.is_empty())
+        .collect();
+
+    if let Some(path) = &input.from_file {
+        let content = if path == "-" {
+            let mut buf = String::new();
+            std::io::stdin().read_to_string(&mut buf)?;
+            buf
+        } else {
+            std::fs::read_to_string(path).map_err(|e| anyhow!("Cannot read {}: {}", path, e))?
+        };
+
+        targets.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(String::from),
+        );
+    }
+
+    Ok(targets)
+}
+
+fn dedupe(items: Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    items
+        .into_iter()
+        .filter(|i| seen.insert(i.clone()))
+        .collect()
+}
+
+/// Turn URLs into purge items, one per combination of cache key header values
+///
+/// `-H "CF-Device-Type: mobile" -H "CF-Device-Type: desktop"` purges both
+/// variants of every URL.
+fn with_cache_key_headers(urls: Vec<String>, headers: &[String]) -> Result<Vec<Value>> {
+    if headers.is_empty() {
+        return Ok(urls.into_iter().map(Value::String).collect());
+    }
+
+    let mut by_name: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for header in headers {
+        let (name, value) = header
+            .split_once(':')
+            .or_else(|| header.split_once('='))
+            .ok_or_else(|| anyhow!("Invalid header (use 'Name: value'): {}", header))?;
+        by_name
+            .entry(name.trim().to_string())
+            .or_default()
+            .push(value.trim().to_string());
+    }
+
+    // Cartesian product of all header values
+    let mut combinations: Vec<serde_json::Map<String, Value>> = vec![serde_json::Map::new()];
+    for (name, values) in &by_name {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|combo| {
+                values.iter().map(move |v| {
+                    let mut next = combo.clone();
+                    next.insert(name.clone(), json!(v));
+                    next
+                })
+            })
+            .collect();
+    }
+
+    Ok(urls
+        .iter()
+        .flat_map(|url| {
+            combinations
+                .iter()
+                .map(move |headers| json!({ "url": url, "headers": headers }))
+        })
+        .collect())
+}
+
+/// Collect page URLs from a sitemap (local path or URL), following sitemap indexes
+async fn sitemap_urls(source: &str) -> Result<Vec<String>> {
+    let mut urls = Vec::new();
+    let mut queue = vec![(source.to_string(), 0)];
+
+    while let Some((location, depth)) = queue.pop() {
+        let content = if location.starts_with("http://") || location.starts_with("https://") {
+            let response = reqwest::get(&location).await?;
+            if !response.status().is_success() {
+                return Err(anyhow!(
+                    "Cannot fetch sitemap {}: {}",
+                    location,
+                    response.status()
+                ));
+            }
+            response.text().await?
+        } else {
+            std::fs::read_to_string(&location)
+                .map_err(|e| anyhow!("Cannot read sitemap {}: {}", location, e))?
+        };
+
+        let doc = roxmltree::Document::parse(&content)
+            .map_err(|e| anyhow!("Invalid sitemap XML in {}: {}", location, e))?;
+        let root = doc.root_element();
+
+        let locs = root.children().filter(|n| n.is_element()).filter_map(|n| {
+            n.children()
+                .find(|c| c.tag_name().name() == "loc")
+                .and_then(|c| c.text())
+                .map(|t| t.trim().to_string())
+        });
+
+        match root.tag_name().name() {
+            "sitemapindex" if depth < MAX_SITEMAP_DEPTH => {
+                queue.extend(locs.map(|l| (l, depth + 1)));
+            }
+            "sitemapindex" => {
+                output::warning(&format!("Sitemap index nested too deep: {}", location));
+            }
+            "urlset" => urls.extend(locs),
+            other => {
+                return Err(anyhow!(
+                    "{} is not a sitemap (root element <{}>)",
+                    location,
+                    other
+                ))
+            }
+        }
+    }
+
+    Ok(urls)
+}
+
+/// File extensions Cloudflare caches by default
+const DEFAULT_CACHED_EXTENSIONS: &[&str] = &[
+    "7z", "avi", "avif", "apk", "bin", "bmp", "bz2", "class", "css", "csv", "doc", "docx", "dmg",
+    "ejs", "eot", "eps", "exe", "flac", "gif", "gz", "ico", "iso", "jar", "jpeg", "jpg", "js",
+    "mid", "midi", "mkv", "mp3", "mp4", "ogg", "otf", "pdf", "pict", "pls", "png", "ppt", "pptx",
+    "ps", "rar", "svg", "svgz", "swf", "tar", "tif", "tiff", "ttf", "webm", "webp", "woff",
+    "woff2", "xls", "xlsx", "zip", "zst",
+];
+
+/// Response headers shown by `cache probe`
+const PROBE_HEADERS: &[&str] = &[
+    "cf-cache-status",
+    "age",
+    "cache-control",
+    "expires",
+    "vary",
+    "set-cookie",
+    "content-type",
+    "cf-ray",
+];
+
+/// Zone settings that decide what the edge caches, as used by `cache probe`
+#[derive(Debug, Default)]
+struct ZoneCache {
+    /// aggressive, basic or simplified
+    cache_level: Option<String>,
+    /// Seconds; 0 respects the origin's headers
+    browser_cache_ttl: Option<u64>,
+    /// Enabled cache rules that turn caching on or off, in evaluation order
+    rules: Vec<CacheRuleSummary>,
+}
+
+#[derive(Debug)]
+struct CacheRuleSummary {
+    description: String,
+    expression: String,
+    cache: bool,
+}
+
+impl ZoneCache {
+    /// The last enabled rule matching `url` that sets cache eligibility
+    ///
+    /// Only the URL is known, so rules on headers, cookies or the client see
+    /// empty values; rules that cannot be evaluated are skipped.
+    fn matching_rule(&self, url: &str) -> Option<&CacheRuleSummary> {
+        let request = crate::expr::eval::Request::from_json(&json!({ "url": url })).ok()?;
+        self.rules.iter().rev().find(|rule| {
+            let checked = crate::expr::parse(&rule.expression);
+            match checked.ast {
+                Some(ast) if checked.errors.is_empty() => matches!(
+                    crate::expr::eval::eval(&ast, &request),
+                    Ok(crate::expr::eval::Value::Bool(true))
+                ),
+                _ => false,
+            }
+        })
+    }
+}
+
+/// Fetch the zone's cache level, browser cache TTL and cache rules, ignoring any failure
+async fn zone_cache_settings(config: &Config, zone: Option<&str>) -> Option<ZoneCache> {
+    let client = CloudflareClient::new(config.clone()).ok()?;
+    let zone = config.resolve_zone(zone).ok()?;
+    let zone_id = client.resolve_zone_id(&zone).await.ok()?;
+
+    let cache_level = get_value(&client, &format!("/zones/{}/settings/cache_level", zone_id))
+        .await
+        .as_str()
+        .map(String::from);
+    let browser_cache_ttl = get_value(
+        &client,
+        &format!("/zones/{}/settings/browser_cache_ttl", zone_id),
+    )
+    .await
+    .as_u64();
+    let rules = rulesets::get_entrypoint(&client, &format!("/zones/{}", zone_id), PHASE_CACHE)
+        .await
+        .ok()
+        .flatten()
+        .map(|ruleset| rulesets::rules(&ruleset))
+        .unwrap_or_default()
+        .iter()
+        .filter(|rule| rule.get("enabled").and_then(|v| v.as_bool()) != Some(false))
+        .filter_map(|rule| {
+            Some(CacheRuleSummary {
+                description: rule
+                    .get("description")
+                    .and_then(|v| v.as_str())
+                    .filter(|d| !d.is_empty())
+                    .or_else(|| rule.get("id").and_then(|v| v.as_str()))
+                    .unwrap_or("-")
+                    .to_string(),
+                expression: rule.get("expression")?.as_str()?.to_string(),
+                cache: rule.pointer("/action_parameters/cache")?.as_bool()?,
+            })
+        })
+        .collect();
+
+    Some(ZoneCache {
+        cache_level,
+        browser_cache_ttl,
+        rules,
+    })
+}
+
+async fn probe(
+    urls: &[String],
+    headers: &[String],
+    resolve: &[String],
+    twice: bool,
+    zone: Option<&ZoneCache>,
+) -> Result<()> {
+    let mut builder = reqwest::Client::builder()
+        .user_agent("cli5/0.1.0")
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(Duration::from_secs(30));
+
+    for entry in resolve {
+        let (host, addr) = parse_resolve(entry)?;
+        builder = builder.resolve(&host, addr);
+    }
+    let http = builder.build()?;
+
+    let mut extra = reqwest::header::HeaderMap::new();
+    for header in headers {
+        let (name, value) = header
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid header (use 'Name: value'): {}", header))?;
+        extra.insert(
+            reqwest::header::HeaderName::from_bytes(name.trim().as_bytes())?,
+            value.trim().parse()?,
+        );
+    }
+
+    if let Some(zone) = zone {
+        output::info(&format!(
+            "Zone cache level: {}, browser cache TTL: {}, {} cache rule(s)",
+            zone.cache_level.as_deref().unwrap_or("unknown"),
+            zone.browser_cache_ttl
+                .map(|ttl| format!("{}s", ttl))
+                .unwrap_or_else(|| "unknown".to_string()),
+            zone.rules.len()
+        ));
+    }
+
+    for url in urls {
+        println!("{}", url.bold());
+
+        let first = http.get(url).headers(extra.clone()).send().await;
+        let first = match first {
+            Ok(r) => r,
+            Err(e) => {
+                output::error(&format!("Request failed: {}", e));
+                continue;
+            }
+        };
+        let status = first.status();
+        let response_headers = first.headers().clone();
+
+        println!("  {:<16}{}", "status", status);
+        for name in PROBE_HEADERS {
+            for value in response_headers.get_all(*name) {
+                let value = value.to_str().unwrap_or("<binary>");
+                let shown = match *name {
+                    "cf-cache-status" => color_cache_status(value),
+                    "cf-ray" => match value.rsplit_once('-') {
+                        Some((_, colo)) => format!("{} (colo {})", value, colo.cyan()),
+                        None => value.to_string(),
+                    },
+                    _ => value.to_string(),
+                };
+                println!("  {:<16}{}", name, shown);
+            }
+        }
+
+        let mut second_status = None;
+        if twice {
+            match http.get(url).headers(extra.clone()).send().await {
+                Ok(second) => {
+                    let status = second
+                        .headers()
+                        .get("cf-cache-status")
+                        .and_then(|v| v.to_str().ok())
+                        .unwrap_or("-")
+                        .to_string();
+                    println!("  {:<16}{}", "2nd request", color_cache_status(&status));
+                    second_status = Some(status);
+                }
+                Err(e) => output::error(&format!("Second request failed: {}", e)),
+            }
+        }
+
+        let verdict = cache_verdict(
+            url,
+            status.as_u16(),
+            &response_headers,
+            second_status.as_deref(),
+            zone,
+        );
+        for note in &verdict.notes {
+            output::info(note);
+        }
+        if verdict.reasons.is_empty() {
+            output::success("Looks cacheable");
+        } else {
+            for reason in &verdict.reasons {
+                output::warning(reason);
+            }
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+/// `host:ip` or `host:port:ip` (as in curl --resolve)
+fn parse_resolve(entry: &str) -> Result<(String, std::net::SocketAddr)> {
+    let invalid = || anyhow!("Invalid --resolve {} (use host:ip or host:port:ip)", entry);
+
+    let (host, rest) = entry.split_once(':').ok_or_else(invalid)?;
+    let parse_ip = |addr: &str| {
+        addr.trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<std::net::IpAddr>()
+    };
+    // A bare IPv6 address also contains ':', so try the whole rest first
+    let (port, ip) = match parse_ip(rest) {
+        Ok(ip) => (0, ip),
+        Err(_) => {
+            let (port, addr) = rest.split_once(':').ok_or_else(invalid)?;
+            let port: u16 = port.parse().map_err(|_| invalid())?;
+            (port, parse_ip(addr).map_err(|_| invalid())?)
+        }
+    };
+
+    // reqwest always connects to the URL's port; the port only matters to curl
+    Ok((host.to_string(), std::net::SocketAddr::new(ip, port)))
+}
+
+fn color_cache_status(status: &str) -> String {
+    match status.to_uppercase().as_str() {
+        "HIT" | "REVALIDATED" | "UPDATING" | "STALE" => status.green().to_string(),
+        "MISS" | "EXPIRED" => status.yellow().to_string(),
+        "BYPASS" | "DYNAMIC" => status.red().to_string(),
+        _ => status.to_string(),
+    }
+}
+
+/// Outcome of `cache_verdict`
+#[derive(Debug, Default)]
+struct Verdict {
+    /// Why the response would not be (or was not) served from cache
+    reasons: Vec<String>,
+    /// How the zone's settings affect caching without preventing it
+    notes: Vec<String>,
+}
+
+/// Judge whether the response looks cacheable under the zone's settings.
+/// `second` is the cf-cache-status of the repeated request, with --twice.
+fn cache_verdict(
+    url: &str,
+    status: u16,
+    headers: &reqwest::header::HeaderMap,
+    second: Option<&str>,
+    zone: Option<&ZoneCache>,
+) -> Verdict {
+    let mut reasons = Vec::new();
+    let mut notes = Vec::new();
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_lowercase())
+    };
+
+    let cache_status = header("cf-cache-status");
+    // Any status other than these means the response was eligible for cache
+    let eligible = !matches!(cache_status.as_deref(), None | Some("dynamic" | "bypass"));
+
+    match cache_status.as_deref() {
+        None => reasons.push(
+            "No cf-cache-status header: not proxied through Cloudflare (check DNS or --resolve)"
+                .to_string(),
+        ),
+        Some("dynamic") => reasons.push(
+            "DYNAMIC: not eligible for cache by default; add a cache rule to cache it".to_string(),
+        ),
+        Some("bypass") => reasons
+            .push("BYPASS: origin headers or a rule told Cloudflare not to cache".to_string()),
+        _ => {}
+    }
+
+    if let Some(second) = second.map(str::to_lowercase) {
+        if eligible
+            && !matches!(
+                second.as_str(),
+                "hit" | "stale" | "updating" | "revalidated"
+            )
+        {
+            reasons.push(format!(
+                "2nd request was {} instead of HIT: the response was not stored (check Cache-Control and the cache TTL)",
+                second.to_uppercase()
+            ));
+        }
+    }
+
+    if ![200, 206, 301, 302, 303, 404, 410].contains(&status) {
+        reasons.push(format!("Status {} is not cached by default", status));
+    }
+
+    if let Some(cc) = header("cache-control") {
+        for directive in ["private", "no-store", "no-cache", "max-age=0", "s-maxage=0"] {
+            if cc.split(',').any(|d| d.trim() == directive) {
+                reasons.push(format!("Cache-Control: {} prevents caching", directive));
+            }
+        }
+    }
+
+    if headers.contains_key("set-cookie") {
+        reasons.push("Set-Cookie in response prevents caching".to_string());
+    }
+
+    if let Some(vary) = header("vary") {
+        let other: Vec<&str> = vary
+            .split(',')
+            .map(str::trim)
+            .filter(|v| !v.is_empty() && *v != "accept-encoding" && *v != "accept")
+            .collect();
+        if !other.is_empty() {
+            reasons.push(format!(
+                "Vary: {} prevents caching (only Accept-Encoding is supported)",
+                other.join(", ")
+            ));
+        }
+    }
+
+    let parsed = reqwest::Url::parse(url).ok();
+    let has_query = parsed
+        .as_ref()
+        .and_then(|u| u.query())
+        .is_some_and(|q| !q.is_empty());
+    let extension = parsed.as_ref().and_then(|u| {
+        u.path()
+            .rsplit('/')
+            .next()
+            .and_then(|f| f.rsplit_once('.'))
+            .map(|(_, ext)| ext.to_lowercase())
+    });
+    let default_cached = extension
+        .as_deref()
+        .is_some_and(|e| DEFAULT_CACHED_EXTENSIONS.contains(&e));
+
+    let rule = zone.and_then(|z| z.matching_rule(url));
+    match rule {
+        Some(rule) if rule.cache => notes.push(format!(
+            "Cache rule '{}' makes this URL eligible for cache",
+            rule.description
+        )),
+        Some(rule) => reasons.push(format!(
+            "Cache rule '{}' bypasses the cache for this URL",
+            rule.description
+        )),
+        None => {}
+    }
+    let rule_caches = rule.is_some_and(|r| r.cache);
+
+    if !default_cached && !eligible && !rule_caches {
+        reasons.push(format!(
+            "Extension {} is not cached by default (needs a cache rule)",
+            extension.as_deref().unwrap_or("(none)")
+        ));
+    }
+
+    if let Some(zone) = zone {
+        match zone.cache_level.as_deref() {
+            Some("basic") if has_query && !rule_caches => reasons.push(
+                "Cache level basic (no query string): URLs with a query string are not cached"
+                    .to_string(),
+            ),
+            Some("simplified") if has_query => notes.push(
+                "Cache level simplified ignores the query string: all variants share one cached copy"
+                    .to_string(),
+            ),
+            _ => {}
+        }
+
+        // A zone Browser Cache TTL only replaces a shorter (or missing) origin max-age
+        if let Some(ttl) = zone.browser_cache_ttl.filter(|ttl| *ttl > 0) {
+            let max_age = header("cache-control").and_then(|cc| {
+                cc.split(',')
+                    .find_map(|d| d.trim().strip_prefix("max-age=")?.parse::<u64>().ok())
+            });
+            if max_age.is_none_or(|age| age < ttl) {
+                notes.push(format!(
+                    "Browser Cache TTL {}s overrides the origin's {} for browsers",
+                    ttl,
+                    max_age
+                        .map(|age| format!("max-age={}", age))
+                        .unwrap_or_else(|| "missing max-age".to_string())
+                ));
+            }
+        }
+    }
+
+    Verdict { reasons, notes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(*name, HeaderValue::from_static(value));
+        }
+        map
+    }
+
+    fn zone(level: &str, ttl: u64, rules: &[(&str, bool)]) -> ZoneCache {
+        ZoneCache {
+            cache_level: Some(level.to_string()),
+            browser_cache_ttl: Some(ttl),
+            rules: rules
+                .iter()
+                .map(|(expression, cache)| CacheRuleSummary {
+                    description: expression.to_string(),
+                    expression: expression.to_string(),
+                    cache: *cache,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn verdict_from_response_headers() {
+        let hit = headers(&[
+            ("cf-cache-status", "HIT"),
+            ("cache-control", "public, max-age=3600"),
+        ]);
+        let verdict = cache_verdict("https://example.com/app.js", 200, &hit, None, None);
+        assert!(verdict.reasons.is_empty(), "{:?}", verdict.reasons);
+
+        let private = headers(&[
+            ("cf-cache-status", "BYPASS"),
+            ("cache-control", "private, max-age=0"),
+            ("set-cookie", "a=b"),
+            ("vary", "Accept-Encoding, User-Agent"),
+        ]);
+        let reasons = cache_verdict("https://example.com/page", 500, &private, None, None).reasons;
+        assert_eq!(
+            reasons,
+            [
+                "BYPASS: origin headers or a rule told Cloudflare not to cache",
+                "Status 500 is not cached by default",
+                "Cache-Control: private prevents caching",
+                "Cache-Control: max-age=0 prevents caching",
+                "Set-Cookie in response prevents caching",
+                "Vary: user-agent prevents caching (only Accept-Encoding is supported)",
+                "Extension (none) is not cached by default (needs a cache rule)",
+            ]
+        );
+
+        let reasons = cache_verdict(
+            "https://example.com/a.css",
+            200,
+            &HeaderMap::new(),
+            None,
+            None,
+        )
+        .reasons;
+        assert!(reasons[0].starts_with("No cf-cache-status header"));
+    }
+
+    #[test]
+    fn verdict_checks_the_second_request() {
+        let miss = headers(&[("cf-cache-status", "MISS")]);
+        let url = "https://example.com/logo.png";
+        assert!(cache_verdict(url, 200, &miss, Some("HIT"), None)
+            .reasons
+            .is_empty());
+        assert_eq!(
+            cache_verdict(url, 200, &miss, Some("MISS"), None).reasons,
+            ["2nd request was MISS instead of HIT: the response was not stored (check Cache-Control and the cache TTL)"]
+        );
+        // DYNAMIC is never stored, so a second DYNAMIC says nothing new
+        let dynamic = headers(&[("cf-cache-status", "DYNAMIC")]);
+        assert_eq!(
+            cache_verdict(url, 200, &dynamic, Some("DYNAMIC"), None)
+                .reasons
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn verdict_uses_zone_settings() {
+        let miss = headers(&[("cf-cache-status", "MISS"), ("cache-control", "max-age=60")]);
+        let url = "https://example.com/logo.png?v=2";
+
+        let basic = zone("basic", 0, &[]);
+        assert_eq!(
+            cache_verdict(url, 200, &miss, None, Some(&basic)).reasons,
+            ["Cache level basic (no query string): URLs with a query string are not cached"]
+        );
+        let aggressive = zone("aggressive", 0, &[]);
+        let verdict = cache_verdict(url, 200, &miss, None, Some(&aggressive));
+        assert!(verdict.reasons.is_empty() && verdict.notes.is_empty());
+
+        let simplified = zone("simplified", 14400, &[]);
+        assert_eq!(
+            cache_verdict(url, 200, &miss, None, Some(&simplified)).notes,
+            [
+                "Cache level simplified ignores the query string: all variants share one cached copy",
+                "Browser Cache TTL 14400s overrides the origin's max-age=60 for browsers",
+            ]
+        );
+    }
+
+    #[test]
+    fn verdict_applies_the_last_matching_cache_rule() {
+        let dynamic = headers(&[("cf-cache-status", "DYNAMIC")]);
+        let url = "https://example.com/api/items";
+        let rules = zone(
+            "aggressive",
+            0,
+            &[
+                (r#"starts_with(http.request.uri.path, "/api")"#, true),
+                (r#"http.request.uri.path eq "/api/private""#, false),
+            ],
+        );
+
+        // The cache rule replaces the extension warning
+        let verdict = cache_verdict(url, 200, &dynamic, None, Some(&rules));
+        assert_eq!(
+            verdict.notes,
+            [
+                r#"Cache rule 'starts_with(http.request.uri.path, "/api")' makes this URL eligible for cache"#
+            ]
+        );
+        assert_eq!(verdict.reasons.len(), 1);
+
+        let private = cache_verdict(
+            "https://example.com/api/private",
+            200,
+            &dynamic,
+            None,
+            Some(&rules),
+        );
+        assert!(private.reasons[1].contains("bypasses the cache for this URL"));
+    }
+
+    #[test]
+    fn parses_resolve_entries() {
+        let (host, addr) = parse_resolve("example.com:127.0.0.1").unwrap();
+        assert_eq!(host, "example.com");
+        assert_eq!(addr, "127.0.0.1:0".parse().unwrap());
+
+        let (_, addr) = parse_resolve("example.com:443:[::1]").unwrap();
+        assert_eq!(addr, "[::1]:443".parse().unwrap());
+        let (_, addr) = parse_resolve("example.com:2001:db8::1").unwrap();
+        assert_eq!(
+            addr.ip(),
+            "2001:db8::1".parse::<std::net::IpAddr>().unwrap()
+        );
+
+        for invalid in ["example.com", "example.com:nope", "example.com:443:"] {
+            assert!(parse_resolve(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_sends_requests_to_a_local_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let n = socket.read(&mut request).await.unwrap();
+            socket
+                .write_all(
+                    b"HTTP/1.1 200 OK\r\ncf-cache-status: HIT\r\ncache-control: max-age=600\r\ncontent-length: 0\r\n\r\n",
+                )
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&request[..n]).to_lowercase()
+        });
+
+        let (host, addr) = parse_resolve("cache.test:127.0.0.1").unwrap();
+        let http = reqwest::Client::builder()
+            .resolve(&host, addr)
+            .build()
+            .unwrap();
+        let url = format!("http://cache.test:{}/style.css", port);
+        let response = http.get(&url).send().await.unwrap();
+
+        let verdict = cache_verdict(&url, 200, response.headers(), None, None);
+        assert!(verdict.reasons.is_empty(), "{:?}", verdict.reasons);
+        assert!(server
+            .await
+            .unwrap()
+            .contains(&format!("host: cache.test:{}", port)));
+    }
+
+    fn purge_input(from_file: Option<String>) -> PurgeInput {
+        PurgeInput {
+            from_file,
+            batch_size: None,
+            delay: 0,
+            dry_run: true,
+        }
+    }
+
+    fn temp_file(name: &str, content: &str) -> String {
+        let path = std::env::temp_dir().join(format!("cli5-{}-{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn batch_size_defaults_and_limits_per_kind() {
+        assert_eq!(batch_size("files", None).unwrap(), 30);
+        assert_eq!(batch_size("tags", None).unwrap(), 100);
+        assert_eq!(batch_size("prefixes", None).unwrap(), 100);
+        assert_eq!(batch_size("hosts", None).unwrap(), 100);
+        assert_eq!(batch_size("files", Some(500)).unwrap(), 500);
+        assert!(batch_size("hosts", Some(501)).is_err());
+        assert!(batch_size("tags", Some(0)).is_err());
+    }
+
+    #[test]
+    fn rate_limits_come_from_the_status_and_retry_after() {
+        let limited: anyhow::Error = ApiError {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            retry_after: Some(Duration::from_secs(7)),
+            body: String::new(),
+        }
+        .into();
+        assert!(is_rate_limited(&limited));
+        assert_eq!(retry_wait(&limited, 1), Duration::from_secs(7));
+
+        let long: anyhow::Error = ApiError {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            retry_after: Some(Duration::from_secs(3600)),
+            body: String::new(),
+        }
+        .into();
+        assert_eq!(retry_wait(&long, 1), MAX_RETRY_WAIT);
+
+        let bare: anyhow::Error = ApiError {
+            status: StatusCode::TOO_MANY_REQUESTS,
+            retry_after: None,
+            body: String::new(),
+        }
+        .into();
+        assert_eq!(retry_wait(&bare, 3), Duration::from_secs(8));
+
+        // Only the status counts, not wording in the body
+        let other: anyhow::Error = ApiError {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            retry_after: None,
+            body: "rate limit (429)".to_string(),
+        }
+        .into();
+        assert!(!is_rate_limited(&other));
+        assert!(!is_rate_limited(&anyhow!("429 rate limit")));
+    }
+
+    #[test]
+    fn collects_targets_from_args_and_file() {
+        let path = temp_file(
+            "targets.txt",
+            "# purge list\nhttps://example.com/c\n\n   https://example.com/d  \n",
+        );
+        let args = vec![
+            "https://example.com/a, https://example.com/b".to_string(),
+            ",".to_string(),
+        ];
+        let targets = collect_targets(&args, &purge_input(Some(path.clone()))).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            targets,
+            vec![
+                "https://example.com/a",
+                "https://example.com/b",
+                "https://example.com/c",
+                "https://example.com/d",
+            ]
+        );
+
+        let missing = purge_input(Some("/nonexistent/cli5-targets.txt".to_string()));
+        assert!(collect_targets(&args, &missing).is_err());
+    }
+
+    #[test]
+    fn cache_key_headers_expand_to_every_combination() {
+        let urls = vec!["https://example.com/".to_string()];
+        assert_eq!(
+            with_cache_key_headers(urls.clone(), &[]).unwrap(),
+            vec![json!("https://example.com/")]
+        );
+
+        let items = with_cache_key_headers(
+            urls.clone(),
+            &[
+                "CF-Device-Type: mobile".to_string(),
+                "CF-Device-Type: desktop".to_string(),
+                "CF-IPCountry=US".to_string(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            items,
+            vec![
+                json!({"url": "https://example.com/", "headers": {"CF-Device-Type": "mobile", "CF-IPCountry": "US"}}),
+                json!({"url": "https://example.com/", "headers": {"CF-Device-Type": "desktop", "CF-IPCountry": "US"}}),
+            ]
+        );
+
+        assert!(with_cache_key_headers(urls, &["CF-Device-Type".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn sitemap_follows_local_and_remote_indexes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let body = "<urlset><url><loc>https://example.com/remote</loc></url></urlset>";
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let _ = socket.read(&mut request).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/xml\r\ncontent-length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let pages = temp_file(
+            "pages.xml",
+            "<?xml version=\"1.0\"?>\n<urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n  <url><loc> https://example.com/a </loc></url>\n  <url><loc>https://example.com/b</loc><lastmod>2026-01-01</lastmod></url>\n</urlset>\n",
+        );
+        let index = temp_file(
+            "index.xml",
+            &format!(
+                "<sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n  <sitemap><loc>{}</loc></sitemap>\n  <sitemap><loc>http://127.0.0.1:{}/sitemap.xml</loc></sitemap>\n</sitemapindex>\n",
+                pages, port
+            ),
+        );
+        let not_a_sitemap = temp_file("feed.xml", "<rss><channel/></rss>");
+
+        let mut urls = sitemap_urls(&index).await.unwrap();
+        urls.sort();
+        let rss = sitemap_urls(&not_a_sitemap).await;
+        for path in [&pages, &index, &not_a_sitemap] {
+            std::fs::remove_file(path).unwrap();
+        }
+        server.await.unwrap();
+
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/a",
+                "https://example.com/b",
+                "https://example.com/remote",
+            ]
+        );
+        assert!(rss.is_err());
+    }
+}
@@ -0,0 +1,140 @@
+//! Shell completion command
+
+use anyhow::Result;
+use clap::{Args, CommandFactory};
+
+use super::Cli;
+use crate::api::settings as schema;
+
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+    /// Shell
+    #[arg(value_parser = ["bash", "zsh", "fish"])]
+    pub shell: String,
+}
+
+pub fn execute(args: CompletionsArgs) -> Result<()> {
+    print!("{}", completion_script(&args.shell));
+    Ok(())
+}
+
+/// Shell completion for all of cli5, generated from the clap definitions
+///
+/// Setting names and values for `settings get|set|describe <name> [value]`
+/// come from the schema; a wrapper completes those and hands everything else
+/// to the generated completion.
+fn completion_script(shell: &str) -> String {
+    let generator = match shell {
+        "bash" => clap_complete::Shell::Bash,
+        "zsh" => clap_complete::Shell::Zsh,
+        _ => clap_complete::Shell::Fish,
+    };
+    let mut generated = Vec::new();
+    clap_complete::generate(generator, &mut Cli::command(), "cli5", &mut generated);
+    let mut script = String::from_utf8_lossy(&generated).into_owned();
+
+    let names: Vec<&str> = schema::SCHEMA.iter().map(|s| s.name).collect();
+    let with_values: Vec<(&str, String)> = schema::SCHEMA
+        .iter()
+        .map(|s| (s.name, s.kind.values().join(" ")))
+        .filter(|(_, values)| !values.is_empty())
+        .collect();
+
+    match shell {
+        "bash" => {
+            let cases: String = with_values
+                .iter()
+                .map(|(name, values)| {
+                    format!(
+                        "                        {}) values=\"{}\" ;;\n",
+                        name, values
+                    )
+                })
+                .collect();
+            script.push_str(&format!(
+                r#"
+# cli5 settings names and values (bash)
+_cli5_settings() {{
+    local cur=${{COMP_WORDS[COMP_CWORD]}} i sub="" name="" pos=0
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case ${{COMP_WORDS[i]}} in
+            -*) ;;
+            *) pos=$((pos + 1))
+               [[ $pos -eq 2 ]] && sub=${{COMP_WORDS[i]}}
+               [[ $pos -eq 3 ]] && name=${{COMP_WORDS[i]}} ;;
+        esac
+    done
+    if [[ ${{COMP_WORDS[1]}} == settings && $cur != -* ]]; then
+        case $sub in
+            get|set|describe)
+                if [[ -z $name ]]; then
+                    COMPREPLY=($(compgen -W "{}" -- "$cur"))
+                    return
+                elif [[ $sub == set ]]; then
+                    local values=""
+                    case $name in
+{}                    esac
+                    COMPREPLY=($(compgen -W "$values" -- "$cur"))
+                    return
+                fi ;;
+        esac
+    fi
+    _cli5 "$@"
+}}
+complete -F _cli5_settings -o bashdefault -o default cli5
+"#,
+                names.join(" "),
+                cases
+            ));
+        }
+        "zsh" => {
+            let cases: String = with_values
+                .iter()
+                .map(|(name, values)| {
+                    format!("                        {}) values=({}) ;;\n", name, values)
+                })
+                .collect();
+            script.push_str(&format!(
+                r#"
+# cli5 settings names and values (zsh)
+_cli5_settings() {{
+    local -a words_=(${{words:#-*}}) values
+    if [[ ${{words_[2]}} == settings && ${{words[CURRENT]}} != -* ]]; then
+        case ${{words_[3]}} in
+            get|set|describe)
+                if (( ${{#words_}} <= 4 )); then
+                    compadd -- {}
+                    return
+                elif [[ ${{words_[3]}} == set ]]; then
+                    case ${{words_[4]}} in
+{}                    esac
+                    compadd -a values
+                    return
+                fi ;;
+        esac
+    fi
+    _cli5 "$@"
+}}
+compdef _cli5_settings cli5
+"#,
+                names.join(" "),
+                cases
+            ));
+        }
+        _ => {
+            script.push_str("\n# cli5 settings names and values (fish)\n");
+            script.push_str(&format!(
+                "complete -c cli5 -n '__fish_seen_subcommand_from settings; and __fish_seen_subcommand_from get set describe; and test (count (commandline -opc)) -le 3' -f -a '{}'\n",
+                names.join(" ")
+            ));
+            for (name, values) in &with_values {
+                script.push_str(&format!(
+                    "complete -c cli5 -n '__fish_seen_subcommand_from set; and __fish_seen_subcommand_from {}' -f -a '{}'\n",
+                    name, values
+                ));
+            }
+        }
+    }
+
+    script
+}
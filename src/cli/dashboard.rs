@@ -0,0 +1,545 @@
+//! Full-screen live analytics dashboard for `analytics dashboard`
+
+use std::future::Future;
+use std::io::Write;
+use std::pin::Pin;
+use std::time::Duration as StdDuration;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use console::{Key, Term};
+use futures_util::future::join_all;
+use owo_colors::OwoColorize;
+
+use super::analytics::{
+    auto_granularity, count_rows, fetch_series, format_bytes, query_range, Point,
+    FIREWALL_ADAPTIVE, HTTP_ADAPTIVE,
+};
+use super::multizone::{Zone, ZoneSelection};
+use super::timerange::{TimeRange, TimeRangeArgs};
+use crate::api::graphql;
+use crate::api::CloudflareClient;
+use crate::output::chart;
+
+/// A top-N list below the overview
+struct Panel {
+    title: &'static str,
+    dataset: &'static str,
+    build: fn(&str, &str, &str, u32) -> Result<String>,
+    /// Labels are HTTP status codes, colored by class
+    statuses: bool,
+}
+
+const PANELS: &[Panel] = &[
+    Panel {
+        title: "Top paths",
+        dataset: HTTP_ADAPTIVE,
+        build: graphql::top_urls_query,
+        statuses: false,
+    },
+    Panel {
+        title: "Countries",
+        dataset: HTTP_ADAPTIVE,
+        build: graphql::top_countries_query,
+        statuses: false,
+    },
+    Panel {
+        title: "Status codes",
+        dataset: HTTP_ADAPTIVE,
+        build: graphql::status_codes_query,
+        statuses: true,
+    },
+    Panel {
+        title: "Firewall events",
+        dataset: FIREWALL_ADAPTIVE,
+        build: graphql::firewall_events_query,
+        statuses: false,
+    },
+];
+
+/// Longest window the dashboard shows, so every panel is a single query
+const MAX_WINDOW_HOURS: i64 = 24;
+
+/// Rows of a panel: label and count
+type Rows = Vec<(String, u64)>;
+
+/// One refresh worth of data; failed parts keep their error message
+struct Snapshot {
+    range: TimeRange,
+    granularity: &'static str,
+    points: std::result::Result<Vec<Point>, String>,
+    panels: Vec<std::result::Result<Rows, String>>,
+    fetched_at: DateTime<Utc>,
+}
+
+struct State {
+    zones: Vec<Zone>,
+    zone: usize,
+    panel: usize,
+    scroll: usize,
+    interval: u64,
+    loading: bool,
+    snapshot: Option<Snapshot>,
+}
+
+enum Action {
+    None,
+    Refresh,
+    Quit,
+}
+
+/// Restores the terminal when the dashboard ends, also on errors
+struct Screen<'a>(&'a Term);
+
+impl<'a> Screen<'a> {
+    fn enter(term: &'a Term) -> Result<Self> {
+        // Alternate screen buffer, so the shell scrollback is left alone
+        write!(&mut &*term, "\x1b[?1049h")?;
+        term.hide_cursor()?;
+        Ok(Screen(term))
+    }
+}
+
+impl Drop for Screen<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.show_cursor();
+        let _ = write!(&mut &*self.0, "\x1b[?1049l");
+        let _ = self.0.flush();
+    }
+}
+
+/// Run the dashboard until q, Esc or Ctrl-C
+pub async fn run(
+    client: &CloudflareClient,
+    start: Option<String>,
+    names: &[String],
+    time: &TimeRangeArgs,
+    interval: u64,
+    limit: u32,
+) -> Result<()> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        return Err(anyhow!("The dashboard needs an interactive terminal"));
+    }
+    let range = time.resolve()?;
+    if range.until - range.since > Duration::hours(MAX_WINDOW_HOURS) {
+        return Err(anyhow!(
+            "The dashboard shows at most the last {} hours; use a shorter --since",
+            MAX_WINDOW_HOURS
+        ));
+    }
+
+    let zones = resolve_zones(client, start, names).await?;
+
+    // console reads keys in blocking calls, so they come from a thread
+    let (keys, mut key_events) = tokio::sync::mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let term = Term::stdout();
+        while let Ok(key) = term.read_key() {
+            // Stop reading after a quit key, leaving the terminal out of raw mode
+            let quit = matches!(key, Key::Char('q') | Key::Escape | Key::CtrlC);
+            if keys.send(key).is_err() || quit {
+                break;
+            }
+        }
+    });
+
+    let _screen = Screen::enter(&term)?;
+    let mut state = State {
+        zones,
+        zone: 0,
+        panel: 0,
+        scroll: 0,
+        interval,
+        loading: true,
+        snapshot: None,
+    };
+    let mut ticker = tokio::time::interval(StdDuration::from_secs(interval));
+    // The refresh in flight; polled alongside key events so keys stay responsive,
+    // and dropped (cancelling its requests) on a zone switch or quit
+    let mut refresh: Option<Pin<Box<dyn Future<Output = Snapshot> + '_>>> = None;
+
+    loop {
+        draw(&term, &state)?;
+        tokio::select! {
+            _ = ticker.tick() => {
+                // A slow refresh is left to finish rather than restarted
+                if refresh.is_none() {
+                    state.loading = true;
+                    let zone_id = state.zones[state.zone].id.clone();
+                    refresh = Some(Box::pin(async move {
+                        fetch(client, &zone_id, time, limit).await
+                    }));
+                }
+            }
+            snapshot = async {
+                match refresh.as_mut() {
+                    Some(pending) => pending.await,
+                    None => std::future::pending().await,
+                }
+            } => {
+                refresh = None;
+                state.snapshot = Some(snapshot);
+                state.loading = false;
+            }
+            key = key_events.recv() => {
+                let Some(key) = key else { break };
+                match state.handle(key) {
+                    Action::None => {}
+                    Action::Refresh => {
+                        // Data for the previous zone (or an older request) is no longer wanted
+                        refresh = None;
+                        ticker.reset_immediately();
+                    }
+                    Action::Quit => break,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Zones to switch between, starting with `start` (--zone or the default zone)
+async fn resolve_zones(
+    client: &CloudflareClient,
+    start: Option<String>,
+    names: &[String],
+) -> Result<Vec<Zone>> {
+    let selection = ZoneSelection {
+        zones: names.to_vec(),
+        all_zones: false,
+        zone_filter: Vec::new(),
+        concurrency: 1,
+    };
+    let mut zones = selection.resolve(client).await?;
+
+    if let Some(start) = start {
+        match zones.iter().position(|z| z.name == start || z.id == start) {
+            Some(index) => zones.rotate_left(index),
+            None => {
+                let id = client.resolve_zone_id(&start).await?;
+                zones.insert(0, Zone { name: start, id });
+            }
+        }
+    }
+    if zones.is_empty() {
+        return Err(anyhow!("No zones to show"));
+    }
+    Ok(zones)
+}
+
+impl State {
+    fn handle(&mut self, key: Key) -> Action {
+        match key {
+            Key::Char('q') | Key::Escape | Key::CtrlC => return Action::Quit,
+            Key::Char('r') => return Action::Refresh,
+            Key::Tab | Key::ArrowRight | Key::Char('l') => {
+                self.panel = (self.panel + 1) % PANELS.len();
+                self.scroll = 0;
+            }
+            Key::BackTab | Key::ArrowLeft | Key::Char('h') => {
+                self.panel = (self.panel + PANELS.len() - 1) % PANELS.len();
+                self.scroll = 0;
+            }
+            Key::Char(c @ '1'..='9') => {
+                let index = c as usize - '1' as usize;
+                if index < PANELS.len() {
+                    self.panel = index;
+                    self.scroll = 0;
+                }
+            }
+            Key::ArrowDown | Key::Char('j') => {
+                let rows = match &self.snapshot {
+                    Some(Snapshot { panels, .. }) => {
+                        panels[self.panel].as_ref().map_or(0, Vec::len)
+                    }
+                    None => 0,
+                };
+                self.scroll = (self.scroll + 1).min(rows.saturating_sub(1));
+            }
+            Key::ArrowUp | Key::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            Key::Char('z') | Key::Char('Z') if self.zones.len() > 1 => {
+                let count = self.zones.len();
+                self.zone = if key == Key::Char('z') {
+                    (self.zone + 1) % count
+                } else {
+                    (self.zone + count - 1) % count
+                };
+                self.scroll = 0;
+                self.snapshot = None;
+                return Action::Refresh;
+            }
+            _ => {}
+        }
+        Action::None
+    }
+}
+
+/// Query the traffic series and every panel for the current window
+async fn fetch(
+    client: &CloudflareClient,
+    zone_id: &str,
+    time: &TimeRangeArgs,
+    limit: u32,
+) -> Snapshot {
+    // Relative windows move along with every refresh
+    let range = match time.resolve() {
+        Ok(range) => range,
+        Err(e) => {
+            return Snapshot {
+                range: TimeRange {
+                    since: Utc::now(),
+                    until: Utc::now(),
+                },
+                granularity: "1h",
+                points: Err(e.to_string()),
+                panels: PANELS.iter().map(|_| Err(e.to_string())).collect(),
+                fetched_at: Utc::now(),
+            };
+        }
+    };
+    let granularity = auto_granularity(&range);
+
+    let series = fetch_series(client, zone_id, &range, granularity);
+    let panels = join_all(PANELS.iter().map(|panel| async {
+        let response = query_range(
+            client,
+            &range,
+            panel.dataset,
+            "count_DESC",
+            Some(limit as usize),
+            |since, until| (panel.build)(zone_id, since, until, limit),
+        )
+        .await?;
+        Ok::<_, anyhow::Error>(count_rows(&response, panel.dataset))
+    }));
+    let (points, panels) = tokio::join!(series, panels);
+
+    Snapshot {
+        range,
+        granularity,
+        points: points.map_err(|e| e.to_string()),
+        panels: panels
+            .into_iter()
+            .map(|rows| rows.map_err(|e| e.to_string()))
+            .collect(),
+        fetched_at: Utc::now(),
+    }
+}
+
+fn draw(term: &Term, state: &State) -> Result<()> {
+    let (rows, columns) = term.size();
+    let (rows, columns) = (rows as usize, columns as usize);
+    let mut lines = header(state);
+
+    match &state.snapshot {
+        None => lines.push(format!(" {}", "Loading…".dimmed())),
+        Some(snapshot) => {
+            lines.extend(overview(snapshot, columns));
+            lines.push(String::new());
+            lines.push(tabs(state.panel));
+            let height = rows.saturating_sub(lines.len() + 2);
+            lines.extend(panel(
+                &snapshot.panels[state.panel],
+                state.panel,
+                state.scroll,
+                height,
+                columns,
+            ));
+        }
+    }
+
+    let footer = " ←/→ panel  ↑/↓ scroll  z/Z zone  r refresh  q quit";
+    while lines.len() + 1 < rows {
+        lines.push(String::new());
+    }
+    lines.truncate(rows.saturating_sub(1));
+    lines.push(footer.dimmed().to_string());
+
+    let mut frame = String::from("\x1b[H");
+    for (i, line) in lines.iter().enumerate() {
+        frame.push_str(&console::truncate_str(line, columns, ""));
+        frame.push_str("\x1b[K");
+        if i + 1 < lines.len() {
+            frame.push_str("\r\n");
+        }
+    }
+    frame.push_str("\x1b[J");
+    write!(&mut &*term, "{}", frame)?;
+    term.flush()?;
+    Ok(())
+}
+
+fn header(state: &State) -> Vec<String> {
+    let zone = &state.zones[state.zone];
+    let position = if state.zones.len() > 1 {
+        format!(" ({}/{})", state.zone + 1, state.zones.len())
+    } else {
+        String::new()
+    };
+    let status = match (&state.snapshot, state.loading) {
+        (_, true) => "refreshing…".yellow().to_string(),
+        (Some(snapshot), false) => format!(
+            "updated {} · every {}s",
+            snapshot.fetched_at.format("%H:%M:%S UTC"),
+            state.interval
+        )
+        .dimmed()
+        .to_string(),
+        (None, false) => String::new(),
+    };
+    let window = state
+        .snapshot
+        .as_ref()
+        .map(|s| {
+            format!(
+                "{} to {} · {} buckets",
+                s.range.since.format("%m-%d %H:%M"),
+                s.range.until.format("%m-%d %H:%M"),
+                s.granularity
+            )
+        })
+        .unwrap_or_default();
+
+    vec![
+        format!(
+            " {}{}   {}   {}",
+            zone.name.bold(),
+            position,
+            window.dimmed(),
+            status
+        ),
+        String::new(),
+    ]
+}
+
+fn overview(snapshot: &Snapshot, columns: usize) -> Vec<String> {
+    let points = match &snapshot.points {
+        Ok(points) if !points.is_empty() => points,
+        Ok(_) => return vec![format!(" {}", "No traffic in this window".dimmed())],
+        Err(e) => return vec![format!(" {} {}", "Traffic:".red(), e)],
+    };
+
+    let total = |value: fn(&Point) -> u64| points.iter().map(value).sum::<u64>();
+    let requests = total(|p| p.requests);
+    let percent = |part: u64| {
+        if requests == 0 {
+            0.0
+        } else {
+            part as f64 * 100.0 / requests as f64
+        }
+    };
+    let bucket_seconds = match snapshot.granularity {
+        "1m" => 60.0,
+        "1d" => 86400.0,
+        _ => 3600.0,
+    };
+    // The last bucket is still filling up, so the rate comes from the one before
+    let latest = points
+        .len()
+        .checked_sub(2)
+        .map_or(&points[0], |i| &points[i]);
+    let window_seconds = (snapshot.range.until - snapshot.range.since)
+        .num_seconds()
+        .max(1) as f64;
+
+    let mut lines = vec![format!(
+        " {} {:.1} (avg {:.1})   {} {}   {} {}   {} {:.1}%   {} {}   {} {:.2}%",
+        "req/s".bold(),
+        latest.requests as f64 / bucket_seconds,
+        requests as f64 / window_seconds,
+        "requests".bold(),
+        chart::human_count(requests as f64),
+        "bandwidth".bold(),
+        format_bytes(total(|p| p.bytes)),
+        "cache hit".bold(),
+        percent(total(|p| p.cached_requests)),
+        "threats".bold(),
+        chart::human_count(total(|p| p.threats) as f64),
+        "5xx".bold(),
+        percent(total(|p| p.errors)),
+    )];
+    lines.push(String::new());
+
+    let width = columns.saturating_sub(13).max(10);
+    let series: [(&str, Vec<f64>); 4] = [
+        (
+            "requests",
+            points.iter().map(|p| p.requests as f64).collect(),
+        ),
+        ("bandwidth", points.iter().map(|p| p.bytes as f64).collect()),
+        ("cache hit", points.iter().map(Point::cache_ratio).collect()),
+        ("5xx rate", points.iter().map(Point::error_rate).collect()),
+    ];
+    for (label, values) in series {
+        let line = chart::sparkline(&chart::resample(&values, width));
+        lines.push(format!(" {:<10} {}", label, line.cyan()));
+    }
+    lines
+}
+
+fn tabs(selected: usize) -> String {
+    let tabs: Vec<String> = PANELS
+        .iter()
+        .enumerate()
+        .map(|(i, panel)| {
+            let title = format!(" {} {} ", i + 1, panel.title);
+            if i == selected {
+                title.reversed().bold().to_string()
+            } else {
+                title.dimmed().to_string()
+            }
+        })
+        .collect();
+    format!(" {}", tabs.join(" "))
+}
+
+fn panel(
+    rows: &std::result::Result<Rows, String>,
+    index: usize,
+    scroll: usize,
+    height: usize,
+    columns: usize,
+) -> Vec<String> {
+    let rows = match rows {
+        Ok(rows) if rows.is_empty() => return vec![format!(" {}", "No data".dimmed())],
+        Ok(rows) => rows,
+        Err(e) => return vec![format!(" {} {}", "Error:".red(), e)],
+    };
+
+    let total: u64 = rows.iter().map(|(_, count)| count).sum();
+    let max = rows.iter().map(|(_, count)| *count).max().unwrap_or(0) as f64;
+    let bar_width = (columns / 5).clamp(5, 30);
+    let scroll = scroll.min(rows.len().saturating_sub(height.max(1)));
+
+    rows.iter()
+        .skip(scroll)
+        .take(height)
+        .map(|(label, count)| {
+            let share = if total == 0 {
+                0.0
+            } else {
+                *count as f64 * 100.0 / total as f64
+            };
+            let bar = chart::bar(*count as f64, max, bar_width);
+            let padding = bar_width.saturating_sub(bar.chars().count());
+            let label = if PANELS[index].statuses {
+                match label.chars().next() {
+                    Some('5') => label.red().to_string(),
+                    Some('4') => label.yellow().to_string(),
+                    _ => label.green().to_string(),
+                }
+            } else {
+                label.clone()
+            };
+            format!(
+                " {:>9} {:>5.1}% {}{} {}",
+                chart::human_count(*count as f64),
+                share,
+                bar.cyan(),
+                " ".repeat(padding),
+                label
+            )
+        })
+        .collect()
+}
@@ -1,11 +1,22 @@
 //! Firewall command
 
-use anyhow::Result;
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use clap::{Args, Subcommand};
-use serde_json::json;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
-use crate::api::CloudflareClient;
+use super::lists::{self, IpEntry};
+use super::multizone::{self, Zone, ZoneSelection};
+use super::timerange::parse_duration;
+use crate::api::client::ApiError;
+use crate::api::rulesets;
+use crate::api::{self, CloudflareClient};
 use crate::config::Config;
+use crate::expr;
 use crate::output;
 
 #[derive(Args, Debug)]
@@ -14,6 +25,17 @@ pub struct FirewallArgs {
     #[arg(short, long)]
     pub zone: Option<String>,
 
+    /// Manage account-level access rules (apply to all zones)
+    #[arg(long, conflicts_with_all = ["zone", "user", "zones", "all_zones", "zone_filter"])]
+    pub account: bool,
+
+    /// Manage user-level access rules (apply to all zones you own)
+    #[arg(long, conflicts_with_all = ["zone", "zones", "all_zones", "zone_filter"])]
+    pub user: bool,
+
+    #[command(flatten)]
+    pub targets: ZoneSelection,
+
     #[command(subcommand)]
     pub command: FirewallCommand,
 }
@@ -21,16 +43,36 @@ pub struct FirewallArgs {
 #[derive(Subcommand, Debug)]
 pub enum FirewallCommand {
     /// List access rules
-    List,
+    List {
+        /// Only rules with this mode
+        #[arg(short, long, value_parser = ACCESS_RULE_MODES)]
+        mode: Option<String>,
+
+        /// Only rules with this target
+        #[arg(short, long, value_parser = ACCESS_RULE_TARGETS)]
+        target: Option<String>,
 
-    /// Block an IP address
+        /// Only rules for this exact value (IP, range, ASN or country)
+        #[arg(long)]
+        value: Option<String>,
+
+        /// Only rules whose notes contain this text
+        #[arg(long)]
+        notes: Option<String>,
+    },
+
+    /// Block an IP address or range
     BlockIp {
-        /// IP address to block
+        /// IP address or CIDR range to block
         ip: String,
 
         /// Note/reason
         #[arg(short, long)]
         note: Option<String>,
+
+        /// Remove the rule after this long (e.g. 30m, 24h, 7d; see `firewall expire`)
+        #[arg(long)]
+        expires: Option<String>,
     },
 
     /// Block a country
@@ -41,26 +83,104 @@ pub enum FirewallCommand {
         /// Note/reason
         #[arg(short, long)]
         note: Option<String>,
+
+        /// Remove the rule after this long (e.g. 30m, 24h, 7d; see `firewall expire`)
+        #[arg(long)]
+        expires: Option<String>,
     },
 
-    /// Whitelist an IP address
+    /// Block an autonomous system
+    BlockAsn {
+        /// AS number (e.g., AS13335 or 13335)
+        asn: String,
+
+        /// Note/reason
+        #[arg(short, long)]
+        note: Option<String>,
+
+        /// Remove the rule after this long (e.g. 30m, 24h, 7d; see `firewall expire`)
+        #[arg(long)]
+        expires: Option<String>,
+    },
+
+    /// Whitelist an IP address or range
     WhitelistIp {
-        /// IP address to whitelist
+        /// IP address or CIDR range to whitelist
         ip: String,
 
         /// Note/reason
         #[arg(short, long)]
         note: Option<String>,
+
+        /// Remove the rule after this long (e.g. 30m, 24h, 7d; see `firewall expire`)
+        #[arg(long)]
+        expires: Option<String>,
     },
 
-    /// Challenge an IP (CAPTCHA)
+    /// Challenge an IP or range (CAPTCHA)
     ChallengeIp {
-        /// IP address to challenge
+        /// IP address or CIDR range to challenge
         ip: String,
 
         /// Note/reason
         #[arg(short, long)]
         note: Option<String>,
+
+        /// Remove the rule after this long (e.g. 30m, 24h, 7d; see `firewall expire`)
+        #[arg(long)]
+        expires: Option<String>,
+    },
+
+    /// Create an access rule for any target (IP, range, ASN or country)
+    Add {
+        /// Value; the target is detected (1.2.3.4, 10.0.0.0/24, 2001:db8::1, AS13335, DE)
+        value: String,
+
+        /// Rule mode
+        #[arg(short, long, default_value = "block", value_parser = ACCESS_RULE_MODES)]
+        mode: String,
+
+        /// Override the detected target
+        #[arg(short, long, value_parser = ACCESS_RULE_TARGETS)]
+        target: Option<String>,
+
+        /// Note/reason
+        #[arg(short, long)]
+        note: Option<String>,
+
+        /// Remove the rule after this long (e.g. 30m, 24h, 7d; see `firewall expire`)
+        #[arg(long)]
+        expires: Option<String>,
+    },
+
+    /// Apply many IPs/CIDRs at once, as access rules or IP list items
+    Block {
+        /// IPs or CIDRs (comma-separated or repeated)
+        ips: Vec<String>,
+
+        /// Read IPs/CIDRs from a file (one per line or CSV `ip,comment`; '-' for stdin)
+        #[arg(long, required_unless_present = "ips")]
+        from_file: Option<String>,
+
+        /// Access rule mode
+        #[arg(short, long, default_value = "block", value_parser = ACCESS_RULE_MODES)]
+        mode: String,
+
+        /// Add to this account IP list (name or ID) instead of creating access rules
+        #[arg(long, conflicts_with = "expires")]
+        list: Option<String>,
+
+        /// Note/reason (per-line CSV comments take precedence)
+        #[arg(short, long)]
+        note: Option<String>,
+
+        /// Remove the rules after this long (e.g. 30m, 24h, 7d; see `firewall expire`)
+        #[arg(long)]
+        expires: Option<String>,
+
+        /// Only validate the input and show what would be applied
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Delete an access rule
@@ -69,149 +189,1303 @@ pub enum FirewallCommand {
         id: String,
     },
 
+    /// Delete access rules whose --expires time has passed
+    Expire {
+        /// Show the recorded expiry times instead of deleting
+        #[arg(long)]
+        list: bool,
+
+        /// Show what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// List firewall rules
     Rules,
 
     /// List WAF packages (Pro+)
     Waf,
+
+    /// Rate limiting rules (http_ratelimit phase)
+    Ratelimit {
+        #[command(subcommand)]
+        cmd: RatelimitCommand,
+    },
+
+    /// Check and test rule expressions offline
+    Expr {
+        #[command(subcommand)]
+        cmd: ExprCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RatelimitCommand {
+    /// List rate limiting rules
+    List,
+
+    /// Create a rate limiting rule
+    Create {
+        #[command(flatten)]
+        rule: RatelimitArgs,
+
+        /// Start from a template (flags override its values)
+        #[arg(long, value_parser = ["login-protection", "api-protection", "site-wide"])]
+        preset: Option<String>,
+    },
+
+    /// Update a rate limiting rule (only given fields change)
+    Update {
+        /// Rule ID
+        rule_id: String,
+
+        #[command(flatten)]
+        rule: RatelimitArgs,
+    },
+
+    /// Delete a rate limiting rule
+    Delete {
+        /// Rule ID
+        rule_id: String,
+
+        /// Skip confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct RatelimitArgs {
+    /// Requests matching this expression are counted
+    #[arg(short, long)]
+    pub expression: Option<String>,
+
+    /// Counting characteristics: ip, asn, country, ja3, header:NAME, cookie:NAME, query:NAME (comma-separated)
+    #[arg(short, long, value_delimiter = ',')]
+    pub characteristics: Vec<String>,
+
+    /// Counting period in seconds
+    #[arg(short, long, value_parser = ["10", "60", "120", "300", "600", "3600"])]
+    pub period: Option<String>,
+
+    /// Requests allowed per period
+    #[arg(short, long)]
+    pub requests: Option<u64>,
+
+    /// Seconds to keep mitigating once the limit is hit (0 for challenge actions)
+    #[arg(short, long)]
+    pub timeout: Option<u64>,
+
+    /// Action when the limit is exceeded
+    #[arg(short, long, value_parser = ["block", "challenge", "js_challenge", "managed_challenge", "log"])]
+    pub action: Option<String>,
+
+    /// Rule description
+    #[arg(short, long)]
+    pub description: Option<String>,
+
+    /// Send the expression without validating it locally first
+    #[arg(long)]
+    pub no_check: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExprCommand {
+    /// Parse an expression and report syntax errors and unknown fields
+    Check {
+        /// Expression (or use --file)
+        #[arg(required_unless_present = "file")]
+        expression: Option<String>,
+
+        /// Read the expression from a file
+        #[arg(long, conflicts_with = "expression")]
+        file: Option<String>,
+    },
+
+    /// Evaluate an expression against a sample request
+    Eval {
+        /// Expression (or use --file)
+        #[arg(required_unless_present = "file")]
+        expression: Option<String>,
+
+        /// Read the expression from a file
+        #[arg(long, conflicts_with = "expression")]
+        file: Option<String>,
+
+        /// Sample request JSON (url, method, headers, ip, country, asn, body, fields, lists)
+        #[arg(short, long)]
+        request: String,
+    },
+
+    /// List known fields
+    Fields {
+        /// Only show fields containing this text
+        filter: Option<String>,
+    },
+
+    /// List known functions
+    Functions,
+}
+
+const ACCESS_RULE_MODES: [&str; 5] = [
+    "block",
+    "challenge",
+    "js_challenge",
+    "managed_challenge",
+    "whitelist",
+];
+
+const ACCESS_RULE_TARGETS: [&str; 5] = ["ip", "ip6", "ip_range", "asn", "country"];
+
+/// Local file with access rules created with --expires
+const EXPIRY_FILE: &str = "expiring_access_rules.json";
+
+const PHASE_RATELIMIT: &str = "http_ratelimit";
+
+/// Rate limiting presets: (name, description, expression, period, requests, timeout, action)
+const RATELIMIT_PRESETS: &[(&str, &str, &str, u64, u64, u64, &str)] = &[
+    (
+        "login-protection",
+        "Login brute-force protection",
+        r#"http.request.method eq "POST" and http.request.uri.path in {"/login" "/signin" "/wp-login.php" "/user/login"}"#,
+        60,
+        5,
+        600,
+        "block",
+    ),
+    (
+        "api-protection",
+        "API rate limit",
+        r#"starts_with(http.request.uri.path, "/api/")"#,
+        60,
+        100,
+        60,
+        "block",
+    ),
+    (
+        "site-wide",
+        "Site-wide flood protection",
+        "true",
+        10,
+        50,
+        0,
+        "managed_challenge",
+    ),
+];
+
+impl FirewallCommand {
+    /// Commands that work without API credentials
+    pub fn is_offline(&self) -> bool {
+        matches!(
+            self,
+            FirewallCommand::Expr { .. } | FirewallCommand::Expire { list: true, .. }
+        )
+    }
+}
+
+/// Where access rules are managed
+enum Scope {
+    Zone(String),
+    Account(String),
+    User,
+}
+
+impl Scope {
+    /// Base path of the access rules endpoint
+    fn rules_path(&self) -> String {
+        match self {
+            Scope::Zone(id) => format!("/zones/{}/firewall/access_rules/rules", id),
+            Scope::Account(id) => format!("/accounts/{}/firewall/access_rules/rules", id),
+            Scope::User => "/user/firewall/access_rules/rules".to_string(),
+        }
+    }
+
+    fn zone_id(&self) -> Result<&str> {
+        match self {
+            Scope::Zone(id) => Ok(id),
+            _ => Err(anyhow!(
+                "This command needs a zone; --account/--user only apply to access rules"
+            )),
+        }
+    }
 }
 
 pub async fn execute(config: &Config, args: FirewallArgs) -> Result<()> {
+    if let FirewallCommand::Expr { cmd } = args.command {
+        return execute_expr(cmd);
+    }
+
     let client = CloudflareClient::new(config.clone())?;
-    let zone = config.resolve_zone(args.zone.as_deref())?;
-    let zone_id = client.resolve_zone_id(&zone).await?;
+
+    if let FirewallCommand::Expire { list, dry_run } = args.command {
+        return expire_rules(&client, list, dry_run).await;
+    }
+
+    if args.targets.is_active() {
+        let zones = args.targets.resolve(&client).await?;
+        return execute_multi(&client, &zones, args.targets.concurrency, args.command).await;
+    }
+
+    let scope = if args.user {
+        Scope::User
+    } else if args.account {
+        Scope::Account(get_account_id(&client).await?)
+    } else {
+        let zone = config.resolve_zone(args.zone.as_deref())?;
+        Scope::Zone(client.resolve_zone_id(&zone).await?)
+    };
 
     match args.command {
-        FirewallCommand::List => {
-            let response = client
-                .get_raw(&format!("/zones/{}/firewall/access_rules/rules", zone_id))
-                .await?;
+        FirewallCommand::List {
+            mode,
+            target,
+            value,
+            notes,
+        } => {
+            let mut filters = Vec::new();
+            if let Some(mode) = mode {
+                filters.push(("mode", mode));
+            }
+            if let Some(target) = target {
+                filters.push(("configuration.target", target));
+            }
+            if let Some(value) = value {
+                filters.push(("configuration.value", value));
+            }
+            if let Some(notes) = notes {
+                filters.push(("notes", notes));
+            }
 
-            if let Some(rules) = response.get("result").and_then(|r| r.as_array()) {
-                output::table_header(&["MODE", "TARGET", "VALUE", "NOTES", "ID"]);
-                for rule in rules {
-                    output::print_firewall_rule(rule);
-                }
-                output::info(&format!("Total: {} rules", rules.len()));
+            let rules = access_rules(&client, &scope, &filters).await?;
+
+            output::table_header(&["MODE", "TARGET", "VALUE", "NOTES", "ID"]);
+            for rule in &rules {
+                output::print_firewall_rule(rule);
             }
+            output::info(&format!("Total: {} rules", rules.len()));
         }
 
-        FirewallCommand::BlockIp { ip, note } => {
-            let body = json!({
-                "mode": "block",
-                "configuration": {
-                    "target": "ip",
-                    "value": ip
-                },
-                "notes": note.unwrap_or_default()
-            });
-
-            let response = client
-                .post_raw(
-                    &format!("/zones/{}/firewall/access_rules/rules", zone_id),
-                    body,
-                )
-                .await?;
+        FirewallCommand::BlockIp { ip, note, expires } => {
+            let target = ip_target(&ip)?;
+            let rule =
+                create_access_rule(&client, &scope, "block", target, &ip, note, expires).await?;
             output::success(&format!("Blocked IP: {}", ip));
+            output::print_firewall_rule(&rule);
+        }
 
-            if let Some(result) = response.get("result") {
-                output::print_firewall_rule(result);
-            }
+        FirewallCommand::BlockCountry {
+            code,
+            note,
+            expires,
+        } => {
+            let code = code.to_uppercase();
+            let rule =
+                create_access_rule(&client, &scope, "block", "country", &code, note, expires)
+                    .await?;
+            output::success(&format!("Blocked country: {}", code));
+            output::print_firewall_rule(&rule);
         }
 
-        FirewallCommand::BlockCountry { code, note } => {
-            let body = json!({
-                "mode": "block",
-                "configuration": {
-                    "target": "country",
-                    "value": code.to_uppercase()
-                },
-                "notes": note.unwrap_or_default()
-            });
+        FirewallCommand::BlockAsn { asn, note, expires } => {
+            let asn = normalize_asn(&asn)?;
+            let rule =
+                create_access_rule(&client, &scope, "block", "asn", &asn, note, expires).await?;
+            output::success(&format!("Blocked ASN: {}", asn));
+            output::print_firewall_rule(&rule);
+        }
 
-            let response = client
-                .post_raw(
-                    &format!("/zones/{}/firewall/access_rules/rules", zone_id),
-                    body,
-                )
+        FirewallCommand::WhitelistIp { ip, note, expires } => {
+            let target = ip_target(&ip)?;
+            let rule = create_access_rule(&client, &scope, "whitelist", target, &ip, note, expires)
                 .await?;
-            output::success(&format!("Blocked country: {}", code.to_uppercase()));
-
-            if let Some(result) = response.get("result") {
-                output::print_firewall_rule(result);
-            }
+            output::success(&format!("Whitelisted IP: {}", ip));
+            output::print_firewall_rule(&rule);
         }
 
-        FirewallCommand::WhitelistIp { ip, note } => {
-            let body = json!({
-                "mode": "whitelist",
-                "configuration": {
-                    "target": "ip",
-                    "value": ip
-                },
-                "notes": note.unwrap_or_default()
-            });
-
-            let response = client
-                .post_raw(
-                    &format!("/zones/{}/firewall/access_rules/rules", zone_id),
-                    body,
-                )
+        FirewallCommand::ChallengeIp { ip, note, expires } => {
+            let target = ip_target(&ip)?;
+            let rule = create_access_rule(&client, &scope, "challenge", target, &ip, note, expires)
                 .await?;
-            output::success(&format!("Whitelisted IP: {}", ip));
+            output::success(&format!("Challenge enabled for IP: {}", ip));
+            output::print_firewall_rule(&rule);
+        }
 
-            if let Some(result) = response.get("result") {
-                output::print_firewall_rule(result);
-            }
+        FirewallCommand::Add {
+            value,
+            mode,
+            target,
+            note,
+            expires,
+        } => {
+            let (target, value) = match target {
+                Some(target) if target == "asn" => (target, normalize_asn(&value)?),
+                Some(target) => (target, value),
+                None => detect_target(&value)?,
+            };
+            let rule =
+                create_access_rule(&client, &scope, &mode, &target, &value, note, expires).await?;
+            output::success(&format!("Created {} rule for {} {}", mode, target, value));
+            output::print_firewall_rule(&rule);
         }
 
-        FirewallCommand::ChallengeIp { ip, note } => {
-            let body = json!({
-                "mode": "challenge",
-                "configuration": {
-                    "target": "ip",
-                    "value": ip
-                },
-                "notes": note.unwrap_or_default()
-            });
+        FirewallCommand::Block {
+            ips,
+            from_file,
+            mode,
+            list,
+            note,
+            expires,
+            dry_run,
+        } => {
+            let input = lists::read_ip_entries(&ips, from_file.as_deref())?;
+            input.report();
+            if input.entries.is_empty() {
+                return Err(anyhow!("No valid IPs or CIDRs given"));
+            }
 
-            let response = client
-                .post_raw(
-                    &format!("/zones/{}/firewall/access_rules/rules", zone_id),
-                    body,
-                )
-                .await?;
-            output::success(&format!("Challenge enabled for IP: {}", ip));
+            match list {
+                Some(list) => {
+                    if dry_run {
+                        for entry in &input.entries {
+                            println!("{}", entry.value);
+                        }
+                        output::info(&format!(
+                            "Dry run: {} item(s) would be added to list {}",
+                            input.entries.len(),
+                            list
+                        ));
+                        return Ok(());
+                    }
 
-            if let Some(result) = response.get("result") {
-                output::print_firewall_rule(result);
+                    let account_id = match &scope {
+                        Scope::Zone(zone_id) => zone_account_id(&client, zone_id).await?,
+                        Scope::Account(id) => id.clone(),
+                        Scope::User => get_account_id(&client).await?,
+                    };
+                    let target = api::lists::find(&client, &account_id, &list).await?;
+                    let added = lists::add_to_list(
+                        &client,
+                        &account_id,
+                        &target,
+                        &input.entries,
+                        note.as_deref(),
+                    )
+                    .await?;
+                    output::success(&format!("Added {} item(s) to list {}", added, list));
+                    output::info(&format!(
+                        "Reference it in a rule expression with: ip.src in ${}",
+                        list
+                    ));
+                }
+                None => {
+                    let expires_at = expires.as_deref().map(expiry_time).transpose()?;
+                    block_access_rules(
+                        &client,
+                        &scope,
+                        &input.entries,
+                        &mode,
+                        note,
+                        expires_at,
+                        dry_run,
+                    )
+                    .await?;
+                }
             }
         }
 
         FirewallCommand::Delete { id } => {
-            client
-                .delete_raw(&format!(
-                    "/zones/{}/firewall/access_rules/rules/{}",
-                    zone_id, id
-                ))
-                .await?;
+            let path = format!("{}/{}", scope.rules_path(), id);
+            client.delete_raw(&path).await?;
+            forget_expiry(&[path])?;
             output::success(&format!("Deleted firewall rule: {}", id));
         }
 
         FirewallCommand::Rules => {
             let response = client
-                .get_raw(&format!("/zones/{}/firewall/rules", zone_id))
+                .get_raw(&format!("/zones/{}/firewall/rules", scope.zone_id()?))
                 .await?;
             output::print_output(&response.get("result"), &config.output_format)?;
         }
 
         FirewallCommand::Waf => {
             let response = client
-                .get_raw(&format!("/zones/{}/firewall/waf/packages", zone_id))
+                .get_raw(&format!(
+                    "/zones/{}/firewall/waf/packages",
+                    scope.zone_id()?
+                ))
                 .await?;
             output::print_output(&response.get("result"), &config.output_format)?;
         }
+
+        FirewallCommand::Ratelimit { cmd } => {
+            execute_ratelimit(&client, &format!("/zones/{}", scope.zone_id()?), cmd).await?;
+        }
+
+        FirewallCommand::Expr { .. } | FirewallCommand::Expire { .. } => unreachable!(), // Handled above
     }
 
     Ok(())
 }
+
+/// Create or count access rules on many zones
+async fn execute_multi(
+    client: &CloudflareClient,
+    zones: &[Zone],
+    concurrency: usize,
+    command: FirewallCommand,
+) -> Result<()> {
+    let (mode, target, value, note, expires) = match command {
+        FirewallCommand::List {
+            mode,
+            target,
+            value,
+            notes,
+        } => {
+            let filters: Vec<(&str, String)> = [
+                ("mode", mode),
+                ("configuration.target", target),
+                ("configuration.value", value),
+                ("notes", notes),
+            ]
+            .into_iter()
+            .filter_map(|(key, v)| v.map(|v| (key, v)))
+            .collect();
+            let filters = &filters;
+
+            return multizone::run(zones, concurrency, |zone| async move {
+                let rules = access_rules(client, &Scope::Zone(zone.id), filters).await?;
+                Ok(format!("{} rule(s)", rules.len()))
+            })
+            .await;
+        }
+        FirewallCommand::BlockIp { ip, note, expires } => (
+            "block".to_string(),
+            ip_target(&ip)?.to_string(),
+            ip,
+            note,
+            expires,
+        ),
+        FirewallCommand::BlockCountry {
+            code,
+            note,
+            expires,
+        } => (
+            "block".to_string(),
+            "country".to_string(),
+            code.to_uppercase(),
+            note,
+            expires,
+        ),
+        FirewallCommand::BlockAsn { asn, note, expires } => (
+            "block".to_string(),
+            "asn".to_string(),
+            normalize_asn(&asn)?,
+            note,
+            expires,
+        ),
+        FirewallCommand::WhitelistIp { ip, note, expires } => (
+            "whitelist".to_string(),
+            ip_target(&ip)?.to_string(),
+            ip,
+            note,
+            expires,
+        ),
+        FirewallCommand::ChallengeIp { ip, note, expires } => (
+            "challenge".to_string(),
+            ip_target(&ip)?.to_string(),
+            ip,
+            note,
+            expires,
+        ),
+        FirewallCommand::Add {
+            value,
+            mode,
+            target,
+            note,
+            expires,
+        } => {
+            let (target, value) = match target {
+                Some(target) if target == "asn" => (target, normalize_asn(&value)?),
+                Some(target) => (target, value),
+                None => detect_target(&value)?,
+            };
+            (mode, target, value, note, expires)
+        }
+        _ => {
+            return Err(anyhow!(
+                "Multiple zones are only supported by list, add, block-* and whitelist/challenge-ip"
+            ))
+        }
+    };
+    // Fail on a bad --expires before touching any zone
+    expires.as_deref().map(expiry_time).transpose()?;
+
+    let (mode, target, value, note, expires) = (&mode, &target, &value, &note, &expires);
+    multizone::run(zones, concurrency, |zone| async move {
+        let rule = create_access_rule(
+            client,
+            &Scope::Zone(zone.id),
+            mode,
+            target,
+            value,
+            note.clone(),
+            expires.clone(),
+        )
+        .await?;
+        Ok(format!(
+            "{} {} {} ({})",
+            mode,
+            target,
+            value,
+            rule.get("id").and_then(|v| v.as_str()).unwrap_or("-")
+        ))
+    })
+    .await
+}
+
+async fn execute_ratelimit(
+    client: &CloudflareClient,
+    base: &str,
+    cmd: RatelimitCommand,
+) -> Result<()> {
+    match cmd {
+        RatelimitCommand::List => {
+            let rules = match rulesets::get_entrypoint(client, base, PHASE_RATELIMIT).await? {
+                Some(ruleset) => rulesets::rules(&ruleset),
+                None => Vec::new(),
+            };
+
+            output::table_header(&["#", "STATE", "ACTION", "DESCRIPTION", "ID"]);
+            for (i, rule) in rules.iter().enumerate() {
+                output::print_ratelimit_rule(i + 1, rule);
+            }
+            output::info(&format!("Total: {} rate limiting rules", rules.len()));
+        }
+
+        RatelimitCommand::Create { rule, preset } => {
+            let mut body = match preset.as_deref() {
+                Some(name) => {
+                    let (_, description, expression, period, requests, timeout, action) =
+                        RATELIMIT_PRESETS
+                            .iter()
+                            .find(|p| p.0 == name)
+                            .ok_or_else(|| anyhow!("Unknown preset: {}", name))?;
+                    json!({
+                        "description": description,
+                        "expression": expression,
+                        "action": action,
+                        "ratelimit": {
+                            "characteristics": ["ip.src", "cf.colo.id"],
+                            "period": period,
+                            "requests_per_period": requests,
+                            "mitigation_timeout": timeout
+                        }
+                    })
+                }
+                None => json!({ "action": "block", "ratelimit": {} }),
+            };
+            apply_ratelimit_args(&mut body, &rule)?;
+
+            for (key, flag) in [
+                ("/expression", "--expression"),
+                ("/ratelimit/period", "--period"),
+                ("/ratelimit/requests_per_period", "--requests"),
+            ] {
+                if body.pointer(key).is_none() {
+                    return Err(anyhow!("{} is required (or use --preset)", flag));
+                }
+            }
+            if body.pointer("/ratelimit/characteristics").is_none() {
+                body["ratelimit"]["characteristics"] = json!(["ip.src", "cf.colo.id"]);
+            }
+            if body.pointer("/ratelimit/mitigation_timeout").is_none() {
+                let challenge = body["action"].as_str().is_some_and(is_challenge);
+                body["ratelimit"]["mitigation_timeout"] = json!(if challenge { 0 } else { 60 });
+            }
+
+            let ruleset = rulesets::ensure_entrypoint(client, base, PHASE_RATELIMIT).await?;
+            let updated = rulesets::add_rule(client, base, &rulesets::id(&ruleset)?, body).await?;
+            output::success("Created rate limiting rule");
+
+            let rules = rulesets::rules(&updated);
+            if let Some(created) = rules.last() {
+                output::print_ratelimit_rule(rules.len(), created);
+            }
+        }
+
+        RatelimitCommand::Update { rule_id, rule } => {
+            let ruleset = rulesets::resolve(client, base, PHASE_RATELIMIT).await?;
+            let current = rulesets::find_rule(&ruleset, &rule_id)?;
+
+            // Start from the current ratelimit block so unchanged settings are kept
+            let mut changes = json!({ "ratelimit": current["ratelimit"].clone() });
+            apply_ratelimit_args(&mut changes, &rule)?;
+
+            let updated = rulesets::update_rule(client, base, &ruleset, &rule_id, changes).await?;
+            output::success(&format!("Updated rate limiting rule: {}", rule_id));
+
+            let rules = rulesets::rules(&updated);
+            if let Some(i) = rules
+                .iter()
+                .position(|r| r.get("id").and_then(|v| v.as_str()) == Some(rule_id.as_str()))
+            {
+                output::print_ratelimit_rule(i + 1, &rules[i]);
+            }
+        }
+
+        RatelimitCommand::Delete { rule_id, yes } => {
+            if !yes {
+                output::warning(&format!(
+                    "Are you sure you want to delete rate limiting rule {}?",
+                    rule_id
+                ));
+                output::info("Use -y to skip this confirmation");
+                return Ok(());
+            }
+
+            let ruleset = rulesets::resolve(client, base, PHASE_RATELIMIT).await?;
+            rulesets::delete_rule(client, base, &rulesets::id(&ruleset)?, &rule_id).await?;
+            output::success(&format!("Deleted rate limiting rule: {}", rule_id));
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply rate limit flags on top of a rule body
+fn apply_ratelimit_args(body: &mut Value, args: &RatelimitArgs) -> Result<()> {
+    if let Some(ref expression) = args.expression {
+        if !args.no_check {
+            super::rulesets::check_expression(expression)?;
+        }
+        body["expression"] = json!(expression);
+    }
+    if let Some(ref action) = args.action {
+        body["action"] = json!(action);
+    }
+    if let Some(ref description) = args.description {
+        body["description"] = json!(description);
+    }
+
+    if !body["ratelimit"].is_object() {
+        body["ratelimit"] = json!({});
+    }
+    let ratelimit = &mut body["ratelimit"];
+
+    if !args.characteristics.is_empty() {
+        let mut fields = args
+            .characteristics
+            .iter()
+            .map(|c| characteristic_field(c))
+            .collect::<Result<Vec<_>>>()?;
+        // Counting is always per data center
+        if !fields.iter().any(|f| f == "cf.colo.id") {
+            fields.push("cf.colo.id".to_string());
+        }
+        ratelimit["characteristics"] = json!(fields);
+    }
+    if let Some(ref period) = args.period {
+        ratelimit["period"] = json!(period.parse::<u64>()?);
+    }
+    if let Some(requests) = args.requests {
+        ratelimit["requests_per_period"] = json!(requests);
+    }
+    if let Some(timeout) = args.timeout {
+        ratelimit["mitigation_timeout"] = json!(timeout);
+    }
+
+    // Challenges are issued per request, so the API only accepts a timeout of 0
+    if body["action"].as_str().is_some_and(is_challenge) {
+        match args.timeout {
+            Some(0) => {}
+            Some(timeout) => {
+                return Err(anyhow!(
+                    "--timeout must be 0 for challenge actions (got {})",
+                    timeout
+                ))
+            }
+            None if args.action.is_some() => body["ratelimit"]["mitigation_timeout"] = json!(0),
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn is_challenge(action: &str) -> bool {
+    action.contains("challenge")
+}
+
+/// Map a characteristic shorthand to the field Cloudflare counts by
+fn characteristic_field(value: &str) -> Result<String> {
+    let field = match value.split_once(':') {
+        Some(("header", name)) => format!(r#"http.request.headers["{}"]"#, name.to_lowercase()),
+        Some(("cookie", name)) => format!(r#"http.request.cookies["{}"]"#, name),
+        Some(("query", name)) => format!(r#"http.request.uri.args["{}"]"#, name),
+        Some((kind, _)) => return Err(anyhow!("Unknown characteristic type: {}", kind)),
+        None => match value {
+            "ip" => "ip.src".to_string(),
+            "asn" => "ip.src.asnum".to_string(),
+            "country" => "ip.src.country".to_string(),
+            "ja3" => "cf.bot_management.ja3_hash".to_string(),
+            "host" => "http.host".to_string(),
+            other => other.to_string(),
+        },
+    };
+    Ok(field)
+}
+
+/// Create one access rule per entry, skipping values that already have a rule
+async fn block_access_rules(
+    client: &CloudflareClient,
+    scope: &Scope,
+    entries: &[IpEntry],
+    mode: &str,
+    note: Option<String>,
+    expires_at: Option<DateTime<Utc>>,
+    dry_run: bool,
+) -> Result<()> {
+    // Access rules only support single IPs and a few range sizes
+    let (supported, unsupported): (Vec<&IpEntry>, Vec<&IpEntry>) = entries
+        .iter()
+        .partition(|e| access_rule_target(e).is_some());
+    for entry in &unsupported {
+        output::warning(&format!(
+            "{}: access rules only accept /16 or /24 IPv4 and /32, /48 or /64 IPv6 ranges (use --list)",
+            entry.value
+        ));
+    }
+
+    let existing: HashSet<String> = access_rules(client, scope, &[])
+        .await?
+        .iter()
+        .filter_map(|r| r.pointer("/configuration/value").and_then(|v| v.as_str()))
+        .map(String::from)
+        .collect();
+
+    let (skipped, pending): (Vec<&IpEntry>, Vec<&IpEntry>) = supported
+        .into_iter()
+        .partition(|e| existing.contains(&e.value));
+    if !skipped.is_empty() {
+        output::info(&format!(
+            "{} IP(s) already have an access rule",
+            skipped.len()
+        ));
+    }
+
+    if dry_run {
+        for entry in &pending {
+            println!(
+                "{}\t{}",
+                access_rule_target(entry).unwrap_or("-"),
+                entry.value
+            );
+        }
+        output::info(&format!(
+            "Dry run: {} access rule(s) would be created with mode {}",
+            pending.len(),
+            mode
+        ));
+        return Ok(());
+    }
+
+    let path = scope.rules_path();
+    let mut failed = Vec::new();
+    let mut records = Vec::new();
+
+    for (i, entry) in pending.iter().enumerate() {
+        let body = json!({
+            "mode": mode,
+            "configuration": {
+                "target": access_rule_target(entry),
+                "value": entry.value
+            },
+            "notes": entry.comment.clone().or_else(|| note.clone()).unwrap_or_default()
+        });
+
+        match client.post_raw(&path, body).await {
+            Ok(response) => {
+                if let (Some(at), Some(id)) = (
+                    expires_at,
+                    response.pointer("/result/id").and_then(|v| v.as_str()),
+                ) {
+                    records.push(ExpiryRecord::new(&path, id, mode, &entry.value, at));
+                }
+            }
+            Err(e) => failed.push((entry.value.clone(), e)),
+        }
+        output::progress(i + 1, pending.len(), &entry.value);
+    }
+
+    remember_expiry(records)?;
+
+    for (value, e) in &failed {
+        output::error(&format!("{}: {}", value, e));
+    }
+    output::success(&format!(
+        "Created {} access rule(s) with mode {}",
+        pending.len() - failed.len(),
+        mode
+    ));
+    if let Some(at) = expires_at {
+        output::info(&format!("Rules expire at {}", at.to_rfc3339()));
+    }
+
+    if !failed.is_empty() || !unsupported.is_empty() {
+        return Err(anyhow!(
+            "{} failed, {} unsupported",
+            failed.len(),
+            unsupported.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Create a single access rule, recording its expiry if requested
+async fn create_access_rule(
+    client: &CloudflareClient,
+    scope: &Scope,
+    mode: &str,
+    target: &str,
+    value: &str,
+    note: Option<String>,
+    expires: Option<String>,
+) -> Result<Value> {
+    let expires_at = expires.as_deref().map(expiry_time).transpose()?;
+
+    let body = json!({
+        "mode": mode,
+        "configuration": {
+            "target": target,
+            "value": value
+        },
+        "notes": note.unwrap_or_default()
+    });
+
+    let path = scope.rules_path();
+    let response = client.post_raw(&path, body).await?;
+    let rule = response.get("result").cloned().unwrap_or_default();
+
+    if let Some(at) = expires_at {
+        let id = rule
+            .get("id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Created rule has no ID; cannot record expiry"))?;
+        remember_expiry(vec![ExpiryRecord::new(&path, id, mode, value, at)])?;
+        output::info(&format!("Expires at {}", at.to_rfc3339()));
+    }
+
+    Ok(rule)
+}
+
+/// Access rule target for an entry, if access rules support it
+fn access_rule_target(entry: &IpEntry) -> Option<&'static str> {
+    match (entry.ip.is_ipv4(), entry.prefix) {
+        (true, 32) => Some("ip"),
+        (false, 128) => Some("ip6"),
+        (true, 16 | 24) | (false, 32 | 48 | 64) => Some("ip_range"),
+        _ => None,
+    }
+}
+
+/// Target for an IP or CIDR value
+fn ip_target(value: &str) -> Result<&'static str> {
+    let (ip, prefix) =
+        expr::parse_cidr(value).ok_or_else(|| anyhow!("Not an IP address or CIDR: {}", value))?;
+    let entry = IpEntry {
+        value: value.to_string(),
+        ip,
+        prefix,
+        comment: None,
+    };
+    access_rule_target(&entry).ok_or_else(|| {
+        anyhow!(
+            "{}: access rules only accept /16 or /24 IPv4 and /32, /48 or /64 IPv6 ranges",
+            value
+        )
+    })
+}
+
+/// Detect the target of a value: IP, IP range, ASN or country
+fn detect_target(value: &str) -> Result<(String, String)> {
+    if expr::parse_cidr(value).is_some() {
+        return Ok((ip_target(value)?.to_string(), value.to_string()));
+    }
+    if let Ok(asn) = normalize_asn(value) {
+        return Ok(("asn".to_string(), asn));
+    }
+    if value.len() == 2 && value.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Ok(("country".to_string(), value.to_uppercase()));
+    }
+    Err(anyhow!("Cannot detect target for {}; use --target", value))
+}
+
+/// Normalize `13335` / `as13335` to `AS13335`
+fn normalize_asn(value: &str) -> Result<String> {
+    let digits = value
+        .strip_prefix("AS")
+        .or_else(|| value.strip_prefix("as"))
+        .unwrap_or(value);
+    digits
+        .parse::<u32>()
+        .map(|n| format!("AS{}", n))
+        .map_err(|_| anyhow!("Not an AS number: {}", value))
+}
+
+/// Fetch all access rules of a scope (all pages), with optional filters
+async fn access_rules(
+    client: &CloudflareClient,
+    scope: &Scope,
+    filters: &[(&str, String)],
+) -> Result<Vec<Value>> {
+    let mut all = Vec::new();
+    let mut page = 1;
+
+    let mut params: Vec<(&str, String)> = filters.to_vec();
+    if !filters.is_empty() {
+        params.push(("match", "all".to_string()));
+    }
+
+    loop {
+        let mut query = params.clone();
+        query.push(("per_page", "1000".to_string()));
+        query.push(("page", page.to_string()));
+        let url = reqwest::Url::parse_with_params("https://api.cloudflare.com/", &query)?;
+
+        let response = client
+            .get_raw(&format!(
+                "{}?{}",
+                scope.rules_path(),
+                url.query().unwrap_or_default()
+            ))
+            .await?;
+
+        if let Some(rules) = response.get("result").and_then(|r| r.as_array()) {
+            all.extend(rules.iter().cloned());
+        }
+
+        let total_pages = response
+            .pointer("/result_info/total_pages")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(1);
+        if page >= total_pages {
+            break;
+        }
+        page += 1;
+    }
+
+    Ok(all)
+}
+
+/// Access rule created with --expires
+#[derive(Debug, Serialize, Deserialize)]
+struct ExpiryRecord {
+    /// Full API path of the rule (scope included)
+    path: String,
+    id: String,
+    mode: String,
+    value: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl ExpiryRecord {
+    fn new(rules_path: &str, id: &str, mode: &str, value: &str, at: DateTime<Utc>) -> Self {
+        Self {
+            path: format!("{}/{}", rules_path, id),
+            id: id.to_string(),
+            mode: mode.to_string(),
+            value: value.to_string(),
+            expires_at: at,
+        }
+    }
+}
+
+/// Parse an expiry like `30m`, `24h`, `7d` or `2w` into an absolute time
+fn expiry_time(value: &str) -> Result<DateTime<Utc>> {
+    let duration = parse_duration(value)
+        .ok_or_else(|| anyhow!("Invalid duration: {} (use e.g. 30m, 24h, 7d)", value))?;
+    Utc::now()
+        .checked_add_signed(duration)
+        .ok_or_else(|| anyhow!("Duration too long: {}", value))
+}
+
+fn load_expiry() -> Result<Vec<ExpiryRecord>> {
+    let path = Config::config_dir()?.join(EXPIRY_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)?;
+    serde_json::from_str(&content).map_err(|e| anyhow!("Invalid {}: {}", path.display(), e))
+}
+
+fn save_expiry(records: &[ExpiryRecord]) -> Result<()> {
+    let path = Config::config_dir()?.join(EXPIRY_FILE);
+    std::fs::write(&path, serde_json::to_string_pretty(records)?)?;
+    Ok(())
+}
+
+fn remember_expiry(new: Vec<ExpiryRecord>) -> Result<()> {
+    if new.is_empty() {
+        return Ok(());
+    }
+    let mut records = load_expiry()?;
+    records.extend(new);
+    save_expiry(&records)
+}
+
+/// Drop records for rules that were deleted by other means
+fn forget_expiry(paths: &[String]) -> Result<()> {
+    let mut records = load_expiry()?;
+    let before = records.len();
+    records.retain(|r| !paths.contains(&r.path));
+    if records.len() != before {
+        save_expiry(&records)?;
+    }
+    Ok(())
+}
+
+/// Delete access rules whose recorded expiry time has passed
+async fn expire_rules(client: &CloudflareClient, list: bool, dry_run: bool) -> Result<()> {
+    let records = load_expiry()?;
+    let now = Utc::now();
+
+    if list {
+        output::table_header(&["EXPIRES", "MODE", "VALUE", "ID"]);
+        for r in &records {
+            let when = if r.expires_at <= now {
+                format!("{} (overdue)", r.expires_at.to_rfc3339())
+            } else {
+                r.expires_at.to_rfc3339()
+            };
+            println!("{}\t{}\t{}\t{}", when, r.mode, r.value, r.id);
+        }
+        output::info(&format!("Total: {} expiring rules", records.len()));
+        return Ok(());
+    }
+
+    let (due, pending): (Vec<ExpiryRecord>, Vec<ExpiryRecord>) =
+        records.into_iter().partition(|r| r.expires_at <= now);
+
+    if due.is_empty() {
+        output::info(&format!("No expired rules ({} pending)", pending.len()));
+        return Ok(());
+    }
+
+    if dry_run {
+        for r in &due {
+            println!("{}\t{}\t{}", r.mode, r.value, r.id);
+        }
+        output::info(&format!("Dry run: {} rule(s) would be deleted", due.len()));
+        return Ok(());
+    }
+
+    let mut kept = pending;
+    let mut deleted = 0;
+    let mut failed = 0;
+    let total = due.len();
+
+    for (i, record) in due.into_iter().enumerate() {
+        output::progress(i + 1, total, &record.value);
+        match client.delete_raw(&record.path).await {
+            Ok(_) => deleted += 1,
+            // Already gone (deleted by hand or in the dashboard)
+            Err(e) if ApiError::has_status(&e, StatusCode::NOT_FOUND) => deleted += 1,
+            Err(e) => {
+                output::error(&format!("{} ({}): {}", record.value, record.id, e));
+                failed += 1;
+                kept.push(record);
+            }
+        }
+    }
+
+    save_expiry(&kept)?;
+    output::success(&format!("Deleted {} expired rule(s)", deleted));
+
+    if failed > 0 {
+        return Err(anyhow!("{} rule(s) could not be deleted", failed));
+    }
+    Ok(())
+}
+
+/// Account that owns the zone
+async fn zone_account_id(client: &CloudflareClient, zone_id: &str) -> Result<String> {
+    let response = client.get_raw(&format!("/zones/{}", zone_id)).await?;
+    response
+        .pointer("/result/account/id")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .ok_or_else(|| anyhow!("Could not determine account ID"))
+}
+
+fn execute_expr(cmd: ExprCommand) -> Result<()> {
+    match cmd {
+        ExprCommand::Check { expression, file } => {
+            let src = read_expression(expression, file)?;
+            let checked = expr::parse(&src);
+            output::print_expr_diagnostics(&src, &checked);
+
+            if !checked.is_ok() {
+                return Err(anyhow!("{} error(s) in expression", checked.errors.len()));
+            }
+
+            output::success("Expression is valid");
+            if let Some(ast) = &checked.ast {
+                output::info(&format!("Fields: {}", ast.fields().join(", ")));
+            }
+        }
+
+        ExprCommand::Eval {
+            expression,
+            file,
+            request,
+        } => {
+            let src = read_expression(expression, file)?;
+            let checked = expr::parse(&src);
+            output::print_expr_diagnostics(&src, &checked);
+
+            let ast = match checked.ast {
+                Some(ast) if checked.errors.is_empty() => ast,
+                _ => return Err(anyhow!("{} error(s) in expression", checked.errors.len())),
+            };
+
+            let content = std::fs::read_to_string(&request)
+                .map_err(|e| anyhow!("Cannot read request file {}: {}", request, e))?;
+            let json: serde_json::Value = serde_json::from_str(&content)
+                .map_err(|e| anyhow!("Invalid request JSON in {}: {}", request, e))?;
+            let req = expr::eval::Request::from_json(&json)?;
+
+            let missing = req.missing_fields(&ast);
+            if !missing.is_empty() {
+                output::warning(&format!(
+                    "Not set in request (defaults used): {}",
+                    missing.join(", ")
+                ));
+            }
+
+            output::table_header(&["FIELD", "VALUE"]);
+            for (name, value) in expr::eval::field_values(&ast, &req) {
+                println!("{}\t{}", name, value);
+            }
+            println!();
+
+            match expr::eval::eval(&ast, &req)? {
+                expr::eval::Value::Bool(true) => output::success("Expression MATCHES the request"),
+                _ => output::info("Expression does NOT match the request"),
+            }
+        }
+
+        ExprCommand::Fields { filter } => {
+            output::table_header(&["FIELD", "TYPE"]);
+            for field in expr::schema::FIELDS
+                .iter()
+                .filter(|f| filter.as_deref().is_none_or(|q| f.name.contains(q)))
+            {
+                let note = field
+                    .replaced_by
+                    .map(|r| format!(" (deprecated, use {})", r))
+                    .unwrap_or_default();
+                println!("{}\t{}{}", field.name, field.ty.to_type(), note);
+            }
+        }
+
+        ExprCommand::Functions => {
+            output::table_header(&["FUNCTION", "DESCRIPTION"]);
+            for func in expr::schema::FUNCTIONS {
+                println!("{}()\t{}", func.name, func.description);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_expression(expression: Option<String>, file: Option<String>) -> Result<String> {
+    match (expression, file) {
+        (Some(e), _) => Ok(e),
+        (None, Some(path)) => std::fs::read_to_string(&path)
+            .map(|s| s.trim().to_string())
+            .map_err(|e| anyhow!("Cannot read expression file {}: {}", path, e)),
+        (None, None) => Err(anyhow!("No expression given")),
+    }
+}
+
+async fn get_account_id(client: &CloudflareClient) -> Result<String> {
+    let response = client.get_raw("/zones?per_page=1").await?;
+    if let Some(zones) = response.get("result").and_then(|r| r.as_array()) {
+        if let Some(zone) = zones.first() {
+            if let Some(account) = zone.get("account") {
+                if let Some(id) = account.get("id").and_then(|i| i.as_str()) {
+                    return Ok(id.to_string());
+                }
+            }
+        }
+    }
+    Err(anyhow::anyhow!("Could not determine account ID"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct RatelimitCli {
+        #[command(flatten)]
+        rule: RatelimitArgs,
+    }
+
+    fn ratelimit_args(args: &[&str]) -> RatelimitArgs {
+        RatelimitCli::parse_from(std::iter::once("test").chain(args.iter().copied())).rule
+    }
+
+    #[test]
+    fn challenge_actions_reset_the_timeout() {
+        let block = || {
+            json!({
+                "action": "block",
+                "ratelimit": {"period": 60, "requests_per_period": 100, "mitigation_timeout": 600}
+            })
+        };
+
+        let mut rule = block();
+        apply_ratelimit_args(&mut rule, &ratelimit_args(&["-a", "managed_challenge"])).unwrap();
+        assert_eq!(rule["action"], "managed_challenge");
+        assert_eq!(rule["ratelimit"]["mitigation_timeout"], 0);
+        assert_eq!(rule["ratelimit"]["requests_per_period"], 100);
+
+        let mut rule = block();
+        let err =
+            apply_ratelimit_args(&mut rule, &ratelimit_args(&["-a", "challenge", "-t", "60"]))
+                .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--timeout must be 0 for challenge actions (got 60)"
+        );
+
+        let mut rule = block();
+        apply_ratelimit_args(&mut rule, &ratelimit_args(&["-t", "120"])).unwrap();
+        assert_eq!(rule["ratelimit"]["mitigation_timeout"], 120);
+
+        let mut rule = block();
+        rule["action"] = json!("js_challenge");
+        rule["ratelimit"]["mitigation_timeout"] = json!(0);
+        apply_ratelimit_args(&mut rule, &ratelimit_args(&["-r", "50"])).unwrap();
+        assert_eq!(rule["ratelimit"]["mitigation_timeout"], 0);
+        assert!(apply_ratelimit_args(&mut rule, &ratelimit_args(&["-t", "10"])).is_err());
+    }
+
+    #[test]
+    fn expiry_time_uses_shared_duration_syntax() {
+        let expires = expiry_time("1d12h").unwrap();
+        let hours = (expires - Utc::now()).num_minutes() as f64 / 60.0;
+        assert!((35.9..=36.0).contains(&hours), "{}", hours);
+
+        for invalid in ["", "24", "5y", "-1h"] {
+            assert!(expiry_time(invalid).is_err(), "{}", invalid);
+        }
+        // Would overflow chrono instead of panicking
+        assert!(expiry_time("9223372036854775807w").is_err());
+        assert!(expiry_time("999999999w").is_err());
+    }
+}
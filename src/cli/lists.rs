@@ -0,0 +1,504 @@
+//! Lists command - account-level IP lists used in rule expressions ($name)
+
+use std::collections::HashSet;
+use std::io::Read;
+use std::net::IpAddr;
+
+use anyhow::{anyhow, Result};
+use clap::{Args, Subcommand};
+use serde_json::{json, Value};
+
+use crate::api::lists;
+use crate::api::CloudflareClient;
+use crate::config::Config;
+use crate::expr;
+use crate::output;
+
+#[derive(Args, Debug)]
+pub struct ListsArgs {
+    #[command(subcommand)]
+    pub command: ListsCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ListsCommand {
+    /// List all lists in the account
+    List,
+
+    /// Create an IP list
+    Create {
+        /// List name (letters, digits and underscores; used as $name in expressions)
+        name: String,
+
+        /// Description
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+
+    /// Show the items of a list
+    Items {
+        /// List name or ID
+        list: String,
+    },
+
+    /// Add IPs/CIDRs to a list
+    Add {
+        /// List name or ID
+        list: String,
+
+        /// IPs or CIDRs
+        items: Vec<String>,
+
+        /// Read IPs/CIDRs from a file (one per line or CSV `ip,comment`; '-' for stdin)
+        #[arg(long)]
+        from_file: Option<String>,
+
+        /// Comment for items that don't have one
+        #[arg(short, long)]
+        comment: Option<String>,
+    },
+
+    /// Remove IPs/CIDRs from a list
+    Remove {
+        /// List name or ID
+        list: String,
+
+        /// IPs or CIDRs
+        items: Vec<String>,
+
+        /// Read IPs/CIDRs from a file (one per line or CSV; '-' for stdin)
+        #[arg(long)]
+        from_file: Option<String>,
+    },
+
+    /// Delete a list
+    Delete {
+        /// List name or ID
+        list: String,
+
+        /// Skip confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+/// A validated IP or CIDR with an optional comment
+#[derive(Debug, Clone)]
+pub struct IpEntry {
+    /// Normalized form (`1.2.3.4`, `10.0.0.0/8`)
+    pub value: String,
+    pub ip: IpAddr,
+    pub prefix: u8,
+    pub comment: Option<String>,
+}
+
+/// IPs/CIDRs collected from arguments and input files
+#[derive(Debug, Default)]
+pub struct IpEntries {
+    pub entries: Vec<IpEntry>,
+    /// Source location and text of lines that could not be parsed
+    pub invalid: Vec<(String, String)>,
+    pub duplicates: usize,
+}
+
+impl IpEntries {
+    /// Print a summary of skipped input
+    pub fn report(&self) {
+        for (location, text) in &self.invalid {
+            output::warning(&format!("{}: not an IP or CIDR: {}", location, text));
+        }
+        if self.duplicates > 0 {
+            output::info(&format!("Skipped {} duplicate(s)", self.duplicates));
+        }
+    }
+}
+
+/// Read, validate and dedupe IPs/CIDRs from arguments and an optional file
+///
+/// Files contain one entry per line or CSV rows whose first column is the
+/// IP and second column an optional comment. Blank lines, `#` comments and
+/// a header row are ignored. `-` reads from stdin.
+pub fn read_ip_entries(args: &[String], file: Option<&str>) -> Result<IpEntries> {
+    let mut result = IpEntries::default();
+    let mut seen = HashSet::new();
+
+    let mut push =
+        |result: &mut IpEntries, location: String, raw: &str, comment: Option<String>| {
+            match normalize(raw) {
+                Some((value, ip, prefix)) => {
+                    if seen.insert(value.clone()) {
+                        result.entries.push(IpEntry {
+                            value,
+                            ip,
+                            prefix,
+                            comment,
+                        });
+                    } else {
+                        result.duplicates += 1;
+                    }
+                }
+                None => result.invalid.push((location, raw.to_string())),
+            }
+        };
+
+    for arg in args {
+        for part in arg.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            push(&mut result, "argument".to_string(), part, None);
+        }
+    }
+
+    if let Some(path) = file {
+        let content = if path == "-" {
+            let mut buf = String::new();
+            std::io::stdin().read_to_string(&mut buf)?;
+            buf
+        } else {
+            std::fs::read_to_string(path).map_err(|e| anyhow!("Cannot read {}: {}", path, e))?
+        };
+        let name = if path == "-" { "stdin" } else { path };
+
+        for (n, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut columns = line
+                .split([',', ';', '\t'])
+                .map(|c| c.trim().trim_matches('"'));
+            let raw = columns.next().unwrap_or("");
+            let comment = columns.next().filter(|c| !c.is_empty()).map(String::from);
+
+            // Header row, e.g. "ip,comment"
+            if n == 0 && normalize(raw).is_none() && !raw.chars().any(|c| c.is_ascii_digit()) {
+                continue;
+            }
+
+            push(&mut result, format!("{}:{}", name, n + 1), raw, comment);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Parse an IP/CIDR and normalize it to its network address
+fn normalize(raw: &str) -> Option<(String, IpAddr, u8)> {
+    let (ip, prefix) = expr::parse_cidr(raw)?;
+    let network = match ip {
+        IpAddr::V4(v4) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            IpAddr::V4((u32::from(v4) & mask).into())
+        }
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            IpAddr::V6((u128::from(v6) & mask).into())
+        }
+    };
+
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    let value = if prefix == max {
+        network.to_string()
+    } else {
+        format!("{}/{}", network, prefix)
+    };
+
+    Some((value, network, prefix))
+}
+
+/// Add entries to an IP list in bulk, skipping ones already present
+///
+/// Returns the number of items added.
+pub async fn add_to_list(
+    client: &CloudflareClient,
+    account_id: &str,
+    list: &Value,
+    entries: &[IpEntry],
+    default_comment: Option<&str>,
+) -> Result<usize> {
+    let list_id = lists_id(list)?;
+
+    let existing: HashSet<String> = lists::items(client, account_id, &list_id)
+        .await?
+        .iter()
+        .map(lists::item_value)
+        .collect();
+
+    let new: Vec<&IpEntry> = entries
+        .iter()
+        .filter(|e| !existing.contains(&e.value))
+        .collect();
+
+    if new.len() < entries.len() {
+        output::info(&format!(
+            "{} item(s) already in the list",
+            entries.len() - new.len()
+        ));
+    }
+    if new.is_empty() {
+        return Ok(0);
+    }
+
+    let mut done = 0;
+    for chunk in new.chunks(lists::ITEMS_PER_REQUEST) {
+        let items = chunk
+            .iter()
+            .map(|e| {
+                let mut item = json!({ "ip": e.value });
+                if let Some(comment) = e.comment.as_deref().or(default_comment) {
+                    item["comment"] = json!(comment);
+                }
+                item
+            })
+            .collect();
+
+        let operation = lists::add_items(client, account_id, &list_id, items).await?;
+        lists::wait_operation(client, account_id, &operation).await?;
+
+        done += chunk.len();
+        output::progress(done, new.len(), "items added");
+    }
+
+    Ok(done)
+}
+
+pub async fn execute(config: &Config, args: ListsArgs) -> Result<()> {
+    let client = CloudflareClient::new(config.clone())?;
+    let account_id = get_account_id(&client).await?;
+
+    match args.command {
+        ListsCommand::List => {
+            let all = lists::list(&client, &account_id).await?;
+
+            output::table_header(&["NAME", "KIND", "ITEMS", "REFERENCED BY", "ID"]);
+            for list in &all {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    list.get("name").and_then(|v| v.as_str()).unwrap_or("-"),
+                    list.get("kind").and_then(|v| v.as_str()).unwrap_or("-"),
+                    list.get("num_items").and_then(|v| v.as_u64()).unwrap_or(0),
+                    list.get("num_referencing_filters")
+                        .and_then(|v| v.as_u64())
+                        .unwrap_or(0),
+                    list.get("id").and_then(|v| v.as_str()).unwrap_or("-"),
+                );
+            }
+            output::info(&format!("Total: {} lists", all.len()));
+        }
+
+        ListsCommand::Create { name, description } => {
+            let list =
+                lists::create(&client, &account_id, &name, "ip", description.as_deref()).await?;
+            output::success(&format!("Created IP list: {} ({})", name, lists_id(&list)?));
+            output::info(&format!("Use it in expressions as ${}", name));
+        }
+
+        ListsCommand::Items { list } => {
+            let target = lists::find(&client, &account_id, &list).await?;
+            let items = lists::items(&client, &account_id, &lists_id(&target)?).await?;
+
+            output::table_header(&["VALUE", "COMMENT", "CREATED"]);
+            for item in &items {
+                println!(
+                    "{}\t{}\t{}",
+                    lists::item_value(item),
+                    item.get("comment").and_then(|v| v.as_str()).unwrap_or(""),
+                    item.get("created_on")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("-"),
+                );
+            }
+            output::info(&format!("Total: {} items", items.len()));
+        }
+
+        ListsCommand::Add {
+            list,
+            items,
+            from_file,
+            comment,
+        } => {
+            let input = read_ip_entries(&items, from_file.as_deref())?;
+            input.report();
+            if input.entries.is_empty() {
+                return Err(anyhow!("No valid IPs or CIDRs given"));
+            }
+
+            let target = lists::find(&client, &account_id, &list).await?;
+            let added = add_to_list(
+                &client,
+                &account_id,
+                &target,
+                &input.entries,
+                comment.as_deref(),
+            )
+            .await?;
+            output::success(&format!("Added {} item(s) to {}", added, list));
+        }
+
+        ListsCommand::Remove {
+            list,
+            items,
+            from_file,
+        } => {
+            let input = read_ip_entries(&items, from_file.as_deref())?;
+            input.report();
+            if input.entries.is_empty() {
+                return Err(anyhow!("No valid IPs or CIDRs given"));
+            }
+
+            let target = lists::find(&client, &account_id, &list).await?;
+            let list_id = lists_id(&target)?;
+            let wanted: HashSet<&str> = input.entries.iter().map(|e| e.value.as_str()).collect();
+
+            let ids: Vec<String> = lists::items(&client, &account_id, &list_id)
+                .await?
+                .iter()
+                .filter(|item| wanted.contains(lists::item_value(item).as_str()))
+                .filter_map(|item| item.get("id").and_then(|v| v.as_str()).map(String::from))
+                .collect();
+
+            if ids.len() < wanted.len() {
+                output::info(&format!(
+                    "{} item(s) not in the list",
+                    wanted.len() - ids.len()
+                ));
+            }
+
+            let mut done = 0;
+            for chunk in ids.chunks(lists::ITEMS_PER_REQUEST) {
+                let operation = lists::remove_items(&client, &account_id, &list_id, chunk).await?;
+                lists::wait_operation(&client, &account_id, &operation).await?;
+
+                done += chunk.len();
+                output::progress(done, ids.len(), "items removed");
+            }
+            output::success(&format!("Removed {} item(s) from {}", done, list));
+        }
+
+        ListsCommand::Delete { list, yes } => {
+            if !yes {
+                output::warning(&format!("Are you sure you want to delete list {}?", list));
+                output::info("Use -y to skip this confirmation");
+                return Ok(());
+            }
+
+            let target = lists::find(&client, &account_id, &list).await?;
+            client
+                .delete_raw(&format!(
+                    "/accounts/{}/rules/lists/{}",
+                    account_id,
+                    lists_id(&target)?
+                ))
+                .await?;
+            output::success(&format!("Deleted list: {}", list));
+        }
+    }
+
+    Ok(())
+}
+
+fn lists_id(list: &Value) -> Result<String> {
+    list.get("id")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .ok_or_else(|| anyhow!("List has no ID"))
+}
+
+async fn get_account_id(client: &CloudflareClient) -> Result<String> {
+    let response = client.get_raw("/zones?per_page=1").await?;
+    if let Some(zones) = response.get("result").and_then(|r| r.as_array()) {
+        if let Some(zone) = zones.first() {
+            if let Some(account) = zone.get("account") {
+                if let Some(id) = account.get("id").and_then(|i| i.as_str()) {
+                    return Ok(id.to_string());
+                }
+            }
+        }
+    }
+    Err(anyhow::anyhow!("Could not determine account ID"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(entries: &IpEntries) -> Vec<&str> {
+        entries.entries.iter().map(|e| e.value.as_str()).collect()
+    }
+
+    #[test]
+    fn normalizes_to_network_addresses() {
+        let (value, ip, prefix) = normalize("10.1.2.3/8").unwrap();
+        assert_eq!((value.as_str(), prefix), ("10.0.0.0/8", 8));
+        assert_eq!(ip, "10.0.0.0".parse::<IpAddr>().unwrap());
+
+        assert_eq!(normalize("192.0.2.1/32").unwrap().0, "192.0.2.1");
+        assert_eq!(normalize("0.0.0.0/0").unwrap().0, "0.0.0.0/0");
+        assert_eq!(normalize("2001:db8::1/32").unwrap().0, "2001:db8::/32");
+        assert_eq!(normalize("2001:DB8::1").unwrap().0, "2001:db8::1");
+        for invalid in ["", "example.com", "10.0.0.0/33", "300.1.1.1", "::1/129"] {
+            assert!(normalize(invalid).is_none(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn reads_arguments_and_dedupes() {
+        let args = vec![
+            "192.0.2.1, 10.1.0.0/16".to_string(),
+            "10.1.255.255/16".to_string(),
+            "nope".to_string(),
+        ];
+        let entries = read_ip_entries(&args, None).unwrap();
+        assert_eq!(values(&entries), ["192.0.2.1", "10.1.0.0/16"]);
+        assert_eq!(entries.duplicates, 1);
+        assert_eq!(
+            entries.invalid,
+            [("argument".to_string(), "nope".to_string())]
+        );
+    }
+
+    #[test]
+    fn reads_csv_files_with_comments() {
+        let path = std::env::temp_dir().join(format!("cli5-lists-{}.csv", std::process::id()));
+        std::fs::write(
+            &path,
+            "ip,comment\n\
+             # exported from the WAF\n\
+             198.51.100.7,\"scanner\"\n\
+             \n\
+             203.0.113.0/24;office # trailing comment\n\
+             198.51.100.7,again\n\
+             2001:db8::/48\tv6 range\n\
+             not-an-ip,oops\n",
+        )
+        .unwrap();
+
+        let entries = read_ip_entries(&[], path.to_str()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            values(&entries),
+            ["198.51.100.7", "203.0.113.0/24", "2001:db8::/48"]
+        );
+        let comments: Vec<Option<&str>> = entries
+            .entries
+            .iter()
+            .map(|e| e.comment.as_deref())
+            .collect();
+        assert_eq!(
+            comments,
+            [Some("scanner"), Some("office"), Some("v6 range")]
+        );
+        assert_eq!(entries.duplicates, 1);
+        assert_eq!(entries.invalid.len(), 1);
+        assert!(entries.invalid[0].0.ends_with(":8"));
+        assert_eq!(entries.invalid[0].1, "not-an-ip");
+    }
+
+    #[test]
+    fn reports_missing_files() {
+        let err = read_ip_entries(&[], Some("/nonexistent/ips.txt")).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Cannot read /nonexistent/ips.txt"));
+    }
+}
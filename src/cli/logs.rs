@@ -0,0 +1,1715 @@
+//! Logs command - Logpush jobs, dataset fields, Instant Logs and offline analysis of exported logs
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::future::Future;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use clap::{Args, Subcommand};
+use flate2::read::MultiGzDecoder;
+use futures_util::StreamExt;
+use owo_colors::OwoColorize;
+use serde_json::{json, Map, Value};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use crate::api::logpush;
+use crate::api::CloudflareClient;
+use crate::cli::analytics::format_bytes;
+use crate::config::{Config, OutputFormat};
+use crate::output;
+
+#[derive(Args, Debug)]
+pub struct LogsArgs {
+    /// Zone name or ID
+    #[arg(short, long, global = true)]
+    pub zone: Option<String>,
+
+    #[command(subcommand)]
+    pub command: LogsCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum LogsCommand {
+    /// Manage Logpush jobs
+    Push {
+        /// Account-level jobs instead of zone jobs
+        #[arg(long, global = true)]
+        account: bool,
+
+        #[command(subcommand)]
+        command: Box<PushCommand>,
+    },
+
+    /// List the fields of a Logpush dataset
+    Fields {
+        /// Dataset, e.g. http_requests, firewall_events, dns_logs, workers_trace_events
+        dataset: String,
+
+        /// Account-level dataset instead of a zone dataset
+        #[arg(long)]
+        account: bool,
+    },
+
+    /// Stream a zone's HTTP requests live (Instant Logs) until Ctrl-C
+    Tail {
+        /// Fields to show, comma-separated (see `logs fields http_requests`)
+        #[arg(long, value_delimiter = ',')]
+        fields: Vec<String>,
+
+        /// Keep one in N requests
+        #[arg(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+        sample: u32,
+
+        /// Status code, class or comparison: 404, 5xx, >=400; repeatable, any may match
+        #[arg(long)]
+        status: Vec<String>,
+
+        /// Path prefix; repeatable, any may match
+        #[arg(long)]
+        path: Vec<String>,
+
+        /// Country codes, comma-separated
+        #[arg(long, value_delimiter = ',')]
+        country: Vec<String>,
+
+        /// Client IPs, comma-separated
+        #[arg(long, value_delimiter = ',')]
+        ip: Vec<String>,
+
+        /// Any other condition, as in `logs push create --filter`; repeatable
+        #[arg(long)]
+        filter: Vec<String>,
+
+        /// Stop after this many requests
+        #[arg(short = 'n', long)]
+        count: Option<u64>,
+
+        /// Read from this WebSocket URL instead of starting a session (ws:// or wss://);
+        /// sampling and filters are then up to the server
+        #[arg(long)]
+        url: Option<String>,
+    },
+
+    /// Summarize Logpush NDJSON files (gzip too) offline, with the analytics top-N tables
+    Parse {
+        /// Exported files ('-' for stdin)
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Table to compute (default: totals and the main tables)
+        #[arg(long, default_value = "summary", value_parser = ["summary", "top-urls", "top-ips", "top-countries", "errors", "cache", "bandwidth", "bots", "hosts", "firewall"])]
+        report: String,
+
+        /// Rows per table
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PushCommand {
+    /// List Logpush jobs
+    List,
+
+    /// Create a Logpush job
+    Create {
+        /// Dataset, e.g. http_requests (see `logs fields`)
+        #[arg(long)]
+        dataset: String,
+
+        /// Job name
+        #[arg(long)]
+        name: Option<String>,
+
+        #[command(flatten)]
+        options: JobOptions,
+
+        /// Create the job disabled
+        #[arg(long)]
+        disabled: bool,
+    },
+
+    /// Change the destination, fields, filter or sampling of a job
+    Update {
+        /// Job ID
+        id: u64,
+
+        #[command(flatten)]
+        options: JobOptions,
+    },
+
+    /// Delete a job
+    Delete {
+        /// Job ID
+        id: u64,
+
+        /// Skip confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Start pushing logs
+    Enable {
+        /// Job ID
+        id: u64,
+    },
+
+    /// Stop pushing logs
+    Disable {
+        /// Job ID
+        id: u64,
+    },
+}
+
+/// Destination, output and filter options of a Logpush job
+#[derive(Args, Debug)]
+pub struct JobOptions {
+    /// Destination in Cloudflare's format (s3://, gs://, azure://, https://, r2://, ...)
+    #[arg(long, conflicts_with_all = ["http_url", "r2_bucket"])]
+    destination: Option<String>,
+
+    /// HTTPS endpoint receiving batches of gzipped NDJSON
+    #[arg(long, conflicts_with = "r2_bucket")]
+    http_url: Option<String>,
+
+    /// Header sent to the HTTP endpoint, as 'Name: value'; repeatable
+    #[arg(long, requires = "http_url")]
+    http_header: Vec<String>,
+
+    /// R2 bucket with an optional path, e.g. logs or logs/http (files go to <path>/<date>)
+    #[arg(long, requires_all = ["r2_access_key_id", "r2_secret_access_key"])]
+    r2_bucket: Option<String>,
+
+    /// R2 access key ID
+    #[arg(long, env = "R2_ACCESS_KEY_ID", hide_env_values = true)]
+    r2_access_key_id: Option<String>,
+
+    /// R2 secret access key
+    #[arg(long, env = "R2_SECRET_ACCESS_KEY", hide_env_values = true)]
+    r2_secret_access_key: Option<String>,
+
+    /// Ownership challenge token, for destinations that need one (S3, GCS, Azure, ...)
+    #[arg(long)]
+    ownership_challenge: Option<String>,
+
+    /// Fields to push, comma-separated (default on create: every field of the dataset)
+    #[arg(long, value_delimiter = ',')]
+    fields: Vec<String>,
+
+    /// Only push matching records: 'Field op value' with =, !=, <, <=, >, >=, ~ (contains),
+    /// !~, ^= (starts with), $= (ends with), or 'Field in a,b'; repeatable, all must match
+    #[arg(long)]
+    filter: Vec<String>,
+
+    /// Share of records to push, from 0.0001 to 1
+    #[arg(long)]
+    sample_rate: Option<f64>,
+
+    /// Timestamp format (default on create: rfc3339)
+    #[arg(long, value_parser = ["rfc3339", "unix", "unixnano"])]
+    timestamps: Option<String>,
+}
+
+impl LogsCommand {
+    /// Commands that only read local files or a given WebSocket
+    pub fn is_offline(&self) -> bool {
+        matches!(
+            self,
+            LogsCommand::Parse { .. } | LogsCommand::Tail { url: Some(_), .. }
+        )
+    }
+}
+
+pub async fn execute(config: &Config, args: LogsArgs) -> Result<()> {
+    let (account, command) = match args.command {
+        LogsCommand::Parse {
+            files,
+            report,
+            limit,
+        } => return parse_files(&files, &report, limit, &config.output_format),
+        LogsCommand::Fields { dataset, account } => {
+            let client = CloudflareClient::new(config.clone())?;
+            let base = scope_base(&client, config, args.zone.as_deref(), account).await?;
+            return list_fields(&client, &base, &dataset, &config.output_format).await;
+        }
+        LogsCommand::Tail {
+            fields,
+            sample,
+            status,
+            path,
+            country,
+            ip,
+            filter,
+            count,
+            url,
+        } => {
+            let fields = if fields.is_empty() {
+                TAIL_FIELDS.iter().map(|f| f.to_string()).collect()
+            } else {
+                fields
+            };
+            let url = match url {
+                Some(url) => url,
+                None => {
+                    let client = CloudflareClient::new(config.clone())?;
+                    let zone = config.resolve_zone(args.zone.as_deref())?;
+                    let zone_id = client.resolve_zone_id(&zone).await?;
+                    let known =
+                        logpush::fields(&client, &format!("/zones/{}", zone_id), "http_requests")
+                            .await?;
+                    check_fields(&fields, &known, "http_requests")?;
+
+                    let mut conditions = Vec::new();
+                    if !status.is_empty() {
+                        conditions.push(any_of(
+                            status
+                                .iter()
+                                .map(|s| status_condition(s))
+                                .collect::<Result<_>>()?,
+                        ));
+                    }
+                    if !path.is_empty() {
+                        conditions.push(any_of(
+                            path.iter()
+                                .map(|p| json!({ "key": "ClientRequestPath", "operator": "startsWith", "value": p }))
+                                .collect(),
+                        ));
+                    }
+                    if !country.is_empty() {
+                        let codes: Vec<String> =
+                            country.iter().map(|c| c.trim().to_lowercase()).collect();
+                        conditions.push(
+                            json!({ "key": "ClientCountry", "operator": "in", "value": codes }),
+                        );
+                    }
+                    if !ip.is_empty() {
+                        let ips: Vec<&str> = ip.iter().map(|i| i.trim()).collect();
+                        conditions
+                            .push(json!({ "key": "ClientIP", "operator": "in", "value": ips }));
+                    }
+                    conditions.extend(parse_conditions(&filter, &known)?);
+
+                    let filter = if conditions.is_empty() {
+                        String::new()
+                    } else {
+                        json!({ "where": { "and": conditions } }).to_string()
+                    };
+                    let session = logpush::instant_logs(
+                        &client,
+                        &zone_id,
+                        json!({
+                            "fields": fields.join(","),
+                            "sample": sample,
+                            "filter": filter,
+                            "kind": "instant-logs",
+                        }),
+                    )
+                    .await?;
+                    let url = session
+                        .get("destination_conf")
+                        .and_then(|v| v.as_str())
+                        .ok_or_else(|| anyhow!("Instant Logs session without a WebSocket URL"))?
+                        .to_string();
+                    output::info(&format!(
+                        "Streaming requests of {}{}, Ctrl-C to stop",
+                        zone,
+                        if sample > 1 {
+                            format!(" (1 in {})", sample)
+                        } else {
+                            String::new()
+                        }
+                    ));
+                    url
+                }
+            };
+            return tail(&url, &fields, count, &config.output_format).await;
+        }
+        LogsCommand::Push { account, command } => (account, command),
+    };
+
+    let client = CloudflareClient::new(config.clone())?;
+    let base = scope_base(&client, config, args.zone.as_deref(), account).await?;
+
+    match *command {
+        PushCommand::List => {
+            let jobs = logpush::list(&client, &base).await?;
+            if matches!(config.output_format, OutputFormat::Json) {
+                return output::print_json_pretty(&jobs);
+            }
+
+            output::table_header(&[
+                "ID",
+                "NAME",
+                "DATASET",
+                "ENABLED",
+                "DESTINATION",
+                "LAST PUSH",
+            ]);
+            for job in &jobs {
+                let text = |key: &str| job.get(key).and_then(|v| v.as_str()).unwrap_or("-");
+                let enabled = if job.get("enabled").and_then(|v| v.as_bool()) == Some(true) {
+                    "yes".green().to_string()
+                } else {
+                    "no".dimmed().to_string()
+                };
+                let last = match job.get("error_message").and_then(|v| v.as_str()) {
+                    Some(error) if !error.is_empty() => format!("{}", error.red()),
+                    _ => text("last_complete").to_string(),
+                };
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}",
+                    job.get("id").and_then(|v| v.as_u64()).unwrap_or(0),
+                    text("name"),
+                    text("dataset"),
+                    enabled,
+                    redact_destination(text("destination_conf")),
+                    last
+                );
+            }
+            output::info(&format!("Total: {} jobs", jobs.len()));
+        }
+
+        PushCommand::Create {
+            dataset,
+            name,
+            options,
+            disabled,
+        } => {
+            let known = logpush::fields(&client, &base, &dataset).await?;
+            let destination = options.destination(&client).await?.ok_or_else(|| {
+                anyhow!("Give a destination: --destination, --http-url or --r2-bucket")
+            })?;
+
+            let fields = if options.fields.is_empty() {
+                let mut all: Vec<String> = known.keys().cloned().collect();
+                all.sort();
+                all
+            } else {
+                check_fields(&options.fields, &known, &dataset)?;
+                options.fields.clone()
+            };
+
+            let mut output_options = json!({
+                "field_names": fields,
+                "timestamp_format": options.timestamps.as_deref().unwrap_or("rfc3339"),
+            });
+            if let Some(rate) = options.sample_rate {
+                output_options["sample_rate"] = json!(check_sample_rate(rate)?);
+            }
+
+            let mut body = json!({
+                "dataset": dataset,
+                "destination_conf": destination,
+                "enabled": !disabled,
+                "output_options": output_options,
+            });
+            if let Some(name) = &name {
+                body["name"] = json!(name);
+            }
+            if let Some(filter) = build_filter(&options.filter, &known)? {
+                body["filter"] = json!(filter);
+            }
+            if let Some(token) = &options.ownership_challenge {
+                body["ownership_challenge"] = json!(token);
+            }
+
+            let job = logpush::create(&client, &base, body).await?;
+            output::success(&format!(
+                "Created Logpush job {} for {} ({} fields{})",
+                job.get("id").and_then(|v| v.as_u64()).unwrap_or(0),
+                dataset,
+                fields.len(),
+                if disabled { ", disabled" } else { "" }
+            ));
+        }
+
+        PushCommand::Update { id, options } => {
+            let job = logpush::get(&client, &base, id).await?;
+            let dataset = job
+                .get("dataset")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string();
+            let known = logpush::fields(&client, &base, &dataset).await?;
+
+            let mut body = json!({});
+            if let Some(destination) = options.destination(&client).await? {
+                body["destination_conf"] = json!(destination);
+            }
+            if let Some(token) = &options.ownership_challenge {
+                body["ownership_challenge"] = json!(token);
+            }
+            if let Some(filter) = build_filter(&options.filter, &known)? {
+                body["filter"] = json!(filter);
+            }
+
+            // Output options are replaced as a whole, so start from the current ones
+            let mut output_options = job
+                .get("output_options")
+                .filter(|o| o.is_object())
+                .cloned()
+                .unwrap_or_else(|| json!({}));
+            let mut changed = false;
+            if !options.fields.is_empty() {
+                check_fields(&options.fields, &known, &dataset)?;
+                output_options["field_names"] = json!(options.fields);
+                changed = true;
+            }
+            if let Some(rate) = options.sample_rate {
+                output_options["sample_rate"] = json!(check_sample_rate(rate)?);
+                changed = true;
+            }
+            if let Some(format) = &options.timestamps {
+                output_options["timestamp_format"] = json!(format);
+                changed = true;
+            }
+            if changed {
+                body["output_options"] = output_options;
+            }
+
+            if body.as_object().is_none_or(|b| b.is_empty()) {
+                return Err(anyhow!("Nothing to update: give a destination, --fields, --filter, --sample-rate or --timestamps"));
+            }
+            logpush::update(&client, &base, id, body).await?;
+            output::success(&format!("Updated Logpush job {}", id));
+        }
+
+        PushCommand::Delete { id, yes } => {
+            if !yes {
+                output::warning(&format!(
+                    "Are you sure you want to delete Logpush job {}?",
+                    id
+                ));
+                output::info("Use -y to skip this confirmation");
+                return Ok(());
+            }
+            logpush::delete(&client, &base, id).await?;
+            output::success(&format!("Deleted Logpush job {}", id));
+        }
+
+        PushCommand::Enable { id } => {
+            logpush::update(&client, &base, id, json!({ "enabled": true })).await?;
+            output::success(&format!("Enabled Logpush job {}", id));
+        }
+
+        PushCommand::Disable { id } => {
+            logpush::update(&client, &base, id, json!({ "enabled": false })).await?;
+            output::success(&format!("Disabled Logpush job {}", id));
+        }
+    }
+
+    Ok(())
+}
+
+/// `/zones/{id}` or `/accounts/{id}`
+async fn scope_base(
+    client: &CloudflareClient,
+    config: &Config,
+    zone: Option<&str>,
+    account: bool,
+) -> Result<String> {
+    if account {
+        Ok(format!("/accounts/{}", get_account_id(client).await?))
+    } else {
+        let zone = config.resolve_zone(zone)?;
+        Ok(format!("/zones/{}", client.resolve_zone_id(&zone).await?))
+    }
+}
+
+impl JobOptions {
+    /// The destination_conf from whichever destination option was given
+    async fn destination(&self, client: &CloudflareClient) -> Result<Option<String>> {
+        if let Some(destination) = &self.destination {
+            return Ok(Some(destination.clone()));
+        }
+
+        if let Some(url) = &self.http_url {
+            let parsed = reqwest::Url::parse(url)
+                .map_err(|e| anyhow!("Invalid --http-url '{}': {}", url, e))?;
+            if parsed.scheme() != "https" {
+                return Err(anyhow!("Logpush only pushes to https:// endpoints"));
+            }
+            let mut destination = url.clone();
+            for header in &self.http_header {
+                let (name, value) = header.split_once(':').ok_or_else(|| {
+                    anyhow!("Invalid --http-header '{}': use 'Name: value'", header)
+                })?;
+                destination.push(if destination.contains('?') { '&' } else { '?' });
+                destination.push_str(&format!(
+                    "header_{}={}",
+                    encode(name.trim()),
+                    encode(value.trim())
+                ));
+            }
+            return Ok(Some(destination));
+        }
+
+        if let Some(bucket) = &self.r2_bucket {
+            let (Some(key_id), Some(secret)) = (&self.r2_access_key_id, &self.r2_secret_access_key)
+            else {
+                return Err(anyhow!(
+                    "R2 destinations need --r2-access-key-id and --r2-secret-access-key"
+                ));
+            };
+            let account_id = get_account_id(client).await?;
+            return Ok(Some(format!(
+                "r2://{}/{{DATE}}?account-id={}&access-key-id={}&secret-access-key={}",
+                bucket.trim_matches('/'),
+                encode(&account_id),
+                encode(key_id),
+                encode(secret)
+            )));
+        }
+
+        Ok(None)
+    }
+}
+
+/// Percent-encode a query string component
+fn encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Destination without its query string, which holds credentials
+fn redact_destination(destination: &str) -> String {
+    match destination.split_once('?') {
+        Some((base, _)) => format!("{}?…", base),
+        None => destination.to_string(),
+    }
+}
+
+fn check_fields(fields: &[String], known: &Map<String, Value>, dataset: &str) -> Result<()> {
+    let unknown: Vec<&str> = fields
+        .iter()
+        .map(String::as_str)
+        .filter(|f| !known.contains_key(*f))
+        .collect();
+    if unknown.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Unknown field(s) for {}: {} (see `cli5 logs fields {}`)",
+        dataset,
+        unknown.join(", "),
+        dataset
+    ))
+}
+
+fn check_sample_rate(rate: f64) -> Result<f64> {
+    if (0.0001..=1.0).contains(&rate) {
+        Ok(rate)
+    } else {
+        Err(anyhow!("--sample-rate must be between 0.0001 and 1"))
+    }
+}
+
+/// Build the Logpush filter (a JSON document in a string) from --filter conditions
+fn build_filter(conditions: &[String], known: &Map<String, Value>) -> Result<Option<String>> {
+    if conditions.is_empty() {
+        return Ok(None);
+    }
+    let conditions = parse_conditions(conditions, known)?;
+    Ok(Some(json!({ "where": { "and": conditions } }).to_string()))
+}
+
+/// Parse --filter conditions, checking their fields against the dataset
+fn parse_conditions(conditions: &[String], known: &Map<String, Value>) -> Result<Vec<Value>> {
+    conditions
+        .iter()
+        .map(|raw| {
+            let condition = parse_condition(raw)?;
+            let key = condition["key"].as_str().unwrap_or_default();
+            if !known.contains_key(key) {
+                return Err(anyhow!("Unknown field '{}' in filter '{}'", key, raw));
+            }
+            Ok(condition)
+        })
+        .collect()
+}
+
+/// One condition, or an "or" group of several
+fn any_of(mut conditions: Vec<Value>) -> Value {
+    if conditions.len() == 1 {
+        conditions.remove(0)
+    } else {
+        json!({ "or": conditions })
+    }
+}
+
+/// `404`, `5xx` or a comparison such as `>=400` on EdgeResponseStatus
+fn status_condition(raw: &str) -> Result<Value> {
+    let raw = raw.trim();
+    if let Ok(code) = raw.parse::<u16>() {
+        return Ok(json!({ "key": "EdgeResponseStatus", "operator": "eq", "value": code }));
+    }
+    if let Some(class) = raw
+        .strip_suffix("xx")
+        .and_then(|c| c.parse::<u16>().ok())
+        .filter(|c| (1..=5).contains(c))
+    {
+        return Ok(json!({ "and": [
+            { "key": "EdgeResponseStatus", "operator": "geq", "value": class * 100 },
+            { "key": "EdgeResponseStatus", "operator": "lt", "value": (class + 1) * 100 },
+        ] }));
+    }
+    let condition = parse_condition(&format!("EdgeResponseStatus{}", raw))
+        .map_err(|_| anyhow!("Invalid --status '{}': use 404, 5xx or >=400", raw))?;
+    if !condition["value"].is_u64() {
+        return Err(anyhow!("Invalid --status '{}': use 404, 5xx or >=400", raw));
+    }
+    Ok(condition)
+}
+
+/// Fields shown by `logs tail` unless --fields is given
+const TAIL_FIELDS: &[&str] = &[
+    "EdgeStartTimestamp",
+    "EdgeResponseStatus",
+    "ClientRequestMethod",
+    "ClientRequestHost",
+    "ClientRequestPath",
+    "ClientCountry",
+    "ClientIP",
+    "EdgeResponseBytes",
+    "CacheCacheStatus",
+];
+
+/// Print log records from a WebSocket until it closes, `count` is reached or Ctrl-C
+async fn tail(
+    url: &str,
+    fields: &[String],
+    count: Option<u64>,
+    format: &OutputFormat,
+) -> Result<()> {
+    let json_output = matches!(format, OutputFormat::Json);
+    let mut header = !json_output;
+    let (shown, invalid) = stream_records(
+        url,
+        count,
+        async {
+            let _ = tokio::signal::ctrl_c().await;
+        },
+        |record| {
+            if header {
+                let headers: Vec<String> = fields.iter().map(|f| f.to_uppercase()).collect();
+                let headers: Vec<&str> = headers.iter().map(String::as_str).collect();
+                output::table_header(&headers);
+                header = false;
+            }
+            if json_output {
+                println!("{}", record);
+            } else {
+                println!("{}", tail_line(&record, fields));
+            }
+        },
+    )
+    .await?;
+
+    if invalid > 0 {
+        output::warning(&format!(
+            "Skipped {} message line(s) that are not JSON",
+            invalid
+        ));
+    }
+    output::info(&format!("Session closed after {} requests", shown));
+    Ok(())
+}
+
+/// Read NDJSON records from a WebSocket until the server closes it, `count`
+/// records were read or `stop` completes, then close it cleanly.
+/// Returns the number of records and of lines that were not JSON.
+async fn stream_records(
+    url: &str,
+    count: Option<u64>,
+    stop: impl Future<Output = ()>,
+    mut each: impl FnMut(Value),
+) -> Result<(u64, u64)> {
+    let (mut socket, _) = tokio_tungstenite::connect_async(url)
+        .await
+        .map_err(|e| anyhow!("Cannot connect to {}: {}", url, e))?;
+
+    tokio::pin!(stop);
+    let (mut records, mut invalid) = (0u64, 0u64);
+    'stream: loop {
+        // Polling the stream is cancel-safe: pongs and close replies are
+        // queued inside the socket and flushed whole on the next poll or close
+        let message = tokio::select! {
+            _ = &mut stop => break,
+            message = socket.next() => message,
+        };
+        let text = match message {
+            Some(Ok(WsMessage::Text(text))) => text.to_string(),
+            Some(Ok(WsMessage::Binary(data))) => String::from_utf8_lossy(&data).into_owned(),
+            Some(Ok(WsMessage::Close(frame))) => {
+                if let Some(frame) = frame.filter(|f| f.code != CloseCode::Normal) {
+                    output::warning(&format!(
+                        "Stream closed by the server: {} {}",
+                        u16::from(frame.code),
+                        frame.reason
+                    ));
+                }
+                break;
+            }
+            // Pings are answered by the socket itself
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(anyhow!("WebSocket error: {}", e)),
+            None => break,
+        };
+
+        // A message may carry several NDJSON records
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let Ok(record) = serde_json::from_str::<Value>(line) else {
+                invalid += 1;
+                continue;
+            };
+            each(record);
+            records += 1;
+            if count.is_some_and(|count| records >= count) {
+                break 'stream;
+            }
+        }
+    }
+
+    // Closing handshake: send (or answer) the close frame, then wait briefly
+    // for the server to finish; it may already be gone
+    let _ = socket
+        .close(Some(CloseFrame {
+            code: CloseCode::Normal,
+            reason: "".into(),
+        }))
+        .await;
+    let _ = tokio::time::timeout(Duration::from_secs(2), async {
+        while let Some(Ok(_)) = socket.next().await {}
+    })
+    .await;
+    Ok((records, invalid))
+}
+
+/// A record's fields, in order, with the status colored by class
+fn tail_line(record: &Value, fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            let value = record.get(field).filter(|v| !v.is_null());
+            match (field.as_str(), value) {
+                (_, None) => "-".dimmed().to_string(),
+                ("EdgeResponseStatus" | "OriginResponseStatus", Some(v)) => {
+                    let status = v.as_u64().unwrap_or(0);
+                    let text = status.to_string();
+                    match status {
+                        500.. => text.red().bold().to_string(),
+                        400..=499 => text.yellow().bold().to_string(),
+                        300..=399 => text.cyan().to_string(),
+                        _ => text.green().to_string(),
+                    }
+                }
+                ("EdgeStartTimestamp" | "EdgeEndTimestamp", Some(v)) => match parse_timestamp(v) {
+                    Some(time) => time
+                        .with_timezone(&chrono::Local)
+                        .format("%H:%M:%S")
+                        .to_string()
+                        .dimmed()
+                        .to_string(),
+                    None => v.to_string(),
+                },
+                ("EdgeResponseBytes" | "ClientRequestBytes", Some(v)) if v.is_u64() => {
+                    format_bytes(v.as_u64().unwrap_or(0))
+                }
+                ("ClientRequestMethod", Some(Value::String(method))) => method.bold().to_string(),
+                (_, Some(Value::String(text))) if text.is_empty() => "-".dimmed().to_string(),
+                (_, Some(Value::String(text))) => text.clone(),
+                (_, Some(other)) => other.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\t")
+}
+
+/// Parse `Field op value` into a Logpush filter condition
+fn parse_condition(raw: &str) -> Result<Value> {
+    const OPERATORS: &[(&str, &str)] = &[
+        (" !in ", "!in"),
+        (" in ", "in"),
+        ("!~", "!contains"),
+        ("^=", "startsWith"),
+        ("$=", "endsWith"),
+        (">=", "geq"),
+        ("<=", "leq"),
+        ("!=", "!eq"),
+        ("~", "contains"),
+        (">", "gt"),
+        ("<", "lt"),
+        ("=", "eq"),
+    ];
+
+    // The leftmost operator wins; on a tie, the longest
+    let (position, symbol, operator) = OPERATORS
+        .iter()
+        .filter_map(|(symbol, operator)| raw.find(symbol).map(|p| (p, *symbol, *operator)))
+        .min_by_key(|(position, symbol, _)| (*position, usize::MAX - symbol.len()))
+        .ok_or_else(|| {
+            anyhow!(
+                "Invalid filter '{}': use 'Field op value', e.g. 'EdgeResponseStatus>=500'",
+                raw
+            )
+        })?;
+
+    let key = raw[..position].trim();
+    let value = raw[position + symbol.len()..].trim();
+    if key.is_empty() || value.is_empty() {
+        return Err(anyhow!("Invalid filter '{}': missing field or value", raw));
+    }
+
+    let value = if operator.ends_with("in") {
+        Value::Array(value.split(',').map(|v| filter_value(v.trim())).collect())
+    } else {
+        filter_value(value)
+    };
+    Ok(json!({ "key": key, "operator": operator, "value": value }))
+}
+
+/// Numbers and booleans as such, anything else (or anything quoted) as a string
+fn filter_value(raw: &str) -> Value {
+    for quote in ['"', '\''] {
+        if let Some(inner) = raw.strip_prefix(quote).and_then(|r| r.strip_suffix(quote)) {
+            return Value::from(inner);
+        }
+    }
+    if let Ok(number) = raw.parse::<i64>() {
+        Value::from(number)
+    } else if let Ok(boolean) = raw.parse::<bool>() {
+        Value::from(boolean)
+    } else if let Some(number) = raw.parse::<f64>().ok().filter(|n| n.is_finite()) {
+        Value::from(number)
+    } else {
+        Value::from(raw)
+    }
+}
+
+async fn list_fields(
+    client: &CloudflareClient,
+    base: &str,
+    dataset: &str,
+    format: &OutputFormat,
+) -> Result<()> {
+    let fields = logpush::fields(client, base, dataset).await?;
+    if matches!(format, OutputFormat::Json) {
+        return output::print_json_pretty(&fields);
+    }
+
+    let mut names: Vec<&String> = fields.keys().collect();
+    names.sort();
+    output::table_header(&["FIELD", "DESCRIPTION"]);
+    for name in &names {
+        let description = fields[name.as_str()].as_str().unwrap_or_default();
+        println!("{}\t{}", name.bold(), description.replace('\n', " "));
+    }
+    output::info(&format!("{} fields in {}", names.len(), dataset));
+    Ok(())
+}
+
+/// A top-N table over log fields, named like the matching analytics command
+struct LogReport {
+    name: &'static str,
+    title: &'static str,
+    fields: &'static [&'static str],
+    /// Sum this field per group instead of only counting records
+    sum: Option<&'static str>,
+    /// Only records with a 4xx or 5xx status
+    errors_only: bool,
+}
+
+const LOG_REPORTS: &[LogReport] = &[
+    LogReport {
+        name: "top-urls",
+        title: "Top paths",
+        fields: &["ClientRequestPath"],
+        sum: None,
+        errors_only: false,
+    },
+    LogReport {
+        name: "top-ips",
+        title: "Top visitor IPs",
+        fields: &["ClientIP"],
+        sum: None,
+        errors_only: false,
+    },
+    LogReport {
+        name: "top-countries",
+        title: "Top countries",
+        fields: &["ClientCountry"],
+        sum: None,
+        errors_only: false,
+    },
+    LogReport {
+        name: "errors",
+        title: "Error responses",
+        fields: &["EdgeResponseStatus", "ClientRequestPath"],
+        sum: None,
+        errors_only: true,
+    },
+    LogReport {
+        name: "cache",
+        title: "Cache status",
+        fields: &["CacheCacheStatus"],
+        sum: None,
+        errors_only: false,
+    },
+    LogReport {
+        name: "bandwidth",
+        title: "Bandwidth by status code",
+        fields: &["EdgeResponseStatus"],
+        sum: Some("EdgeResponseBytes"),
+        errors_only: false,
+    },
+    LogReport {
+        name: "bots",
+        title: "Device types and user agents",
+        fields: &["ClientDeviceType", "ClientRequestUserAgent"],
+        sum: None,
+        errors_only: false,
+    },
+    LogReport {
+        name: "hosts",
+        title: "Top hosts",
+        fields: &["ClientRequestHost"],
+        sum: None,
+        errors_only: false,
+    },
+    LogReport {
+        name: "firewall",
+        title: "Firewall events",
+        fields: &["Action", "ClientIP", "ClientCountry", "ClientRequestPath"],
+        sum: None,
+        errors_only: false,
+    },
+];
+
+/// Tables shown by `--report summary`
+const SUMMARY_REPORTS: &[&str] = &["top-urls", "top-countries", "errors", "cache"];
+
+/// Cache statuses that count as served from cache
+const CACHED: &[&str] = &["hit", "stale", "updating", "revalidated"];
+
+/// Counts (and sums) per distinct combination of a report's field values
+struct Tally<'a> {
+    report: &'a LogReport,
+    groups: HashMap<Vec<String>, (u64, u64)>,
+}
+
+impl<'a> Tally<'a> {
+    fn new(report: &'a LogReport) -> Self {
+        Self {
+            report,
+            groups: HashMap::new(),
+        }
+    }
+
+    fn add(&mut self, record: &Value) {
+        if self.report.errors_only && status(record).is_none_or(|s| s < 400) {
+            return;
+        }
+        let values: Vec<Option<&Value>> = self
+            .report
+            .fields
+            .iter()
+            .map(|f| record.get(*f).filter(|v| !v.is_null()))
+            .collect();
+        // The leading field identifies the dataset (e.g. Action for firewall events)
+        if values[0].is_none() {
+            return;
+        }
+
+        let key = values
+            .into_iter()
+            .map(|v| match v {
+                Some(Value::String(s)) if s.is_empty() => "-".to_string(),
+                Some(Value::String(s)) => s.clone(),
+                Some(other) => other.to_string(),
+                None => "-".to_string(),
+            })
+            .collect();
+        let amount = self
+            .report
+            .sum
+            .and_then(|f| record.get(f))
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        let entry = self.groups.entry(key).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 += amount;
+    }
+
+    /// Groups ordered by the summed field, or by count
+    fn top(&self, limit: usize) -> Vec<(&Vec<String>, u64, u64)> {
+        let mut rows: Vec<_> = self
+            .groups
+            .iter()
+            .map(|(key, (count, sum))| (key, *count, *sum))
+            .collect();
+        let summed = self.report.sum.is_some();
+        rows.sort_by(|a, b| {
+            let (x, y) = if summed { (a.2, b.2) } else { (a.1, b.1) };
+            y.cmp(&x).then_with(|| a.0.cmp(b.0))
+        });
+        rows.truncate(limit);
+        rows
+    }
+
+    fn to_json(&self, limit: usize) -> Value {
+        Value::Array(
+            self.top(limit)
+                .into_iter()
+                .map(|(key, count, sum)| {
+                    let mut row = Map::new();
+                    row.insert("count".to_string(), json!(count));
+                    if let Some(field) = self.report.sum {
+                        row.insert(field.to_string(), json!(sum));
+                    }
+                    for (field, value) in self.report.fields.iter().zip(key) {
+                        row.insert(field.to_string(), json!(value));
+                    }
+                    Value::Object(row)
+                })
+                .collect(),
+        )
+    }
+
+    fn print(&self, limit: usize) {
+        let mut headers = vec!["COUNT".to_string()];
+        if let Some(field) = self.report.sum {
+            headers.push(field.to_uppercase());
+        }
+        headers.extend(self.report.fields.iter().map(|f| f.to_uppercase()));
+        let headers: Vec<&str> = headers.iter().map(String::as_str).collect();
+        output::table_header(&headers);
+
+        for (key, count, sum) in self.top(limit) {
+            let mut columns = vec![count.to_string().yellow().bold().to_string()];
+            if self.report.sum.is_some() {
+                columns.push(format_bytes(sum));
+            }
+            columns.extend(key.iter().cloned());
+            println!("{}", columns.join("\t"));
+        }
+        output::info(&format!("Total groups: {}", self.groups.len()));
+    }
+}
+
+/// Totals over every record
+#[derive(Default)]
+struct Totals {
+    records: u64,
+    invalid: u64,
+    bytes: u64,
+    cached: u64,
+    client_errors: u64,
+    server_errors: u64,
+    first: Option<DateTime<Utc>>,
+    last: Option<DateTime<Utc>>,
+}
+
+impl Totals {
+    fn add(&mut self, record: &Value) {
+        self.records += 1;
+        self.bytes += record
+            .get("EdgeResponseBytes")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        if record
+            .get("CacheCacheStatus")
+            .and_then(|v| v.as_str())
+            .is_some_and(|s| CACHED.contains(&s))
+        {
+            self.cached += 1;
+        }
+        match status(record) {
+            Some(400..=499) => self.client_errors += 1,
+            Some(500..=599) => self.server_errors += 1,
+            _ => {}
+        }
+        if let Some(time) = ["EdgeStartTimestamp", "Datetime", "Timestamp"]
+            .iter()
+            .find_map(|f| record.get(*f))
+            .and_then(parse_timestamp)
+        {
+            self.first = Some(self.first.map_or(time, |t| t.min(time)));
+            self.last = Some(self.last.map_or(time, |t| t.max(time)));
+        }
+    }
+
+    fn percent(&self, part: u64) -> f64 {
+        part as f64 * 100.0 / self.records.max(1) as f64
+    }
+}
+
+fn status(record: &Value) -> Option<u64> {
+    record.get("EdgeResponseStatus").and_then(|v| v.as_u64())
+}
+
+/// Timestamps as RFC 3339, Unix seconds or Unix nanoseconds
+fn parse_timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    match value {
+        Value::String(text) => DateTime::parse_from_rfc3339(text)
+            .ok()
+            .map(|t| t.with_timezone(&Utc)),
+        Value::Number(number) => {
+            let number = number.as_i64()?;
+            if number > 1_000_000_000_000_000 {
+                Some(DateTime::from_timestamp_nanos(number))
+            } else {
+                DateTime::from_timestamp(number, 0)
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Open a file ('-' for stdin) for reading line by line, decompressing gzip
+fn open_log_file(path: &Path) -> Result<Box<dyn BufRead>> {
+    let mut reader: Box<dyn BufRead> = if path.as_os_str() == "-" {
+        Box::new(BufReader::new(std::io::stdin()))
+    } else {
+        let file =
+            File::open(path).map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))?;
+        Box::new(BufReader::new(file))
+    };
+    if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        // Logpush files may be several gzip members concatenated
+        reader = Box::new(BufReader::new(MultiGzDecoder::new(reader)));
+    }
+    Ok(reader)
+}
+
+/// Tally the records of `files` for a report (or the summary reports)
+fn read_records(files: &[PathBuf], report: &str) -> Result<(Vec<Tally<'static>>, Totals)> {
+    let names: &[&str] = if report == "summary" {
+        SUMMARY_REPORTS
+    } else {
+        std::slice::from_ref(&report)
+    };
+    let mut tallies: Vec<Tally<'static>> = names
+        .iter()
+        .filter_map(|name| LOG_REPORTS.iter().find(|r| r.name == *name))
+        .map(Tally::new)
+        .collect();
+    let mut totals = Totals::default();
+
+    let mut line = Vec::new();
+    for path in files {
+        let mut reader = open_log_file(path)?;
+        loop {
+            line.clear();
+            let read = reader
+                .read_until(b'\n', &mut line)
+                .map_err(|e| anyhow!("{}: {}", path.display(), e))?;
+            if read == 0 {
+                break;
+            }
+            let line = line.trim_ascii();
+            if line.is_empty() {
+                continue;
+            }
+            let Ok(record) = serde_json::from_slice::<Value>(line) else {
+                totals.invalid += 1;
+                continue;
+            };
+            totals.add(&record);
+            for tally in &mut tallies {
+                tally.add(&record);
+            }
+        }
+    }
+
+    Ok((tallies, totals))
+}
+
+fn parse_files(files: &[PathBuf], report: &str, limit: usize, format: &OutputFormat) -> Result<()> {
+    let (tallies, totals) = read_records(files, report)?;
+
+    if totals.invalid > 0 {
+        output::warning(&format!(
+            "Skipped {} line(s) that are not JSON",
+            totals.invalid
+        ));
+    }
+    if totals.records == 0 {
+        return Err(anyhow!("No log records found"));
+    }
+
+    if matches!(format, OutputFormat::Json) {
+        let tables: Map<String, Value> = tallies
+            .iter()
+            .map(|t| (t.report.name.to_string(), t.to_json(limit)))
+            .collect();
+        if report != "summary" {
+            return output::print_json_pretty(&tables[report]);
+        }
+        return output::print_json_pretty(&json!({
+            "records": totals.records,
+            "bytes": totals.bytes,
+            "cached": totals.cached,
+            "client_errors": totals.client_errors,
+            "server_errors": totals.server_errors,
+            "first": totals.first,
+            "last": totals.last,
+            "tables": tables,
+        }));
+    }
+
+    if report == "summary" {
+        let span = match (totals.first, totals.last) {
+            (Some(first), Some(last)) => format!(
+                "{} to {}",
+                first.format("%Y-%m-%d %H:%M:%S"),
+                last.format("%Y-%m-%d %H:%M:%S")
+            ),
+            _ => "-".to_string(),
+        };
+        println!("{}\t{}", "Records".bold(), totals.records);
+        println!("{}\t{}", "Time span".bold(), span);
+        println!("{}\t{}", "Bandwidth".bold(), format_bytes(totals.bytes));
+        println!(
+            "{}\t{:.1}%",
+            "Cache hit".bold(),
+            totals.percent(totals.cached)
+        );
+        println!(
+            "{}\t{:.2}% 4xx, {:.2}% 5xx",
+            "Errors".bold(),
+            totals.percent(totals.client_errors),
+            totals.percent(totals.server_errors)
+        );
+    }
+
+    for tally in &tallies {
+        if report == "summary" {
+            println!();
+            println!("{}", tally.report.title.bold());
+        }
+        if tally.groups.is_empty() {
+            output::info(&format!(
+                "No records with {}",
+                tally.report.fields.join(" or ")
+            ));
+            continue;
+        }
+        tally.print(limit);
+    }
+    Ok(())
+}
+
+async fn get_account_id(client: &CloudflareClient) -> Result<String> {
+    let response = client.get_raw("/zones?per_page=1").await?;
+    if let Some(zones) = response.get("result").and_then(|r| r.as_array()) {
+        if let Some(zone) = zones.first() {
+            if let Some(account) = zone.get("account") {
+                if let Some(id) = account.get("id").and_then(|i| i.as_str()) {
+                    return Ok(id.to_string());
+                }
+            }
+        }
+    }
+    Err(anyhow!("Could not determine account ID"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+
+    /// Accept one connection and answer the WebSocket handshake by hand
+    async fn accept(listener: &TcpListener) -> TcpStream {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        while !request.ends_with(b"\r\n\r\n") {
+            request.push(stream.read_u8().await.unwrap());
+        }
+        let request = String::from_utf8(request).unwrap();
+        let key = request
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.eq_ignore_ascii_case("sec-websocket-key"))
+            .map(|(_, value)| value.trim())
+            .unwrap();
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+            derive_accept_key(key.as_bytes())
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        stream
+    }
+
+    /// An unmasked server frame
+    fn frame(fin: bool, opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![if fin { 0x80 } else { 0 } | opcode];
+        match payload.len() {
+            len if len < 126 => frame.push(len as u8),
+            len if len <= u16::MAX as usize => {
+                frame.push(126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// Read a client frame, which must be masked
+    async fn read_frame(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let head = [
+            stream.read_u8().await.unwrap(),
+            stream.read_u8().await.unwrap(),
+        ];
+        assert_ne!(head[1] & 0x80, 0, "client frames must be masked");
+        let len = match head[1] & 0x7f {
+            126 => stream.read_u16().await.unwrap() as usize,
+            127 => stream.read_u64().await.unwrap() as usize,
+            len => len as usize,
+        };
+        let mut mask = [0u8; 4];
+        stream.read_exact(&mut mask).await.unwrap();
+        let mut payload = vec![0u8; len];
+        stream.read_exact(&mut payload).await.unwrap();
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+        (head[0] & 0x0f, payload)
+    }
+
+    fn record(status: u16, path: &str) -> String {
+        json!({ "EdgeResponseStatus": status, "ClientRequestPath": path }).to_string()
+    }
+
+    #[tokio::test]
+    async fn stream_records_reads_fragments_pings_and_large_frames() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/session", listener.local_addr().unwrap());
+
+        let big_path = format!("/{}", "x".repeat(70 * 1024));
+        let big = record(503, &big_path);
+        let server = tokio::spawn(async move {
+            let mut stream = accept(&listener).await;
+
+            // Two records in one text message, split in fragments with a ping between them
+            let message = format!("{}\n{}", record(200, "/"), record(404, "/missing"));
+            let (first, rest) = message.as_bytes().split_at(20);
+            let mut out = frame(false, 0x1, first);
+            out.extend(frame(true, 0x9, b"keepalive"));
+            out.extend(frame(true, 0x0, rest));
+            // A frame over 64 KiB, then a line that is not JSON, then a normal close
+            out.extend(frame(true, 0x1, big.as_bytes()));
+            out.extend(frame(true, 0x1, b"not json"));
+            out.extend(frame(true, 0x8, &1000u16.to_be_bytes()));
+            stream.write_all(&out).await.unwrap();
+
+            let mut seen = Vec::new();
+            while let Ok(frame) =
+                tokio::time::timeout(Duration::from_secs(5), read_frame(&mut stream)).await
+            {
+                let close = frame.0 == 0x8;
+                seen.push(frame);
+                if close {
+                    break;
+                }
+            }
+            seen
+        });
+
+        let mut records = Vec::new();
+        let (count, invalid) =
+            stream_records(&url, None, std::future::pending(), |r| records.push(r))
+                .await
+                .unwrap();
+
+        assert_eq!((count, invalid), (3, 1));
+        let paths: Vec<&str> = records
+            .iter()
+            .map(|r| r["ClientRequestPath"].as_str().unwrap())
+            .collect();
+        assert_eq!(paths, ["/", "/missing", big_path.as_str()]);
+        assert_eq!(records[2]["EdgeResponseStatus"], 503);
+
+        let seen = server.await.unwrap();
+        assert_eq!(seen.first(), Some(&(0xA, b"keepalive".to_vec())));
+        assert_eq!(
+            seen.last().map(|f| f.0),
+            Some(0x8),
+            "close was not answered"
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_records_closes_after_count() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/", listener.local_addr().unwrap());
+
+        let server = tokio::spawn(async move {
+            let mut stream = accept(&listener).await;
+            for path in ["/a", "/b", "/c"] {
+                stream
+                    .write_all(&frame(true, 0x1, record(200, path).as_bytes()))
+                    .await
+                    .unwrap();
+            }
+            let (opcode, payload) = read_frame(&mut stream).await;
+            stream
+                .write_all(&frame(true, 0x8, &1000u16.to_be_bytes()))
+                .await
+                .unwrap();
+            (opcode, payload)
+        });
+
+        let mut records = Vec::new();
+        let (count, _) = stream_records(&url, Some(2), std::future::pending(), |r| records.push(r))
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(records.len(), 2);
+
+        let (opcode, payload) = server.await.unwrap();
+        assert_eq!(opcode, 0x8);
+        assert_eq!(payload, 1000u16.to_be_bytes());
+    }
+
+    #[test]
+    fn filter_operators() {
+        let cases = [
+            (
+                "ClientCountry in us, de",
+                json!({"key": "ClientCountry", "operator": "in", "value": ["us", "de"]}),
+            ),
+            (
+                "EdgeResponseStatus !in 200,304",
+                json!({"key": "EdgeResponseStatus", "operator": "!in", "value": [200, 304]}),
+            ),
+            (
+                "ClientRequestPath!~/static",
+                json!({"key": "ClientRequestPath", "operator": "!contains", "value": "/static"}),
+            ),
+            (
+                "ClientRequestPath^=/api",
+                json!({"key": "ClientRequestPath", "operator": "startsWith", "value": "/api"}),
+            ),
+            (
+                "ClientRequestPath$=.php",
+                json!({"key": "ClientRequestPath", "operator": "endsWith", "value": ".php"}),
+            ),
+            (
+                "EdgeResponseStatus>=500",
+                json!({"key": "EdgeResponseStatus", "operator": "geq", "value": 500}),
+            ),
+            (
+                "EdgeResponseBytes <= 1.5",
+                json!({"key": "EdgeResponseBytes", "operator": "leq", "value": 1.5}),
+            ),
+            (
+                "ClientRequestMethod != GET",
+                json!({"key": "ClientRequestMethod", "operator": "!eq", "value": "GET"}),
+            ),
+            (
+                "ClientRequestUserAgent~bot",
+                json!({"key": "ClientRequestUserAgent", "operator": "contains", "value": "bot"}),
+            ),
+            (
+                "EdgeResponseBytes>1000",
+                json!({"key": "EdgeResponseBytes", "operator": "gt", "value": 1000}),
+            ),
+            (
+                "OriginResponseTime<-1",
+                json!({"key": "OriginResponseTime", "operator": "lt", "value": -1}),
+            ),
+            (
+                "WAFFlags=true",
+                json!({"key": "WAFFlags", "operator": "eq", "value": true}),
+            ),
+            // Quoted values stay strings
+            (
+                "EdgeResponseStatus='404'",
+                json!({"key": "EdgeResponseStatus", "operator": "eq", "value": "404"}),
+            ),
+            // The leftmost operator wins, so '=' in the value is kept
+            (
+                "ClientRequestQuery~a=b",
+                json!({"key": "ClientRequestQuery", "operator": "contains", "value": "a=b"}),
+            ),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(parse_condition(raw).unwrap(), expected, "{}", raw);
+        }
+
+        assert!(parse_condition("EdgeResponseStatus").is_err());
+        assert!(parse_condition("=500").is_err());
+        assert!(parse_condition("EdgeResponseStatus>=").is_err());
+    }
+
+    #[test]
+    fn status_filters() {
+        assert_eq!(
+            status_condition("404").unwrap(),
+            json!({"key": "EdgeResponseStatus", "operator": "eq", "value": 404})
+        );
+        assert_eq!(
+            status_condition("5xx").unwrap(),
+            json!({"and": [
+                {"key": "EdgeResponseStatus", "operator": "geq", "value": 500},
+                {"key": "EdgeResponseStatus", "operator": "lt", "value": 600},
+            ]})
+        );
+        assert_eq!(
+            status_condition(" >=400").unwrap(),
+            json!({"key": "EdgeResponseStatus", "operator": "geq", "value": 400})
+        );
+        assert_eq!(
+            status_condition("!=200").unwrap(),
+            json!({"key": "EdgeResponseStatus", "operator": "!eq", "value": 200})
+        );
+        for bad in ["6xx", "0xx", ">=abc", "teapot"] {
+            assert!(status_condition(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn filter_document_checks_fields() {
+        let known: Map<String, Value> = ["EdgeResponseStatus", "ClientCountry"]
+            .iter()
+            .map(|f| (f.to_string(), json!("")))
+            .collect();
+        let filter = build_filter(
+            &[
+                "EdgeResponseStatus>=500".to_string(),
+                "ClientCountry in us,de".to_string(),
+            ],
+            &known,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&filter).unwrap(),
+            json!({"where": {"and": [
+                {"key": "EdgeResponseStatus", "operator": "geq", "value": 500},
+                {"key": "ClientCountry", "operator": "in", "value": ["us", "de"]},
+            ]}})
+        );
+        assert_eq!(build_filter(&[], &known).unwrap(), None);
+        assert!(build_filter(&["ClientIP=1.2.3.4".to_string()], &known).is_err());
+    }
+
+    #[test]
+    fn parse_files_reads_concatenated_gzip_members() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let gzip = |lines: &[String]| {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            for line in lines {
+                writeln!(encoder, "{}", line).unwrap();
+            }
+            encoder.finish().unwrap()
+        };
+        let line = |status: u16, path: &str, country: &str, cache: &str| {
+            json!({
+                "EdgeStartTimestamp": "2026-10-18T12:00:00Z",
+                "EdgeResponseStatus": status,
+                "EdgeResponseBytes": 100,
+                "ClientRequestPath": path,
+                "ClientCountry": country,
+                "CacheCacheStatus": cache,
+            })
+            .to_string()
+        };
+
+        // Two gzip members, as Logpush writes them, plus a line that is not JSON
+        let mut data = gzip(&[
+            line(200, "/", "us", "hit"),
+            line(200, "/", "de", "miss"),
+            line(404, "/missing", "us", "miss"),
+        ]);
+        data.extend(gzip(&[
+            line(200, "/", "us", "hit"),
+            line(502, "/api", "fr", "dynamic"),
+            line(200, "/api", "us", "dynamic"),
+            "not json".to_string(),
+        ]));
+        let path = std::env::temp_dir().join(format!("cli5-{}-logs.ndjson.gz", std::process::id()));
+        std::fs::write(&path, data).unwrap();
+
+        let files = std::slice::from_ref(&path);
+        let (tallies, totals) = read_records(files, "summary").unwrap();
+        let (top_urls, _) = read_records(files, "top-urls").unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(totals.records, 6);
+        assert_eq!(totals.invalid, 1);
+        assert_eq!(totals.bytes, 600);
+        assert_eq!(totals.cached, 2);
+        assert_eq!((totals.client_errors, totals.server_errors), (1, 1));
+
+        assert_eq!(
+            top_urls[0].to_json(2),
+            json!([
+                {"count": 3, "ClientRequestPath": "/"},
+                {"count": 2, "ClientRequestPath": "/api"},
+            ])
+        );
+
+        let tables: Vec<(&str, Value)> = tallies
+            .iter()
+            .map(|t| (t.report.name, t.to_json(10)))
+            .collect();
+        assert_eq!(
+            tables,
+            vec![
+                (
+                    "top-urls",
+                    json!([
+                        {"count": 3, "ClientRequestPath": "/"},
+                        {"count": 2, "ClientRequestPath": "/api"},
+                        {"count": 1, "ClientRequestPath": "/missing"},
+                    ])
+                ),
+                (
+                    "top-countries",
+                    json!([
+                        {"count": 4, "ClientCountry": "us"},
+                        {"count": 1, "ClientCountry": "de"},
+                        {"count": 1, "ClientCountry": "fr"},
+                    ])
+                ),
+                (
+                    "errors",
+                    json!([
+                        {"count": 1, "EdgeResponseStatus": "404", "ClientRequestPath": "/missing"},
+                        {"count": 1, "EdgeResponseStatus": "502", "ClientRequestPath": "/api"},
+                    ])
+                ),
+                (
+                    "cache",
+                    json!([
+                        {"count": 2, "CacheCacheStatus": "dynamic"},
+                        {"count": 2, "CacheCacheStatus": "hit"},
+                        {"count": 2, "CacheCacheStatus": "miss"},
+                    ])
+                ),
+            ]
+        );
+    }
+}
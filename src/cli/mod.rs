@@ -1,22 +1,44 @@
 //! CLI commands module
 
 pub mod ai;
+pub mod alerts;
 pub mod analytics;
 pub mod cache;
+pub mod completions;
 pub mod config_cmd;
+pub mod dashboard;
 pub mod dns;
 pub mod firewall;
+pub mod lists;
+pub mod logs;
+pub mod multizone;
 pub mod pages;
 pub mod raw;
+pub mod report;
+pub mod rulesets;
 pub mod settings;
 pub mod ssl;
 pub mod storage;
+pub mod timerange;
 pub mod tunnel;
 pub mod workers;
 pub mod zones;
 
 use clap::{Parser, Subcommand};
 
+/// A non-zero exit status for commands whose outcome is a status (checks,
+/// expiry reports); `main` exits with it without printing an error
+#[derive(Debug)]
+pub struct ExitStatus(pub u8);
+
+impl std::fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "exit status {}", self.0)
+    }
+}
+
+impl std::error::Error for ExitStatus {}
+
 /// CLI5 - Modern Cloudflare CLI
 #[derive(Parser, Debug)]
 #[command(name = "cli5")]
@@ -51,6 +73,12 @@ pub enum Commands {
     /// Manage firewall rules
     Firewall(firewall::FirewallArgs),
 
+    /// Account-level IP lists for use in rule expressions
+    Lists(lists::ListsArgs),
+
+    /// Rulesets engine - WAF custom rules, transforms, redirects, managed rulesets
+    Rulesets(rulesets::RulesetsArgs),
+
     /// Cache management
     Cache(cache::CacheArgs),
 
@@ -60,6 +88,9 @@ pub enum Commands {
     /// Analytics and logs (GraphQL)
     Analytics(analytics::AnalyticsArgs),
 
+    /// Logpush jobs, log fields and local log analysis
+    Logs(logs::LogsArgs),
+
     /// Cloudflare Workers
     Workers(workers::WorkersArgs),
 
@@ -80,4 +111,19 @@ pub enum Commands {
 
     /// Configuration management
     Config(config_cmd::ConfigArgs),
+
+    /// Print a shell completion script, including setting names and values
+    Completions(completions::CompletionsArgs),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn command_tree_is_valid() {
+        // Completions build every subcommand, so clashes must not reach a panic
+        Cli::command().debug_assert();
+    }
 }
@@ -0,0 +1,276 @@
+//! Running zone commands across many zones at once
+
+use std::future::Future;
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use futures_util::stream::{self, StreamExt};
+use owo_colors::OwoColorize;
+use serde_json::Value;
+
+use crate::api::CloudflareClient;
+use crate::output;
+
+/// Select several zones instead of a single --zone
+#[derive(Args, Debug)]
+pub struct ZoneSelection {
+    /// Run against these zones (comma-separated names or IDs)
+    #[arg(long, value_delimiter = ',', conflicts_with = "zone")]
+    pub zones: Vec<String>,
+
+    /// Run against every zone in the account
+    #[arg(long, conflicts_with_all = ["zone", "zones"])]
+    pub all_zones: bool,
+
+    /// Only zones matching a name glob (*.example.com), status:<status> or plan:<plan>; repeatable
+    #[arg(long, conflicts_with = "zone")]
+    pub zone_filter: Vec<String>,
+
+    /// How many zones to process at once
+    #[arg(long, default_value_t = 8)]
+    pub concurrency: usize,
+}
+
+/// A zone selected for a multi-zone run
+#[derive(Debug, Clone)]
+pub struct Zone {
+    pub name: String,
+    pub id: String,
+}
+
+impl ZoneSelection {
+    /// Whether any multi-zone option was given
+    pub fn is_active(&self) -> bool {
+        !self.zones.is_empty() || self.all_zones || !self.zone_filter.is_empty()
+    }
+
+    /// Resolve the selection to zones, in name order (all zones when nothing is selected)
+    pub async fn resolve(&self, client: &CloudflareClient) -> Result<Vec<Zone>> {
+        let all = list_zones(client).await?;
+        if !self.is_active() {
+            return Ok(to_zones(all.iter()));
+        }
+
+        let mut selected: Vec<&Value> = if self.zones.is_empty() {
+            all.iter().collect()
+        } else {
+            self.zones
+                .iter()
+                .map(|wanted| {
+                    all.iter()
+                        .find(|z| field(z, "name") == wanted || field(z, "id") == wanted)
+                        .ok_or_else(|| anyhow!("Zone not found: {}", wanted))
+                })
+                .collect::<Result<_>>()?
+        };
+
+        for filter in &self.zone_filter {
+            selected.retain(|z| zone_matches(z, filter));
+        }
+
+        let zones = to_zones(selected.into_iter());
+        if zones.is_empty() {
+            return Err(anyhow!("No zones match the selection"));
+        }
+        Ok(zones)
+    }
+}
+
+/// Zones sorted by name, without duplicates
+fn to_zones<'a>(zones: impl Iterator<Item = &'a Value>) -> Vec<Zone> {
+    let mut zones: Vec<Zone> = zones
+        .map(|z| Zone {
+            name: field(z, "name").to_string(),
+            id: field(z, "id").to_string(),
+        })
+        .collect();
+    zones.sort_by(|a, b| a.name.cmp(&b.name));
+    zones.dedup_by(|a, b| a.id == b.id);
+    zones
+}
+
+/// Run an operation on every zone concurrently and print one result row per zone
+///
+/// The operation returns a short description of what it did. Fails when any
+/// zone failed, after all zones have been processed.
+pub async fn run<F, Fut>(zones: &[Zone], concurrency: usize, op: F) -> Result<()>
+where
+    F: Fn(Zone) -> Fut,
+    Fut: Future<Output = Result<String>>,
+{
+    let total = zones.len();
+    let mut results = Vec::with_capacity(total);
+
+    let mut pending = stream::iter(zones.iter().cloned())
+        .map(|zone| {
+            let name = zone.name.clone();
+            let fut = op(zone);
+            async move { (name, fut.await) }
+        })
+        .buffer_unordered(concurrency.max(1));
+
+    while let Some(result) = pending.next().await {
+        results.push(result);
+        output::progress(results.len(), total, "zones");
+    }
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+
+    output::table_header(&["ZONE", "STATUS", "RESULT"]);
+    let mut failed = 0;
+    for (name, result) in &results {
+        match result {
+            Ok(message) => println!("{}\t{}\t{}", name.bold(), "ok".green(), message),
+            Err(e) => {
+                failed += 1;
+                println!("{}\t{}\t{}", name.bold(), "failed".red(), e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        output::error(&format!("{} of {} zone(s) failed", failed, total));
+        return Err(anyhow!("{} zone(s) failed", failed));
+    }
+    output::success(&format!("All {} zone(s) done", total));
+    Ok(())
+}
+
+/// All zones the credentials can see
+async fn list_zones(client: &CloudflareClient) -> Result<Vec<Value>> {
+    let mut zones = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let response = client
+            .get_raw(&format!("/zones?per_page=50&page={}", page))
+            .await?;
+        let batch = response
+            .get("result")
+            .and_then(|r| r.as_array())
+            .cloned()
+            .unwrap_or_default();
+        let total_pages = response
+            .pointer("/result_info/total_pages")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(1);
+
+        zones.extend(batch);
+        if page >= total_pages {
+            break;
+        }
+        page += 1;
+    }
+
+    Ok(zones)
+}
+
+fn field<'a>(zone: &'a Value, name: &str) -> &'a str {
+    zone.get(name).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+/// Check a zone against `status:<status>`, `plan:<plan>` or a name glob
+fn zone_matches(zone: &Value, filter: &str) -> bool {
+    if let Some(status) = filter.strip_prefix("status:") {
+        return field(zone, "status").eq_ignore_ascii_case(status);
+    }
+    if let Some(plan) = filter.strip_prefix("plan:") {
+        let plan = plan.to_lowercase();
+        let zone_plan = zone.get("plan").unwrap_or(&Value::Null);
+        return field(zone_plan, "legacy_id").eq_ignore_ascii_case(&plan)
+            || field(zone_plan, "name").to_lowercase().contains(&plan);
+    }
+    glob_match(&filter.to_lowercase(), &field(zone, "name").to_lowercase())
+}
+
+/// Match `*` (any run of characters) and `?` (one character)
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn glob_wildcards_and_anchors() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "example.com"));
+        assert!(glob_match("*.example.com", "shop.example.com"));
+        assert!(glob_match("*.example.com", "a.b.example.com"));
+        assert!(glob_match("example.*", "example.org"));
+        assert!(glob_match("*shop*", "myshop.example.com"));
+        assert!(glob_match("ex?mple.com", "exSmple.com"));
+        assert!(glob_match("a*b*c", "aXXbYYbc"));
+        assert!(glob_match("example.com**", "example.com"));
+
+        // Patterns are anchored at both ends
+        assert!(!glob_match("*.example.com", "example.com"));
+        assert!(!glob_match("example.com", "shop.example.com"));
+        assert!(!glob_match("example", "example.com"));
+        assert!(!glob_match("ex?mple.com", "exmple.com"));
+        assert!(!glob_match("?", ""));
+        assert!(!glob_match("a*c", "abcd"));
+        assert!(!glob_match("", "example.com"));
+    }
+
+    fn zone(name: &str, status: &str, plan: &str, legacy_id: &str) -> Value {
+        json!({
+            "id": format!("id-{}", name),
+            "name": name,
+            "status": status,
+            "plan": { "name": plan, "legacy_id": legacy_id },
+        })
+    }
+
+    #[test]
+    fn zone_filters() {
+        let shop = zone("Shop.Example.com", "active", "Pro Website", "pro");
+        let parked = zone("parked.org", "pending", "Free Website", "free");
+
+        assert!(zone_matches(&shop, "*.example.com"));
+        assert!(zone_matches(&shop, "SHOP.*"));
+        assert!(!zone_matches(&parked, "*.example.com"));
+
+        assert!(zone_matches(&shop, "status:active"));
+        assert!(zone_matches(&parked, "status:PENDING"));
+        assert!(!zone_matches(&shop, "status:pending"));
+        assert!(!zone_matches(&shop, "status:act"));
+
+        assert!(zone_matches(&shop, "plan:pro"));
+        assert!(zone_matches(&parked, "plan:free website"));
+        assert!(!zone_matches(&parked, "plan:pro"));
+        assert!(!zone_matches(&json!({"name": "bare.com"}), "plan:free"));
+        assert!(!zone_matches(&json!({"name": "bare.com"}), "status:active"));
+    }
+
+    #[test]
+    fn zones_are_sorted_and_deduplicated() {
+        let a = zone("b.com", "active", "Free Website", "free");
+        let b = zone("a.com", "active", "Free Website", "free");
+        let zones = to_zones([&a, &b, &a].into_iter());
+        let names: Vec<&str> = zones.iter().map(|z| z.name.as_str()).collect();
+        assert_eq!(names, vec!["a.com", "b.com"]);
+    }
+}
@@ -0,0 +1,572 @@
+//! Period-over-period comparisons and Markdown/HTML traffic reports
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+
+use super::analytics::{
+    auto_granularity, count_rows, fetch_series, format_bytes, preset, query_range, Point, Preset,
+};
+use super::timerange::TimeRange;
+use crate::api::CloudflareClient;
+use crate::config::OutputFormat;
+use crate::output;
+use crate::output::chart;
+
+/// How a compared value is shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Count,
+    Bytes,
+    /// Already a percentage; changes are in percentage points
+    Percent,
+}
+
+/// One compared value: a traffic metric or a top-N entry
+#[derive(Debug, Clone, Serialize)]
+struct Delta {
+    name: String,
+    current: f64,
+    previous: f64,
+    /// Relative change in percent (percentage points for ratios); None when new
+    change: Option<f64>,
+    status: &'static str,
+    #[serde(skip)]
+    kind: Kind,
+}
+
+impl Delta {
+    fn new(name: &str, current: f64, previous: f64, kind: Kind) -> Self {
+        let change = match kind {
+            Kind::Percent => Some(current - previous),
+            _ if previous == 0.0 => None,
+            _ => Some((current - previous) * 100.0 / previous),
+        };
+        let status = if previous == 0.0 && current > 0.0 && kind != Kind::Percent {
+            "new"
+        } else if current == 0.0 && previous > 0.0 && kind != Kind::Percent {
+            "gone"
+        } else if current > previous {
+            "up"
+        } else if current < previous {
+            "down"
+        } else {
+            "same"
+        };
+        Self {
+            name: name.to_string(),
+            current,
+            previous,
+            change,
+            status,
+            kind,
+        }
+    }
+
+    fn display(&self, value: f64) -> String {
+        match self.kind {
+            Kind::Count => value.to_string(),
+            Kind::Bytes => format_bytes(value as u64),
+            Kind::Percent => format!("{:.1}%", value),
+        }
+    }
+
+    fn change_text(&self) -> String {
+        match (self.status, self.change) {
+            ("new", _) => "new".to_string(),
+            ("gone", _) => "gone".to_string(),
+            (_, Some(change)) if self.kind == Kind::Percent => format!("{:+.1} pp", change),
+            (_, Some(change)) => format!("{:+.1}%", change),
+            (_, None) => "-".to_string(),
+        }
+    }
+}
+
+/// Traffic totals of two windows as deltas
+fn traffic_deltas(current: &Point, previous: &Point) -> Vec<Delta> {
+    vec![
+        Delta::new(
+            "requests",
+            current.requests as f64,
+            previous.requests as f64,
+            Kind::Count,
+        ),
+        Delta::new(
+            "bandwidth",
+            current.bytes as f64,
+            previous.bytes as f64,
+            Kind::Bytes,
+        ),
+        Delta::new(
+            "cache hit ratio",
+            current.cache_ratio(),
+            previous.cache_ratio(),
+            Kind::Percent,
+        ),
+        Delta::new(
+            "threats",
+            current.threats as f64,
+            previous.threats as f64,
+            Kind::Count,
+        ),
+        Delta::new(
+            "5xx responses",
+            current.errors as f64,
+            previous.errors as f64,
+            Kind::Count,
+        ),
+        Delta::new(
+            "5xx rate",
+            current.error_rate(),
+            previous.error_rate(),
+            Kind::Percent,
+        ),
+    ]
+}
+
+async fn totals(client: &CloudflareClient, zone_id: &str, range: &TimeRange) -> Result<Point> {
+    let points = fetch_series(client, zone_id, range, auto_granularity(range)).await?;
+    Ok(Point::total(&points))
+}
+
+/// Compare traffic totals or a top-N report between two windows
+pub async fn compare(
+    client: &CloudflareClient,
+    zone_id: &str,
+    report: &str,
+    windows: (&TimeRange, &TimeRange),
+    limit: u32,
+    format: &OutputFormat,
+) -> Result<()> {
+    let (current, baseline) = windows;
+    let (deltas, heading) = if report == "traffic" {
+        let now = totals(client, zone_id, current).await?;
+        let before = totals(client, zone_id, baseline).await?;
+        (traffic_deltas(&now, &before), "METRIC".to_string())
+    } else {
+        let preset = preset(report)?;
+        let deltas = compare_top(client, zone_id, preset, current, baseline, limit).await?;
+        (deltas, preset.main_dim.to_uppercase())
+    };
+
+    if matches!(format, OutputFormat::Json) {
+        return output::print_json_pretty(&deltas);
+    }
+
+    output::table_header(&[&heading, "CURRENT", "PREVIOUS", "CHANGE"]);
+    for delta in &deltas {
+        let change = delta.change_text();
+        // More threats or errors is bad, more traffic is good
+        let bad = matches!(
+            delta.name.as_str(),
+            "threats" | "5xx responses" | "5xx rate"
+        );
+        let change = match (delta.status, bad) {
+            ("up" | "new", false) | ("down" | "gone", true) => change.green().to_string(),
+            ("up" | "new", true) | ("down" | "gone", false) => change.red().to_string(),
+            _ => change.dimmed().to_string(),
+        };
+        println!(
+            "{}\t{}\t{}\t{}",
+            delta.name,
+            delta.display(delta.current),
+            delta.display(delta.previous),
+            change
+        );
+    }
+
+    if report != "traffic" {
+        let count = |status: &str| deltas.iter().filter(|d| d.status == status).count();
+        output::info(&format!(
+            "{} new, {} gone (relative to the top {} of each window)",
+            count("new"),
+            count("gone"),
+            limit.saturating_mul(2)
+        ));
+    }
+    output::info(&format!(
+        "{} to {} vs {} to {}",
+        current.since_str(),
+        current.until_str(),
+        baseline.since_str(),
+        baseline.until_str()
+    ));
+    Ok(())
+}
+
+/// Run a top-N preset for both windows and pair up the entries
+///
+/// Twice the limit is fetched per window, so an entry that merely dropped a
+/// few places out of the top N is not reported as gone.
+async fn compare_top(
+    client: &CloudflareClient,
+    zone_id: &str,
+    preset: &Preset,
+    current: &TimeRange,
+    baseline: &TimeRange,
+    limit: u32,
+) -> Result<Vec<Delta>> {
+    if preset.order_by != "count_DESC" {
+        return Err(anyhow!("'{}' cannot be compared", preset.name));
+    }
+    let fetch_limit = limit.saturating_mul(2).min(10000);
+    let fetch = |range: &TimeRange| {
+        let range = *range;
+        async move {
+            let response = query_range(
+                client,
+                &range,
+                preset.dataset,
+                preset.order_by,
+                Some(fetch_limit as usize),
+                |since, until| (preset.build)(zone_id, since, until, fetch_limit),
+            )
+            .await?;
+            Ok::<_, anyhow::Error>(count_rows(&response, preset.dataset))
+        }
+    };
+    let now = fetch(current).await?;
+    let before = fetch(baseline).await?;
+
+    let before_counts: HashMap<&str, u64> = before
+        .iter()
+        .map(|(label, count)| (label.as_str(), *count))
+        .collect();
+    let now_counts: HashMap<&str, u64> = now
+        .iter()
+        .map(|(label, count)| (label.as_str(), *count))
+        .collect();
+
+    let mut deltas: Vec<Delta> = now
+        .iter()
+        .take(limit as usize)
+        .map(|(label, count)| {
+            let previous = before_counts.get(label.as_str()).copied().unwrap_or(0);
+            Delta::new(label, *count as f64, previous as f64, Kind::Count)
+        })
+        .collect();
+    // Entries that were in the baseline's top N but are nowhere in the current list
+    deltas.extend(
+        before
+            .iter()
+            .take(limit as usize)
+            .filter(|(label, _)| !now_counts.contains_key(label.as_str()))
+            .map(|(label, count)| Delta::new(label, 0.0, *count as f64, Kind::Count)),
+    );
+    Ok(deltas)
+}
+
+/// A titled table of a report
+struct Section {
+    title: String,
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+    /// Shown instead of the table, e.g. when the data is not available
+    note: Option<String>,
+}
+
+/// Top-N tables in a report: preset and title
+const REPORT_TOPS: &[(&str, &str)] = &[
+    ("top-urls", "Top paths"),
+    ("top-countries", "Top countries"),
+    ("errors", "Error responses"),
+    ("cache", "Cache status"),
+    ("firewall", "Firewall events"),
+];
+
+/// Write a summary of the window as Markdown or HTML, to `output` or stdout
+pub async fn report(
+    client: &CloudflareClient,
+    zone: (&str, &str),
+    range: &TimeRange,
+    limit: u32,
+    html: bool,
+    output_path: Option<&Path>,
+) -> Result<()> {
+    let (zone_name, zone_id) = zone;
+    let granularity = auto_granularity(range);
+    let points = fetch_series(client, zone_id, range, granularity).await?;
+    let now = Point::total(&points);
+    let before = totals(client, zone_id, &range.previous()).await?;
+
+    let mut sections = vec![Section {
+        title: "Summary".to_string(),
+        headers: vec!["Metric", "This period", "Previous period", "Change"],
+        rows: traffic_deltas(&now, &before)
+            .iter()
+            .map(|d| {
+                vec![
+                    d.name.clone(),
+                    d.display(d.current),
+                    d.display(d.previous),
+                    d.change_text(),
+                ]
+            })
+            .collect(),
+        note: None,
+    }];
+
+    let requests: Vec<f64> = points.iter().map(|p| p.requests as f64).collect();
+    let trend = chart::sparkline(&chart::resample(&requests, 60));
+
+    for (name, title) in REPORT_TOPS {
+        let preset = preset(name)?;
+        let result = query_range(
+            client,
+            range,
+            preset.dataset,
+            preset.order_by,
+            Some(limit as usize),
+            |since, until| (preset.build)(zone_id, since, until, limit),
+        )
+        .await;
+        let mut section = Section {
+            title: title.to_string(),
+            headers: vec!["#", "Item", "Count", "Share"],
+            rows: Vec::new(),
+            note: None,
+        };
+        match result {
+            Ok(response) => {
+                let rows = count_rows(&response, preset.dataset);
+                let total: u64 = rows.iter().map(|(_, count)| count).sum();
+                section.rows = rows
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (label, count))| {
+                        vec![
+                            (i + 1).to_string(),
+                            label.clone(),
+                            count.to_string(),
+                            format!("{:.1}%", *count as f64 * 100.0 / total.max(1) as f64),
+                        ]
+                    })
+                    .collect();
+                if rows.is_empty() {
+                    section.note = Some("No data in this period.".to_string());
+                }
+            }
+            Err(e) => section.note = Some(format!("Not available: {}", e)),
+        }
+        sections.push(section);
+    }
+
+    let title = format!("Traffic report: {}", zone_name);
+    let period = format!(
+        "{} to {} ({} buckets of {})",
+        range.since_str(),
+        range.until_str(),
+        points.len(),
+        granularity
+    );
+    let text = if html {
+        render_html(&title, &period, &trend, &sections)
+    } else {
+        render_markdown(&title, &period, &trend, &sections)
+    };
+
+    match output_path {
+        Some(path) => {
+            std::fs::write(path, text)
+                .map_err(|e| anyhow!("Cannot write {}: {}", path.display(), e))?;
+            output::success(&format!("Report written to {}", path.display()));
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+fn render_markdown(title: &str, period: &str, trend: &str, sections: &[Section]) -> String {
+    let cell = |text: &str| escape_markdown(text).replace('\n', " ");
+    let mut out = format!(
+        "# {}\n\n{}\n\nRequests over time: `{}`\n",
+        cell(title),
+        cell(period),
+        trend
+    );
+    for section in sections {
+        out.push_str(&format!("\n## {}\n\n", section.title));
+        if let Some(note) = &section.note {
+            out.push_str(&format!("_{}_\n", cell(note)));
+            continue;
+        }
+        out.push_str(&format!("| {} |\n", section.headers.join(" | ")));
+        // Numbers are right-aligned, text left-aligned
+        let align: Vec<&str> = section
+            .headers
+            .iter()
+            .map(|h| {
+                if *h == "Item" || *h == "Metric" {
+                    "---"
+                } else {
+                    "---:"
+                }
+            })
+            .collect();
+        out.push_str(&format!("| {} |\n", align.join(" | ")));
+        for row in &section.rows {
+            let cells: Vec<String> = row.iter().map(|c| cell(c)).collect();
+            out.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+    }
+    out
+}
+
+fn render_html(title: &str, period: &str, trend: &str, sections: &[Section]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\n\
+         body {{ font-family: -apple-system, 'Segoe UI', sans-serif; margin: 2em; color: #222; }}\n\
+         table {{ border-collapse: collapse; margin-bottom: 1.5em; }}\n\
+         th, td {{ border-bottom: 1px solid #ddd; padding: 4px 12px; }}\n\
+         th {{ text-align: left; background: #f4f4f4; }}\n\
+         td.num {{ text-align: right; font-variant-numeric: tabular-nums; }}\n\
+         .trend {{ font-size: 1.4em; letter-spacing: -1px; color: #f38020; }}\n\
+         </style>\n</head>\n<body>\n<h1>{title}</h1>\n<p>{period}</p>\n\
+         <p>Requests over time: <span class=\"trend\">{trend}</span></p>\n",
+        title = escape_html(title),
+        period = escape_html(period),
+        trend = escape_html(trend),
+    );
+    for section in sections {
+        out.push_str(&format!("<h2>{}</h2>\n", escape_html(&section.title)));
+        if let Some(note) = &section.note {
+            out.push_str(&format!("<p><em>{}</em></p>\n", escape_html(note)));
+            continue;
+        }
+        out.push_str("<table>\n<tr>");
+        for header in &section.headers {
+            out.push_str(&format!("<th>{}</th>", escape_html(header)));
+        }
+        out.push_str("</tr>\n");
+        for row in &section.rows {
+            out.push_str("<tr>");
+            for (header, cell) in section.headers.iter().zip(row) {
+                let class = if *header == "Item" || *header == "Metric" {
+                    ""
+                } else {
+                    " class=\"num\""
+                };
+                out.push_str(&format!("<td{}>{}</td>", class, escape_html(cell)));
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Escape text for a Markdown table cell: pipes, code spans and inline HTML
+fn escape_markdown(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('|', "\\|")
+        .replace('`', "\\`")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delta_status_and_change() {
+        let up = Delta::new("requests", 150.0, 100.0, Kind::Count);
+        assert_eq!((up.status, up.change), ("up", Some(50.0)));
+        assert_eq!(up.change_text(), "+50.0%");
+
+        let down = Delta::new("bandwidth", 75.0, 100.0, Kind::Bytes);
+        assert_eq!((down.status, down.change), ("down", Some(-25.0)));
+        assert_eq!(down.change_text(), "-25.0%");
+
+        let same = Delta::new("threats", 3.0, 3.0, Kind::Count);
+        assert_eq!((same.status, same.change), ("same", Some(0.0)));
+
+        let new = Delta::new("/new", 5.0, 0.0, Kind::Count);
+        assert_eq!((new.status, new.change), ("new", None));
+        assert_eq!(new.change_text(), "new");
+
+        let gone = Delta::new("/old", 0.0, 5.0, Kind::Count);
+        assert_eq!((gone.status, gone.change), ("gone", Some(-100.0)));
+        assert_eq!(gone.change_text(), "gone");
+
+        let quiet = Delta::new("5xx responses", 0.0, 0.0, Kind::Count);
+        assert_eq!((quiet.status, quiet.change_text().as_str()), ("same", "-"));
+
+        // Ratios change in percentage points and are never new or gone
+        let ratio = Delta::new("cache hit ratio", 42.5, 0.0, Kind::Percent);
+        assert_eq!((ratio.status, ratio.change), ("up", Some(42.5)));
+        assert_eq!(ratio.change_text(), "+42.5 pp");
+        assert_eq!(ratio.display(42.5), "42.5%");
+        assert_eq!(
+            Delta::new("5xx rate", 0.0, 1.0, Kind::Percent).status,
+            "down"
+        );
+    }
+
+    fn sections() -> Vec<Section> {
+        vec![
+            Section {
+                title: "Top paths".to_string(),
+                headers: vec!["Item", "Requests"],
+                rows: vec![
+                    vec!["/a|b".to_string(), "10".to_string()],
+                    vec!["/<script>`x`&y".to_string(), "2".to_string()],
+                ],
+                note: None,
+            },
+            Section {
+                title: "Firewall events".to_string(),
+                headers: vec!["Item", "Events"],
+                rows: Vec::new(),
+                note: Some("Not available on this plan".to_string()),
+            },
+        ]
+    }
+
+    #[test]
+    fn markdown_escapes_cells() {
+        let markdown = render_markdown(
+            "example.com <report>",
+            "2026-10-11 to 2026-10-18",
+            "▁▃▅",
+            &sections(),
+        );
+        assert_eq!(
+            markdown,
+            "# example.com &lt;report&gt;\n\n2026-10-11 to 2026-10-18\n\nRequests over time: `▁▃▅`\n\
+             \n## Top paths\n\n\
+             | Item | Requests |\n\
+             | --- | ---: |\n\
+             | /a\\|b | 10 |\n\
+             | /&lt;script&gt;\\`x\\`&amp;y | 2 |\n\
+             \n## Firewall events\n\n\
+             _Not available on this plan_\n"
+        );
+    }
+
+    #[test]
+    fn html_escapes_cells() {
+        let html = render_html("example.com", "period", "▁▃▅", &sections());
+        assert!(html.contains("<title>example.com</title>"));
+        assert!(
+            html.contains("<h2>Top paths</h2>\n<table>\n<tr><th>Item</th><th>Requests</th></tr>\n")
+        );
+        assert!(html.contains("<tr><td>/a|b</td><td class=\"num\">10</td></tr>"));
+        assert!(html.contains("<td>/&lt;script&gt;`x`&amp;y</td>"));
+        assert!(html.contains("<p><em>Not available on this plan</em></p>"));
+        assert!(!html.contains("<script>"));
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+}
@@ -0,0 +1,676 @@
+//! Rulesets engine command - WAF custom rules, rate limiting, transforms,
+//! cache rules, redirects and managed rulesets (zone or account level)
+
+use anyhow::{anyhow, Result};
+use clap::{Args, Subcommand};
+use owo_colors::OwoColorize;
+use serde_json::{json, Value};
+
+use crate::api::rulesets;
+use crate::api::CloudflareClient;
+use crate::config::Config;
+use crate::expr;
+use crate::output;
+
+#[derive(Args, Debug)]
+pub struct RulesetsArgs {
+    /// Zone name or ID
+    #[arg(short, long)]
+    pub zone: Option<String>,
+
+    /// Use account-level rulesets instead of zone rulesets
+    #[arg(short, long)]
+    pub account: bool,
+
+    #[command(subcommand)]
+    pub command: RulesetsCommand,
+}
+
+/// Rule definition arguments shared by add/update
+#[derive(Args, Debug)]
+pub struct RuleArgs {
+    /// Rule expression (e.g. 'http.request.uri.path contains "/admin"')
+    #[arg(short, long, conflicts_with = "expression_file")]
+    pub expression: Option<String>,
+
+    /// Read the rule expression from a file
+    #[arg(long)]
+    pub expression_file: Option<String>,
+
+    /// Action: block, challenge, managed_challenge, js_challenge, log, skip, execute, rewrite, redirect, set_cache_settings, ...
+    #[arg(long)]
+    pub action: Option<String>,
+
+    /// Rule description
+    #[arg(short, long)]
+    pub description: Option<String>,
+
+    /// Action parameters (JSON)
+    #[arg(long)]
+    pub action_parameters: Option<String>,
+
+    /// Read the full rule definition (JSON) from a file; flags override its fields
+    #[arg(long)]
+    pub rule_file: Option<String>,
+
+    /// Send the expression without validating it locally first
+    #[arg(long)]
+    pub no_check: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RulesetsCommand {
+    /// List rulesets
+    List {
+        /// Filter by phase (name or alias: custom, managed, ratelimit, transform, cache, redirect)
+        #[arg(short, long)]
+        phase: Option<String>,
+
+        /// Filter by kind: zone, root, managed, custom
+        #[arg(short, long)]
+        kind: Option<String>,
+    },
+
+    /// Show a ruleset and its rules
+    Show {
+        /// Ruleset ID or phase (name or alias) of the entrypoint ruleset
+        ruleset: String,
+
+        /// Print the raw ruleset JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List known phases and their aliases
+    Phases,
+
+    /// Add a rule to a phase entrypoint (created if missing) or ruleset
+    Add {
+        /// Ruleset ID or phase (name or alias)
+        ruleset: String,
+
+        #[command(flatten)]
+        rule: RuleArgs,
+
+        /// Create the rule disabled
+        #[arg(long)]
+        disabled: bool,
+    },
+
+    /// Update a rule
+    Update {
+        /// Ruleset ID or phase (name or alias)
+        ruleset: String,
+
+        /// Rule ID
+        rule_id: String,
+
+        #[command(flatten)]
+        rule: RuleArgs,
+    },
+
+    /// Delete a rule
+    Delete {
+        /// Ruleset ID or phase (name or alias)
+        ruleset: String,
+
+        /// Rule ID
+        rule_id: String,
+
+        /// Skip confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Move a rule within its ruleset
+    Move {
+        /// Ruleset ID or phase (name or alias)
+        ruleset: String,
+
+        /// Rule ID
+        rule_id: String,
+
+        /// Place before this rule ID
+        #[arg(long, conflicts_with_all = ["after", "index"])]
+        before: Option<String>,
+
+        /// Place after this rule ID
+        #[arg(long, conflicts_with = "index")]
+        after: Option<String>,
+
+        /// Place at this position (1-based)
+        #[arg(long)]
+        index: Option<u32>,
+    },
+
+    /// Enable a rule
+    Enable {
+        /// Ruleset ID or phase (name or alias)
+        ruleset: String,
+
+        /// Rule ID
+        rule_id: String,
+    },
+
+    /// Disable a rule
+    Disable {
+        /// Ruleset ID or phase (name or alias)
+        ruleset: String,
+
+        /// Rule ID
+        rule_id: String,
+    },
+
+    /// Deploy a managed ruleset (e.g. Cloudflare Managed Ruleset, OWASP)
+    ///
+    /// If the phase already executes this ruleset, that rule is updated
+    /// (expression included) instead of adding another one.
+    Deploy {
+        /// Managed ruleset ID or name
+        managed: String,
+
+        /// Expression selecting traffic the ruleset applies to
+        #[arg(short, long, default_value = "true")]
+        expression: String,
+
+        /// Phase to deploy into
+        #[arg(short, long, default_value = "http_request_firewall_managed")]
+        phase: String,
+
+        /// Override the action of all rules (e.g. log, block)
+        #[arg(long)]
+        override_action: Option<String>,
+
+        /// Override a single rule: RULE_ID=action or RULE_ID=disable
+        #[arg(long = "override-rule")]
+        override_rules: Vec<String>,
+
+        /// Override a category: TAG=action or TAG=disable
+        #[arg(long = "override-category")]
+        override_categories: Vec<String>,
+
+        /// Rule description
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+}
+
+pub async fn execute(config: &Config, args: RulesetsArgs) -> Result<()> {
+    let client = CloudflareClient::new(config.clone())?;
+    let base = if args.account {
+        format!("/accounts/{}", get_account_id(&client).await?)
+    } else {
+        let zone = config.resolve_zone(args.zone.as_deref())?;
+        format!("/zones/{}", client.resolve_zone_id(&zone).await?)
+    };
+
+    match args.command {
+        RulesetsCommand::List { phase, kind } => {
+            let phase = phase.map(|p| rulesets::resolve_phase(&p));
+            let all = rulesets::list(&client, &base).await?;
+
+            let filtered: Vec<&Value> = all
+                .iter()
+                .filter(|r| {
+                    phase
+                        .as_deref()
+                        .is_none_or(|p| r.get("phase").and_then(|v| v.as_str()) == Some(p))
+                })
+                .filter(|r| {
+                    kind.as_deref()
+                        .is_none_or(|k| r.get("kind").and_then(|v| v.as_str()) == Some(k))
+                })
+                .collect();
+
+            if filtered.is_empty() {
+                output::info("No rulesets found");
+                return Ok(());
+            }
+
+            output::table_header(&["KIND", "PHASE", "NAME", "VERSION", "ID"]);
+            for ruleset in &filtered {
+                print_ruleset(ruleset);
+            }
+            output::info(&format!("Total: {} rulesets", filtered.len()));
+        }
+
+        RulesetsCommand::Show { ruleset, json } => {
+            let ruleset = rulesets::resolve(&client, &base, &ruleset).await?;
+
+            if json {
+                output::print_json_pretty(&ruleset)?;
+                return Ok(());
+            }
+
+            print_ruleset(&ruleset);
+            println!();
+            let rules = rulesets::rules(&ruleset);
+            if rules.is_empty() {
+                output::info("No rules");
+            } else {
+                output::table_header(&["#", "STATE", "ACTION", "DESCRIPTION", "ID"]);
+                for (i, rule) in rules.iter().enumerate() {
+                    output::print_ruleset_rule(i + 1, rule);
+                }
+                output::info(&format!("Total: {} rules", rules.len()));
+            }
+        }
+
+        RulesetsCommand::Phases => {
+            output::table_header(&["ALIAS", "PHASE"]);
+            for (alias, phase) in rulesets::PHASE_ALIASES {
+                println!("{}\t{}", alias.cyan(), phase);
+            }
+        }
+
+        RulesetsCommand::Add {
+            ruleset,
+            rule,
+            disabled,
+        } => {
+            let mut body = build_rule(&rule)?;
+            if body.get("expression").is_none() {
+                return Err(anyhow!(
+                    "Missing rule expression (--expression or --expression-file)"
+                ));
+            }
+            if body.get("action").is_none() {
+                return Err(anyhow!("Missing rule action (--action)"));
+            }
+            if disabled {
+                body["enabled"] = json!(false);
+            }
+
+            let target = resolve_or_create(&client, &base, &ruleset).await?;
+            let updated = rulesets::add_rule(&client, &base, &rulesets::id(&target)?, body).await?;
+
+            output::success("Rule added");
+            let rules = rulesets::rules(&updated);
+            if let Some(rule) = rules.last() {
+                output::print_ruleset_rule(rules.len(), rule);
+            }
+        }
+
+        RulesetsCommand::Update {
+            ruleset,
+            rule_id,
+            rule,
+        } => {
+            let changes = build_rule(&rule)?;
+            let target = rulesets::resolve(&client, &base, &ruleset).await?;
+            rulesets::update_rule(&client, &base, &target, &rule_id, changes).await?;
+            output::success(&format!("Updated rule: {}", rule_id));
+        }
+
+        RulesetsCommand::Delete {
+            ruleset,
+            rule_id,
+            yes,
+        } => {
+            if !yes {
+                output::warning(&format!(
+                    "Are you sure you want to delete rule {}?",
+                    rule_id
+                ));
+                output::info("Use -y to skip this confirmation");
+                return Ok(());
+            }
+
+            let target = rulesets::resolve(&client, &base, &ruleset).await?;
+            rulesets::delete_rule(&client, &base, &rulesets::id(&target)?, &rule_id).await?;
+            output::success(&format!("Deleted rule: {}", rule_id));
+        }
+
+        RulesetsCommand::Move {
+            ruleset,
+            rule_id,
+            before,
+            after,
+            index,
+        } => {
+            let position = if let Some(id) = before {
+                json!({"before": id})
+            } else if let Some(id) = after {
+                json!({"after": id})
+            } else if let Some(i) = index {
+                json!({"index": i})
+            } else {
+                return Err(anyhow!("Specify --before, --after or --index"));
+            };
+
+            let target = rulesets::resolve(&client, &base, &ruleset).await?;
+            rulesets::update_rule(
+                &client,
+                &base,
+                &target,
+                &rule_id,
+                json!({"position": position}),
+            )
+            .await?;
+            output::success(&format!("Moved rule: {}", rule_id));
+        }
+
+        RulesetsCommand::Enable { ruleset, rule_id } => {
+            let target = rulesets::resolve(&client, &base, &ruleset).await?;
+            rulesets::update_rule(&client, &base, &target, &rule_id, json!({"enabled": true}))
+                .await?;
+            output::success(&format!("Enabled rule: {}", rule_id));
+        }
+
+        RulesetsCommand::Disable { ruleset, rule_id } => {
+            let target = rulesets::resolve(&client, &base, &ruleset).await?;
+            rulesets::update_rule(&client, &base, &target, &rule_id, json!({"enabled": false}))
+                .await?;
+            output::success(&format!("Disabled rule: {}", rule_id));
+        }
+
+        RulesetsCommand::Deploy {
+            managed,
+            expression,
+            phase,
+            override_action,
+            override_rules,
+            override_categories,
+            description,
+        } => {
+            let managed_ruleset = find_managed_ruleset(&client, &base, &managed).await?;
+            let managed_id = rulesets::id(&managed_ruleset)?;
+            let managed_name = managed_ruleset
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or(&managed_id)
+                .to_string();
+
+            let mut overrides = serde_json::Map::new();
+            if let Some(action) = override_action {
+                overrides.insert("action".to_string(), json!(action));
+            }
+            if !override_rules.is_empty() {
+                let rules = override_rules
+                    .iter()
+                    .map(|o| parse_override("id", o))
+                    .collect::<Result<Vec<_>>>()?;
+                overrides.insert("rules".to_string(), json!(rules));
+            }
+            if !override_categories.is_empty() {
+                let categories = override_categories
+                    .iter()
+                    .map(|o| parse_override("category", o))
+                    .collect::<Result<Vec<_>>>()?;
+                overrides.insert("categories".to_string(), json!(categories));
+            }
+
+            let mut action_parameters = json!({"id": managed_id});
+            if !overrides.is_empty() {
+                action_parameters["overrides"] = Value::Object(overrides);
+            }
+
+            let body = json!({
+                "action": "execute",
+                "expression": expression,
+                "description": description.unwrap_or_else(|| format!("Deploy {}", managed_name)),
+                "action_parameters": action_parameters
+            });
+
+            let phase = rulesets::resolve_phase(&phase);
+            let entrypoint = rulesets::ensure_entrypoint(&client, &base, &phase).await?;
+
+            // Deploying again updates the rule instead of executing the ruleset twice
+            match deployed_rule_id(&entrypoint, &managed_id) {
+                Some(rule_id) => {
+                    rulesets::update_rule(&client, &base, &entrypoint, &rule_id, body).await?;
+                    output::success(&format!(
+                        "Updated deployment of managed ruleset: {} (rule {})",
+                        managed_name, rule_id
+                    ));
+                }
+                None => {
+                    rulesets::add_rule(&client, &base, &rulesets::id(&entrypoint)?, body).await?;
+                    output::success(&format!("Deployed managed ruleset: {}", managed_name));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolve a ruleset, creating the phase entrypoint if it does not exist yet
+async fn resolve_or_create(client: &CloudflareClient, base: &str, target: &str) -> Result<Value> {
+    if rulesets::is_id(target) {
+        rulesets::get(client, base, target).await
+    } else {
+        rulesets::ensure_entrypoint(client, base, &rulesets::resolve_phase(target)).await
+    }
+}
+
+/// Build a rule body from CLI arguments (only fields that were given)
+fn build_rule(args: &RuleArgs) -> Result<Value> {
+    let mut rule = match &args.rule_file {
+        Some(path) => {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Cannot read rule file {}: {}", path, e))?;
+            serde_json::from_str(&content)
+                .map_err(|e| anyhow!("Invalid rule JSON in {}: {}", path, e))?
+        }
+        None => json!({}),
+    };
+
+    if !rule.is_object() {
+        return Err(anyhow!("Rule definition must be a JSON object"));
+    }
+
+    if let Some(ref expr) = args.expression {
+        rule["expression"] = json!(expr);
+    }
+    if let Some(ref path) = args.expression_file {
+        let expr = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read expression file {}: {}", path, e))?;
+        rule["expression"] = json!(expr.trim());
+    }
+    if let Some(ref action) = args.action {
+        rule["action"] = json!(action);
+    }
+    if let Some(ref description) = args.description {
+        rule["description"] = json!(description);
+    }
+    if let Some(ref params) = args.action_parameters {
+        let parsed: Value = serde_json::from_str(params)
+            .map_err(|e| anyhow!("Invalid --action-parameters JSON: {}", e))?;
+        rule["action_parameters"] = parsed;
+    }
+
+    if !args.no_check {
+        if let Some(src) = rule.get("expression").and_then(|v| v.as_str()) {
+            check_expression(src)?;
+        }
+    }
+
+    Ok(rule)
+}
+
+/// Validate an expression locally, printing diagnostics
+pub fn check_expression(src: &str) -> Result<()> {
+    let checked = expr::parse(src);
+    output::print_expr_diagnostics(src, &checked);
+    if !checked.is_ok() {
+        return Err(anyhow!(
+            "Invalid expression ({} error(s)); use --no-check to send it anyway",
+            checked.errors.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Parse a `KEY=action` override; `disable` turns the rule/category off
+fn parse_override(key: &str, value: &str) -> Result<Value> {
+    let (target, action) = value
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Invalid override '{}', expected KEY=action", value))?;
+
+    Ok(match action {
+        "disable" | "off" => json!({ key: target, "enabled": false }),
+        "enable" | "on" => json!({ key: target, "enabled": true }),
+        _ => json!({ key: target, "action": action }),
+    })
+}
+
+/// Find a managed ruleset by ID or (partial, case-insensitive) name
+async fn find_managed_ruleset(
+    client: &CloudflareClient,
+    base: &str,
+    managed: &str,
+) -> Result<Value> {
+    let all = rulesets::list(client, base).await?;
+    pick_managed_ruleset(all, managed)
+}
+
+/// The managed ruleset with this exact ID or name, else the only one whose name contains it
+fn pick_managed_ruleset(all: Vec<Value>, managed: &str) -> Result<Value> {
+    let str_of = |r: &Value, key: &str| r.get(key).and_then(|v| v.as_str()).map(String::from);
+    let needle = managed.to_lowercase();
+
+    let candidates: Vec<Value> = all
+        .into_iter()
+        .filter(|r| r.get("kind").and_then(|v| v.as_str()) == Some("managed"))
+        .collect();
+
+    if let Some(exact) = candidates.iter().find(|r| {
+        str_of(r, "id").as_deref() == Some(managed)
+            || str_of(r, "name").is_some_and(|n| n.to_lowercase() == needle)
+    }) {
+        return Ok(exact.clone());
+    }
+
+    let mut matches: Vec<Value> = candidates
+        .into_iter()
+        .filter(|r| str_of(r, "name").is_some_and(|n| n.to_lowercase().contains(&needle)))
+        .collect();
+
+    match matches.len() {
+        0 => Err(anyhow!("Managed ruleset not found: {}", managed)),
+        1 => Ok(matches.remove(0)),
+        _ => Err(anyhow!(
+            "'{}' matches several managed rulesets, use the ID or full name:\n{}",
+            managed,
+            matches
+                .iter()
+                .map(|r| format!(
+                    "  {} ({})",
+                    str_of(r, "name").unwrap_or_default(),
+                    str_of(r, "id").unwrap_or_default()
+                ))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )),
+    }
+}
+
+/// The rule of an entrypoint that already executes the managed ruleset
+fn deployed_rule_id(entrypoint: &Value, managed_id: &str) -> Option<String> {
+    rulesets::rules(entrypoint).into_iter().find_map(|rule| {
+        let executes = rule.get("action").and_then(|v| v.as_str()) == Some("execute")
+            && rule
+                .pointer("/action_parameters/id")
+                .and_then(|v| v.as_str())
+                == Some(managed_id);
+        let id = rule.get("id").and_then(|v| v.as_str())?;
+        executes.then(|| id.to_string())
+    })
+}
+
+fn print_ruleset(ruleset: &Value) {
+    let id = ruleset.get("id").and_then(|v| v.as_str()).unwrap_or("-");
+    let kind = ruleset.get("kind").and_then(|v| v.as_str()).unwrap_or("-");
+    let phase = ruleset.get("phase").and_then(|v| v.as_str()).unwrap_or("-");
+    let name = ruleset.get("name").and_then(|v| v.as_str()).unwrap_or("-");
+    let version = ruleset
+        .get("version")
+        .and_then(|v| v.as_str())
+        .unwrap_or("-");
+
+    println!(
+        "{}\t{}\t{}\t{}\t{}",
+        kind.cyan(),
+        phase,
+        name.bold(),
+        version.dimmed(),
+        id.dimmed()
+    );
+}
+
+async fn get_account_id(client: &CloudflareClient) -> Result<String> {
+    let response = client.get_raw("/zones?per_page=1").await?;
+    if let Some(zones) = response.get("result").and_then(|r| r.as_array()) {
+        if let Some(zone) = zones.first() {
+            if let Some(account) = zone.get("account") {
+                if let Some(id) = account.get("id").and_then(|i| i.as_str()) {
+                    return Ok(id.to_string());
+                }
+            }
+        }
+    }
+    Err(anyhow::anyhow!("Could not determine account ID"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn managed() -> Vec<Value> {
+        vec![
+            json!({"id": "efb7b8c949ac4650a09736fc376e9aee", "kind": "managed", "name": "Cloudflare Managed Ruleset"}),
+            json!({"id": "4814384a9e5d4991b9815dcfc25d2f1f", "kind": "managed", "name": "Cloudflare OWASP Core Ruleset"}),
+            json!({"id": "c2e184081120413c86c3ab7e14069605", "kind": "managed", "name": "Cloudflare Exposed Credentials Check Ruleset"}),
+            json!({"id": "0000", "kind": "custom", "name": "OWASP notes"}),
+        ]
+    }
+
+    fn pick(needle: &str) -> Result<String> {
+        pick_managed_ruleset(managed(), needle).map(|r| r["id"].as_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn managed_ruleset_prefers_exact_matches() {
+        assert_eq!(
+            pick("4814384a9e5d4991b9815dcfc25d2f1f").unwrap(),
+            "4814384a9e5d4991b9815dcfc25d2f1f"
+        );
+        // Also a substring of the other two names
+        assert_eq!(
+            pick("cloudflare managed ruleset").unwrap(),
+            "efb7b8c949ac4650a09736fc376e9aee"
+        );
+        assert_eq!(pick("owasp").unwrap(), "4814384a9e5d4991b9815dcfc25d2f1f");
+        assert!(pick("0000").is_err());
+        assert!(pick("bot fight").is_err());
+    }
+
+    #[test]
+    fn ambiguous_managed_ruleset_lists_candidates() {
+        let err = pick("Cloudflare").unwrap_err().to_string();
+        assert!(err.contains("matches several"), "{}", err);
+        assert!(err.contains("Cloudflare OWASP Core Ruleset (4814384a9e5d4991b9815dcfc25d2f1f)"));
+        assert!(err.contains("Cloudflare Exposed Credentials Check Ruleset"));
+    }
+
+    #[test]
+    fn redeploy_finds_the_rule_by_ruleset_id() {
+        let entrypoint = json!({
+            "id": "entry",
+            "rules": [
+                {"id": "r1", "action": "block", "expression": "true"},
+                {"id": "r2", "action": "execute", "expression": "http.host eq \"a.example\"",
+                 "action_parameters": {"id": "efb7b8c949ac4650a09736fc376e9aee"}},
+            ]
+        });
+        assert_eq!(
+            deployed_rule_id(&entrypoint, "efb7b8c949ac4650a09736fc376e9aee").as_deref(),
+            Some("r2")
+        );
+        assert_eq!(
+            deployed_rule_id(&entrypoint, "4814384a9e5d4991b9815dcfc25d2f1f"),
+            None
+        );
+    }
+}
@@ -1,9 +1,14 @@
 //! Settings command
 
-use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{anyhow, Result};
 use clap::{Args, Subcommand};
-use serde_json::json;
+use owo_colors::OwoColorize;
+use serde_json::{json, Value};
 
+use super::multizone::{self, Zone, ZoneSelection};
+use crate::api::settings as schema;
 use crate::api::CloudflareClient;
 use crate::config::Config;
 use crate::output;
@@ -14,6 +19,9 @@ pub struct SettingsArgs {
     #[arg(short, long)]
     pub zone: Option<String>,
 
+    #[command(flatten)]
+    pub targets: ZoneSelection,
+
     #[command(subcommand)]
     pub command: SettingsCommand,
 }
@@ -49,7 +57,7 @@ pub enum SettingsCommand {
 
     /// Set cache level
     CacheLevel {
-        /// Level: bypass, basic, simplified, aggressive
+        /// Level: basic, simplified, aggressive
         level: String,
     },
 
@@ -74,18 +82,146 @@ pub enum SettingsCommand {
         js: Option<bool>,
     },
 
-    /// Set a custom setting value
+    /// Set any setting (validated against the built-in schema when known)
     Set {
         /// Setting name
         name: String,
 
-        /// Setting value (as JSON)
+        /// Setting value (plain word, number, or JSON for objects)
         value: String,
     },
+
+    /// Explain a setting and the values it accepts (all settings if omitted)
+    Describe {
+        /// Setting name
+        name: Option<String>,
+    },
+
+    /// Export all zone settings to JSON or YAML
+    Export {
+        /// Write to this file instead of stdout (.yaml/.yml selects YAML)
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Print YAML instead of JSON
+        #[arg(long)]
+        yaml: bool,
+    },
+
+    /// Apply settings from an exported file (only changed, editable values)
+    Apply {
+        /// JSON or YAML file (see `settings export`)
+        file: String,
+
+        #[command(flatten)]
+        filter: SettingsFilter,
+
+        /// Show the changes without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Compare this zone's settings with a file or another zone
+    ///
+    /// A file only covers the settings it lists; settings missing from it are
+    /// not reported as differences.
+    Diff {
+        /// Settings file or zone name/ID
+        other: String,
+
+        #[command(flatten)]
+        filter: SettingsFilter,
+
+        /// Exit with an error when there are differences (for drift checks)
+        #[arg(long)]
+        exit_code: bool,
+    },
+
+    /// Copy settings from one zone to another
+    Copy {
+        /// Source zone name or ID
+        #[arg(long)]
+        from: String,
+
+        /// Target zone name or ID
+        #[arg(long)]
+        to: String,
+
+        #[command(flatten)]
+        filter: SettingsFilter,
+
+        /// Show the changes without applying them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+impl SettingsCommand {
+    /// Commands that only use the built-in schema
+    pub fn is_offline(&self) -> bool {
+        matches!(self, SettingsCommand::Describe { .. })
+    }
+}
+
+/// Limit bulk operations to some settings
+#[derive(Args, Debug)]
+pub struct SettingsFilter {
+    /// Only these settings (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    pub only: Vec<String>,
+
+    /// Skip these settings (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+}
+
+impl SettingsFilter {
+    fn matches(&self, name: &str) -> bool {
+        (self.only.is_empty() || self.only.iter().any(|n| n == name))
+            && !self.exclude.iter().any(|n| n == name)
+    }
+}
+
+/// Current value of a zone setting
+struct Setting {
+    value: Value,
+    editable: bool,
 }
 
 pub async fn execute(config: &Config, args: SettingsArgs) -> Result<()> {
+    if let SettingsCommand::Describe { name } = &args.command {
+        return describe(name.as_deref());
+    }
+
     let client = CloudflareClient::new(config.clone())?;
+
+    // Copy names both zones explicitly
+    if let SettingsCommand::Copy {
+        from,
+        to,
+        filter,
+        dry_run,
+    } = args.command
+    {
+        let from_id = client.resolve_zone_id(&from).await?;
+        let to_id = client.resolve_zone_id(&to).await?;
+
+        let source = fetch_settings(&client, &from_id).await?;
+        let desired = source
+            .into_iter()
+            .filter(|(_, s)| s.editable)
+            .map(|(name, s)| (name, s.value))
+            .collect();
+
+        output::info(&format!("Copying settings from {} to {}", from, to));
+        return apply_settings(&client, &to_id, desired, &filter, dry_run).await;
+    }
+
+    if args.targets.is_active() {
+        let zones = args.targets.resolve(&client).await?;
+        return execute_multi(&client, &zones, args.targets.concurrency, args.command).await;
+    }
+
     let zone = config.resolve_zone(args.zone.as_deref())?;
     let zone_id = client.resolve_zone_id(&zone).await?;
 
@@ -123,93 +259,29 @@ pub async fn execute(config: &Config, args: SettingsArgs) -> Result<()> {
             output::print_output(&response.get("result"), &config.output_format)?;
         }
 
-        SettingsCommand::Ssl { mode } => {
-            let valid = ["off", "flexible", "full", "strict"];
-            if !valid.contains(&mode.as_str()) {
-                output::error(&format!(
-                    "Invalid SSL mode. Valid options: {}",
-                    valid.join(", ")
-                ));
-                return Ok(());
-            }
-
-            let body = json!({"value": mode});
-            client
-                .patch_raw(&format!("/zones/{}/settings/ssl", zone_id), body)
-                .await?;
-            output::success(&format!("SSL mode set to: {}", mode));
-        }
-
-        SettingsCommand::Https { value } => {
-            let val = match value.to_lowercase().as_str() {
-                "on" | "true" | "1" => "on",
-                "off" | "false" | "0" => "off",
-                _ => {
-                    output::error("Invalid value. Use 'on' or 'off'");
-                    return Ok(());
-                }
+        command @ (SettingsCommand::Ssl { .. }
+        | SettingsCommand::Https { .. }
+        | SettingsCommand::Security { .. }
+        | SettingsCommand::CacheLevel { .. }
+        | SettingsCommand::BrowserCacheTtl { .. }
+        | SettingsCommand::Set { .. }) => {
+            let Some((name, value)) = setting_change(&command)? else {
+                unreachable!() // Every setter changes a setting
             };
-
-            let body = json!({"value": val});
-            client
-                .patch_raw(
-                    &format!("/zones/{}/settings/always_use_https", zone_id),
-                    body,
-                )
-                .await?;
-            output::success(&format!("Always Use HTTPS: {}", val));
-        }
-
-        SettingsCommand::Security { level } => {
-            let valid = [
-                "off",
-                "essentially_off",
-                "low",
-                "medium",
-                "high",
-                "under_attack",
-            ];
-            if !valid.contains(&level.as_str()) {
-                output::error(&format!(
-                    "Invalid security level. Valid options: {}",
-                    valid.join(", ")
+            if schema::find(&name).is_none() {
+                output::warning(&format!(
+                    "{} is not in the built-in schema; sending the value unchecked",
+                    name
                 ));
-                return Ok(());
             }
 
-            let body = json!({"value": level});
-            client
-                .patch_raw(&format!("/zones/{}/settings/security_level", zone_id), body)
-                .await?;
-            output::success(&format!("Security level set to: {}", level));
-        }
-
-        SettingsCommand::CacheLevel { level } => {
-            let valid = ["bypass", "basic", "simplified", "aggressive"];
-            if !valid.contains(&level.as_str()) {
-                output::error(&format!(
-                    "Invalid cache level. Valid options: {}",
-                    valid.join(", ")
-                ));
-                return Ok(());
-            }
-
-            let body = json!({"value": level});
-            client
-                .patch_raw(&format!("/zones/{}/settings/cache_level", zone_id), body)
-                .await?;
-            output::success(&format!("Cache level set to: {}", level));
-        }
-
-        SettingsCommand::BrowserCacheTtl { seconds } => {
-            let body = json!({"value": seconds});
             client
                 .patch_raw(
-                    &format!("/zones/{}/settings/browser_cache_ttl", zone_id),
-                    body,
+                    &format!("/zones/{}/settings/{}", zone_id, name),
+                    json!({ "value": value }),
                 )
                 .await?;
-            output::success(&format!("Browser cache TTL set to: {} seconds", seconds));
+            output::success(&format!("{} set to: {}", name, compact(&value)));
         }
 
         SettingsCommand::Minify { css, html, js } => {
@@ -234,6 +306,9 @@ pub async fn execute(config: &Config, args: SettingsArgs) -> Result<()> {
                     current_value.get("js").and_then(|v| v.as_str()).unwrap_or("off")
                 ),
             });
+            if let Some(spec) = schema::find("minify") {
+                spec.validate(&new_value)?;
+            }
 
             let body = json!({"value": new_value});
             client
@@ -242,17 +317,365 @@ pub async fn execute(config: &Config, args: SettingsArgs) -> Result<()> {
             output::success(&format!("Minification updated: {}", new_value));
         }
 
-        SettingsCommand::Set { name, value } => {
-            let parsed_value: serde_json::Value =
-                serde_json::from_str(&value).unwrap_or_else(|_| json!(value));
+        SettingsCommand::Export { output: path, yaml } => {
+            let settings: BTreeMap<String, Value> = fetch_settings(&client, &zone_id)
+                .await?
+                .into_iter()
+                .map(|(name, s)| (name, s.value))
+                .collect();
+            let document = json!({
+                "zone": zone,
+                "exported_at": chrono::Utc::now().to_rfc3339(),
+                "settings": settings
+            });
 
-            let body = json!({"value": parsed_value});
-            client
-                .patch_raw(&format!("/zones/{}/settings/{}", zone_id, name), body)
+            let yaml = yaml || path.as_deref().is_some_and(is_yaml_path);
+            let text = if yaml {
+                serde_yaml::to_string(&document)?
+            } else {
+                serde_json::to_string_pretty(&document)? + "\n"
+            };
+
+            match path {
+                Some(path) => {
+                    std::fs::write(&path, text)?;
+                    output::success(&format!("Exported {} settings to {}", settings.len(), path));
+                }
+                None => print!("{}", text),
+            }
+        }
+
+        SettingsCommand::Apply {
+            file,
+            filter,
+            dry_run,
+        } => {
+            let desired = load_settings_file(&file)?;
+            apply_settings(&client, &zone_id, desired, &filter, dry_run).await?;
+        }
+
+        SettingsCommand::Diff {
+            other,
+            filter,
+            exit_code,
+        } => {
+            let current = fetch_settings(&client, &zone_id).await?;
+
+            let is_file = std::path::Path::new(&other).exists();
+            let (label, theirs) = if is_file {
+                (other.clone(), load_settings_file(&other)?)
+            } else {
+                let other_id = client.resolve_zone_id(&other).await?;
+                let settings = fetch_settings(&client, &other_id)
+                    .await?
+                    .into_iter()
+                    .map(|(name, s)| (name, s.value))
+                    .collect();
+                (other.clone(), settings)
+            };
+
+            // A file is usually a partial export: compare only what it sets
+            let names: BTreeSet<&String> = if is_file {
+                theirs.keys().filter(|n| filter.matches(n)).collect()
+            } else {
+                current
+                    .keys()
+                    .chain(theirs.keys())
+                    .filter(|n| filter.matches(n))
+                    .collect()
+            };
+
+            let mut differences = 0;
+            for name in names {
+                let ours = current.get(name).map(|s| &s.value);
+                let other_value = theirs.get(name);
+                if ours == other_value {
+                    continue;
+                }
+                differences += 1;
+                println!(
+                    "{}: {} -> {}",
+                    name.cyan(),
+                    ours.map(compact)
+                        .unwrap_or_else(|| "(missing)".to_string())
+                        .red(),
+                    other_value
+                        .map(compact)
+                        .unwrap_or_else(|| "(missing)".to_string())
+                        .green()
+                );
+            }
+
+            if differences == 0 {
+                output::success(&format!("{} matches {}", zone, label));
+            } else {
+                output::info(&format!(
+                    "{} setting(s) differ between {} and {}",
+                    differences, zone, label
+                ));
+                if exit_code {
+                    return Err(anyhow!("{} setting(s) differ", differences));
+                }
+            }
+        }
+
+        SettingsCommand::Copy { .. } | SettingsCommand::Describe { .. } => unreachable!(), // Handled above
+    }
+
+    Ok(())
+}
+
+/// Read or change one setting on many zones
+async fn execute_multi(
+    client: &CloudflareClient,
+    zones: &[Zone],
+    concurrency: usize,
+    command: SettingsCommand,
+) -> Result<()> {
+    if let SettingsCommand::Get { name } = &command {
+        return multizone::run(zones, concurrency, |zone| async move {
+            let response = client
+                .get_raw(&format!("/zones/{}/settings/{}", zone.id, name))
                 .await?;
-            output::success(&format!("Setting {} updated", name));
+            Ok(compact(
+                response.pointer("/result/value").unwrap_or(&Value::Null),
+            ))
+        })
+        .await;
+    }
+
+    let (name, value) = setting_change(&command)?.ok_or_else(|| {
+        anyhow!("--zones/--all-zones/--zone-filter only work with get, set and the typed setters")
+    })?;
+    let (name, value) = (&name, &value);
+
+    multizone::run(zones, concurrency, |zone| async move {
+        client
+            .patch_raw(
+                &format!("/zones/{}/settings/{}", zone.id, name),
+                json!({ "value": value }),
+            )
+            .await?;
+        Ok(format!("{} set to {}", name, compact(value)))
+    })
+    .await
+}
+
+/// The setting and validated value a setter command changes
+fn setting_change(command: &SettingsCommand) -> Result<Option<(String, Value)>> {
+    let (name, raw) = match command {
+        SettingsCommand::Ssl { mode } => ("ssl", mode.clone()),
+        SettingsCommand::Https { value } => ("always_use_https", value.clone()),
+        SettingsCommand::Security { level } => ("security_level", level.clone()),
+        SettingsCommand::CacheLevel { level } => ("cache_level", level.clone()),
+        SettingsCommand::BrowserCacheTtl { seconds } => ("browser_cache_ttl", seconds.to_string()),
+        SettingsCommand::Set { name, value } => (name.as_str(), value.clone()),
+        _ => return Ok(None),
+    };
+
+    let value = match schema::find(name) {
+        Some(spec) => spec.parse(&raw)?,
+        None => schema::parse_json(name, &raw)?,
+    };
+    Ok(Some((name.to_string(), value)))
+}
+
+/// All settings of a zone with their editability
+async fn fetch_settings(
+    client: &CloudflareClient,
+    zone_id: &str,
+) -> Result<BTreeMap<String, Setting>> {
+    let response = client
+        .get_raw(&format!("/zones/{}/settings", zone_id))
+        .await?;
+
+    Ok(response
+        .get("result")
+        .and_then(|r| r.as_array())
+        .map(|settings| {
+            settings
+                .iter()
+                .filter_map(|s| {
+                    let name = s.get("id")?.as_str()?.to_string();
+                    let setting = Setting {
+                        value: s.get("value").cloned().unwrap_or(Value::Null),
+                        editable: s.get("editable").and_then(|v| v.as_bool()).unwrap_or(false),
+                    };
+                    Some((name, setting))
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Set every desired value that differs from the zone, skipping read-only settings
+async fn apply_settings(
+    client: &CloudflareClient,
+    zone_id: &str,
+    desired: BTreeMap<String, Value>,
+    filter: &SettingsFilter,
+    dry_run: bool,
+) -> Result<()> {
+    let current = fetch_settings(client, zone_id).await?;
+    let mut changes = Vec::new();
+
+    for (name, value) in desired.iter().filter(|(n, _)| filter.matches(n)) {
+        match current.get(name) {
+            None => output::warning(&format!("Skipping unknown setting: {}", name)),
+            Some(setting) if setting.value == *value => {}
+            Some(setting) if !setting.editable => {
+                output::warning(&format!("Skipping read-only setting: {}", name))
+            }
+            Some(setting) => changes.push((name, &setting.value, value)),
+        }
+    }
+
+    let invalid: Vec<String> = changes
+        .iter()
+        .filter_map(|(name, _, value)| schema::find(name)?.validate(value).err())
+        .map(|e| e.to_string())
+        .collect();
+    if !invalid.is_empty() {
+        for message in &invalid {
+            output::error(message);
+        }
+        return Err(anyhow!("{} invalid setting value(s)", invalid.len()));
+    }
+
+    if changes.is_empty() {
+        output::success("Settings already up to date");
+        return Ok(());
+    }
+
+    for (name, from, to) in &changes {
+        println!(
+            "{}: {} -> {}",
+            name.cyan(),
+            compact(from).red(),
+            compact(to).green()
+        );
+    }
+
+    if dry_run {
+        output::info(&format!("Dry run: {} change(s)", changes.len()));
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for (name, _, value) in &changes {
+        let result = client
+            .patch_raw(
+                &format!("/zones/{}/settings/{}", zone_id, name),
+                json!({ "value": value }),
+            )
+            .await;
+        if let Err(e) = result {
+            output::error(&format!("{}: {}", name, e));
+            failed += 1;
         }
     }
 
+    output::success(&format!(
+        "Applied {} of {} change(s)",
+        changes.len() - failed,
+        changes.len()
+    ));
+    if failed > 0 {
+        return Err(anyhow!("{} setting(s) could not be changed", failed));
+    }
+
     Ok(())
 }
+
+/// Print the schema entry of one setting, or a summary of all of them
+fn describe(name: Option<&str>) -> Result<()> {
+    let Some(name) = name else {
+        output::table_header(&["SETTING", "VALUES", "PLAN"]);
+        for spec in schema::SCHEMA {
+            println!(
+                "{}\t{}\t{}",
+                spec.name.cyan(),
+                spec.kind.describe(),
+                spec.plan.unwrap_or("").dimmed()
+            );
+        }
+        output::info(&format!("Total: {} settings", schema::SCHEMA.len()));
+        return Ok(());
+    };
+
+    let spec = schema::find(name).ok_or_else(|| {
+        anyhow!(
+            "Unknown setting: {} (see `cli5 settings describe` for all settings)",
+            name
+        )
+    })?;
+
+    println!("{}", spec.name.bold());
+    println!("  {}", spec.description);
+    println!("  {}: {}", "Values".cyan(), spec.kind.describe());
+    if let Some(plan) = spec.plan {
+        println!("  {}: {}", "Plan".cyan(), plan);
+    }
+    Ok(())
+}
+
+/// Read settings from an export (JSON or YAML); a bare map of settings also works
+fn load_settings_file(path: &str) -> Result<BTreeMap<String, Value>> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| anyhow!("Cannot read {}: {}", path, e))?;
+
+    let document: Value = if is_yaml_path(path) {
+        serde_yaml::from_str(&content).map_err(|e| anyhow!("Invalid YAML in {}: {}", path, e))?
+    } else {
+        serde_json::from_str(&content).map_err(|e| anyhow!("Invalid JSON in {}: {}", path, e))?
+    };
+
+    let settings = document.get("settings").unwrap_or(&document);
+    settings
+        .as_object()
+        .map(|m| m.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .ok_or_else(|| anyhow!("{} does not contain a settings map", path))
+}
+
+fn is_yaml_path(path: &str) -> bool {
+    path.ends_with(".yaml") || path.ends_with(".yml")
+}
+
+fn compact(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_setters_use_the_schema() {
+        let change = |command| setting_change(&command).map(|c| c.unwrap());
+
+        assert_eq!(
+            change(SettingsCommand::Https {
+                value: "true".to_string()
+            })
+            .unwrap(),
+            ("always_use_https".to_string(), json!("on"))
+        );
+        assert_eq!(
+            change(SettingsCommand::BrowserCacheTtl { seconds: 3600 }).unwrap(),
+            ("browser_cache_ttl".to_string(), json!(3600))
+        );
+        assert!(change(SettingsCommand::CacheLevel {
+            level: "bypass".to_string()
+        })
+        .is_err());
+        assert!(change(SettingsCommand::BrowserCacheTtl { seconds: 3601 }).is_err());
+        assert!(change(SettingsCommand::Ssl {
+            mode: "full_strict".to_string()
+        })
+        .is_err());
+        assert!(setting_change(&SettingsCommand::List).unwrap().is_none());
+    }
+}
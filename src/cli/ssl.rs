@@ -1,12 +1,22 @@
 //! SSL/TLS command
 
-use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
 use clap::{Args, Subcommand};
-use serde_json::json;
+use futures_util::stream::{self, StreamExt};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use serde_json::{json, Value};
 
+use super::multizone::{self, Zone, ZoneSelection};
+use super::ExitStatus;
+use crate::api::settings as schema;
 use crate::api::CloudflareClient;
-use crate::config::Config;
+use crate::config::{Config, OutputFormat};
 use crate::output;
+use crate::pki;
 
 #[derive(Args, Debug)]
 pub struct SslArgs {
@@ -14,6 +24,9 @@ pub struct SslArgs {
     #[arg(short, long)]
     pub zone: Option<String>,
 
+    #[command(flatten)]
+    pub targets: ZoneSelection,
+
     #[command(subcommand)]
     pub command: SslCommand,
 }
@@ -30,6 +43,7 @@ pub enum SslCommand {
     },
 
     /// Set minimum TLS version
+    #[command(disable_version_flag = true)]
     MinTls {
         /// TLS version: 1.0, 1.1, 1.2, 1.3
         version: String,
@@ -55,10 +69,384 @@ pub enum SslCommand {
         /// on or off
         state: String,
     },
+
+    /// Report certificate expiry across zones (all zones unless --zone/--zones is given)
+    ///
+    /// Exit code 1 when a certificate is within --warn-days, 2 when one is
+    /// within --fail-days or a zone could not be read.
+    Expiry {
+        /// Warn about certificates expiring within this many days
+        #[arg(long, default_value_t = 30)]
+        warn_days: i64,
+
+        /// Fail on certificates expiring within this many days
+        #[arg(long, default_value_t = 7)]
+        fail_days: i64,
+    },
+
+    /// Custom (uploaded) SSL certificates
+    Custom {
+        #[command(subcommand)]
+        cmd: CustomCommand,
+    },
+
+    /// Origin CA certificates for your origin servers
+    OriginCa {
+        #[command(subcommand)]
+        cmd: OriginCaCommand,
+    },
+
+    /// Custom hostnames (Cloudflare for SaaS)
+    CustomHostnames {
+        #[command(subcommand)]
+        cmd: CustomHostnamesCommand,
+    },
+
+    /// Certificate packs (advanced certificates)
+    Packs {
+        #[command(subcommand)]
+        cmd: PacksCommand,
+    },
+
+    /// Show or change HSTS (Strict-Transport-Security) settings
+    Hsts {
+        /// on or off (omit to show the current settings)
+        state: Option<String>,
+
+        /// max-age in seconds (e.g. 31536000 for one year)
+        #[arg(long)]
+        max_age: Option<u64>,
+
+        /// Apply to subdomains
+        #[arg(long)]
+        include_subdomains: Option<bool>,
+
+        /// Allow inclusion in browser preload lists
+        #[arg(long)]
+        preload: Option<bool>,
+
+        /// Send X-Content-Type-Options: nosniff
+        #[arg(long)]
+        nosniff: Option<bool>,
+    },
+
+    /// Authenticated origin pulls (TLS client certificate towards the origin)
+    OriginPulls {
+        #[command(subcommand)]
+        cmd: OriginPullsCommand,
+    },
+
+    /// Client certificates for mTLS (issued by the zone's Cloudflare CA)
+    ClientCerts {
+        #[command(subcommand)]
+        cmd: ClientCertsCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum OriginPullsCommand {
+    /// Show zone and per-hostname settings and uploaded certificates
+    Status,
+
+    /// Enable authenticated origin pulls for the zone or a hostname
+    Enable {
+        /// Hostname (per-hostname AOP); omit for the whole zone
+        #[arg(long)]
+        hostname: Option<String>,
+
+        /// Certificate to present for the hostname (see `upload --per-hostname`)
+        #[arg(long, requires = "hostname")]
+        cert_id: Option<String>,
+    },
+
+    /// Disable authenticated origin pulls for the zone or a hostname
+    Disable {
+        /// Hostname (per-hostname AOP); omit for the whole zone
+        #[arg(long)]
+        hostname: Option<String>,
+    },
+
+    /// Upload a client certificate for origin pulls (key is checked locally)
+    Upload {
+        #[command(flatten)]
+        files: CertFiles,
+
+        /// Upload as a per-hostname certificate instead of the zone certificate
+        #[arg(long)]
+        per_hostname: bool,
+    },
+
+    /// Delete an uploaded certificate
+    Delete {
+        /// Certificate ID
+        id: String,
+
+        /// The certificate is a per-hostname certificate
+        #[arg(long)]
+        per_hostname: bool,
+
+        /// Skip confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ClientCertsCommand {
+    /// List client certificates
+    List,
+
+    /// Issue a client certificate for a key and CSR generated locally
+    Issue {
+        /// Common name of the client (e.g. device or user name)
+        #[arg(long)]
+        name: String,
+
+        /// Validity in days
+        #[arg(long, default_value_t = 3650)]
+        validity: u32,
+
+        /// Output path prefix; writes <prefix>.pem and <prefix>.key (default: name)
+        #[arg(short, long)]
+        out: Option<String>,
+
+        /// Overwrite existing files
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Revoke a client certificate
+    Revoke {
+        /// Client certificate ID
+        id: String,
+
+        /// Skip confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CustomHostnamesCommand {
+    /// List custom hostnames
+    List {
+        /// Only hostnames containing this text
+        #[arg(long)]
+        search: Option<String>,
+    },
+
+    /// Add a custom hostname and show the records the customer must create
+    Add {
+        /// Customer hostname (e.g. app.customer.com)
+        hostname: String,
+
+        /// Certificate validation method
+        #[arg(short, long, default_value = "http", value_parser = VALIDATION_METHODS)]
+        method: String,
+
+        /// Minimum TLS version for the hostname
+        #[arg(long, value_parser = ["1.0", "1.1", "1.2", "1.3"])]
+        min_tls: Option<String>,
+
+        /// Send traffic to this origin instead of the fallback origin
+        #[arg(long)]
+        origin: Option<String>,
+    },
+
+    /// Show a custom hostname with its status and validation records
+    Get {
+        /// Hostname or ID
+        hostname: String,
+    },
+
+    /// Delete a custom hostname
+    Delete {
+        /// Hostname or ID
+        hostname: String,
+
+        /// Skip confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+
+    /// Restart certificate validation (optionally with another method)
+    Refresh {
+        /// Hostname or ID
+        hostname: String,
+
+        /// Switch to this validation method
+        #[arg(short, long, value_parser = VALIDATION_METHODS)]
+        method: Option<String>,
+    },
+
+    /// Show or set the fallback origin for custom hostnames
+    FallbackOrigin {
+        /// New fallback origin hostname (a proxied record in this zone)
+        origin: Option<String>,
+
+        /// Remove the fallback origin
+        #[arg(long, conflicts_with = "origin")]
+        remove: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum PacksCommand {
+    /// List certificate packs
+    List,
+
+    /// Order an advanced certificate pack
+    Order {
+        /// Hostnames (comma-separated; default: the zone and *.zone)
+        #[arg(long, value_delimiter = ',')]
+        hosts: Vec<String>,
+
+        /// Certificate authority
+        #[arg(long, default_value = "lets_encrypt", value_parser = ["lets_encrypt", "google", "ssl_com"])]
+        ca: String,
+
+        /// Validation method
+        #[arg(short, long, default_value = "txt", value_parser = VALIDATION_METHODS)]
+        method: String,
+
+        /// Validity in days
+        #[arg(long, default_value = "90", value_parser = ["14", "30", "90", "365"])]
+        validity: String,
+
+        /// Add a Cloudflare branded hostname to the certificate
+        #[arg(long)]
+        cloudflare_branding: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CustomCommand {
+    /// List custom certificates
+    List,
+
+    /// Upload a certificate (checked locally against its key and the zone first)
+    Upload {
+        #[command(flatten)]
+        files: CertFiles,
+
+        /// Chain of trust used by Cloudflare
+        #[arg(long, default_value = "ubiquitous", value_parser = BUNDLE_METHODS)]
+        bundle_method: String,
+
+        /// Certificate type
+        #[arg(long = "type", default_value = "sni_custom", value_parser = ["sni_custom", "legacy_custom"])]
+        cert_type: String,
+
+        /// Only run the local checks
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Replace the certificate and key of a custom certificate
+    Update {
+        /// Custom certificate ID
+        id: String,
+
+        #[command(flatten)]
+        files: CertFiles,
+
+        /// Chain of trust used by Cloudflare
+        #[arg(long, value_parser = BUNDLE_METHODS)]
+        bundle_method: Option<String>,
+
+        /// Only run the local checks
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Delete a custom certificate
+    Delete {
+        /// Custom certificate ID
+        id: String,
+
+        /// Skip confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+/// PEM files of a certificate upload
+#[derive(Args, Debug)]
+pub struct CertFiles {
+    /// Certificate PEM file (may include the chain after the leaf)
+    #[arg(long)]
+    pub cert: String,
+
+    /// Private key PEM file
+    #[arg(long)]
+    pub key: String,
+
+    /// Intermediate certificates PEM file
+    #[arg(long)]
+    pub chain: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum OriginCaCommand {
+    /// List Origin CA certificates of the zone
+    List,
+
+    /// Issue a certificate for a key and CSR generated locally
+    Create {
+        /// Hostnames (comma-separated; default: the zone and *.zone)
+        #[arg(long, value_delimiter = ',')]
+        hostnames: Vec<String>,
+
+        /// Validity in days
+        #[arg(long, default_value = "5475", value_parser = ["7", "30", "90", "365", "730", "1095", "5475"])]
+        validity: String,
+
+        /// Output path prefix; writes <prefix>.pem and <prefix>.key (default: zone name)
+        #[arg(short, long)]
+        out: Option<String>,
+
+        /// Overwrite existing files
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Revoke an Origin CA certificate
+    Revoke {
+        /// Certificate ID
+        id: String,
+
+        /// Skip confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
 }
 
+const BUNDLE_METHODS: [&str; 3] = ["ubiquitous", "optimal", "force"];
+const VALIDATION_METHODS: [&str; 3] = ["http", "txt", "email"];
+
 pub async fn execute(config: &Config, args: SslArgs) -> Result<()> {
     let client = CloudflareClient::new(config.clone())?;
+
+    if let SslCommand::Expiry {
+        warn_days,
+        fail_days,
+    } = args.command
+    {
+        let zones = match args.zone.as_deref() {
+            Some(zone) => vec![Zone {
+                name: zone.to_string(),
+                id: client.resolve_zone_id(zone).await?,
+            }],
+            None => args.targets.resolve(&client).await?,
+        };
+        return expiry_report(config, &client, &zones, &args.targets, warn_days, fail_days).await;
+    }
+
+    if args.targets.is_active() {
+        let zones = args.targets.resolve(&client).await?;
+        return execute_multi(&client, &zones, args.targets.concurrency, &args.command).await;
+    }
+
     let zone = config.resolve_zone(args.zone.as_deref())?;
     let zone_id = client.resolve_zone_id(&zone).await?;
 
@@ -136,56 +524,1318 @@ pub async fn execute(config: &Config, args: SslArgs) -> Result<()> {
             let path = format!("/zones/{}/ssl/certificate_packs", zone_id);
             let response = client.get_raw(&path).await?;
 
-            if let Some(certs) = response.get("result").and_then(|r| r.as_array()) {
+            if let Some(packs) = response.get("result").and_then(|r| r.as_array()) {
                 output::table_header(&["STATUS", "HOSTS", "ISSUER", "EXPIRES"]);
 
-                for pack in certs {
-                    if let Some(certificates) = pack.get("certificates").and_then(|c| c.as_array())
-                    {
-                        for cert in certificates {
-                            let status = cert.get("status").and_then(|s| s.as_str()).unwrap_or("-");
-                            let hosts = cert
-                                .get("hosts")
-                                .and_then(|h| h.as_array())
-                                .map(|arr| {
-                                    arr.iter()
-                                        .filter_map(|v| v.as_str())
-                                        .collect::<Vec<_>>()
-                                        .join(", ")
-                                })
-                                .unwrap_or_else(|| "-".to_string());
-                            let issuer = cert.get("issuer").and_then(|i| i.as_str()).unwrap_or("-");
-                            let expires = cert
-                                .get("expires_on")
-                                .and_then(|e| e.as_str())
-                                .map(|s| s.split('T').next().unwrap_or(s))
-                                .unwrap_or("-");
-
-                            let status_colored = if status == "active" {
-                                format!("\x1b[32m{}\x1b[0m", status)
-                            } else {
-                                status.to_string()
-                            };
-
-                            println!("{}\t{}\t{}\t{}", status_colored, hosts, issuer, expires);
-                        }
-                    }
+                for cert in pack_certificates(&zone, packs) {
+                    println!(
+                        "{}\t{}\t{}\t{}",
+                        format_status(&cert.status),
+                        cert.hosts_display(),
+                        cert.issuer,
+                        cert.expires_date()
+                    );
                 }
             } else {
                 output::warning("No certificates found");
             }
         }
 
+        SslCommand::Expiry { .. } => unreachable!(), // Handled above
+
         SslCommand::AutoHttps { state } => {
             let state = normalize_on_off(&state)?;
             set_setting(&client, &zone_id, "automatic_https_rewrites", &state).await?;
             output::success(&format!("Automatic HTTPS Rewrites: {}", state));
         }
+
+        SslCommand::Custom { cmd } => execute_custom(&client, &zone_id, cmd).await?,
+
+        SslCommand::OriginCa { cmd } => execute_origin_ca(&client, &zone_id, cmd).await?,
+
+        SslCommand::CustomHostnames { cmd } => {
+            execute_custom_hostnames(&client, &zone_id, cmd).await?
+        }
+
+        SslCommand::Packs { cmd } => execute_packs(&client, &zone_id, cmd).await?,
+
+        SslCommand::Hsts {
+            state,
+            max_age,
+            include_subdomains,
+            preload,
+            nosniff,
+        } => {
+            let current = client
+                .get_raw(&format!("/zones/{}/settings/security_header", zone_id))
+                .await?;
+            let mut hsts = current
+                .pointer("/result/value/strict_transport_security")
+                .cloned()
+                .unwrap_or_else(|| json!({}));
+
+            let changed = state.is_some()
+                || max_age.is_some()
+                || include_subdomains.is_some()
+                || preload.is_some()
+                || nosniff.is_some();
+            if !changed {
+                print_hsts(&hsts);
+                return Ok(());
+            }
+
+            if let Some(state) = state {
+                hsts["enabled"] = json!(normalize_on_off(&state)? == "on");
+            }
+            if let Some(max_age) = max_age {
+                hsts["max_age"] = json!(max_age);
+            }
+            if let Some(include) = include_subdomains {
+                hsts["include_subdomains"] = json!(include);
+            }
+            if let Some(preload) = preload {
+                hsts["preload"] = json!(preload);
+            }
+            if let Some(nosniff) = nosniff {
+                hsts["nosniff"] = json!(nosniff);
+            }
+
+            let flag = |key: &str| hsts.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+            if flag("preload")
+                && (!flag("include_subdomains")
+                    || hsts.get("max_age").and_then(|v| v.as_u64()).unwrap_or(0) < 31536000)
+            {
+                output::warning(
+                    "Preload lists require include_subdomains and a max-age of at least 31536000",
+                );
+            }
+            if flag("enabled") && !flag("preload") && flag("include_subdomains") {
+                output::info("HSTS on all subdomains: make sure every subdomain serves HTTPS");
+            }
+
+            let body = json!({ "value": { "strict_transport_security": hsts } });
+            client
+                .patch_raw(
+                    &format!("/zones/{}/settings/security_header", zone_id),
+                    body,
+                )
+                .await?;
+            output::success("HSTS settings updated");
+            print_hsts(&hsts);
+        }
+
+        SslCommand::OriginPulls { cmd } => execute_origin_pulls(&client, &zone_id, cmd).await?,
+
+        SslCommand::ClientCerts { cmd } => execute_client_certs(&client, &zone_id, cmd).await?,
     }
 
     Ok(())
 }
 
+async fn execute_custom(
+    client: &CloudflareClient,
+    zone_id: &str,
+    cmd: CustomCommand,
+) -> Result<()> {
+    let base = format!("/zones/{}/custom_certificates", zone_id);
+
+    match cmd {
+        CustomCommand::List => {
+            let response = client.get_raw(&base).await?;
+            let certs = response
+                .get("result")
+                .and_then(|r| r.as_array())
+                .cloned()
+                .unwrap_or_default();
+
+            output::table_header(&["STATUS", "HOSTS", "ISSUER", "EXPIRES", "ID"]);
+            for cert in &certs {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    format_status(cert.get("status").and_then(|v| v.as_str()).unwrap_or("-")),
+                    join_strings(cert.get("hosts")),
+                    cert.get("issuer").and_then(|v| v.as_str()).unwrap_or("-"),
+                    cert.get("expires_on")
+                        .and_then(|v| v.as_str())
+                        .map(|s| s.split('T').next().unwrap_or(s))
+                        .unwrap_or("-"),
+                    cert.get("id").and_then(|v| v.as_str()).unwrap_or("-"),
+                );
+            }
+            output::info(&format!("Total: {} custom certificates", certs.len()));
+        }
+
+        CustomCommand::Upload {
+            files,
+            bundle_method,
+            cert_type,
+            dry_run,
+        } => {
+            let zone = zone_name(client, zone_id).await?;
+            let (certificate, private_key) = check_certificate(&files, Some(&zone))?;
+            if dry_run {
+                output::success("Certificate and key passed the local checks");
+                return Ok(());
+            }
+
+            let body = json!({
+                "certificate": certificate,
+                "private_key": private_key,
+                "bundle_method": bundle_method,
+                "type": cert_type
+            });
+            let response = client.post_raw(&base, body).await?;
+            output::success(&format!(
+                "Uploaded custom certificate: {}",
+                response
+                    .pointer("/result/id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("-")
+            ));
+        }
+
+        CustomCommand::Update {
+            id,
+            files,
+            bundle_method,
+            dry_run,
+        } => {
+            let zone = zone_name(client, zone_id).await?;
+            let (certificate, private_key) = check_certificate(&files, Some(&zone))?;
+            if dry_run {
+                output::success("Certificate and key passed the local checks");
+                return Ok(());
+            }
+
+            let mut body = json!({
+                "certificate": certificate,
+                "private_key": private_key
+            });
+            if let Some(method) = bundle_method {
+                body["bundle_method"] = json!(method);
+            }
+            client.patch_raw(&format!("{}/{}", base, id), body).await?;
+            output::success(&format!("Updated custom certificate: {}", id));
+        }
+
+        CustomCommand::Delete { id, yes } => {
+            if !yes {
+                output::warning(&format!(
+                    "Are you sure you want to delete custom certificate {}?",
+                    id
+                ));
+                output::info("Use -y to skip this confirmation");
+                return Ok(());
+            }
+
+            client.delete_raw(&format!("{}/{}", base, id)).await?;
+            output::success(&format!("Deleted custom certificate: {}", id));
+        }
+    }
+
+    Ok(())
+}
+
+async fn execute_origin_ca(
+    client: &CloudflareClient,
+    zone_id: &str,
+    cmd: OriginCaCommand,
+) -> Result<()> {
+    match cmd {
+        OriginCaCommand::List => {
+            let response = client
+                .get_raw(&format!("/certificates?zone_id={}", zone_id))
+                .await?;
+            let certs = response
+                .get("result")
+                .and_then(|r| r.as_array())
+                .cloned()
+                .unwrap_or_default();
+
+            output::table_header(&["HOSTS", "TYPE", "EXPIRES", "ID"]);
+            for cert in &certs {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    join_strings(cert.get("hostnames")),
+                    cert.get("request_type")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("-"),
+                    cert.get("expires_on")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("-"),
+                    cert.get("id").and_then(|v| v.as_str()).unwrap_or("-"),
+                );
+            }
+            output::info(&format!("Total: {} Origin CA certificates", certs.len()));
+        }
+
+        OriginCaCommand::Create {
+            hostnames,
+            validity,
+            out,
+            force,
+        } => {
+            let zone = zone_name(client, zone_id).await?;
+            let hostnames = if hostnames.is_empty() {
+                vec![zone.clone(), format!("*.{}", zone)]
+            } else {
+                hostnames
+            };
+
+            let (cert_path, key_path) = output_paths(&out.unwrap_or_else(|| zone.clone()), force)?;
+            let request = pki::generate_csr(&hostnames[0], &hostnames)?;
+            let body = json!({
+                "hostnames": hostnames,
+                "requested_validity": validity.parse::<u32>()?,
+                "request_type": "origin-ecc",
+                "csr": request.csr_pem
+            });
+            let response = client.post_raw("/certificates", body).await?;
+            let certificate = response
+                .pointer("/result/certificate")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("No certificate in the response"))?;
+
+            output::success(&format!(
+                "Issued Origin CA certificate for {}: {}",
+                hostnames.join(", "),
+                response
+                    .pointer("/result/id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("-")
+            ));
+            write_issued(&cert_path, certificate, &key_path, &request.key_pem, force)?;
+        }
+
+        OriginCaCommand::Revoke { id, yes } => {
+            if !yes {
+                output::warning(&format!(
+                    "Are you sure you want to revoke Origin CA certificate {}?",
+                    id
+                ));
+                output::info("Use -y to skip this confirmation");
+                return Ok(());
+            }
+
+            client.delete_raw(&format!("/certificates/{}", id)).await?;
+            output::success(&format!("Revoked Origin CA certificate: {}", id));
+        }
+    }
+
+    Ok(())
+}
+
+async fn execute_custom_hostnames(
+    client: &CloudflareClient,
+    zone_id: &str,
+    cmd: CustomHostnamesCommand,
+) -> Result<()> {
+    let base = format!("/zones/{}/custom_hostnames", zone_id);
+
+    match cmd {
+        CustomHostnamesCommand::List { search } => {
+            let mut hostnames = Vec::new();
+            let mut page = 1;
+            loop {
+                let mut path = format!("{}?per_page=50&page={}", base, page);
+                if let Some(search) = &search {
+                    path.push_str(&format!("&hostname={}", search));
+                }
+                let response = client.get_raw(&path).await?;
+                hostnames.extend(
+                    response
+                        .get("result")
+                        .and_then(|r| r.as_array())
+                        .cloned()
+                        .unwrap_or_default(),
+                );
+                let total_pages = response
+                    .pointer("/result_info/total_pages")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(1);
+                if page >= total_pages {
+                    break;
+                }
+                page += 1;
+            }
+
+            output::table_header(&["HOSTNAME", "STATUS", "SSL", "METHOD", "ID"]);
+            for hostname in &hostnames {
+                output::print_custom_hostname(hostname);
+            }
+            output::info(&format!("Total: {} custom hostnames", hostnames.len()));
+        }
+
+        CustomHostnamesCommand::Add {
+            hostname,
+            method,
+            min_tls,
+            origin,
+        } => {
+            let mut body = json!({
+                "hostname": hostname,
+                "ssl": { "method": method, "type": "dv" }
+            });
+            if let Some(version) = min_tls {
+                body["ssl"]["settings"] = json!({ "min_tls_version": version });
+            }
+            if let Some(origin) = origin {
+                body["custom_origin_server"] = json!(origin);
+            }
+
+            let response = client.post_raw(&base, body).await?;
+            let created = response.get("result").cloned().unwrap_or_default();
+            output::success(&format!("Added custom hostname: {}", hostname));
+            print_custom_hostname_details(&created);
+        }
+
+        CustomHostnamesCommand::Get { hostname } => {
+            let found = find_custom_hostname(client, &base, &hostname).await?;
+            print_custom_hostname_details(&found);
+        }
+
+        CustomHostnamesCommand::Delete { hostname, yes } => {
+            if !yes {
+                output::warning(&format!(
+                    "Are you sure you want to delete custom hostname {}?",
+                    hostname
+                ));
+                output::info("Use -y to skip this confirmation");
+                return Ok(());
+            }
+
+            let found = find_custom_hostname(client, &base, &hostname).await?;
+            client
+                .delete_raw(&format!("{}/{}", base, custom_hostname_id(&found)?))
+                .await?;
+            output::success(&format!("Deleted custom hostname: {}", hostname));
+        }
+
+        CustomHostnamesCommand::Refresh { hostname, method } => {
+            let found = find_custom_hostname(client, &base, &hostname).await?;
+            let method = method.unwrap_or_else(|| {
+                found
+                    .pointer("/ssl/method")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("http")
+                    .to_string()
+            });
+
+            let response = client
+                .patch_raw(
+                    &format!("{}/{}", base, custom_hostname_id(&found)?),
+                    json!({ "ssl": { "method": method, "type": "dv" } }),
+                )
+                .await?;
+            output::success(&format!(
+                "Restarted validation of {} ({})",
+                hostname, method
+            ));
+            print_custom_hostname_details(&response.get("result").cloned().unwrap_or_default());
+        }
+
+        CustomHostnamesCommand::FallbackOrigin { origin, remove } => {
+            let path = format!("{}/fallback_origin", base);
+
+            if remove {
+                client.delete_raw(&path).await?;
+                output::success("Removed the fallback origin");
+            } else if let Some(origin) = origin {
+                client.put_raw(&path, json!({ "origin": origin })).await?;
+                output::success(&format!("Fallback origin set to: {}", origin));
+            } else {
+                let response = client.get_raw(&path).await?;
+                let result = response.get("result").cloned().unwrap_or_default();
+                println!(
+                    "Origin: {}",
+                    result.get("origin").and_then(|v| v.as_str()).unwrap_or("-")
+                );
+                println!(
+                    "Status: {}",
+                    format_status(result.get("status").and_then(|v| v.as_str()).unwrap_or("-"))
+                );
+                for error in result
+                    .get("errors")
+                    .and_then(|v| v.as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|e| e.as_str())
+                {
+                    output::warning(error);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn execute_packs(client: &CloudflareClient, zone_id: &str, cmd: PacksCommand) -> Result<()> {
+    let base = format!("/zones/{}/ssl/certificate_packs", zone_id);
+
+    match cmd {
+        PacksCommand::List => {
+            let response = client.get_raw(&base).await?;
+            let packs = response
+                .get("result")
+                .and_then(|r| r.as_array())
+                .cloned()
+                .unwrap_or_default();
+
+            output::table_header(&["TYPE", "STATUS", "CA", "HOSTS", "ID"]);
+            for pack in &packs {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    pack.get("type").and_then(|v| v.as_str()).unwrap_or("-"),
+                    format_status(pack.get("status").and_then(|v| v.as_str()).unwrap_or("-")),
+                    pack.get("certificate_authority")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("-"),
+                    join_strings(pack.get("hosts")),
+                    pack.get("id").and_then(|v| v.as_str()).unwrap_or("-"),
+                );
+            }
+            output::info(&format!("Total: {} certificate packs", packs.len()));
+        }
+
+        PacksCommand::Order {
+            hosts,
+            ca,
+            method,
+            validity,
+            cloudflare_branding,
+        } => {
+            let hosts = if hosts.is_empty() {
+                let zone = zone_name(client, zone_id).await?;
+                vec![zone.clone(), format!("*.{}", zone)]
+            } else {
+                hosts
+            };
+
+            let body = json!({
+                "type": "advanced",
+                "hosts": hosts,
+                "certificate_authority": ca,
+                "validation_method": method,
+                "validity_days": validity.parse::<u32>()?,
+                "cloudflare_branding": cloudflare_branding
+            });
+            let response = client.post_raw(&format!("{}/order", base), body).await?;
+            let pack = response.get("result").cloned().unwrap_or_default();
+
+            output::success(&format!(
+                "Ordered advanced certificate for {}: {}",
+                hosts.join(", "),
+                pack.get("id").and_then(|v| v.as_str()).unwrap_or("-")
+            ));
+            output::info(&format!(
+                "Status: {}",
+                pack.get("status").and_then(|v| v.as_str()).unwrap_or("-")
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+async fn execute_origin_pulls(
+    client: &CloudflareClient,
+    zone_id: &str,
+    cmd: OriginPullsCommand,
+) -> Result<()> {
+    let base = format!("/zones/{}/origin_tls_client_auth", zone_id);
+
+    match cmd {
+        OriginPullsCommand::Status => {
+            let settings = client.get_raw(&format!("{}/settings", base)).await?;
+            let enabled = settings
+                .pointer("/result/enabled")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            println!(
+                "Zone origin pulls: {}",
+                format_on_off(if enabled { "on" } else { "off" })
+            );
+
+            for (title, path) in [
+                ("Zone certificates", base.clone()),
+                (
+                    "Per-hostname certificates",
+                    format!("{}/hostnames/certificates", base),
+                ),
+            ] {
+                let response = client.get_raw(&path).await?;
+                let certs = response
+                    .get("result")
+                    .and_then(|r| r.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+
+                println!();
+                println!("{}", title.cyan());
+                if certs.is_empty() {
+                    println!("  (none)");
+                }
+                for cert in &certs {
+                    println!(
+                        "  {}\t{}\t{}\t{}",
+                        format_status(cert.get("status").and_then(|v| v.as_str()).unwrap_or("-")),
+                        cert.get("issuer").and_then(|v| v.as_str()).unwrap_or("-"),
+                        cert.get("expires_on")
+                            .and_then(|v| v.as_str())
+                            .map(|s| s.split('T').next().unwrap_or(s))
+                            .unwrap_or("-"),
+                        cert.get("id").and_then(|v| v.as_str()).unwrap_or("-"),
+                    );
+                }
+            }
+        }
+
+        OriginPullsCommand::Enable { hostname, cert_id } => match hostname {
+            Some(hostname) => {
+                let mut config = json!({ "hostname": hostname, "enabled": true });
+                if let Some(cert_id) = cert_id {
+                    config["cert_id"] = json!(cert_id);
+                }
+                client
+                    .put_raw(
+                        &format!("{}/hostnames", base),
+                        json!({ "config": [config] }),
+                    )
+                    .await?;
+                output::success(&format!("Origin pulls enabled for {}", hostname));
+            }
+            None => {
+                client
+                    .put_raw(&format!("{}/settings", base), json!({ "enabled": true }))
+                    .await?;
+                output::success("Origin pulls enabled for the zone");
+                output::info("Configure the origin to require Cloudflare's client certificate");
+            }
+        },
+
+        OriginPullsCommand::Disable { hostname } => match hostname {
+            Some(hostname) => {
+                client
+                    .put_raw(
+                        &format!("{}/hostnames", base),
+                        json!({ "config": [{ "hostname": hostname, "enabled": false }] }),
+                    )
+                    .await?;
+                output::success(&format!("Origin pulls disabled for {}", hostname));
+            }
+            None => {
+                client
+                    .put_raw(&format!("{}/settings", base), json!({ "enabled": false }))
+                    .await?;
+                output::success("Origin pulls disabled for the zone");
+            }
+        },
+
+        OriginPullsCommand::Upload {
+            files,
+            per_hostname,
+        } => {
+            let (certificate, private_key) = check_certificate(&files, None)?;
+            let path = if per_hostname {
+                format!("{}/hostnames/certificates", base)
+            } else {
+                base.clone()
+            };
+
+            let response = client
+                .post_raw(
+                    &path,
+                    json!({ "certificate": certificate, "private_key": private_key }),
+                )
+                .await?;
+            output::success(&format!(
+                "Uploaded origin pull certificate: {}",
+                response
+                    .pointer("/result/id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("-")
+            ));
+            if per_hostname {
+                output::info(
+                    "Enable it with: cli5 ssl origin-pulls enable --hostname <host> --cert-id <id>",
+                );
+            }
+        }
+
+        OriginPullsCommand::Delete {
+            id,
+            per_hostname,
+            yes,
+        } => {
+            if !yes {
+                output::warning(&format!(
+                    "Are you sure you want to delete origin pull certificate {}?",
+                    id
+                ));
+                output::info("Use -y to skip this confirmation");
+                return Ok(());
+            }
+
+            let path = if per_hostname {
+                format!("{}/hostnames/certificates/{}", base, id)
+            } else {
+                format!("{}/{}", base, id)
+            };
+            client.delete_raw(&path).await?;
+            output::success(&format!("Deleted origin pull certificate: {}", id));
+        }
+    }
+
+    Ok(())
+}
+
+async fn execute_client_certs(
+    client: &CloudflareClient,
+    zone_id: &str,
+    cmd: ClientCertsCommand,
+) -> Result<()> {
+    let base = format!("/zones/{}/client_certificates", zone_id);
+
+    match cmd {
+        ClientCertsCommand::List => {
+            let response = client.get_raw(&format!("{}?per_page=50", base)).await?;
+            let certs = response
+                .get("result")
+                .and_then(|r| r.as_array())
+                .cloned()
+                .unwrap_or_default();
+
+            output::table_header(&["STATUS", "NAME", "EXPIRES", "ID"]);
+            for cert in &certs {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    format_status(cert.get("status").and_then(|v| v.as_str()).unwrap_or("-")),
+                    cert.get("common_name")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("-"),
+                    cert.get("expires_on")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("-"),
+                    cert.get("id").and_then(|v| v.as_str()).unwrap_or("-"),
+                );
+            }
+            output::info(&format!("Total: {} client certificates", certs.len()));
+        }
+
+        ClientCertsCommand::Issue {
+            name,
+            validity,
+            out,
+            force,
+        } => {
+            let (cert_path, key_path) = output_paths(&out.unwrap_or_else(|| name.clone()), force)?;
+            let request = pki::generate_csr(&name, &[])?;
+
+            let response = client
+                .post_raw(
+                    &base,
+                    json!({ "csr": request.csr_pem, "validity_days": validity }),
+                )
+                .await?;
+            let certificate = response
+                .pointer("/result/certificate")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("No certificate in the response"))?;
+
+            output::success(&format!(
+                "Issued client certificate for {}: {}",
+                name,
+                response
+                    .pointer("/result/id")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("-")
+            ));
+            write_issued(&cert_path, certificate, &key_path, &request.key_pem, force)?;
+            output::info("Require it with an mTLS rule, e.g. not cf.tls_client_auth.cert_verified");
+        }
+
+        ClientCertsCommand::Revoke { id, yes } => {
+            if !yes {
+                output::warning(&format!(
+                    "Are you sure you want to revoke client certificate {}?",
+                    id
+                ));
+                output::info("Use -y to skip this confirmation");
+                return Ok(());
+            }
+
+            client.delete_raw(&format!("{}/{}", base, id)).await?;
+            output::success(&format!("Revoked client certificate: {}", id));
+        }
+    }
+
+    Ok(())
+}
+
+fn print_hsts(hsts: &Value) {
+    let flag = |key: &str| {
+        let on = hsts.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        format_on_off(if on { "on" } else { "off" })
+    };
+
+    println!("HSTS:               {}", flag("enabled"));
+    println!(
+        "Max-Age:            {}",
+        hsts.get("max_age").and_then(|v| v.as_u64()).unwrap_or(0)
+    );
+    println!("Include subdomains: {}", flag("include_subdomains"));
+    println!("Preload:            {}", flag("preload"));
+    println!("No-Sniff:           {}", flag("nosniff"));
+}
+
+/// Find a custom hostname by ID or exact hostname
+async fn find_custom_hostname(
+    client: &CloudflareClient,
+    base: &str,
+    target: &str,
+) -> Result<Value> {
+    if target.len() == 36 && target.chars().all(|c| c.is_ascii_hexdigit() || c == '-') {
+        let response = client.get_raw(&format!("{}/{}", base, target)).await?;
+        return response
+            .get("result")
+            .cloned()
+            .ok_or_else(|| anyhow!("Custom hostname not found: {}", target));
+    }
+
+    let response = client
+        .get_raw(&format!("{}?hostname={}", base, target))
+        .await?;
+    response
+        .get("result")
+        .and_then(|r| r.as_array())
+        .and_then(|found| {
+            found
+                .iter()
+                .find(|h| h.get("hostname").and_then(|v| v.as_str()) == Some(target))
+        })
+        .cloned()
+        .ok_or_else(|| anyhow!("Custom hostname not found: {}", target))
+}
+
+fn custom_hostname_id(hostname: &Value) -> Result<&str> {
+    hostname
+        .get("id")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Custom hostname has no ID"))
+}
+
+/// Status of a custom hostname and the records the customer has to create
+fn print_custom_hostname_details(hostname: &Value) {
+    let text = |pointer: &str| {
+        hostname
+            .pointer(pointer)
+            .and_then(|v| v.as_str())
+            .unwrap_or("-")
+            .to_string()
+    };
+
+    println!("{}", text("/hostname").bold());
+    println!("  ID:          {}", text("/id"));
+    println!("  Status:      {}", format_status(&text("/status")));
+    println!(
+        "  SSL:         {} ({})",
+        format_status(&text("/ssl/status")),
+        text("/ssl/method")
+    );
+    if let Some(origin) = hostname
+        .get("custom_origin_server")
+        .and_then(|v| v.as_str())
+    {
+        println!("  Origin:      {}", origin);
+    }
+
+    if let Some(ownership) = hostname.get("ownership_verification") {
+        println!();
+        println!("{}", "Hostname verification (TXT record):".cyan());
+        println!(
+            "  {}  TXT  {}",
+            ownership
+                .get("name")
+                .and_then(|v| v.as_str())
+                .unwrap_or("-"),
+            ownership
+                .get("value")
+                .and_then(|v| v.as_str())
+                .unwrap_or("-")
+        );
+    }
+
+    for record in hostname
+        .pointer("/ssl/validation_records")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+    {
+        println!();
+        println!("{}", "Certificate validation:".cyan());
+        if let (Some(name), Some(value)) = (
+            record.get("txt_name").and_then(|v| v.as_str()),
+            record.get("txt_value").and_then(|v| v.as_str()),
+        ) {
+            println!("  {}  TXT  {}", name, value);
+        }
+        if let (Some(url), Some(body)) = (
+            record.get("http_url").and_then(|v| v.as_str()),
+            record.get("http_body").and_then(|v| v.as_str()),
+        ) {
+            println!("  Serve {} with body:", url);
+            println!("  {}", body);
+        }
+        if let Some(emails) = record.get("emails").and_then(|v| v.as_array()) {
+            println!(
+                "  Approval email sent to: {}",
+                join_strings(Some(&json!(emails)))
+            );
+        }
+    }
+
+    for error in hostname
+        .get("verification_errors")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .chain(
+            hostname
+                .pointer("/ssl/validation_errors")
+                .and_then(|v| v.as_array())
+                .into_iter()
+                .flatten(),
+        )
+    {
+        let message = error
+            .as_str()
+            .or_else(|| error.get("message").and_then(|v| v.as_str()))
+            .unwrap_or("-");
+        output::warning(message);
+    }
+}
+
+/// One certificate of a zone, from any of the certificate APIs
+#[derive(Debug, Serialize)]
+struct CertInfo {
+    zone: String,
+    kind: &'static str,
+    hosts: Vec<String>,
+    status: String,
+    issuer: String,
+    expires_on: Option<DateTime<Utc>>,
+    days_left: Option<i64>,
+}
+
+impl CertInfo {
+    fn new(zone: &str, kind: &'static str, cert: &Value, hosts_key: &str, status: &str) -> Self {
+        let expires_on = cert
+            .get("expires_on")
+            .and_then(|v| v.as_str())
+            .and_then(parse_expiry);
+
+        Self {
+            zone: zone.to_string(),
+            kind,
+            hosts: cert
+                .get(hosts_key)
+                .and_then(|h| h.as_array())
+                .map(|arr| {
+                    arr.iter()
+                        .filter_map(|v| v.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            status: cert
+                .get("status")
+                .and_then(|v| v.as_str())
+                .unwrap_or(status)
+                .to_string(),
+            issuer: cert
+                .get("issuer")
+                .and_then(|v| v.as_str())
+                .unwrap_or("-")
+                .to_string(),
+            days_left: expires_on.map(|at| (at - Utc::now()).num_days()),
+            expires_on,
+        }
+    }
+
+    fn hosts_display(&self) -> String {
+        if self.hosts.is_empty() {
+            "-".to_string()
+        } else {
+            self.hosts.join(", ")
+        }
+    }
+
+    fn expires_date(&self) -> String {
+        self.expires_on
+            .map(|at| at.date_naive().to_string())
+            .unwrap_or_else(|| "-".to_string())
+    }
+}
+
+/// Certificates inside certificate packs (edge certificates)
+fn pack_certificates(zone: &str, packs: &[Value]) -> Vec<CertInfo> {
+    packs
+        .iter()
+        .flat_map(|pack| {
+            let status = pack.get("status").and_then(|v| v.as_str()).unwrap_or("-");
+            pack.get("certificates")
+                .and_then(|c| c.as_array())
+                .into_iter()
+                .flatten()
+                .map(move |cert| CertInfo::new(zone, "edge", cert, "hosts", status))
+        })
+        .collect()
+}
+
+/// Parse expiry times (RFC 3339, or `2039-04-15 02:14:00 +0000 UTC` from Origin CA)
+fn parse_expiry(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| {
+            DateTime::parse_from_str(value.trim_end_matches(" UTC"), "%Y-%m-%d %H:%M:%S %z")
+        })
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+/// Edge, custom and Origin CA certificates of one zone
+///
+/// A source the token cannot read (e.g. Origin CA without that permission) is
+/// skipped and reported in the second list; the zone only fails if none can be read.
+async fn zone_certificates(
+    client: &CloudflareClient,
+    zone: &Zone,
+) -> Result<(Vec<CertInfo>, Vec<String>)> {
+    let sources = [
+        ("edge", format!("/zones/{}/ssl/certificate_packs", zone.id)),
+        ("custom", format!("/zones/{}/custom_certificates", zone.id)),
+        ("origin-ca", format!("/certificates?zone_id={}", zone.id)),
+    ];
+
+    let mut certs = Vec::new();
+    let mut skipped = Vec::new();
+    for (kind, path) in &sources {
+        let response = match client.get_raw(path).await {
+            Ok(response) => response,
+            Err(e) => {
+                skipped.push(format!(
+                    "{}: {} certificates skipped: {}",
+                    zone.name, kind, e
+                ));
+                continue;
+            }
+        };
+        let found = response
+            .get("result")
+            .and_then(|r| r.as_array())
+            .cloned()
+            .unwrap_or_default();
+        match *kind {
+            "edge" => certs.extend(pack_certificates(&zone.name, &found)),
+            "custom" => certs.extend(
+                found
+                    .iter()
+                    .map(|cert| CertInfo::new(&zone.name, "custom", cert, "hosts", "-")),
+            ),
+            _ => certs.extend(found.iter().map(|cert| {
+                let mut info = CertInfo::new(&zone.name, "origin-ca", cert, "hostnames", "issued");
+                info.issuer = "Cloudflare Origin CA".to_string();
+                info
+            })),
+        }
+    }
+
+    if skipped.len() == sources.len() {
+        return Err(anyhow!("no certificate source could be read"));
+    }
+    Ok((certs, skipped))
+}
+
+async fn expiry_report(
+    config: &Config,
+    client: &CloudflareClient,
+    zones: &[Zone],
+    targets: &ZoneSelection,
+    warn_days: i64,
+    fail_days: i64,
+) -> Result<()> {
+    let mut pending = stream::iter(zones)
+        .map(|zone| async move { (zone, zone_certificates(client, zone).await) })
+        .buffer_unordered(targets.concurrency.max(1));
+
+    let mut certs = Vec::new();
+    let mut skipped = Vec::new();
+    let mut errors = Vec::new();
+    let mut done = 0;
+    while let Some((zone, result)) = pending.next().await {
+        match result {
+            Ok((found, missing)) => {
+                certs.extend(found);
+                skipped.extend(missing);
+            }
+            Err(e) => errors.push(format!("{}: {}", zone.name, e)),
+        }
+        done += 1;
+        output::progress(done, zones.len(), "zones");
+    }
+    certs.sort_by_key(|c| (c.days_left.unwrap_or(i64::MAX), c.zone.clone()));
+
+    let failing = certs
+        .iter()
+        .filter(|c| c.days_left.is_some_and(|d| d <= fail_days))
+        .count();
+    let warning = certs
+        .iter()
+        .filter(|c| c.days_left.is_some_and(|d| d > fail_days && d <= warn_days))
+        .count();
+
+    if matches!(config.output_format, OutputFormat::Json) {
+        output::print_output(&certs, &config.output_format)?;
+    } else {
+        output::table_header(&[
+            "ZONE", "TYPE", "DAYS", "EXPIRES", "STATUS", "ISSUER", "HOSTS",
+        ]);
+        for cert in &certs {
+            let days = match cert.days_left {
+                Some(d) if d <= fail_days => d.to_string().red().bold().to_string(),
+                Some(d) if d <= warn_days => d.to_string().yellow().to_string(),
+                Some(d) => d.to_string().green().to_string(),
+                None => "-".to_string(),
+            };
+            println!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                cert.zone.bold(),
+                cert.kind,
+                days,
+                cert.expires_date(),
+                format_status(&cert.status),
+                cert.issuer,
+                cert.hosts_display()
+            );
+        }
+        output::info(&format!(
+            "{} certificate(s) in {} zone(s)",
+            certs.len(),
+            zones.len()
+        ));
+    }
+
+    for warning in &skipped {
+        output::warning(warning);
+    }
+    for error in &errors {
+        output::error(error);
+    }
+    if failing > 0 || !errors.is_empty() {
+        output::error(&format!(
+            "{} certificate(s) expire within {} days, {} zone(s) could not be read",
+            failing,
+            fail_days,
+            errors.len()
+        ));
+        return Err(ExitStatus(2).into());
+    }
+    if warning > 0 {
+        output::warning(&format!(
+            "{} certificate(s) expire within {} days",
+            warning, warn_days
+        ));
+        return Err(ExitStatus(1).into());
+    }
+
+    output::success(&format!("No certificate expires within {} days", warn_days));
+    Ok(())
+}
+
+/// Check a certificate and key before upload, and that it covers `zone` if given
+///
+/// Returns the PEM certificate (with chain) and key.
+fn check_certificate(files: &CertFiles, zone: Option<&str>) -> Result<(String, String)> {
+    let mut certs = pki::load_certificates(&files.cert)?;
+    if let Some(chain) = &files.chain {
+        certs.extend(pki::load_certificates(chain)?);
+    }
+    let key = pki::load_private_key(&files.key)?;
+    let leaf = &certs[0];
+
+    if leaf.public_key != key.public_key {
+        return Err(anyhow!(
+            "The private key in {} does not match the certificate in {}",
+            files.key,
+            files.cert
+        ));
+    }
+    if let Some(zone) = zone.filter(|z| !leaf.covers_zone(z)) {
+        return Err(anyhow!(
+            "The certificate ({}) does not cover any hostname in {}",
+            leaf.hostnames().join(", "),
+            zone
+        ));
+    }
+    if leaf.not_before > Utc::now() {
+        return Err(anyhow!(
+            "The certificate is not valid before {}",
+            leaf.not_before.to_rfc3339()
+        ));
+    }
+    match leaf.days_left() {
+        days if days < 0 => {
+            return Err(anyhow!(
+                "The certificate expired on {}",
+                leaf.not_after.date_naive()
+            ))
+        }
+        days if days < 30 => output::warning(&format!("The certificate expires in {} days", days)),
+        _ => {}
+    }
+    for pair in certs.windows(2) {
+        if pair[0].issuer != pair[1].subject {
+            output::warning(&format!(
+                "Chain order: {} is not issued by {}",
+                pair[0].subject.as_deref().unwrap_or("-"),
+                pair[1].subject.as_deref().unwrap_or("-")
+            ));
+        }
+    }
+
+    output::info(&format!(
+        "{} (issuer {}), valid until {}",
+        leaf.hostnames().join(", "),
+        leaf.issuer.as_deref().unwrap_or("-"),
+        leaf.not_after.date_naive()
+    ));
+
+    let certificate = certs
+        .iter()
+        .map(|c| pki::to_pem("CERTIFICATE", &c.der))
+        .collect();
+    Ok((certificate, key.pem))
+}
+
+/// Certificate and key paths for an output prefix
+///
+/// Checked before anything is issued, so a certificate is never issued
+/// without a place for its key.
+fn output_paths(prefix: &str, force: bool) -> Result<(PathBuf, PathBuf)> {
+    let cert_path = PathBuf::from(format!("{}.pem", prefix));
+    let key_path = PathBuf::from(format!("{}.key", prefix));
+    for path in [&cert_path, &key_path] {
+        if path.exists() && !force {
+            return Err(anyhow!(
+                "{} already exists (use --force to overwrite)",
+                path.display()
+            ));
+        }
+    }
+    Ok((cert_path, key_path))
+}
+
+/// Write an issued certificate and its locally generated key (mode 0600)
+fn write_issued(
+    cert_path: &Path,
+    certificate: &str,
+    key_path: &Path,
+    key_pem: &str,
+    force: bool,
+) -> Result<()> {
+    pki::write_private_file(key_path, key_pem, force)?;
+    std::fs::write(cert_path, certificate)?;
+    output::info(&format!("Certificate: {}", cert_path.display()));
+    output::info(&format!("Private key: {} (mode 0600)", key_path.display()));
+    Ok(())
+}
+
+async fn zone_name(client: &CloudflareClient, zone_id: &str) -> Result<String> {
+    let response = client.get_raw(&format!("/zones/{}", zone_id)).await?;
+    response
+        .pointer("/result/name")
+        .and_then(|v| v.as_str())
+        .map(String::from)
+        .ok_or_else(|| anyhow!("Could not determine the zone name"))
+}
+
+fn join_strings(value: Option<&Value>) -> String {
+    value
+        .and_then(|v| v.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn format_status(status: &str) -> String {
+    if status == "active" {
+        format!("\x1b[32m{}\x1b[0m", status)
+    } else {
+        status.to_string()
+    }
+}
+
+/// Show or change SSL/TLS settings on many zones
+async fn execute_multi(
+    client: &CloudflareClient,
+    zones: &[Zone],
+    concurrency: usize,
+    command: &SslCommand,
+) -> Result<()> {
+    let (setting, value) = match command {
+        SslCommand::Status => {
+            return multizone::run(zones, concurrency, |zone| async move {
+                let ssl = get_setting(client, &zone.id, "ssl").await?;
+                let min_tls = get_setting(client, &zone.id, "min_tls_version").await?;
+                let tls13 = get_setting(client, &zone.id, "tls_1_3").await?;
+                let always_https = get_setting(client, &zone.id, "always_use_https").await?;
+                Ok(format!(
+                    "ssl {}, min TLS {}, TLS 1.3 {}, always HTTPS {}",
+                    ssl, min_tls, tls13, always_https
+                ))
+            })
+            .await;
+        }
+        SslCommand::Mode { mode } => ("ssl", mode.clone()),
+        SslCommand::MinTls { version } => ("min_tls_version", version.clone()),
+        SslCommand::Tls13 { state } => ("tls_1_3", normalize_on_off(state)?),
+        SslCommand::AlwaysHttps { state } => ("always_use_https", normalize_on_off(state)?),
+        SslCommand::AutoHttps { state } => ("automatic_https_rewrites", normalize_on_off(state)?),
+        SslCommand::Hsts { .. }
+        | SslCommand::OriginPulls { .. }
+        | SslCommand::ClientCerts { .. } => {
+            return Err(anyhow!(
+                "HSTS, origin pulls and client certificates do not support multiple zones; \
+                 use `settings set security_header` for HSTS"
+            ))
+        }
+        SslCommand::Certs
+        | SslCommand::Custom { .. }
+        | SslCommand::CustomHostnames { .. }
+        | SslCommand::Packs { .. }
+        | SslCommand::OriginCa { .. }
+        | SslCommand::Expiry { .. } => {
+            return Err(anyhow!(
+                "Certificate commands do not support --zones/--all-zones/--zone-filter"
+            ))
+        }
+    };
+
+    if let Some(spec) = schema::find(setting) {
+        spec.validate(&json!(value))?;
+    }
+    let value = &value;
+
+    multizone::run(zones, concurrency, |zone| async move {
+        set_setting(client, &zone.id, setting, value).await?;
+        Ok(format!("{} set to {}", setting, value))
+    })
+    .await
+}
+
 async fn get_setting(client: &CloudflareClient, zone_id: &str, setting: &str) -> Result<String> {
     let path = format!("/zones/{}/settings/{}", zone_id, setting);
     let response = client.get_raw(&path).await?;
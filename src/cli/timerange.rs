@@ -0,0 +1,208 @@
+//! Time ranges for analytics: relative durations, absolute times and chunking
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use clap::Args;
+
+/// Start and end of an analytics window
+#[derive(Args, Debug, Clone)]
+pub struct TimeRangeArgs {
+    /// Start: duration ago (90m, 36h, 3d, 2w, 1d12h), RFC 3339 time, date, today or yesterday
+    #[arg(short, long, default_value = "24h", global = true)]
+    pub since: String,
+
+    /// End, same formats as --since (default: now, or midnight for --since yesterday)
+    #[arg(long, global = true)]
+    pub until: Option<String>,
+}
+
+/// A resolved window, `since` inclusive and `until` exclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeRange {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+}
+
+impl TimeRangeArgs {
+    pub fn resolve(&self) -> Result<TimeRange> {
+        let now = Utc::now();
+        let since = parse_time(&self.since, now)?;
+        let until = match &self.until {
+            Some(until) => parse_time(until, now)?,
+            None if self.since.trim().eq_ignore_ascii_case("yesterday") => start_of_day(now),
+            None => now,
+        }
+        .min(now);
+
+        // Checked after clamping so a window in the future does not end up empty
+        if since >= now {
+            return Err(anyhow!(
+                "Start of the window ({}) is in the future",
+                format_time(since)
+            ));
+        }
+        if since >= until {
+            return Err(anyhow!(
+                "Start of the window ({}) must be before its end ({})",
+                format_time(since),
+                format_time(until)
+            ));
+        }
+        Ok(TimeRange { since, until })
+    }
+}
+
+impl TimeRange {
+    /// Split into consecutive windows no longer than `max`
+    pub fn chunks(&self, max: Duration) -> Vec<TimeRange> {
+        let mut chunks = Vec::new();
+        let mut start = self.since;
+        while start < self.until {
+            let end = (start + max).min(self.until);
+            chunks.push(TimeRange {
+                since: start,
+                until: end,
+            });
+            start = end;
+        }
+        chunks
+    }
+
+    /// The window of the same length right before this one
+    pub fn previous(&self) -> TimeRange {
+        TimeRange {
+            since: self.since - (self.until - self.since),
+            until: self.since,
+        }
+    }
+
+    /// The window to compare against: `previous`, a duration to move this window
+    /// back by (`7d` for the same window a week earlier), or a start time
+    pub fn baseline(&self, vs: &str) -> Result<TimeRange> {
+        if vs.trim().eq_ignore_ascii_case("previous") {
+            return Ok(self.previous());
+        }
+        let length = self.until - self.since;
+        let since = match parse_duration(vs) {
+            Some(offset) => self.since - offset,
+            None => parse_time(vs, Utc::now())?,
+        };
+        Ok(TimeRange {
+            since,
+            until: since + length,
+        })
+    }
+
+    pub fn since_str(&self) -> String {
+        format_time(self.since)
+    }
+
+    pub fn until_str(&self) -> String {
+        format_time(self.until)
+    }
+}
+
+/// Parse a point in time: a duration before `now`, RFC 3339, a date, `now`, `today` or `yesterday`
+pub fn parse_time(raw: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let raw = raw.trim();
+    match raw.to_lowercase().as_str() {
+        "now" => return Ok(now),
+        "today" => return Ok(start_of_day(now)),
+        "yesterday" => return Ok(start_of_day(now) - Duration::days(1)),
+        _ => {}
+    }
+
+    if let Some(duration) = parse_duration(raw) {
+        return now
+            .checked_sub_signed(duration)
+            .ok_or_else(|| anyhow!("Duration too long: {}", raw));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc());
+    }
+
+    Err(anyhow!(
+        "Invalid time '{}': use a duration (90m, 3d, 2w), RFC 3339 (2024-05-01T12:00:00Z), a date (2024-05-01), today or yesterday",
+        raw
+    ))
+}
+
+/// Parse durations like `90m`, `36h`, `3d`, `2w` or `1d12h` (units s, m, h, d, w)
+pub fn parse_duration(raw: &str) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut digits = String::new();
+
+    for c in raw.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let amount: i64 = digits.parse().ok()?;
+        digits.clear();
+        let part = match c.to_ascii_lowercase() {
+            's' => Duration::try_seconds(amount),
+            'm' => Duration::try_minutes(amount),
+            'h' => Duration::try_hours(amount),
+            'd' => Duration::try_days(amount),
+            'w' => Duration::try_weeks(amount),
+            _ => None,
+        }?;
+        total = total.checked_add(&part)?;
+    }
+
+    (digits.is_empty() && total > Duration::zero()).then_some(total)
+}
+
+/// Format a time the way GraphQL datetime filters expect
+pub fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+fn start_of_day(time: DateTime<Utc>) -> DateTime<Utc> {
+    time.date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap_or_default()
+        .and_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(since: &str, until: Option<&str>) -> Result<TimeRange> {
+        TimeRangeArgs {
+            since: since.to_string(),
+            until: until.map(|u| u.to_string()),
+        }
+        .resolve()
+    }
+
+    #[test]
+    fn resolve_clamps_until_before_validating() {
+        let range = resolve("2h", Some("2999-01-01")).unwrap();
+        assert!(range.until <= Utc::now());
+        assert_eq!(range.chunks(Duration::hours(1)).len(), 2);
+
+        let err = resolve("2998-01-01", Some("2999-01-01")).unwrap_err();
+        assert!(err.to_string().contains("is in the future"), "{}", err);
+        let err = resolve("1h", Some("2h")).unwrap_err();
+        assert!(
+            err.to_string().contains("must be before its end"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("1d12h"), Some(Duration::hours(36)));
+        assert_eq!(parse_duration("90m"), Some(Duration::minutes(90)));
+        for invalid in ["", "0s", "12", "3y", "h"] {
+            assert_eq!(parse_duration(invalid), None, "{}", invalid);
+        }
+        assert_eq!(parse_duration("9223372036854775807w"), None);
+    }
+}
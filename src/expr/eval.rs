@@ -0,0 +1,867 @@
+//! Evaluate a checked expression against a sample request
+//!
+//! The request is described in JSON, either with field names as keys
+//! (`"http.request.uri.path": "/login"`) or with shorthand keys that fill in
+//! the related fields: `url`, `method`, `headers`, `body`, `ip`, `country`,
+//! `continent`, `asn`, plus `lists` for named lists used with `in $name`.
+
+use anyhow::{anyhow, Result};
+use serde_json::Value as Json;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use super::schema;
+use super::{
+    ip_in_cidr, parse_cidr, CompareOp, Index, Literal, LogicalOp, Node, NodeKind, Rhs, SetItem,
+    Type,
+};
+
+/// Runtime value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Bytes(String),
+    Ip(IpAddr),
+    Array(Vec<Value>),
+    Map(BTreeMap<String, Value>),
+    /// Missing map key or out-of-range index; never matches
+    Absent,
+}
+
+impl Value {
+    /// Default value of a type, used for fields missing from the request
+    fn default_for(ty: &Type) -> Value {
+        match ty {
+            Type::Bool => Value::Bool(false),
+            Type::Int => Value::Int(0),
+            Type::Bytes => Value::Bytes(String::new()),
+            Type::Ip => Value::Ip(IpAddr::from([0, 0, 0, 0])),
+            Type::Array(_) => Value::Array(Vec::new()),
+            Type::Map(_) => Value::Map(BTreeMap::new()),
+            Type::Unknown => Value::Absent,
+        }
+    }
+
+    fn as_bool(&self) -> bool {
+        matches!(self, Value::Bool(true))
+    }
+}
+
+/// Sample request to evaluate against
+#[derive(Debug, Default)]
+pub struct Request {
+    fields: BTreeMap<String, Value>,
+    lists: BTreeMap<String, Vec<String>>,
+}
+
+impl Request {
+    /// Build a request from its JSON description
+    pub fn from_json(json: &Json) -> Result<Self> {
+        let obj = json
+            .as_object()
+            .ok_or_else(|| anyhow!("Request must be a JSON object"))?;
+        let mut req = Request::default();
+
+        if let Some(url) = obj.get("url").and_then(|v| v.as_str()) {
+            req.set_url(url)?;
+        }
+        if let Some(method) = obj.get("method").and_then(|v| v.as_str()) {
+            req.set("http.request.method", Value::Bytes(method.to_uppercase()));
+        }
+        if let Some(headers) = obj.get("headers").and_then(|v| v.as_object()) {
+            req.set_headers(headers);
+        }
+        if let Some(body) = obj.get("body").and_then(|v| v.as_str()) {
+            req.set("http.request.body.raw", Value::Bytes(body.to_string()));
+            req.set("http.request.body.size", Value::Int(body.len() as i64));
+        }
+        if let Some(ip) = obj.get("ip").and_then(|v| v.as_str()) {
+            let ip: IpAddr = ip.parse().map_err(|_| anyhow!("Invalid ip: {}", ip))?;
+            req.set("ip.src", Value::Ip(ip));
+        }
+        for (key, fields) in [
+            ("country", ["ip.src.country", "ip.geoip.country"]),
+            ("continent", ["ip.src.continent", "ip.geoip.continent"]),
+        ] {
+            if let Some(v) = obj.get(key).and_then(|v| v.as_str()) {
+                for field in fields {
+                    req.set(field, Value::Bytes(v.to_uppercase()));
+                }
+            }
+        }
+        if let Some(asn) = obj.get("asn").and_then(|v| v.as_i64()) {
+            req.set("ip.src.asnum", Value::Int(asn));
+            req.set("ip.geoip.asnum", Value::Int(asn));
+        }
+        if let Some(lists) = obj.get("lists").and_then(|v| v.as_object()) {
+            for (name, items) in lists {
+                let items = items
+                    .as_array()
+                    .map(|a| {
+                        a.iter()
+                            .filter_map(|i| i.as_str().map(|s| s.to_string()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                req.lists.insert(name.clone(), items);
+            }
+        }
+
+        // Explicit fields override anything derived from shorthand keys
+        let explicit = obj
+            .get("fields")
+            .and_then(|v| v.as_object())
+            .into_iter()
+            .flatten()
+            .chain(obj.iter().filter(|(k, _)| schema::field(k).is_some()));
+
+        for (name, value) in explicit {
+            let field = schema::field(name).ok_or_else(|| anyhow!("Unknown field: {}", name))?;
+            let value = json_to_value(value, &field.ty.to_type())
+                .ok_or_else(|| anyhow!("Invalid value for {}: {}", name, value))?;
+            req.set(name, value);
+        }
+
+        Ok(req)
+    }
+
+    fn set(&mut self, field: &str, value: Value) {
+        self.fields.insert(field.to_string(), value);
+    }
+
+    fn set_url(&mut self, url: &str) -> Result<()> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| anyhow!("Invalid url {}: {}", url, e))?;
+
+        let path = parsed.path().to_string();
+        let query = parsed.query().unwrap_or("").to_string();
+        let uri = if query.is_empty() {
+            path.clone()
+        } else {
+            format!("{}?{}", path, query)
+        };
+        let extension = path
+            .rsplit('/')
+            .next()
+            .and_then(|last| last.rsplit_once('.'))
+            .map(|(_, ext)| ext.to_lowercase())
+            .unwrap_or_default();
+
+        let mut args: BTreeMap<String, Value> = BTreeMap::new();
+        let mut names = Vec::new();
+        let mut values = Vec::new();
+        for (k, v) in parsed.query_pairs() {
+            names.push(Value::Bytes(k.to_string()));
+            values.push(Value::Bytes(v.to_string()));
+            match args
+                .entry(k.to_string())
+                .or_insert_with(|| Value::Array(Vec::new()))
+            {
+                Value::Array(a) => a.push(Value::Bytes(v.to_string())),
+                _ => unreachable!(),
+            }
+        }
+
+        let host = parsed.host_str().unwrap_or("").to_lowercase();
+        let https = parsed.scheme() == "https";
+        let port = parsed.port_or_known_default().unwrap_or(80);
+
+        self.set("http.request.full_uri", Value::Bytes(url.to_string()));
+        self.set("raw.http.request.full_uri", Value::Bytes(url.to_string()));
+        self.set("http.host", Value::Bytes(host));
+        self.set("http.request.uri", Value::Bytes(uri.clone()));
+        self.set("raw.http.request.uri", Value::Bytes(uri));
+        self.set("http.request.uri.path", Value::Bytes(path.clone()));
+        self.set("raw.http.request.uri.path", Value::Bytes(path));
+        self.set("http.request.uri.path.extension", Value::Bytes(extension));
+        self.set("http.request.uri.query", Value::Bytes(query.clone()));
+        self.set("raw.http.request.uri.query", Value::Bytes(query));
+        self.set("http.request.uri.args", Value::Map(args.clone()));
+        self.set("raw.http.request.uri.args", Value::Map(args));
+        self.set("http.request.uri.args.names", Value::Array(names));
+        self.set("http.request.uri.args.values", Value::Array(values));
+        self.set("ssl", Value::Bool(https));
+        self.set("cf.edge.server_port", Value::Int(port as i64));
+        self.set("tcp.dstport", Value::Int(port as i64));
+
+        Ok(())
+    }
+
+    fn set_headers(&mut self, headers: &serde_json::Map<String, Json>) {
+        let mut map: BTreeMap<String, Value> = BTreeMap::new();
+        let mut names = Vec::new();
+        let mut values = Vec::new();
+
+        for (name, value) in headers {
+            let name = name.to_lowercase();
+            let items: Vec<String> = match value {
+                Json::Array(a) => a
+                    .iter()
+                    .map(|v| v.as_str().map(|s| s.to_string()).unwrap_or(v.to_string()))
+                    .collect(),
+                Json::String(s) => vec![s.clone()],
+                other => vec![other.to_string()],
+            };
+
+            for item in &items {
+                names.push(Value::Bytes(name.clone()));
+                values.push(Value::Bytes(item.clone()));
+            }
+
+            let first = items.first().cloned().unwrap_or_default();
+            match name.as_str() {
+                "user-agent" => self.set("http.user_agent", Value::Bytes(first)),
+                "referer" => self.set("http.referer", Value::Bytes(first)),
+                "x-forwarded-for" => self.set("http.x_forwarded_for", Value::Bytes(first)),
+                "host" if !self.fields.contains_key("http.host") => {
+                    self.set("http.host", Value::Bytes(first.to_lowercase()))
+                }
+                "cookie" => {
+                    self.set("http.cookie", Value::Bytes(first.clone()));
+                    let mut cookies: BTreeMap<String, Value> = BTreeMap::new();
+                    for pair in first.split(';') {
+                        if let Some((k, v)) = pair.trim().split_once('=') {
+                            match cookies
+                                .entry(k.to_string())
+                                .or_insert_with(|| Value::Array(Vec::new()))
+                            {
+                                Value::Array(a) => a.push(Value::Bytes(v.to_string())),
+                                _ => unreachable!(),
+                            }
+                        }
+                    }
+                    self.set("http.request.cookies", Value::Map(cookies));
+                }
+                "accept-language" => {
+                    let langs = first
+                        .split(',')
+                        .filter_map(|l| l.split(';').next())
+                        .map(|l| Value::Bytes(l.trim().to_string()))
+                        .filter(|l| *l != Value::Bytes(String::new()))
+                        .collect();
+                    self.set("http.request.accepted_languages", Value::Array(langs));
+                }
+                _ => {}
+            }
+
+            map.insert(
+                name,
+                Value::Array(items.into_iter().map(Value::Bytes).collect()),
+            );
+        }
+
+        self.set("http.request.headers", Value::Map(map));
+        self.set("http.request.headers.names", Value::Array(names));
+        self.set("http.request.headers.values", Value::Array(values));
+    }
+
+    /// Fields used by the expression that the request does not set
+    pub fn missing_fields(&self, node: &Node) -> Vec<String> {
+        node.fields()
+            .into_iter()
+            .filter(|f| !self.fields.contains_key(f))
+            .collect()
+    }
+
+    fn get(&self, name: &str, ty: &Type) -> Value {
+        self.fields
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| Value::default_for(ty))
+    }
+}
+
+fn json_to_value(json: &Json, ty: &Type) -> Option<Value> {
+    match ty {
+        Type::Bool => json.as_bool().map(Value::Bool),
+        Type::Int => json.as_i64().map(Value::Int),
+        Type::Bytes => json.as_str().map(|s| Value::Bytes(s.to_string())),
+        Type::Ip => json.as_str().and_then(|s| s.parse().ok()).map(Value::Ip),
+        Type::Array(inner) => match json {
+            Json::Array(items) => items
+                .iter()
+                .map(|i| json_to_value(i, inner))
+                .collect::<Option<Vec<_>>>()
+                .map(Value::Array),
+            // A single value is accepted as a one-element array
+            other => json_to_value(other, inner).map(|v| Value::Array(vec![v])),
+        },
+        Type::Map(inner) => json.as_object().and_then(|obj| {
+            obj.iter()
+                .map(|(k, v)| json_to_value(v, inner).map(|v| (k.to_lowercase(), v)))
+                .collect::<Option<BTreeMap<_, _>>>()
+                .map(Value::Map)
+        }),
+        Type::Unknown => None,
+    }
+}
+
+/// Evaluate an expression; the result of a valid expression is a Boolean
+pub fn eval(node: &Node, req: &Request) -> Result<Value> {
+    match &node.kind {
+        NodeKind::Field(name) => Ok(req.get(name, &node.ty)),
+
+        NodeKind::Literal(lit) => Ok(literal_value(lit)),
+
+        NodeKind::Index(base, index) => {
+            let value = eval(base, req)?;
+            if base.unpacked {
+                match value {
+                    Value::Array(items) => Ok(Value::Array(
+                        items.iter().map(|v| apply_index(v, index)).collect(),
+                    )),
+                    other => Ok(apply_index(&other, index)),
+                }
+            } else {
+                Ok(apply_index(&value, index))
+            }
+        }
+
+        NodeKind::Call(name, args) => {
+            let values = args
+                .iter()
+                .map(|a| eval(a, req))
+                .collect::<Result<Vec<_>>>()?;
+
+            if node.unpacked {
+                // Element-wise call on a [*] value
+                let items = match values.first() {
+                    Some(Value::Array(items)) => items.clone(),
+                    _ => Vec::new(),
+                };
+                let mapped = items
+                    .into_iter()
+                    .map(|item| {
+                        let mut call_args = values.clone();
+                        call_args[0] = item;
+                        call(name, &call_args)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Value::Array(mapped))
+            } else {
+                call(name, &values)
+            }
+        }
+
+        NodeKind::Compare(lhs, op, rhs) => {
+            let value = eval(lhs, req)?;
+            if lhs.unpacked {
+                let items = match value {
+                    Value::Array(items) => items,
+                    _ => Vec::new(),
+                };
+                let results = items
+                    .iter()
+                    .map(|v| compare(v, *op, rhs, req).map(Value::Bool))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Value::Array(results))
+            } else {
+                Ok(Value::Bool(compare(&value, *op, rhs, req)?))
+            }
+        }
+
+        NodeKind::Not(inner) => Ok(Value::Bool(!eval(inner, req)?.as_bool())),
+
+        NodeKind::Logical(op, lhs, rhs) => {
+            let l = eval(lhs, req)?.as_bool();
+            let result = match op {
+                LogicalOp::And => l && eval(rhs, req)?.as_bool(),
+                LogicalOp::Or => l || eval(rhs, req)?.as_bool(),
+                LogicalOp::Xor => l ^ eval(rhs, req)?.as_bool(),
+            };
+            Ok(Value::Bool(result))
+        }
+    }
+}
+
+fn literal_value(lit: &Literal) -> Value {
+    match lit {
+        Literal::Bytes(s) => Value::Bytes(s.clone()),
+        Literal::Int(i) => Value::Int(*i),
+        Literal::Ip(ip, _) => Value::Ip(*ip),
+    }
+}
+
+fn apply_index(value: &Value, index: &Index) -> Value {
+    match (value, index) {
+        (Value::Map(map), Index::Key(key)) => map
+            .get(&key.to_lowercase())
+            .or_else(|| map.get(key))
+            .cloned()
+            .unwrap_or(Value::Absent),
+        (Value::Array(items), Index::Pos(i)) => usize::try_from(*i)
+            .ok()
+            .and_then(|i| items.get(i))
+            .cloned()
+            .unwrap_or(Value::Absent),
+        (Value::Array(items), Index::All) => Value::Array(items.clone()),
+        (Value::Map(map), Index::All) => Value::Array(map.values().cloned().collect()),
+        _ => Value::Absent,
+    }
+}
+
+fn compare(value: &Value, op: CompareOp, rhs: &Rhs, req: &Request) -> Result<bool> {
+    if *value == Value::Absent {
+        return Ok(false);
+    }
+
+    match rhs {
+        Rhs::Literal(lit) => compare_literal(value, op, lit),
+        Rhs::Set(items) => Ok(items.iter().any(|item| match item {
+            SetItem::Literal(Literal::Ip(net, prefix)) => match value {
+                Value::Ip(ip) => ip_in_cidr(ip, net, *prefix),
+                _ => false,
+            },
+            SetItem::Literal(lit) => *value == literal_value(lit),
+            SetItem::IntRange(a, b) => matches!(value, Value::Int(i) if a <= i && i <= b),
+        })),
+        Rhs::List(name) => {
+            let items = req.lists.get(name).ok_or_else(|| {
+                anyhow!("List ${} is not defined in the request's \"lists\"", name)
+            })?;
+            Ok(items.iter().any(|item| match value {
+                Value::Ip(ip) => parse_cidr(item)
+                    .map(|(net, prefix)| ip_in_cidr(ip, &net, prefix))
+                    .unwrap_or(false),
+                Value::Bytes(s) => s.eq_ignore_ascii_case(item),
+                _ => false,
+            }))
+        }
+    }
+}
+
+fn compare_literal(value: &Value, op: CompareOp, lit: &Literal) -> Result<bool> {
+    let result = match (value, lit) {
+        (Value::Bytes(s), Literal::Bytes(l)) => match op {
+            CompareOp::Eq => s == l,
+            CompareOp::Ne => s != l,
+            CompareOp::Lt => s < l,
+            CompareOp::Le => s <= l,
+            CompareOp::Gt => s > l,
+            CompareOp::Ge => s >= l,
+            CompareOp::Contains => s.contains(l.as_str()),
+            CompareOp::Matches => regex::Regex::new(l)?.is_match(s),
+            CompareOp::Wildcard => wildcard_match(&l.to_lowercase(), &s.to_lowercase()),
+            CompareOp::StrictWildcard => wildcard_match(l, s),
+            CompareOp::In => false,
+        },
+        (Value::Int(i), Literal::Int(l)) => match op {
+            CompareOp::Eq => i == l,
+            CompareOp::Ne => i != l,
+            CompareOp::Lt => i < l,
+            CompareOp::Le => i <= l,
+            CompareOp::Gt => i > l,
+            CompareOp::Ge => i >= l,
+            _ => false,
+        },
+        (Value::Ip(ip), Literal::Ip(l, _)) => match op {
+            CompareOp::Eq => ip == l,
+            CompareOp::Ne => ip != l,
+            _ => false,
+        },
+        _ => false,
+    };
+
+    Ok(result)
+}
+
+/// Match `*` wildcards (any sequence, including empty)
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let first = parts[0];
+    let last = parts[parts.len() - 1];
+    if !text.starts_with(first) || text.len() < first.len() + last.len() {
+        return false;
+    }
+
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    if !text.ends_with(last) {
+        return false;
+    }
+
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+fn bytes_arg(args: &[Value], i: usize) -> String {
+    match args.get(i) {
+        Some(Value::Bytes(s)) => s.clone(),
+        _ => String::new(),
+    }
+}
+
+fn int_arg(args: &[Value], i: usize) -> Option<i64> {
+    match args.get(i) {
+        Some(Value::Int(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+fn call(name: &str, args: &[Value]) -> Result<Value> {
+    if args.contains(&Value::Absent) && !matches!(name, "has_key" | "has_value") {
+        return Ok(Value::Absent);
+    }
+
+    let value = match name {
+        "any" | "all" => {
+            let items = match args.first() {
+                Some(Value::Array(items)) => items.iter().map(|v| v.as_bool()).collect(),
+                _ => Vec::new(),
+            };
+            if name == "any" {
+                Value::Bool(items.iter().any(|b| *b))
+            } else {
+                Value::Bool(!items.is_empty() && items.iter().all(|b| *b))
+            }
+        }
+        "lower" => Value::Bytes(bytes_arg(args, 0).to_lowercase()),
+        "upper" => Value::Bytes(bytes_arg(args, 0).to_uppercase()),
+        "len" => match args.first() {
+            Some(Value::Array(items)) => Value::Int(items.len() as i64),
+            Some(Value::Bytes(s)) => Value::Int(s.len() as i64),
+            _ => Value::Int(0),
+        },
+        "starts_with" => Value::Bool(bytes_arg(args, 0).starts_with(&bytes_arg(args, 1))),
+        "ends_with" => Value::Bool(bytes_arg(args, 0).ends_with(&bytes_arg(args, 1))),
+        "concat" => match args.first() {
+            Some(Value::Array(_)) => Value::Array(
+                args.iter()
+                    .flat_map(|a| match a {
+                        Value::Array(items) => items.clone(),
+                        other => vec![other.clone()],
+                    })
+                    .collect(),
+            ),
+            _ => Value::Bytes(
+                args.iter()
+                    .map(|a| match a {
+                        Value::Bytes(s) => s.clone(),
+                        _ => String::new(),
+                    })
+                    .collect(),
+            ),
+        },
+        "url_decode" => Value::Bytes(url_decode(&bytes_arg(args, 0))),
+        "to_string" => Value::Bytes(match args.first() {
+            Some(Value::Bytes(s)) => s.clone(),
+            Some(Value::Int(i)) => i.to_string(),
+            Some(Value::Ip(ip)) => ip.to_string(),
+            Some(Value::Bool(b)) => b.to_string(),
+            _ => String::new(),
+        }),
+        "remove_bytes" => {
+            let remove: Vec<char> = bytes_arg(args, 1).chars().collect();
+            Value::Bytes(
+                bytes_arg(args, 0)
+                    .chars()
+                    .filter(|c| !remove.contains(c))
+                    .collect(),
+            )
+        }
+        "substring" => {
+            let s = bytes_arg(args, 0);
+            let len = s.len() as i64;
+            let norm = |i: i64| if i < 0 { (len + i).max(0) } else { i.min(len) } as usize;
+            let start = norm(int_arg(args, 1).unwrap_or(0));
+            let end = int_arg(args, 2).map(norm).unwrap_or(len as usize);
+            Value::Bytes(s.get(start..end.max(start)).unwrap_or("").to_string())
+        }
+        "lookup_json_string" | "lookup_json_integer" => {
+            let doc: Json = serde_json::from_str(&bytes_arg(args, 0)).unwrap_or(Json::Null);
+            let mut cur = &doc;
+            for key in &args[1..] {
+                cur = match key {
+                    Value::Bytes(k) => cur.get(k.as_str()).unwrap_or(&Json::Null),
+                    Value::Int(i) => cur.get(*i as usize).unwrap_or(&Json::Null),
+                    _ => &Json::Null,
+                };
+            }
+            match (name, cur) {
+                ("lookup_json_string", Json::String(s)) => Value::Bytes(s.clone()),
+                ("lookup_json_integer", Json::Number(n)) if n.is_i64() => {
+                    Value::Int(n.as_i64().unwrap_or(0))
+                }
+                _ => Value::Absent,
+            }
+        }
+        "regex_replace" => {
+            let re = regex::Regex::new(&bytes_arg(args, 1))?;
+            Value::Bytes(
+                re.replacen(&bytes_arg(args, 0), 1, bytes_arg(args, 2).as_str())
+                    .to_string(),
+            )
+        }
+        "wildcard_replace" => {
+            let pattern = bytes_arg(args, 1);
+            let strict = bytes_arg(args, 3).contains('s');
+            let escaped: Vec<String> = pattern.split('*').map(regex::escape).collect();
+            let re = format!(
+                "{}^{}$",
+                if strict { "" } else { "(?i)" },
+                escaped.join("(.*)")
+            );
+            let re = regex::Regex::new(&re)?;
+            let source = bytes_arg(args, 0);
+            if re.is_match(&source) {
+                Value::Bytes(re.replace(&source, bytes_arg(args, 2).as_str()).to_string())
+            } else {
+                Value::Bytes(source)
+            }
+        }
+        "decode_base64" => Value::Bytes(decode_base64(&bytes_arg(args, 0))),
+        "cidr" | "cidr6" => {
+            let ip = match args.first() {
+                Some(Value::Ip(ip)) => *ip,
+                _ => return Ok(Value::Absent),
+            };
+            let bits = match (ip, name) {
+                (IpAddr::V4(_), "cidr") => int_arg(args, 1),
+                (IpAddr::V6(_), "cidr") => int_arg(args, 2),
+                (IpAddr::V6(_), _) => int_arg(args, 1),
+                (IpAddr::V4(_), _) => Some(32),
+            }
+            .unwrap_or(0)
+            .clamp(0, 128) as u32;
+            Value::Ip(network_address(ip, bits))
+        }
+        "has_key" => match args.first() {
+            Some(Value::Map(map)) => {
+                let key = bytes_arg(args, 1);
+                Value::Bool(map.contains_key(&key) || map.contains_key(&key.to_lowercase()))
+            }
+            _ => Value::Bool(false),
+        },
+        "has_value" => {
+            let needle = args.get(1).cloned().unwrap_or(Value::Absent);
+            let found = match args.first() {
+                Some(Value::Array(items)) => items.contains(&needle),
+                Some(Value::Map(map)) => map.values().any(|v| match v {
+                    Value::Array(items) => items.contains(&needle),
+                    other => *other == needle,
+                }),
+                _ => false,
+            };
+            Value::Bool(found)
+        }
+        _ => return Err(anyhow!("Function {}() cannot be evaluated offline", name)),
+    };
+
+    Ok(value)
+}
+
+fn network_address(ip: IpAddr, bits: u32) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => {
+            let bits = bits.min(32);
+            let mask = if bits == 0 {
+                0
+            } else {
+                u32::MAX << (32 - bits)
+            };
+            IpAddr::V4((u32::from(v4) & mask).into())
+        }
+        IpAddr::V6(v6) => {
+            let bits = bits.min(128);
+            let mask = if bits == 0 {
+                0
+            } else {
+                u128::MAX << (128 - bits)
+            };
+            IpAddr::V6((u128::from(v6) & mask).into())
+        }
+    }
+}
+
+fn url_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(b) = u8::from_str_radix(hex, 16) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn decode_base64(s: &str) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = Vec::new();
+    let mut buf = 0u32;
+    let mut bits = 0;
+
+    for c in s.bytes() {
+        let v = match c {
+            b'-' => 62,
+            b'_' => 63,
+            _ => match ALPHABET.iter().position(|a| *a == c) {
+                Some(v) => v as u32,
+                None => continue,
+            },
+        };
+        buf = (buf << 6) | v;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buf >> bits) as u8);
+            buf &= (1 << bits) - 1;
+        }
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Format a value for display
+pub fn display(value: &Value) -> String {
+    match value {
+        Value::Bool(b) => b.to_string(),
+        Value::Int(i) => i.to_string(),
+        Value::Bytes(s) => format!("{:?}", s),
+        Value::Ip(ip) => ip.to_string(),
+        Value::Array(items) => format!(
+            "[{}]",
+            items.iter().map(display).collect::<Vec<_>>().join(", ")
+        ),
+        Value::Map(map) => format!(
+            "{{{}}}",
+            map.iter()
+                .map(|(k, v)| format!("{:?}: {}", k, display(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Value::Absent => "(absent)".to_string(),
+    }
+}
+
+/// Values of the fields used by the expression, for showing what was matched
+pub fn field_values(node: &Node, req: &Request) -> Vec<(String, String)> {
+    node.fields()
+        .into_iter()
+        .map(|name| {
+            let ty = schema::field(&name)
+                .map(|f| f.ty.to_type())
+                .unwrap_or(Type::Unknown);
+            let value = display(&req.get(&name, &ty));
+            (name, value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request() -> Request {
+        Request::from_json(&json!({
+            "url": "https://Example.com/api/v2/users.json?id=1&id=2&q=x",
+            "method": "post",
+            "headers": {"User-Agent": "curl/8.0", "X-Tags": ["a", "b"]},
+            "ip": "192.0.2.10",
+            "country": "de",
+            "asn": 64500,
+            "lists": {"blocked": ["192.0.2.0/24"], "hosts": ["EXAMPLE.com"]},
+            "cf.bot_management.score": 12
+        }))
+        .unwrap()
+    }
+
+    fn matches(src: &str) -> bool {
+        let checked = super::super::parse(src);
+        assert!(checked.is_ok(), "{}: {:?}", src, checked.errors);
+        match eval(checked.ast.as_ref().unwrap(), &request()).unwrap() {
+            Value::Bool(b) => b,
+            other => panic!("{} evaluated to {:?}", src, other),
+        }
+    }
+
+    #[test]
+    fn derives_fields_from_shorthand_keys() {
+        let req = request();
+        let get = |name: &str| req.get(name, &schema::field(name).unwrap().ty.to_type());
+        assert_eq!(get("http.host"), Value::Bytes("example.com".into()));
+        assert_eq!(
+            get("http.request.uri"),
+            Value::Bytes("/api/v2/users.json?id=1&id=2&q=x".into())
+        );
+        assert_eq!(
+            get("http.request.uri.path.extension"),
+            Value::Bytes("json".into())
+        );
+        assert_eq!(get("http.request.method"), Value::Bytes("POST".into()));
+        assert_eq!(get("ip.src.country"), Value::Bytes("DE".into()));
+        assert_eq!(get("cf.edge.server_port"), Value::Int(443));
+        assert_eq!(get("ssl"), Value::Bool(true));
+        // Fields the request does not set get their type's default
+        assert_eq!(get("http.referer"), Value::Bytes(String::new()));
+    }
+
+    #[test]
+    fn evaluates_comparisons() {
+        assert!(matches(r#"http.host eq "example.com" and ssl"#));
+        assert!(matches(r#"http.request.method in {"GET" "POST"}"#));
+        assert!(matches(r#"http.request.uri.path matches r"^/api/v\d+/""#));
+        assert!(matches(r#"http.request.uri.path wildcard "/API/*""#));
+        assert!(!matches(
+            r#"http.request.uri.path strict wildcard "/API/*""#
+        ));
+        assert!(matches("ip.src in {10.0.0.0/8 192.0.2.0/24}"));
+        assert!(!matches("ip.src eq 192.0.2.11"));
+        assert!(matches(
+            "ip.src.asnum in {64000..65000} and cf.bot_management.score lt 30"
+        ));
+        assert!(matches("not (ssl xor ssl)"));
+    }
+
+    #[test]
+    fn evaluates_maps_arrays_and_functions() {
+        assert!(matches(r#"http.request.uri.args["id"][1] eq "2""#));
+        assert!(!matches(r#"http.request.uri.args["missing"][0] eq """#));
+        assert!(matches(r#"any(http.request.headers["x-tags"][*] eq "b")"#));
+        assert!(!matches(r#"all(http.request.headers["x-tags"][*] eq "b")"#));
+        assert!(matches(r#"lower(http.user_agent) contains "curl""#));
+        assert!(matches(r#"len(http.request.uri.args["id"]) eq 2"#));
+        assert!(matches(r#"starts_with(http.request.uri.path, "/api")"#));
+    }
+
+    #[test]
+    fn evaluates_named_lists() {
+        assert!(matches("ip.src in $blocked"));
+        assert!(matches("http.host in $hosts"));
+
+        let checked = super::super::parse("ip.src in $unknown");
+        let err = eval(checked.ast.as_ref().unwrap(), &request()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "List $unknown is not defined in the request's \"lists\""
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_requests() {
+        let err = Request::from_json(&json!({"ip": "nope"})).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid ip: nope");
+        let err = Request::from_json(&json!({"ssl": "yes"})).unwrap_err();
+        assert_eq!(err.to_string(), "Invalid value for ssl: \"yes\"");
+        assert!(Request::from_json(&json!([])).is_err());
+    }
+}
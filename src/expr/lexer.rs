@@ -0,0 +1,316 @@
+//! Tokenizer for rule expressions
+
+use super::{ExprError, Span};
+
+/// Token kinds
+#[derive(Debug, Clone, PartialEq)]
+pub enum Tok {
+    /// Field, function name or word operator (eq, and, contains, ...)
+    Ident(String),
+    /// Quoted string (escapes resolved)
+    Str(String),
+    /// Integer literal
+    Int(i64),
+    /// IP address or CIDR literal
+    Ip(String),
+    /// Named list reference ($name)
+    List(String),
+    /// Symbol operator or punctuation
+    Sym(&'static str),
+}
+
+/// Token with its source span
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub tok: Tok,
+    pub span: Span,
+}
+
+/// Symbols, longest first so `==` wins over `=`
+const SYMBOLS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "^^", "..", "<", ">", "~", "!", "(", ")", "{", "}", "[",
+    "]", ",", "*",
+];
+
+/// Split an expression into tokens
+pub fn tokenize(src: &str) -> Result<Vec<Token>, ExprError> {
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+
+        if c.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        // Strings: "..." and raw strings r"..." / r#"..."#
+        if c == b'"' {
+            let (s, end) = lex_string(src, i)?;
+            tokens.push(Token {
+                tok: Tok::Str(s),
+                span: Span::new(i, end),
+            });
+            i = end;
+            continue;
+        }
+        if c == b'r' && matches!(bytes.get(i + 1), Some(b'"') | Some(b'#')) {
+            let (s, end) = lex_raw_string(src, i)?;
+            tokens.push(Token {
+                tok: Tok::Str(s),
+                span: Span::new(i, end),
+            });
+            i = end;
+            continue;
+        }
+
+        // Named lists: $name
+        if c == b'$' {
+            let start = i;
+            i += 1;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            if i == start + 1 {
+                return Err(ExprError::new(
+                    Span::new(start, i),
+                    "expected list name after '$'",
+                ));
+            }
+            tokens.push(Token {
+                tok: Tok::List(src[start + 1..i].to_string()),
+                span: Span::new(start, i),
+            });
+            continue;
+        }
+
+        // Negative integers
+        if c == b'-' && bytes.get(i + 1).is_some_and(|b| b.is_ascii_digit()) {
+            let start = i;
+            i += 1;
+            while i < bytes.len() && bytes[i].is_ascii_digit() {
+                i += 1;
+            }
+            let value = src[start..i]
+                .parse()
+                .map_err(|_| ExprError::new(Span::new(start, i), "integer out of range"))?;
+            tokens.push(Token {
+                tok: Tok::Int(value),
+                span: Span::new(start, i),
+            });
+            continue;
+        }
+
+        // Words: identifiers, integers, IPv4/IPv6 addresses and CIDRs
+        if c.is_ascii_alphanumeric() || c == b'_' || (c == b':' && bytes.get(i + 1) == Some(&b':'))
+        {
+            let start = i;
+            while i < bytes.len() {
+                let b = bytes[i];
+                // Stop before a range operator (80..443)
+                if b == b'.' && bytes.get(i + 1) == Some(&b'.') {
+                    break;
+                }
+                if b.is_ascii_alphanumeric() || b == b'_' || b == b'.' || b == b':' || b == b'/' {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token {
+                tok: classify_word(&src[start..i], Span::new(start, i))?,
+                span: Span::new(start, i),
+            });
+            continue;
+        }
+
+        // Symbols
+        if let Some(sym) = SYMBOLS.iter().find(|s| src[i..].starts_with(**s)) {
+            tokens.push(Token {
+                tok: Tok::Sym(sym),
+                span: Span::new(i, i + sym.len()),
+            });
+            i += sym.len();
+            continue;
+        }
+
+        let ch = src[i..].chars().next().unwrap_or('?');
+        let hint = match ch {
+            '=' => " (use '==' or 'eq')",
+            '&' => " (use '&&' or 'and')",
+            '|' => " (use '||' or 'or')",
+            '\'' => " (strings use double quotes)",
+            _ => "",
+        };
+        return Err(ExprError::new(
+            Span::new(i, i + ch.len_utf8()),
+            format!("unexpected character '{}'{}", ch, hint),
+        ));
+    }
+
+    Ok(tokens)
+}
+
+fn classify_word(word: &str, span: Span) -> Result<Tok, ExprError> {
+    if word.chars().all(|c| c.is_ascii_digit()) {
+        return word
+            .parse()
+            .map(Tok::Int)
+            .map_err(|_| ExprError::new(span, "integer out of range"));
+    }
+
+    let looks_like_ip =
+        word.contains(':') || word.contains('/') || word.starts_with(|c: char| c.is_ascii_digit());
+
+    if looks_like_ip {
+        if super::parse_cidr(word).is_some() {
+            return Ok(Tok::Ip(word.to_string()));
+        }
+        return Err(ExprError::new(
+            span,
+            format!("invalid IP address or CIDR '{}'", word),
+        ));
+    }
+
+    Ok(Tok::Ident(word.to_string()))
+}
+
+fn lex_string(src: &str, start: usize) -> Result<(String, usize), ExprError> {
+    let mut out = String::new();
+    let mut chars = src[start + 1..].char_indices();
+
+    while let Some((off, c)) = chars.next() {
+        let pos = start + 1 + off;
+        match c {
+            '"' => return Ok((out, pos + 1)),
+            '\\' => match chars.next() {
+                Some((_, '"')) => out.push('"'),
+                Some((_, '\\')) => out.push('\\'),
+                Some((eoff, e)) => {
+                    let epos = start + 1 + eoff;
+                    return Err(ExprError::new(
+                        Span::new(pos, epos + e.len_utf8()),
+                        format!("invalid escape '\\{}' (only \\\" and \\\\ are allowed; use a raw string r\"...\" for regexes)", e),
+                    ));
+                }
+                None => break,
+            },
+            _ => out.push(c),
+        }
+    }
+
+    Err(ExprError::new(
+        Span::new(start, src.len()),
+        "unterminated string",
+    ))
+}
+
+fn lex_raw_string(src: &str, start: usize) -> Result<(String, usize), ExprError> {
+    let rest = &src[start + 1..];
+    let hashes = rest.chars().take_while(|c| *c == '#').count();
+
+    if !rest[hashes..].starts_with('"') {
+        return Err(ExprError::new(
+            Span::new(start, start + 1 + hashes),
+            "expected '\"' to start raw string",
+        ));
+    }
+
+    let body_start = start + 1 + hashes + 1;
+    let terminator = format!("\"{}", "#".repeat(hashes));
+
+    match src[body_start..].find(&terminator) {
+        Some(off) => Ok((
+            src[body_start..body_start + off].to_string(),
+            body_start + off + terminator.len(),
+        )),
+        None => Err(ExprError::new(
+            Span::new(start, src.len()),
+            "unterminated raw string",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toks(src: &str) -> Vec<Tok> {
+        tokenize(src).unwrap().into_iter().map(|t| t.tok).collect()
+    }
+
+    fn error(src: &str) -> ExprError {
+        tokenize(src).unwrap_err()
+    }
+
+    #[test]
+    fn tokenizes_words_symbols_and_ranges() {
+        assert_eq!(
+            toks("tcp.dstport in {80..443 -1} && $blocked"),
+            vec![
+                Tok::Ident("tcp.dstport".into()),
+                Tok::Ident("in".into()),
+                Tok::Sym("{"),
+                Tok::Int(80),
+                Tok::Sym(".."),
+                Tok::Int(443),
+                Tok::Int(-1),
+                Tok::Sym("}"),
+                Tok::Sym("&&"),
+                Tok::List("blocked".into()),
+            ]
+        );
+        assert_eq!(
+            toks("ip.src in {10.0.0.0/8 2001:db8::/32}"),
+            vec![
+                Tok::Ident("ip.src".into()),
+                Tok::Ident("in".into()),
+                Tok::Sym("{"),
+                Tok::Ip("10.0.0.0/8".into()),
+                Tok::Ip("2001:db8::/32".into()),
+                Tok::Sym("}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenizes_strings() {
+        assert_eq!(toks(r#""a\"b\\c""#), vec![Tok::Str(r#"a"b\c"#.into())]);
+        assert_eq!(toks(r###"r#"\d+"x"#"###), vec![Tok::Str(r#"\d+"x"#.into())]);
+
+        let spans: Vec<Span> = tokenize(r#"x eq "é" or"#)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.span)
+            .collect();
+        assert_eq!(spans[2], Span::new(5, 9));
+        assert_eq!(spans[3], Span::new(10, 12));
+    }
+
+    #[test]
+    fn reports_errors_with_positions() {
+        let e = error(r#"http.host = "x""#);
+        assert_eq!(e.span, Span::new(10, 11));
+        assert_eq!(e.message, "unexpected character '=' (use '==' or 'eq')");
+
+        let e = error(r#"x eq "abc"#);
+        assert_eq!(e.span, Span::new(5, 9));
+        assert_eq!(e.message, "unterminated string");
+
+        let e = error(r#"x matches "\d""#);
+        assert_eq!(e.span, Span::new(11, 13));
+        assert!(e.message.starts_with("invalid escape '\\d'"));
+
+        let e = error("ip.src eq 1.2.3.999");
+        assert_eq!(e.span, Span::new(10, 19));
+        assert_eq!(e.message, "invalid IP address or CIDR '1.2.3.999'");
+
+        let e = error("x eq 99999999999999999999");
+        assert_eq!(e.message, "integer out of range");
+
+        assert_eq!(error("x in $").message, "expected list name after '$'");
+        assert_eq!(error(r#"x eq r#"a"#).message, "unterminated raw string");
+    }
+}
@@ -0,0 +1,320 @@
+//! Offline parser, linter and evaluator for the Cloudflare Rules language
+//!
+//! Used by `firewall expr check/eval` and to validate expressions locally
+//! before rules are sent to the API.
+
+pub mod eval;
+pub mod lexer;
+pub mod parser;
+pub mod schema;
+
+use std::fmt;
+use std::net::IpAddr;
+
+pub use parser::parse;
+
+/// Byte range in the source expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Span covering both `self` and `other`
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+}
+
+/// Parse or type error with the position it refers to
+#[derive(Debug, Clone)]
+pub struct ExprError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl ExprError {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+
+    /// 1-based line and column (in characters) of the error start
+    pub fn line_col(&self, src: &str) -> (usize, usize) {
+        let before = &src[..self.span.start.min(src.len())];
+        let line = before.matches('\n').count() + 1;
+        let col = before
+            .rsplit('\n')
+            .next()
+            .map(|l| l.chars().count())
+            .unwrap_or(0)
+            + 1;
+        (line, col)
+    }
+
+    /// Render the error with the offending source line and a caret marker
+    pub fn render(&self, src: &str) -> String {
+        let (line, col) = self.line_col(src);
+        let text = src.lines().nth(line - 1).unwrap_or("");
+        let width = src
+            .get(self.span.start..self.span.end.min(src.len()))
+            .map(|s| s.lines().next().unwrap_or("").chars().count())
+            .unwrap_or(1)
+            .max(1);
+
+        format!(
+            "{}:{}: {}\n  {}\n  {}{}",
+            line,
+            col,
+            self.message,
+            text,
+            " ".repeat(col - 1),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at offset {})", self.message, self.span.start)
+    }
+}
+
+/// Value types of the rules language
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Bool,
+    Int,
+    Bytes,
+    Ip,
+    Array(Box<Type>),
+    Map(Box<Type>),
+    /// Unknown field or function; already reported, so it is not checked further
+    Unknown,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Bool => write!(f, "Boolean"),
+            Type::Int => write!(f, "Integer"),
+            Type::Bytes => write!(f, "String"),
+            Type::Ip => write!(f, "IP address"),
+            Type::Array(t) => write!(f, "Array<{}>", t),
+            Type::Map(t) => write!(f, "Map<{}>", t),
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Logical operators, lowest precedence first
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogicalOp {
+    Or,
+    Xor,
+    And,
+}
+
+/// Comparison operators
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    Matches,
+    Wildcard,
+    StrictWildcard,
+    In,
+}
+
+impl fmt::Display for CompareOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            CompareOp::Eq => "eq",
+            CompareOp::Ne => "ne",
+            CompareOp::Lt => "lt",
+            CompareOp::Le => "le",
+            CompareOp::Gt => "gt",
+            CompareOp::Ge => "ge",
+            CompareOp::Contains => "contains",
+            CompareOp::Matches => "matches",
+            CompareOp::Wildcard => "wildcard",
+            CompareOp::StrictWildcard => "strict wildcard",
+            CompareOp::In => "in",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Literal values
+#[derive(Debug, Clone)]
+pub enum Literal {
+    Bytes(String),
+    Int(i64),
+    /// Address and prefix length
+    Ip(IpAddr, u8),
+}
+
+/// Items of a `{ ... }` set
+#[derive(Debug, Clone)]
+pub enum SetItem {
+    Literal(Literal),
+    IntRange(i64, i64),
+}
+
+/// Right-hand side of a comparison
+#[derive(Debug, Clone)]
+pub enum Rhs {
+    Literal(Literal),
+    Set(Vec<SetItem>),
+    /// Named list ($name)
+    List(String),
+}
+
+/// Index applied to a map or array
+#[derive(Debug, Clone)]
+pub enum Index {
+    Key(String),
+    Pos(i64),
+    /// `[*]` - unpack all elements
+    All,
+}
+
+/// Typed expression tree
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub span: Span,
+    pub ty: Type,
+    /// True when the value came through `[*]` and is evaluated per element
+    pub unpacked: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum NodeKind {
+    Field(String),
+    Literal(Literal),
+    Index(Box<Node>, Index),
+    Call(String, Vec<Node>),
+    Compare(Box<Node>, CompareOp, Rhs),
+    Not(Box<Node>),
+    Logical(LogicalOp, Box<Node>, Box<Node>),
+}
+
+impl Node {
+    /// Names of all fields referenced in the expression
+    pub fn fields(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        self.collect_fields(&mut out);
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    fn collect_fields(&self, out: &mut Vec<String>) {
+        match &self.kind {
+            NodeKind::Field(name) => out.push(name.clone()),
+            NodeKind::Literal(_) => {}
+            NodeKind::Index(base, _) => base.collect_fields(out),
+            NodeKind::Call(_, args) => args.iter().for_each(|a| a.collect_fields(out)),
+            NodeKind::Compare(lhs, _, _) => lhs.collect_fields(out),
+            NodeKind::Not(inner) => inner.collect_fields(out),
+            NodeKind::Logical(_, lhs, rhs) => {
+                lhs.collect_fields(out);
+                rhs.collect_fields(out);
+            }
+        }
+    }
+}
+
+/// Result of checking an expression: the tree plus collected problems
+#[derive(Debug)]
+pub struct Checked {
+    pub ast: Option<Node>,
+    pub errors: Vec<ExprError>,
+    pub warnings: Vec<ExprError>,
+}
+
+impl Checked {
+    pub fn is_ok(&self) -> bool {
+        self.ast.is_some() && self.errors.is_empty()
+    }
+}
+
+/// Parse an IP address or CIDR (`1.2.3.4`, `10.0.0.0/8`, `2001:db8::/32`)
+pub fn parse_cidr(s: &str) -> Option<(IpAddr, u8)> {
+    let (addr, prefix) = match s.split_once('/') {
+        Some((a, p)) => (a, Some(p)),
+        None => (s, None),
+    };
+
+    let ip: IpAddr = addr.parse().ok()?;
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        Some(p) => p.parse::<u8>().ok().filter(|p| *p <= max)?,
+        None => max,
+    };
+
+    Some((ip, prefix))
+}
+
+/// Check whether `ip` is inside the network `net/prefix`
+pub fn ip_in_cidr(ip: &IpAddr, net: &IpAddr, prefix: u8) -> bool {
+    match (ip, net) {
+        (IpAddr::V4(a), IpAddr::V4(b)) => {
+            let mask = if prefix == 0 {
+                0
+            } else {
+                u32::MAX << (32 - prefix as u32)
+            };
+            (u32::from(*a) & mask) == (u32::from(*b) & mask)
+        }
+        (IpAddr::V6(a), IpAddr::V6(b)) => {
+            let mask = if prefix == 0 {
+                0
+            } else {
+                u128::MAX << (128 - prefix as u32)
+            };
+            (u128::from(*a) & mask) == (u128::from(*b) & mask)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_errors_under_their_line() {
+        let src = "ssl and\n  http.hots eq \"a\"";
+        let err = parse(src).errors.remove(0);
+        assert_eq!(err.line_col(src), (2, 3));
+        assert_eq!(
+            err.render(src),
+            "2:3: unknown field 'http.hots' (did you mean 'http.host'?)\n    http.hots eq \"a\"\n    ^^^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn parses_and_matches_cidrs() {
+        let (net, prefix) = parse_cidr("10.0.0.0/8").unwrap();
+        assert_eq!(prefix, 8);
+        assert!(ip_in_cidr(&"10.200.0.1".parse().unwrap(), &net, prefix));
+        assert!(!ip_in_cidr(&"11.0.0.1".parse().unwrap(), &net, prefix));
+        assert_eq!(parse_cidr("2001:db8::1").map(|(_, p)| p), Some(128));
+        assert!(parse_cidr("10.0.0.0/33").is_none());
+        assert!(parse_cidr("example.com").is_none());
+    }
+}
@@ -0,0 +1,1066 @@
+//! Recursive descent parser with type checking and lints
+
+use super::lexer::{tokenize, Tok, Token};
+use super::schema::{self, Param, Ret};
+use super::{
+    parse_cidr, Checked, CompareOp, ExprError, Index, Literal, LogicalOp, Node, NodeKind, Rhs,
+    SetItem, Span, Type,
+};
+
+/// Parse and type-check an expression
+///
+/// Syntax errors stop parsing; unknown fields and type errors are collected
+/// so a single run reports as many problems as possible.
+pub fn parse(src: &str) -> Checked {
+    let tokens = match tokenize(src) {
+        Ok(t) => t,
+        Err(e) => {
+            return Checked {
+                ast: None,
+                errors: vec![e],
+                warnings: Vec::new(),
+            }
+        }
+    };
+
+    let mut parser = Parser {
+        src_len: src.len(),
+        tokens,
+        pos: 0,
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    let ast = parser.parse_root();
+    match ast {
+        Ok(node) => Checked {
+            ast: Some(node),
+            errors: parser.errors,
+            warnings: parser.warnings,
+        },
+        Err(e) => {
+            parser.errors.push(e);
+            parser.errors.sort_by_key(|e| e.span.start);
+            Checked {
+                ast: None,
+                errors: parser.errors,
+                warnings: parser.warnings,
+            }
+        }
+    }
+}
+
+struct Parser {
+    src_len: usize,
+    tokens: Vec<Token>,
+    pos: usize,
+    errors: Vec<ExprError>,
+    warnings: Vec<ExprError>,
+}
+
+type PResult<T> = Result<T, ExprError>;
+
+impl Parser {
+    fn parse_root(&mut self) -> PResult<Node> {
+        if self.tokens.is_empty() {
+            return Err(ExprError::new(Span::new(0, 0), "empty expression"));
+        }
+
+        let node = self.parse_or()?;
+
+        if let Some(tok) = self.peek() {
+            let span = tok.span;
+            let msg = match &tok.tok {
+                Tok::Ident(w) => format!(
+                    "unexpected '{}' (expected 'and', 'or', 'xor' or end of expression)",
+                    w
+                ),
+                Tok::Sym(")") => "unmatched ')'".to_string(),
+                _ => "unexpected token (expected 'and', 'or', 'xor' or end of expression)"
+                    .to_string(),
+            };
+            return Err(ExprError::new(span, msg));
+        }
+
+        self.expect_bool(&node);
+        Ok(node)
+    }
+
+    // ---- token helpers ----
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_tok(&self) -> Option<&Tok> {
+        self.peek().map(|t| &t.tok)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        if tok.is_some() {
+            self.pos += 1;
+        }
+        tok
+    }
+
+    fn end_span(&self) -> Span {
+        Span::new(self.src_len, self.src_len)
+    }
+
+    fn at_sym(&self, sym: &str) -> bool {
+        matches!(self.peek_tok(), Some(Tok::Sym(s)) if *s == sym)
+    }
+
+    fn at_word(&self, word: &str) -> bool {
+        matches!(self.peek_tok(), Some(Tok::Ident(w)) if w == word)
+    }
+
+    fn expect_sym(&mut self, sym: &str, context: &str) -> PResult<Span> {
+        match self.next() {
+            Some(Token {
+                tok: Tok::Sym(s),
+                span,
+            }) if s == sym => Ok(span),
+            Some(t) => Err(ExprError::new(
+                t.span,
+                format!("expected '{}' {}", sym, context),
+            )),
+            None => Err(ExprError::new(
+                self.end_span(),
+                format!("expected '{}' {}, found end of expression", sym, context),
+            )),
+        }
+    }
+
+    // ---- logical operators ----
+
+    fn logical_op(&self, op: LogicalOp) -> bool {
+        match op {
+            LogicalOp::Or => self.at_word("or") || self.at_sym("||"),
+            LogicalOp::Xor => self.at_word("xor") || self.at_sym("^^"),
+            LogicalOp::And => self.at_word("and") || self.at_sym("&&"),
+        }
+    }
+
+    fn parse_or(&mut self) -> PResult<Node> {
+        self.parse_logical(LogicalOp::Or)
+    }
+
+    fn parse_logical(&mut self, op: LogicalOp) -> PResult<Node> {
+        let mut lhs = match op {
+            LogicalOp::Or => self.parse_logical(LogicalOp::Xor)?,
+            LogicalOp::Xor => self.parse_logical(LogicalOp::And)?,
+            LogicalOp::And => self.parse_not()?,
+        };
+
+        while self.logical_op(op) {
+            self.next();
+            let rhs = match op {
+                LogicalOp::Or => self.parse_logical(LogicalOp::Xor)?,
+                LogicalOp::Xor => self.parse_logical(LogicalOp::And)?,
+                LogicalOp::And => self.parse_not()?,
+            };
+            self.expect_bool(&lhs);
+            self.expect_bool(&rhs);
+            let span = lhs.span.to(rhs.span);
+            lhs = Node {
+                kind: NodeKind::Logical(op, Box::new(lhs), Box::new(rhs)),
+                span,
+                ty: Type::Bool,
+                unpacked: false,
+            };
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_not(&mut self) -> PResult<Node> {
+        if self.at_word("not") || self.at_sym("!") {
+            let start = self.next().map(|t| t.span).unwrap_or(self.end_span());
+            let inner = self.parse_not()?;
+            self.expect_bool(&inner);
+            let span = start.to(inner.span);
+            return Ok(Node {
+                kind: NodeKind::Not(Box::new(inner)),
+                span,
+                ty: Type::Bool,
+                unpacked: false,
+            });
+        }
+
+        self.parse_comparison()
+    }
+
+    /// Report a non-boolean operand of a logical operator
+    fn expect_bool(&mut self, node: &Node) {
+        if (node.ty == Type::Bool && !node.unpacked) || node.ty == Type::Unknown {
+            return;
+        }
+
+        let msg = if node.ty == Type::Array(Box::new(Type::Bool)) || node.unpacked {
+            "comparison on [*] values yields an array; wrap it in any(...) or all(...)".to_string()
+        } else {
+            format!(
+                "expected a Boolean expression, found {} (missing comparison operator?)",
+                node.ty
+            )
+        };
+        self.errors.push(ExprError::new(node.span, msg));
+    }
+
+    // ---- comparisons ----
+
+    fn parse_comparison(&mut self) -> PResult<Node> {
+        if self.at_sym("(") {
+            let open = self.next().map(|t| t.span).unwrap_or(self.end_span());
+            let inner = self.parse_or()?;
+            if !self.at_sym(")") {
+                let span = self.peek().map(|t| t.span).unwrap_or(self.end_span());
+                return Err(ExprError::new(
+                    span,
+                    format!("expected ')' to close '(' at offset {}", open.start),
+                ));
+            }
+            let close = self.next().map(|t| t.span).unwrap_or(self.end_span());
+            return Ok(Node {
+                span: open.to(close),
+                ..inner
+            });
+        }
+
+        let value = self.parse_value()?;
+
+        match self.parse_compare_op()? {
+            Some(op) => self.parse_rhs(value, op),
+            None => Ok(value),
+        }
+    }
+
+    fn parse_compare_op(&mut self) -> PResult<Option<CompareOp>> {
+        let op = match self.peek_tok() {
+            Some(Tok::Sym("==")) => CompareOp::Eq,
+            Some(Tok::Sym("!=")) => CompareOp::Ne,
+            Some(Tok::Sym("<")) => CompareOp::Lt,
+            Some(Tok::Sym("<=")) => CompareOp::Le,
+            Some(Tok::Sym(">")) => CompareOp::Gt,
+            Some(Tok::Sym(">=")) => CompareOp::Ge,
+            Some(Tok::Sym("~")) => CompareOp::Matches,
+            Some(Tok::Ident(w)) => match w.as_str() {
+                "eq" => CompareOp::Eq,
+                "ne" => CompareOp::Ne,
+                "lt" => CompareOp::Lt,
+                "le" => CompareOp::Le,
+                "gt" => CompareOp::Gt,
+                "ge" => CompareOp::Ge,
+                "contains" => CompareOp::Contains,
+                "matches" => CompareOp::Matches,
+                "wildcard" => CompareOp::Wildcard,
+                "in" => CompareOp::In,
+                "strict" => {
+                    let span = self.next().map(|t| t.span).unwrap_or(self.end_span());
+                    if !self.at_word("wildcard") {
+                        return Err(ExprError::new(span, "expected 'wildcard' after 'strict'"));
+                    }
+                    CompareOp::StrictWildcard
+                }
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+
+        self.next();
+        Ok(Some(op))
+    }
+
+    fn parse_rhs(&mut self, lhs: Node, op: CompareOp) -> PResult<Node> {
+        let (rhs, rhs_span) = if op == CompareOp::In {
+            self.parse_set()?
+        } else {
+            let (lit, span) = self.parse_literal(&format!("after '{}'", op))?;
+            (Rhs::Literal(lit), span)
+        };
+
+        self.check_comparison(&lhs, op, &rhs, rhs_span);
+
+        let ty = if lhs.unpacked {
+            Type::Array(Box::new(Type::Bool))
+        } else {
+            Type::Bool
+        };
+        let span = lhs.span.to(rhs_span);
+
+        Ok(Node {
+            kind: NodeKind::Compare(Box::new(lhs), op, rhs),
+            span,
+            ty,
+            unpacked: false,
+        })
+    }
+
+    fn parse_literal(&mut self, context: &str) -> PResult<(Literal, Span)> {
+        match self.next() {
+            Some(Token {
+                tok: Tok::Str(s),
+                span,
+            }) => Ok((Literal::Bytes(s), span)),
+            Some(Token {
+                tok: Tok::Int(i),
+                span,
+            }) => Ok((Literal::Int(i), span)),
+            Some(Token {
+                tok: Tok::Ip(ip),
+                span,
+            }) => {
+                let (addr, prefix) = parse_cidr(&ip)
+                    .ok_or_else(|| ExprError::new(span, format!("invalid IP '{}'", ip)))?;
+                Ok((Literal::Ip(addr, prefix), span))
+            }
+            Some(Token {
+                tok: Tok::Sym("{"),
+                span,
+            }) => Err(ExprError::new(
+                span,
+                format!("sets are only allowed with 'in', not {}", context),
+            )),
+            Some(Token {
+                tok: Tok::Ident(w),
+                span,
+            }) => {
+                let hint = if schema::field(&w).is_some() {
+                    " (comparing two fields is not supported)"
+                } else {
+                    " (strings must be quoted)"
+                };
+                Err(ExprError::new(
+                    span,
+                    format!("expected a value {}, found '{}'{}", context, w, hint),
+                ))
+            }
+            Some(t) => Err(ExprError::new(
+                t.span,
+                format!("expected a value {}", context),
+            )),
+            None => Err(ExprError::new(
+                self.end_span(),
+                format!("expected a value {}, found end of expression", context),
+            )),
+        }
+    }
+
+    fn parse_set(&mut self) -> PResult<(Rhs, Span)> {
+        match self.peek().cloned() {
+            Some(Token {
+                tok: Tok::List(name),
+                span,
+            }) => {
+                self.next();
+                return Ok((Rhs::List(name), span));
+            }
+            Some(Token {
+                tok: Tok::Sym("{"), ..
+            }) => {}
+            Some(t) => return Err(ExprError::new(t.span, "expected '{' or $list after 'in'")),
+            None => {
+                return Err(ExprError::new(
+                    self.end_span(),
+                    "expected '{' or $list after 'in', found end of expression",
+                ))
+            }
+        }
+
+        let open = self.expect_sym("{", "to start set")?;
+        let mut items = Vec::new();
+
+        loop {
+            if self.at_sym("}") {
+                let close = self.next().map(|t| t.span).unwrap_or(self.end_span());
+                if items.is_empty() {
+                    self.errors
+                        .push(ExprError::new(open.to(close), "set must not be empty"));
+                }
+                return Ok((Rhs::Set(items), open.to(close)));
+            }
+            if self.at_sym(",") {
+                let span = self.next().map(|t| t.span).unwrap_or(self.end_span());
+                return Err(ExprError::new(
+                    span,
+                    "set items are separated by spaces, not commas",
+                ));
+            }
+            if self.peek().is_none() {
+                return Err(ExprError::new(
+                    self.end_span(),
+                    format!("expected '}}' to close set opened at offset {}", open.start),
+                ));
+            }
+
+            let (lit, span) = self.parse_literal("in set")?;
+            if self.at_sym("..") {
+                self.next();
+                let (end, end_span) = self.parse_literal("after '..'")?;
+                match (lit, end) {
+                    (Literal::Int(a), Literal::Int(b)) => {
+                        if a > b {
+                            self.errors.push(ExprError::new(
+                                span.to(end_span),
+                                "range start is greater than range end",
+                            ));
+                        }
+                        items.push(SetItem::IntRange(a, b));
+                    }
+                    _ => {
+                        return Err(ExprError::new(
+                            span.to(end_span),
+                            "ranges are only supported for integers",
+                        ))
+                    }
+                }
+            } else {
+                items.push(SetItem::Literal(lit));
+            }
+        }
+    }
+
+    // ---- values: fields, function calls, indexes ----
+
+    fn parse_value(&mut self) -> PResult<Node> {
+        let token = match self.next() {
+            Some(t) => t,
+            None => {
+                return Err(ExprError::new(
+                    self.end_span(),
+                    "expected a field, function or '(' but found end of expression",
+                ))
+            }
+        };
+
+        let mut node = match token.tok {
+            Tok::Ident(name) if self.at_sym("(") => self.parse_call(name, token.span)?,
+            Tok::Ident(name) => self.field_node(name, token.span),
+            Tok::Str(_) | Tok::Int(_) | Tok::Ip(_) => {
+                return Err(ExprError::new(
+                    token.span,
+                    "expected a field or function; values go on the right side of an operator",
+                ))
+            }
+            _ => {
+                return Err(ExprError::new(
+                    token.span,
+                    "expected a field, function or '('",
+                ))
+            }
+        };
+
+        while self.at_sym("[") {
+            node = self.parse_index(node)?;
+        }
+
+        Ok(node)
+    }
+
+    fn field_node(&mut self, name: String, span: Span) -> Node {
+        let ty = match schema::field(&name) {
+            Some(field) => {
+                if let Some(new) = field.replaced_by {
+                    self.warnings.push(ExprError::new(
+                        span,
+                        format!("field '{}' is deprecated, use '{}'", name, new),
+                    ));
+                }
+                field.ty.to_type()
+            }
+            None => {
+                let msg = match schema::suggest_field(&name) {
+                    Some(s) => format!("unknown field '{}' (did you mean '{}'?)", name, s),
+                    None => format!("unknown field '{}'", name),
+                };
+                self.errors.push(ExprError::new(span, msg));
+                Type::Unknown
+            }
+        };
+
+        Node {
+            kind: NodeKind::Field(name),
+            span,
+            ty,
+            unpacked: false,
+        }
+    }
+
+    fn parse_index(&mut self, base: Node) -> PResult<Node> {
+        self.expect_sym("[", "to start index")?;
+
+        let index = match self.next() {
+            Some(Token {
+                tok: Tok::Str(key), ..
+            }) => Index::Key(key),
+            Some(Token {
+                tok: Tok::Int(i), ..
+            }) => Index::Pos(i),
+            Some(Token {
+                tok: Tok::Sym("*"), ..
+            }) => Index::All,
+            Some(t) => {
+                return Err(ExprError::new(
+                    t.span,
+                    "expected a quoted key, an integer index or '*'",
+                ))
+            }
+            None => {
+                return Err(ExprError::new(
+                    self.end_span(),
+                    "expected index, found end of expression",
+                ))
+            }
+        };
+
+        let close = self.expect_sym("]", "to close index")?;
+        let span = base.span.to(close);
+
+        let (ty, unpacked) = match (&base.ty, &index) {
+            (Type::Map(inner), Index::Key(_)) => ((**inner).clone(), base.unpacked),
+            (Type::Array(inner), Index::Pos(i)) => {
+                if *i < 0 {
+                    self.errors
+                        .push(ExprError::new(span, "array index must not be negative"));
+                }
+                ((**inner).clone(), base.unpacked)
+            }
+            (Type::Map(inner), Index::All) | (Type::Array(inner), Index::All) => {
+                ((**inner).clone(), true)
+            }
+            (Type::Unknown, _) => (Type::Unknown, base.unpacked),
+            (Type::Map(_), Index::Pos(_)) => {
+                self.errors.push(ExprError::new(
+                    span,
+                    "maps are indexed by quoted keys, e.g. [\"name\"]",
+                ));
+                (Type::Bytes, base.unpacked)
+            }
+            (Type::Array(_), Index::Key(_)) => {
+                self.errors.push(ExprError::new(
+                    span,
+                    "arrays are indexed by integers, e.g. [0]",
+                ));
+                (Type::Bytes, base.unpacked)
+            }
+            (ty, _) => {
+                self.errors
+                    .push(ExprError::new(span, format!("cannot index a {}", ty)));
+                (ty.clone(), base.unpacked)
+            }
+        };
+
+        Ok(Node {
+            kind: NodeKind::Index(Box::new(base), index),
+            span,
+            ty,
+            unpacked,
+        })
+    }
+
+    fn parse_call(&mut self, name: String, name_span: Span) -> PResult<Node> {
+        self.expect_sym("(", "to start arguments")?;
+        let mut args = Vec::new();
+
+        if !self.at_sym(")") {
+            loop {
+                args.push(self.parse_arg()?);
+                if self.at_sym(",") {
+                    self.next();
+                    continue;
+                }
+                break;
+            }
+        }
+
+        let close = self.expect_sym(")", &format!("to close call to {}()", name))?;
+        let span = name_span.to(close);
+        let (ty, unpacked) = self.check_call(&name, name_span, &args);
+
+        Ok(Node {
+            kind: NodeKind::Call(name, args),
+            span,
+            ty,
+            unpacked,
+        })
+    }
+
+    fn parse_arg(&mut self) -> PResult<Node> {
+        match self.peek().cloned() {
+            Some(Token {
+                tok: Tok::Str(_) | Tok::Int(_) | Tok::Ip(_),
+                ..
+            }) => {
+                let (lit, span) = self.parse_literal("as argument")?;
+                let ty = match lit {
+                    Literal::Bytes(_) => Type::Bytes,
+                    Literal::Int(_) => Type::Int,
+                    Literal::Ip(..) => Type::Ip,
+                };
+                Ok(Node {
+                    kind: NodeKind::Literal(lit),
+                    span,
+                    ty,
+                    unpacked: false,
+                })
+            }
+            _ => {
+                // Arguments may be comparisons, e.g. any(x[*] eq "a")
+                let value = self.parse_value()?;
+                match self.parse_compare_op()? {
+                    Some(op) => self.parse_rhs(value, op),
+                    None => Ok(value),
+                }
+            }
+        }
+    }
+
+    fn check_call(&mut self, name: &str, span: Span, args: &[Node]) -> (Type, bool) {
+        let func = match schema::function(name) {
+            Some(f) => f,
+            None => {
+                let msg = if schema::field(name).is_some() {
+                    format!("'{}' is a field, not a function", name)
+                } else {
+                    format!("unknown function '{}'", name)
+                };
+                self.errors.push(ExprError::new(span, msg));
+                return (Type::Unknown, false);
+            }
+        };
+
+        let max = if func.variadic {
+            usize::MAX
+        } else {
+            func.params.len()
+        };
+        if args.len() < func.required || args.len() > max {
+            let expected = if func.variadic {
+                format!("at least {}", func.required)
+            } else if func.required == func.params.len() {
+                func.required.to_string()
+            } else {
+                format!("{} to {}", func.required, func.params.len())
+            };
+            self.errors.push(ExprError::new(
+                span,
+                format!(
+                    "{}() takes {} argument(s), {} given",
+                    name,
+                    expected,
+                    args.len()
+                ),
+            ));
+        }
+
+        let mut unpacked = false;
+        for (i, arg) in args.iter().enumerate() {
+            let param = func.params[i.min(func.params.len() - 1)];
+            if arg.unpacked {
+                if func.elementwise && i == 0 {
+                    unpacked = true;
+                } else {
+                    self.errors.push(ExprError::new(
+                        arg.span,
+                        format!("{}() does not accept [*] values here", name),
+                    ));
+                    continue;
+                }
+            }
+            if !param_accepts(param, &arg.ty) {
+                self.errors.push(ExprError::new(
+                    arg.span,
+                    format!(
+                        "argument {} of {}() must be {}, found {}",
+                        i + 1,
+                        name,
+                        param_name(param),
+                        arg.ty
+                    ),
+                ));
+            }
+        }
+
+        let ty = match func.ret {
+            Ret::Bool => Type::Bool,
+            Ret::Int => Type::Int,
+            Ret::Bytes => Type::Bytes,
+            Ret::Ip => Type::Ip,
+            Ret::First => args.first().map(|a| a.ty.clone()).unwrap_or(Type::Bytes),
+        };
+
+        (ty, unpacked)
+    }
+
+    fn check_comparison(&mut self, lhs: &Node, op: CompareOp, rhs: &Rhs, rhs_span: Span) {
+        let span = lhs.span.to(rhs_span);
+
+        let allowed: &[CompareOp] = match lhs.ty {
+            Type::Bytes => &[
+                CompareOp::Eq,
+                CompareOp::Ne,
+                CompareOp::Lt,
+                CompareOp::Le,
+                CompareOp::Gt,
+                CompareOp::Ge,
+                CompareOp::Contains,
+                CompareOp::Matches,
+                CompareOp::Wildcard,
+                CompareOp::StrictWildcard,
+                CompareOp::In,
+            ],
+            Type::Int => &[
+                CompareOp::Eq,
+                CompareOp::Ne,
+                CompareOp::Lt,
+                CompareOp::Le,
+                CompareOp::Gt,
+                CompareOp::Ge,
+                CompareOp::In,
+            ],
+            Type::Ip => &[CompareOp::Eq, CompareOp::Ne, CompareOp::In],
+            Type::Bool => {
+                self.errors.push(ExprError::new(
+                    span,
+                    "Boolean values cannot be compared; use the field on its own or with 'not'",
+                ));
+                return;
+            }
+            // Already reported where the field or function was named
+            Type::Unknown => return,
+            Type::Array(_) | Type::Map(_) => {
+                self.errors.push(ExprError::new(
+                    lhs.span,
+                    format!(
+                        "cannot compare a {}; index it ([0], [\"key\"], [*]) or use a function",
+                        lhs.ty
+                    ),
+                ));
+                return;
+            }
+        };
+
+        if !allowed.contains(&op) {
+            self.errors.push(ExprError::new(
+                span,
+                format!("operator '{}' is not supported for {} values", op, lhs.ty),
+            ));
+            return;
+        }
+
+        match rhs {
+            Rhs::Literal(lit) => self.check_literal(&lhs.ty, op, lit, rhs_span),
+            Rhs::Set(items) => {
+                for item in items {
+                    match item {
+                        SetItem::Literal(lit) => {
+                            self.check_literal(&lhs.ty, CompareOp::In, lit, rhs_span)
+                        }
+                        SetItem::IntRange(..) if lhs.ty != Type::Int => {
+                            self.errors.push(ExprError::new(
+                                rhs_span,
+                                format!("integer range in a set of {} values", lhs.ty),
+                            ));
+                        }
+                        SetItem::IntRange(..) => {}
+                    }
+                }
+            }
+            Rhs::List(_) => {
+                if lhs.ty == Type::Int {
+                    self.errors.push(ExprError::new(
+                        rhs_span,
+                        "lists can only be used with IP, hostname or string fields",
+                    ));
+                }
+            }
+        }
+
+        self.lint_comparison(lhs, op, rhs, rhs_span);
+    }
+
+    fn check_literal(&mut self, ty: &Type, op: CompareOp, lit: &Literal, span: Span) {
+        match (ty, lit) {
+            (Type::Bytes, Literal::Bytes(s)) => {
+                if op == CompareOp::Matches {
+                    if let Err(e) = regex::Regex::new(s) {
+                        let msg = e.to_string();
+                        let reason = msg
+                            .lines()
+                            .last()
+                            .unwrap_or("invalid regex")
+                            .trim()
+                            .trim_start_matches("error: ");
+                        self.errors.push(ExprError::new(
+                            span,
+                            format!("invalid regular expression: {}", reason),
+                        ));
+                    }
+                }
+            }
+            (Type::Int, Literal::Int(_)) => {}
+            (Type::Ip, Literal::Ip(addr, prefix)) => {
+                let full = if addr.is_ipv4() { 32 } else { 128 };
+                if *prefix != full && op != CompareOp::In {
+                    self.errors.push(ExprError::new(
+                        span,
+                        "CIDR ranges need 'in {...}', e.g. ip.src in {10.0.0.0/8}",
+                    ));
+                }
+            }
+            (Type::Bytes, Literal::Int(_)) => self.errors.push(ExprError::new(
+                span,
+                "expected a quoted string, found an integer",
+            )),
+            (Type::Int, Literal::Bytes(_)) => self
+                .errors
+                .push(ExprError::new(span, "expected an integer, found a string")),
+            (_, lit) => {
+                let found = match lit {
+                    Literal::Bytes(_) => "a string",
+                    Literal::Int(_) => "an integer",
+                    Literal::Ip(..) => "an IP address",
+                };
+                self.errors.push(ExprError::new(
+                    span,
+                    format!("expected {} value, found {}", ty, found),
+                ));
+            }
+        }
+    }
+
+    /// Warnings for expressions that are valid but probably not intended
+    fn lint_comparison(&mut self, lhs: &Node, op: CompareOp, rhs: &Rhs, span: Span) {
+        let field = match &lhs.kind {
+            NodeKind::Field(name) => name.as_str(),
+            _ => return,
+        };
+
+        let values: Vec<&str> = match rhs {
+            Rhs::Literal(Literal::Bytes(s)) => vec![s.as_str()],
+            Rhs::Set(items) => items
+                .iter()
+                .filter_map(|i| match i {
+                    SetItem::Literal(Literal::Bytes(s)) => Some(s.as_str()),
+                    _ => None,
+                })
+                .collect(),
+            _ => return,
+        };
+
+        // Case-insensitive operators can't miss on letter case
+        let case_sensitive = !matches!(op, CompareOp::Wildcard | CompareOp::Matches);
+
+        for value in values {
+            let warning = match field {
+                "http.request.method" if case_sensitive && value != value.to_uppercase() => {
+                    Some("HTTP methods are uppercase; this will never match")
+                }
+                "ip.src.country" | "ip.geoip.country" | "ip.src.continent"
+                | "ip.geoip.continent"
+                    if case_sensitive && value != value.to_uppercase() =>
+                {
+                    Some("country and continent codes are uppercase; this will never match")
+                }
+                "http.host" if case_sensitive && value != value.to_lowercase() => {
+                    Some("hostnames are lowercase; this will never match")
+                }
+                "http.request.uri.path" | "raw.http.request.uri.path"
+                    if op == CompareOp::Eq && value.contains('?') =>
+                {
+                    Some("the path does not include the query string; use http.request.uri")
+                }
+                _ if op == CompareOp::Contains && value.is_empty() => {
+                    Some("'contains \"\"' is always true")
+                }
+                _ => None,
+            };
+
+            if let Some(msg) = warning {
+                self.warnings.push(ExprError::new(span, msg));
+            }
+        }
+    }
+}
+
+fn param_accepts(param: Param, ty: &Type) -> bool {
+    if *ty == Type::Unknown {
+        return true;
+    }
+    match param {
+        Param::Bytes => *ty == Type::Bytes,
+        Param::Int => *ty == Type::Int,
+        Param::Ip => *ty == Type::Ip,
+        Param::BoolArray => *ty == Type::Array(Box::new(Type::Bool)),
+        Param::Collection => matches!(ty, Type::Array(_) | Type::Map(_)),
+        Param::BytesOrArray => matches!(ty, Type::Bytes | Type::Array(_)),
+        Param::Scalar => matches!(ty, Type::Bytes | Type::Int | Type::Ip | Type::Bool),
+    }
+}
+
+fn param_name(param: Param) -> &'static str {
+    match param {
+        Param::Bytes => "a String",
+        Param::Int => "an Integer",
+        Param::Ip => "an IP address",
+        Param::BoolArray => "an Array<Boolean> (a comparison on [*] values)",
+        Param::Collection => "an Array or Map",
+        Param::BytesOrArray => "a String or Array",
+        Param::Scalar => "a String, Integer, IP or Boolean",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid(src: &str) -> Node {
+        let checked = parse(src);
+        assert!(checked.is_ok(), "{}: {:?}", src, checked.errors);
+        checked.ast.unwrap()
+    }
+
+    /// Messages and start offsets of all errors
+    fn errors(src: &str) -> Vec<(String, usize)> {
+        parse(src)
+            .errors
+            .into_iter()
+            .map(|e| (e.message, e.span.start))
+            .collect()
+    }
+
+    fn error(message: &str, at: usize) -> Vec<(String, usize)> {
+        vec![(message.to_string(), at)]
+    }
+
+    #[test]
+    fn accepts_valid_expressions() {
+        for src in [
+            r#"http.host eq "example.com" and not ssl"#,
+            r#"(http.request.uri.path matches r"^/api/v\d+/" or http.request.method in {"PUT" "DELETE"})"#,
+            r#"any(lower(http.request.headers["x-api-key"][*]) contains "test")"#,
+            "ip.src in {10.0.0.0/8 192.168.1.1 2001:db8::/32}",
+            "tcp.dstport in {80 443 8000..8100} && cf.bot_management.score lt 30",
+            "ip.src in $blocked xor ssl",
+            r#"len(http.request.body.raw) gt 0 and starts_with(http.request.uri.path, "/admin")"#,
+        ] {
+            valid(src);
+        }
+    }
+
+    #[test]
+    fn binds_and_tighter_than_or() {
+        let node = valid("ssl or ssl and not ssl");
+        let NodeKind::Logical(LogicalOp::Or, _, rhs) = node.kind else {
+            panic!("expected 'or' at the top: {:?}", node.kind);
+        };
+        assert!(matches!(rhs.kind, NodeKind::Logical(LogicalOp::And, _, _)));
+        assert_eq!(node.span, Span::new(0, 22));
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        assert_eq!(errors(""), error("empty expression", 0));
+        assert_eq!(
+            errors(r#"(http.host eq "a""#),
+            error("expected ')' to close '(' at offset 0", 17)
+        );
+        assert_eq!(errors(r#"http.host eq "a")"#), error("unmatched ')'", 16));
+        assert_eq!(
+            errors(r#"http.host eq "a" ssl"#),
+            error(
+                "unexpected 'ssl' (expected 'and', 'or', 'xor' or end of expression)",
+                17
+            )
+        );
+        assert_eq!(
+            errors("http.host strict contains"),
+            error("expected 'wildcard' after 'strict'", 10)
+        );
+    }
+
+    #[test]
+    fn reports_type_errors() {
+        assert_eq!(
+            errors("http.host eq 1"),
+            error("expected a quoted string, found an integer", 13)
+        );
+        assert_eq!(
+            errors(r#"ip.src contains "1.2""#),
+            error(
+                "operator 'contains' is not supported for IP address values",
+                0
+            )
+        );
+        assert_eq!(
+            errors("ip.src eq 10.0.0.0/8"),
+            error(
+                "CIDR ranges need 'in {...}', e.g. ip.src in {10.0.0.0/8}",
+                10
+            )
+        );
+        assert_eq!(
+            errors("http.host"),
+            error(
+                "expected a Boolean expression, found String (missing comparison operator?)",
+                0
+            )
+        );
+        assert_eq!(
+            errors(r#"http.request.headers["a"][*] eq "b""#),
+            error(
+                "comparison on [*] values yields an array; wrap it in any(...) or all(...)",
+                0
+            )
+        );
+        assert_eq!(
+            errors("len() eq 1"),
+            error("len() takes 1 argument(s), 0 given", 0)
+        );
+        let regex = errors(r#"http.request.uri.path matches "(""#);
+        assert_eq!(regex.len(), 1);
+        assert_eq!(regex[0].1, 30);
+        assert!(regex[0].0.starts_with("invalid regular expression:"));
+    }
+
+    #[test]
+    fn reports_unknown_names_once() {
+        assert_eq!(
+            errors(r#"http.hots eq "example.com""#),
+            error("unknown field 'http.hots' (did you mean 'http.host'?)", 0)
+        );
+        // The operand type of an unknown field is not checked
+        assert_eq!(errors("foo.bar eq 1"), error("unknown field 'foo.bar'", 0));
+        assert_eq!(
+            errors(r#"lower(foo.bar) contains "a" and foo.bar"#),
+            vec![
+                ("unknown field 'foo.bar'".to_string(), 6),
+                ("unknown field 'foo.bar'".to_string(), 32),
+            ]
+        );
+        assert_eq!(
+            errors("nope(ssl) eq 1"),
+            error("unknown function 'nope'", 0)
+        );
+    }
+
+    #[test]
+    fn warns_about_likely_mistakes() {
+        let checked = parse(r#"http.request.method eq "post" and http.host contains """#);
+        assert!(checked.is_ok());
+        let warnings: Vec<&str> = checked
+            .warnings
+            .iter()
+            .map(|w| w.message.as_str())
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                "HTTP methods are uppercase; this will never match",
+                "'contains \"\"' is always true"
+            ]
+        );
+    }
+}
@@ -0,0 +1,505 @@
+//! Known fields and functions of the Cloudflare Rules language
+
+use super::Type;
+
+/// Field type shorthand used in the table below
+#[derive(Debug, Clone, Copy)]
+pub enum FieldType {
+    Bool,
+    Int,
+    Bytes,
+    Ip,
+    BytesArray,
+    IntArray,
+    BytesMap,
+}
+
+impl FieldType {
+    pub fn to_type(self) -> Type {
+        match self {
+            FieldType::Bool => Type::Bool,
+            FieldType::Int => Type::Int,
+            FieldType::Bytes => Type::Bytes,
+            FieldType::Ip => Type::Ip,
+            FieldType::BytesArray => Type::Array(Box::new(Type::Bytes)),
+            FieldType::IntArray => Type::Array(Box::new(Type::Int)),
+            FieldType::BytesMap => Type::Map(Box::new(Type::Array(Box::new(Type::Bytes)))),
+        }
+    }
+}
+
+/// Field definition
+pub struct Field {
+    pub name: &'static str,
+    pub ty: FieldType,
+    /// Replacement for deprecated fields
+    pub replaced_by: Option<&'static str>,
+}
+
+const fn f(name: &'static str, ty: FieldType) -> Field {
+    Field {
+        name,
+        ty,
+        replaced_by: None,
+    }
+}
+
+const fn deprecated(name: &'static str, ty: FieldType, replaced_by: &'static str) -> Field {
+    Field {
+        name,
+        ty,
+        replaced_by: Some(replaced_by),
+    }
+}
+
+use FieldType::*;
+
+/// All known fields
+pub const FIELDS: &[Field] = &[
+    // Request
+    f("http.cookie", Bytes),
+    f("http.host", Bytes),
+    f("http.referer", Bytes),
+    f("http.user_agent", Bytes),
+    f("http.x_forwarded_for", Bytes),
+    f("http.request.full_uri", Bytes),
+    f("http.request.method", Bytes),
+    f("http.request.uri", Bytes),
+    f("http.request.uri.path", Bytes),
+    f("http.request.uri.path.extension", Bytes),
+    f("http.request.uri.query", Bytes),
+    f("http.request.version", Bytes),
+    f("http.request.timestamp.sec", Int),
+    f("http.request.timestamp.msec", Int),
+    f("http.request.uri.args", BytesMap),
+    f("http.request.uri.args.names", BytesArray),
+    f("http.request.uri.args.values", BytesArray),
+    f("http.request.headers", BytesMap),
+    f("http.request.headers.names", BytesArray),
+    f("http.request.headers.values", BytesArray),
+    f("http.request.headers.truncated", Bool),
+    f("http.request.cookies", BytesMap),
+    f("http.request.accepted_languages", BytesArray),
+    f("http.request.body.raw", Bytes),
+    f("http.request.body.size", Int),
+    f("http.request.body.truncated", Bool),
+    f("http.request.body.mime", Bytes),
+    f("http.request.body.form", BytesMap),
+    f("http.request.body.form.names", BytesArray),
+    f("http.request.body.form.values", BytesArray),
+    f("raw.http.request.full_uri", Bytes),
+    f("raw.http.request.uri", Bytes),
+    f("raw.http.request.uri.path", Bytes),
+    f("raw.http.request.uri.query", Bytes),
+    f("raw.http.request.uri.args", BytesMap),
+    // Response
+    f("http.response.code", Int),
+    f("http.response.headers", BytesMap),
+    f("http.response.headers.names", BytesArray),
+    f("http.response.headers.values", BytesArray),
+    f("http.response.content_type.media_type", Bytes),
+    f("cf.response.1xxx_code", Int),
+    f("cf.response.error_type", Bytes),
+    // Connection and geolocation
+    f("ip.src", Ip),
+    f("ip.src.asnum", Int),
+    f("ip.src.country", Bytes),
+    f("ip.src.continent", Bytes),
+    f("ip.src.city", Bytes),
+    f("ip.src.region", Bytes),
+    f("ip.src.region_code", Bytes),
+    f("ip.src.subdivision_1_iso_code", Bytes),
+    f("ip.src.subdivision_2_iso_code", Bytes),
+    f("ip.src.postal_code", Bytes),
+    f("ip.src.metro_code", Bytes),
+    f("ip.src.lat", Bytes),
+    f("ip.src.lon", Bytes),
+    f("ip.src.timezone.name", Bytes),
+    f("ip.src.is_in_european_union", Bool),
+    deprecated("ip.geoip.asnum", Int, "ip.src.asnum"),
+    deprecated("ip.geoip.country", Bytes, "ip.src.country"),
+    deprecated("ip.geoip.continent", Bytes, "ip.src.continent"),
+    deprecated(
+        "ip.geoip.is_in_european_union",
+        Bool,
+        "ip.src.is_in_european_union",
+    ),
+    deprecated(
+        "ip.geoip.subdivision_1_iso_code",
+        Bytes,
+        "ip.src.subdivision_1_iso_code",
+    ),
+    deprecated(
+        "ip.geoip.subdivision_2_iso_code",
+        Bytes,
+        "ip.src.subdivision_2_iso_code",
+    ),
+    f("ssl", Bool),
+    f("tcp.dstport", Int),
+    f("cf.edge.server_ip", Ip),
+    f("cf.edge.server_port", Int),
+    f("cf.ray_id", Bytes),
+    f("cf.zone.name", Bytes),
+    f("cf.zone.plan", Bytes),
+    f("cf.metal.id", Int),
+    f("cf.hostname.metadata", Bytes),
+    f("cf.worker.upstream_zone", Bytes),
+    f("cf.random_seed", Bytes),
+    // Security
+    f("cf.threat_score", Int),
+    f("cf.client.bot", Bool),
+    f("cf.bot_management.score", Int),
+    f("cf.bot_management.verified_bot", Bool),
+    f("cf.bot_management.static_resource", Bool),
+    f("cf.bot_management.corporate_proxy", Bool),
+    f("cf.bot_management.ja3_hash", Bytes),
+    f("cf.bot_management.ja4", Bytes),
+    f("cf.bot_management.js_detection.passed", Bool),
+    f("cf.bot_management.detection_ids", IntArray),
+    f("cf.verified_bot_category", Bytes),
+    f("cf.waf.score", Int),
+    f("cf.waf.score.sqli", Int),
+    f("cf.waf.score.xss", Int),
+    f("cf.waf.score.rce", Int),
+    f("cf.waf.score.class", Bytes),
+    f("cf.waf.content_scan.has_obj", Bool),
+    f("cf.waf.content_scan.has_malicious_obj", Bool),
+    f("cf.waf.credential_check.password_leaked", Bool),
+    f("cf.tls_client_auth.cert_presented", Bool),
+    f("cf.tls_client_auth.cert_verified", Bool),
+    f("cf.tls_client_auth.cert_revoked", Bool),
+    f("cf.tls_client_auth.cert_fingerprint_sha256", Bytes),
+    f("cf.tls_client_auth.cert_subject_dn", Bytes),
+    f("cf.tls_client_auth.cert_issuer_dn", Bytes),
+    f("cf.tls_cipher", Bytes),
+    f("cf.tls_version", Bytes),
+    f("cf.api_gateway.auth_id_present", Bool),
+    f("cf.api_gateway.request_violates_schema", Bool),
+];
+
+/// Look up a field by name
+pub fn field(name: &str) -> Option<&'static Field> {
+    FIELDS.iter().find(|f| f.name == name)
+}
+
+/// Suggest the closest known field name for a typo
+pub fn suggest_field(name: &str) -> Option<&'static str> {
+    FIELDS
+        .iter()
+        .map(|f| (edit_distance(name, f.name), f.name))
+        .filter(|(d, _)| *d <= 3.max(name.len() / 5))
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, n)| n)
+}
+
+/// Argument kinds accepted by functions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Param {
+    Bytes,
+    Int,
+    Ip,
+    BoolArray,
+    /// Array or map of any element type
+    Collection,
+    /// String or array
+    BytesOrArray,
+    /// Any value that can be turned into a string
+    Scalar,
+}
+
+/// Return type of a function
+#[derive(Debug, Clone, Copy)]
+pub enum Ret {
+    Bool,
+    Int,
+    Bytes,
+    Ip,
+    /// Same type as the first argument
+    First,
+}
+
+/// Function definition
+pub struct Function {
+    pub name: &'static str,
+    pub params: &'static [Param],
+    /// Number of required parameters (the rest are optional)
+    pub required: usize,
+    /// Last parameter may repeat
+    pub variadic: bool,
+    pub ret: Ret,
+    /// Applies element-wise to `[*]` values
+    pub elementwise: bool,
+    pub description: &'static str,
+}
+
+/// All known functions
+pub const FUNCTIONS: &[Function] = &[
+    Function {
+        name: "any",
+        params: &[Param::BoolArray],
+        required: 1,
+        variadic: false,
+        ret: Ret::Bool,
+        elementwise: false,
+        description: "true if any element is true",
+    },
+    Function {
+        name: "all",
+        params: &[Param::BoolArray],
+        required: 1,
+        variadic: false,
+        ret: Ret::Bool,
+        elementwise: false,
+        description: "true if all elements are true",
+    },
+    Function {
+        name: "lower",
+        params: &[Param::Bytes],
+        required: 1,
+        variadic: false,
+        ret: Ret::Bytes,
+        elementwise: true,
+        description: "convert to lowercase",
+    },
+    Function {
+        name: "upper",
+        params: &[Param::Bytes],
+        required: 1,
+        variadic: false,
+        ret: Ret::Bytes,
+        elementwise: true,
+        description: "convert to uppercase",
+    },
+    Function {
+        name: "len",
+        params: &[Param::BytesOrArray],
+        required: 1,
+        variadic: false,
+        ret: Ret::Int,
+        elementwise: true,
+        description: "length of a string or array",
+    },
+    Function {
+        name: "starts_with",
+        params: &[Param::Bytes, Param::Bytes],
+        required: 2,
+        variadic: false,
+        ret: Ret::Bool,
+        elementwise: true,
+        description: "true if the string starts with the prefix",
+    },
+    Function {
+        name: "ends_with",
+        params: &[Param::Bytes, Param::Bytes],
+        required: 2,
+        variadic: false,
+        ret: Ret::Bool,
+        elementwise: true,
+        description: "true if the string ends with the suffix",
+    },
+    Function {
+        name: "concat",
+        params: &[Param::BytesOrArray, Param::BytesOrArray],
+        required: 1,
+        variadic: true,
+        ret: Ret::First,
+        elementwise: false,
+        description: "concatenate strings or arrays",
+    },
+    Function {
+        name: "url_decode",
+        params: &[Param::Bytes, Param::Bytes],
+        required: 1,
+        variadic: false,
+        ret: Ret::Bytes,
+        elementwise: true,
+        description: "decode a URL-encoded string",
+    },
+    Function {
+        name: "to_string",
+        params: &[Param::Scalar],
+        required: 1,
+        variadic: false,
+        ret: Ret::Bytes,
+        elementwise: true,
+        description: "convert a value to a string",
+    },
+    Function {
+        name: "remove_bytes",
+        params: &[Param::Bytes, Param::Bytes],
+        required: 2,
+        variadic: false,
+        ret: Ret::Bytes,
+        elementwise: true,
+        description: "remove all occurrences of the given bytes",
+    },
+    Function {
+        name: "substring",
+        params: &[Param::Bytes, Param::Int, Param::Int],
+        required: 2,
+        variadic: false,
+        ret: Ret::Bytes,
+        elementwise: true,
+        description: "part of a string between start and end index",
+    },
+    Function {
+        name: "lookup_json_string",
+        params: &[Param::Bytes, Param::Bytes],
+        required: 2,
+        variadic: true,
+        ret: Ret::Bytes,
+        elementwise: false,
+        description: "string value at a key path in a JSON document",
+    },
+    Function {
+        name: "lookup_json_integer",
+        params: &[Param::Bytes, Param::Bytes],
+        required: 2,
+        variadic: true,
+        ret: Ret::Int,
+        elementwise: false,
+        description: "integer value at a key path in a JSON document",
+    },
+    Function {
+        name: "regex_replace",
+        params: &[Param::Bytes, Param::Bytes, Param::Bytes],
+        required: 3,
+        variadic: false,
+        ret: Ret::Bytes,
+        elementwise: false,
+        description: "replace the first regex match",
+    },
+    Function {
+        name: "wildcard_replace",
+        params: &[Param::Bytes, Param::Bytes, Param::Bytes, Param::Bytes],
+        required: 3,
+        variadic: false,
+        ret: Ret::Bytes,
+        elementwise: false,
+        description: "replace using a wildcard pattern with ${N} captures",
+    },
+    Function {
+        name: "decode_base64",
+        params: &[Param::Bytes],
+        required: 1,
+        variadic: false,
+        ret: Ret::Bytes,
+        elementwise: false,
+        description: "decode a base64 string",
+    },
+    Function {
+        name: "cidr",
+        params: &[Param::Ip, Param::Int, Param::Int],
+        required: 3,
+        variadic: false,
+        ret: Ret::Ip,
+        elementwise: false,
+        description: "network address for IPv4/IPv6 prefix lengths",
+    },
+    Function {
+        name: "cidr6",
+        params: &[Param::Ip, Param::Int],
+        required: 2,
+        variadic: false,
+        ret: Ret::Ip,
+        elementwise: false,
+        description: "network address for an IPv6 prefix length",
+    },
+    Function {
+        name: "has_key",
+        params: &[Param::Collection, Param::Bytes],
+        required: 2,
+        variadic: false,
+        ret: Ret::Bool,
+        elementwise: false,
+        description: "true if the map contains the key",
+    },
+    Function {
+        name: "has_value",
+        params: &[Param::Collection, Param::Scalar],
+        required: 2,
+        variadic: false,
+        ret: Ret::Bool,
+        elementwise: false,
+        description: "true if the array or map contains the value",
+    },
+    Function {
+        name: "uuidv4",
+        params: &[Param::Bytes],
+        required: 1,
+        variadic: false,
+        ret: Ret::Bytes,
+        elementwise: false,
+        description: "random UUIDv4 from a seed",
+    },
+    Function {
+        name: "is_timed_hmac_valid_v0",
+        params: &[
+            Param::Bytes,
+            Param::Bytes,
+            Param::Int,
+            Param::Int,
+            Param::Int,
+            Param::Bytes,
+        ],
+        required: 4,
+        variadic: false,
+        ret: Ret::Bool,
+        elementwise: false,
+        description: "validate a timed HMAC token",
+    },
+];
+
+/// Look up a function by name
+pub fn function(name: &str) -> Option<&'static Function> {
+    FUNCTIONS.iter().find(|f| f.name == name)
+}
+
+/// Levenshtein distance, used for "did you mean" suggestions
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_names_are_unique_and_replacements_exist() {
+        for (i, field) in FIELDS.iter().enumerate() {
+            assert!(
+                FIELDS[..i].iter().all(|f| f.name != field.name),
+                "duplicate field {}",
+                field.name
+            );
+            if let Some(new) = field.replaced_by {
+                assert!(self::field(new).is_some(), "{} -> {}", field.name, new);
+            }
+        }
+        for (i, func) in FUNCTIONS.iter().enumerate() {
+            assert!(FUNCTIONS[..i].iter().all(|f| f.name != func.name));
+            assert!(func.required <= func.params.len() || func.variadic);
+        }
+    }
+
+    #[test]
+    fn suggests_close_field_names() {
+        assert_eq!(suggest_field("http.hots"), Some("http.host"));
+        assert_eq!(suggest_field("ip.src.contry"), Some("ip.src.country"));
+        assert_eq!(suggest_field("something.else.entirely"), None);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
@@ -5,17 +5,35 @@
 mod api;
 mod cli;
 mod config;
+mod expr;
 mod output;
+mod pki;
+
+use std::process::ExitCode;
 
 use anyhow::Result;
 use clap::Parser;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
-use crate::cli::{Cli, Commands};
+use crate::cli::{Cli, Commands, ExitStatus};
 use crate::config::Config;
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        // Checks report their outcome as an exit status; the details are already printed
+        Err(e) => match e.downcast_ref::<ExitStatus>() {
+            Some(status) => ExitCode::from(status.0),
+            None => {
+                eprintln!("Error: {:?}", e);
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
+
+async fn run() -> Result<()> {
     // Load .env file
     dotenvy::dotenv().ok();
 
@@ -35,6 +53,28 @@ async fn main() -> Result<()> {
             let config = Config::load_optional();
             cli::tunnel::execute(&config, args).await
         }
+        Commands::Completions(args) => cli::completions::execute(args),
+        // Offline tools (expression checks, cache probes, local records, settings, GraphQL schema and log files)
+        Commands::Settings(args) if args.command.is_offline() => {
+            let config = Config::load_optional();
+            cli::settings::execute(&config, args).await
+        }
+        Commands::Firewall(args) if args.command.is_offline() => {
+            let config = Config::load_optional();
+            cli::firewall::execute(&config, args).await
+        }
+        Commands::Cache(args) if args.command.is_offline() => {
+            let config = Config::load_optional();
+            cli::cache::execute(&config, args).await
+        }
+        Commands::Analytics(args) if args.command.is_offline() => {
+            let config = Config::load_optional();
+            cli::analytics::execute(&config, args).await
+        }
+        Commands::Logs(args) if args.command.is_offline() => {
+            let config = Config::load_optional();
+            cli::logs::execute(&config, args).await
+        }
         // All other commands require authentication
         _ => {
             let config = Config::load()?;
@@ -43,14 +83,17 @@ async fn main() -> Result<()> {
                 Commands::Dns(args) => cli::dns::execute(&config, args).await,
                 Commands::Settings(args) => cli::settings::execute(&config, args).await,
                 Commands::Firewall(args) => cli::firewall::execute(&config, args).await,
+                Commands::Lists(args) => cli::lists::execute(&config, args).await,
+                Commands::Rulesets(args) => cli::rulesets::execute(&config, args).await,
                 Commands::Cache(args) => cli::cache::execute(&config, args).await,
                 Commands::Ssl(args) => cli::ssl::execute(&config, args).await,
                 Commands::Analytics(args) => cli::analytics::execute(&config, args).await,
+                Commands::Logs(args) => cli::logs::execute(&config, args).await,
                 Commands::Workers(args) => cli::workers::execute(&config, args).await,
                 Commands::Pages(args) => cli::pages::execute(&config, args).await,
                 Commands::Ai(args) => cli::ai::execute(&config, args).await,
                 Commands::Storage(args) => cli::storage::execute(&config, args).await,
-                Commands::Tunnel(_) => unreachable!(), // Handled above
+                Commands::Tunnel(_) | Commands::Completions(_) => unreachable!(), // Handled above
                 Commands::Raw(args) => cli::raw::execute(&config, args).await,
                 Commands::Config(args) => cli::config_cmd::execute(&config, args).await,
             }
@@ -0,0 +1,73 @@
+//! Terminal charts: sparklines and horizontal bars, scaled to the largest value
+
+const TICKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// One character per value, from ▁ (zero) to █ (the largest value)
+pub fn sparkline(values: &[f64]) -> String {
+    let max = values.iter().cloned().fold(0.0, f64::max);
+    values
+        .iter()
+        .map(|&value| {
+            if max <= 0.0 || !value.is_finite() {
+                return TICKS[0];
+            }
+            let level = (value.max(0.0) / max * (TICKS.len() - 1) as f64).round() as usize;
+            TICKS[level.min(TICKS.len() - 1)]
+        })
+        .collect()
+}
+
+/// Fit a series into `width` points by averaging neighbours (shorter series are kept as is)
+pub fn resample(values: &[f64], width: usize) -> Vec<f64> {
+    if width == 0 || values.len() <= width {
+        return values.to_vec();
+    }
+    (0..width)
+        .map(|i| {
+            let start = i * values.len() / width;
+            let end = ((i + 1) * values.len() / width).max(start + 1);
+            let bucket = &values[start..end];
+            bucket.iter().sum::<f64>() / bucket.len() as f64
+        })
+        .collect()
+}
+
+/// A horizontal bar `width` cells long at `max`, drawn in eighths of a cell
+pub fn bar(value: f64, max: f64, width: usize) -> String {
+    if max <= 0.0 || !value.is_finite() || value <= 0.0 {
+        return String::new();
+    }
+    let eighths = ((value / max).min(1.0) * (width * 8) as f64).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    let rest = eighths % 8;
+    if rest > 0 {
+        bar.push(EIGHTHS[rest]);
+    }
+    bar
+}
+
+/// Columns of the terminal, or 100 when not writing to one
+pub fn terminal_width() -> usize {
+    console::Term::stdout()
+        .size_checked()
+        .map(|(_, columns)| columns as usize)
+        .unwrap_or(100)
+}
+
+/// Short form of a count: 950, 12.3K, 4.56M, 7.8B
+pub fn human_count(value: f64) -> String {
+    const UNITS: [(f64, &str); 3] = [(1e9, "B"), (1e6, "M"), (1e3, "K")];
+    for (size, unit) in UNITS {
+        if value.abs() >= size {
+            let scaled = value / size;
+            let precision = if scaled.abs() >= 100.0 { 0 } else { 1 };
+            return format!("{:.*}{}", precision, scaled, unit);
+        }
+    }
+    if value.fract() == 0.0 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.1}", value)
+    }
+}
@@ -1,11 +1,14 @@
 //! Output formatting module
 
+pub mod chart;
+
 use anyhow::Result;
 use owo_colors::OwoColorize;
 use serde::Serialize;
 use serde_json::Value;
 
 use crate::config::OutputFormat;
+use crate::expr::Checked;
 
 /// Print output in the configured format
 pub fn print_output<T: Serialize>(data: &T, format: &OutputFormat) -> Result<()> {
@@ -100,6 +103,14 @@ pub fn info(msg: &str) {
     println!("{} {}", "ℹ".blue().bold(), msg);
 }
 
+/// Print a progress line on stderr, overwritten until `done == total`
+pub fn progress(done: usize, total: usize, msg: &str) {
+    eprint!("\r\x1b[K{} [{}/{}] {}", "…".blue().bold(), done, total, msg);
+    if done >= total {
+        eprintln!();
+    }
+}
+
 /// Print a table header
 pub fn table_header(columns: &[&str]) {
     let header: Vec<String> = columns
@@ -204,6 +215,141 @@ pub fn print_firewall_rule(rule: &Value) {
     );
 }
 
+/// Print ruleset rule in table format (expression on a second line)
+pub fn print_ruleset_rule(index: usize, rule: &Value) {
+    let id = rule.get("id").and_then(|v| v.as_str()).unwrap_or("-");
+    let action = rule.get("action").and_then(|v| v.as_str()).unwrap_or("-");
+    let description = rule
+        .get("description")
+        .and_then(|v| v.as_str())
+        .unwrap_or("");
+    let expression = rule
+        .get("expression")
+        .and_then(|v| v.as_str())
+        .unwrap_or("-");
+    let enabled = rule
+        .get("enabled")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(true);
+
+    let state = if enabled {
+        "on".green().to_string()
+    } else {
+        "off".red().to_string()
+    };
+
+    let action_colored = match action {
+        "block" => action.red().to_string(),
+        "challenge" | "js_challenge" | "managed_challenge" => action.yellow().to_string(),
+        "skip" | "allow" => action.green().to_string(),
+        _ => action.cyan().to_string(),
+    };
+
+    println!(
+        "{}\t{}\t{}\t{}\t{}",
+        index,
+        state,
+        action_colored,
+        description.bold(),
+        id.dimmed()
+    );
+    println!("\t{}", expression.dimmed());
+}
+
+/// Print rate limiting rule (ruleset rule plus its limit on a third line)
+pub fn print_ratelimit_rule(index: usize, rule: &Value) {
+    print_ruleset_rule(index, rule);
+
+    let ratelimit = rule.get("ratelimit").unwrap_or(&Value::Null);
+    let requests = ratelimit
+        .get("requests_per_period")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let period = ratelimit
+        .get("period")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let timeout = ratelimit
+        .get("mitigation_timeout")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(0);
+    let characteristics: Vec<&str> = ratelimit
+        .get("characteristics")
+        .and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|c| c.as_str()).collect())
+        .unwrap_or_default();
+
+    println!(
+        "\t{} req / {}s per {}, timeout {}s",
+        requests.to_string().bold(),
+        period,
+        characteristics.join(", ").cyan(),
+        timeout
+    );
+}
+
+/// Print cache rule (ruleset rule plus its cache settings on a third line)
+pub fn print_cache_rule(index: usize, rule: &Value) {
+    print_ruleset_rule(index, rule);
+
+    let params = rule.get("action_parameters").unwrap_or(&Value::Null);
+    let mut parts = Vec::new();
+
+    match params.get("cache").and_then(|v| v.as_bool()) {
+        Some(true) => parts.push("cache".green().to_string()),
+        Some(false) => parts.push("bypass".red().to_string()),
+        None => {}
+    }
+    for (key, label) in [("edge_ttl", "edge"), ("browser_ttl", "browser")] {
+        if let Some(ttl) = params.get(key) {
+            let mode = ttl.get("mode").and_then(|v| v.as_str()).unwrap_or("-");
+            match ttl.get("default").and_then(|v| v.as_u64()) {
+                Some(secs) => parts.push(format!("{} ttl {}s", label, secs)),
+                None => parts.push(format!("{} ttl {}", label, mode)),
+            }
+        }
+    }
+    if params.get("cache_key").is_some() {
+        parts.push("custom cache key".cyan().to_string());
+    }
+
+    if !parts.is_empty() {
+        println!("\t{}", parts.join(", "));
+    }
+}
+
+/// Print custom hostname (Cloudflare for SaaS) in table format
+pub fn print_custom_hostname(hostname: &Value) {
+    let id = hostname.get("id").and_then(|v| v.as_str()).unwrap_or("-");
+    let name = hostname
+        .get("hostname")
+        .and_then(|v| v.as_str())
+        .unwrap_or("-");
+    let status = hostname
+        .get("status")
+        .and_then(|v| v.as_str())
+        .unwrap_or("-");
+    let ssl = hostname.get("ssl").unwrap_or(&Value::Null);
+    let ssl_status = ssl.get("status").and_then(|v| v.as_str()).unwrap_or("-");
+    let method = ssl.get("method").and_then(|v| v.as_str()).unwrap_or("-");
+
+    let color = |s: &str| match s {
+        "active" => s.green().to_string(),
+        s if s.starts_with("pending") => s.yellow().to_string(),
+        "blocked" | "moved" | "deleted" => s.red().to_string(),
+        _ => s.to_string(),
+    };
+
+    println!(
+        "{}\t{}\t{}\t{}\t{}",
+        name.bold(),
+        color(status),
+        color(ssl_status),
+        method,
+        id.dimmed()
+    );
+}
+
 /// Print analytics result
 pub fn print_analytics_row(count: u64, dimensions: &Value) {
     let dims: Vec<String> = dimensions
@@ -218,3 +364,13 @@ pub fn print_analytics_row(count: u64, dimensions: &Value) {
 
     println!("{}\t{}", count.to_string().yellow().bold(), dims.join("\t"));
 }
+
+/// Print expression errors and warnings with source position markers
+pub fn print_expr_diagnostics(src: &str, checked: &Checked) {
+    for err in &checked.errors {
+        error(&err.render(src));
+    }
+    for warn in &checked.warnings {
+        warning(&warn.render(src));
+    }
+}
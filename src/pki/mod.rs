@@ -0,0 +1,415 @@
+//! Local certificate and key handling for SSL uploads
+//!
+//! Reads PEM certificates and private keys well enough to check that a key
+//! matches its certificate and which hostnames a certificate covers, and
+//! generates ECDSA keys and CSRs for Origin CA certificates.
+
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use pem::{EncodeConfig, LineEnding, Pem};
+use rcgen::{
+    CertificateParams, DistinguishedName, DnType, KeyPair, PublicKeyData, PKCS_ECDSA_P256_SHA256,
+};
+use x509_parser::der_parser::parse_der;
+use x509_parser::prelude::{FromDer, GeneralName, X509Certificate};
+use x509_parser::public_key::PublicKey as ParsedKey;
+use x509_parser::x509::SubjectPublicKeyInfo;
+
+/// A public key, as stored in a certificate or derived from a private key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PublicKey {
+    Rsa {
+        modulus: Vec<u8>,
+        exponent: Vec<u8>,
+    },
+    /// Encoded EC point
+    Ec(Vec<u8>),
+    /// Any other algorithm, as the raw subjectPublicKey bits
+    Other(Vec<u8>),
+}
+
+impl PublicKey {
+    fn from_spki(spki: &SubjectPublicKeyInfo) -> Result<Self> {
+        Ok(match spki.parsed() {
+            Ok(ParsedKey::RSA(rsa)) => PublicKey::rsa(rsa.modulus, rsa.exponent),
+            Ok(ParsedKey::EC(point)) => PublicKey::Ec(point.data().to_vec()),
+            _ => PublicKey::Other(spki.subject_public_key.data.to_vec()),
+        })
+    }
+
+    /// RSA key from big-endian integers, without leading zero bytes
+    fn rsa(modulus: &[u8], exponent: &[u8]) -> Self {
+        let trim = |n: &[u8]| {
+            let start = n.iter().position(|&b| b != 0).unwrap_or(n.len());
+            n[start..].to_vec()
+        };
+        PublicKey::Rsa {
+            modulus: trim(modulus),
+            exponent: trim(exponent),
+        }
+    }
+}
+
+/// Encode DER bytes as a PEM block
+pub fn to_pem(label: &str, der: &[u8]) -> String {
+    pem::encode_config(
+        &Pem::new(label, der),
+        EncodeConfig::new().set_line_ending(LineEnding::LF),
+    )
+}
+
+/// The parts of an X.509 certificate cli5 looks at
+#[derive(Debug, Clone)]
+pub struct Certificate {
+    pub der: Vec<u8>,
+    pub subject: Option<String>,
+    pub issuer: Option<String>,
+    pub dns_names: Vec<String>,
+    pub not_before: DateTime<Utc>,
+    pub not_after: DateTime<Utc>,
+    pub public_key: PublicKey,
+}
+
+impl Certificate {
+    pub fn from_der(der: &[u8]) -> Result<Self> {
+        let (_, cert) = X509Certificate::from_der(der).map_err(|e| anyhow!("{}", e))?;
+
+        let dns_names = match cert.subject_alternative_name() {
+            Ok(Some(san)) => san
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name) => Some(name.to_lowercase()),
+                    _ => None,
+                })
+                .collect(),
+            Ok(None) => Vec::new(),
+            Err(e) => return Err(anyhow!("Invalid subjectAltName: {}", e)),
+        };
+        let time =
+            |t: i64| DateTime::from_timestamp(t, 0).ok_or_else(|| anyhow!("Invalid validity time"));
+        let common_name = |name: &x509_parser::x509::X509Name| {
+            name.iter_common_name()
+                .next()
+                .and_then(|cn| cn.as_str().ok())
+                .map(String::from)
+        };
+
+        Ok(Self {
+            der: der.to_vec(),
+            subject: common_name(cert.subject()),
+            issuer: common_name(cert.issuer()),
+            dns_names,
+            not_before: time(cert.validity().not_before.timestamp())?,
+            not_after: time(cert.validity().not_after.timestamp())?,
+            public_key: PublicKey::from_spki(cert.public_key())?,
+        })
+    }
+
+    /// Hostnames the certificate is valid for (SANs, or the CN without them)
+    pub fn hostnames(&self) -> Vec<String> {
+        if self.dns_names.is_empty() {
+            self.subject.iter().map(|s| s.to_lowercase()).collect()
+        } else {
+            self.dns_names.clone()
+        }
+    }
+
+    /// Whether any hostname belongs to a zone (the apex or a name below it)
+    pub fn covers_zone(&self, zone: &str) -> bool {
+        let zone = zone.to_lowercase();
+        let suffix = format!(".{}", zone);
+        self.hostnames()
+            .iter()
+            .any(|name| *name == zone || name.ends_with(&suffix))
+    }
+
+    pub fn days_left(&self) -> i64 {
+        (self.not_after - Utc::now()).num_days()
+    }
+}
+
+/// Read all certificates from a PEM file (leaf first, then any chain)
+pub fn load_certificates(path: &str) -> Result<Vec<Certificate>> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow!("Cannot read {}: {}", path, e))?;
+    parse_certificates(&text).map_err(|e| anyhow!("{}: {}", path, e))
+}
+
+/// All certificates in a PEM text
+fn parse_certificates(text: &str) -> Result<Vec<Certificate>> {
+    let certs = pem::parse_many(text)
+        .map_err(|e| anyhow!("Invalid PEM: {}", e))?
+        .iter()
+        .filter(|block| block.tag() == "CERTIFICATE")
+        .map(|block| Certificate::from_der(block.contents()))
+        .collect::<Result<Vec<_>>>()
+        .map_err(|e| anyhow!("Invalid certificate: {}", e))?;
+
+    if certs.is_empty() {
+        return Err(anyhow!("No PEM certificate found"));
+    }
+    Ok(certs)
+}
+
+/// A private key read from a PEM file
+pub struct PrivateKey {
+    pub pem: String,
+    pub public_key: PublicKey,
+}
+
+/// Read a PKCS#8, PKCS#1 (RSA) or SEC1 (EC) private key from a PEM file
+pub fn load_private_key(path: &str) -> Result<PrivateKey> {
+    let text = std::fs::read_to_string(path).map_err(|e| anyhow!("Cannot read {}: {}", path, e))?;
+    parse_private_key(&text).map_err(|e| anyhow!("{}: {}", path, e))
+}
+
+/// The first private key in a PEM text
+fn parse_private_key(text: &str) -> Result<PrivateKey> {
+    for block in pem::parse_many(text).map_err(|e| anyhow!("Invalid PEM: {}", e))? {
+        let der = block.contents();
+        let public_key = match block.tag() {
+            "PRIVATE KEY" => pkcs8_public_key(der)?,
+            "RSA PRIVATE KEY" => rsa_public_key(der)?,
+            "EC PRIVATE KEY" => sec1_public_key(der)?,
+            "ENCRYPTED PRIVATE KEY" => {
+                return Err(anyhow!(
+                "The key is encrypted; decrypt it first (openssl pkey -in key.pem -out plain.pem)"
+            ))
+            }
+            _ => continue,
+        };
+        return Ok(PrivateKey {
+            pem: to_pem(block.tag(), der),
+            public_key,
+        });
+    }
+
+    Err(anyhow!("No PEM private key found"))
+}
+
+/// Public key of a PKCS#8 private key (RSA, ECDSA P-256/P-384 or Ed25519)
+fn pkcs8_public_key(der: &[u8]) -> Result<PublicKey> {
+    let key = KeyPair::try_from(der).map_err(|e| anyhow!("Unsupported private key: {}", e))?;
+    let spki = key.subject_public_key_info();
+    let (_, spki) = SubjectPublicKeyInfo::from_der(&spki).map_err(|e| anyhow!("{}", e))?;
+    PublicKey::from_spki(&spki)
+}
+
+/// Modulus and exponent of a PKCS#1 RSAPrivateKey
+fn rsa_public_key(der: &[u8]) -> Result<PublicKey> {
+    let invalid = || anyhow!("Invalid RSA private key");
+    let (_, key) = parse_der(der).map_err(|_| invalid())?;
+    let fields = key.as_sequence().map_err(|_| invalid())?;
+    match fields.as_slice() {
+        [_version, modulus, exponent, ..] => Ok(PublicKey::rsa(
+            modulus.as_slice().map_err(|_| invalid())?,
+            exponent.as_slice().map_err(|_| invalid())?,
+        )),
+        _ => Err(invalid()),
+    }
+}
+
+/// Public point stored in a SEC1 ECPrivateKey (`[1] publicKey`)
+fn sec1_public_key(der: &[u8]) -> Result<PublicKey> {
+    let invalid = || anyhow!("Invalid EC private key");
+    let (_, key) = parse_der(der).map_err(|_| invalid())?;
+    let fields = key.as_sequence().map_err(|_| invalid())?;
+    let wrapper = fields
+        .iter()
+        .skip(2)
+        .find(|field| field.tag().0 == 1)
+        .ok_or_else(|| anyhow!("The EC key has no public key part"))?;
+    let (_, bits) = parse_der(wrapper.as_slice().map_err(|_| invalid())?).map_err(|_| invalid())?;
+    let point = bits.as_bitstring().map_err(|_| invalid())?;
+    Ok(PublicKey::Ec(point.data.to_vec()))
+}
+
+/// A new ECDSA P-256 key with a CSR for some hostnames
+pub struct GeneratedRequest {
+    pub key_pem: String,
+    pub csr_pem: String,
+}
+
+/// Generate a P-256 key and a CSR with a common name and optional DNS names
+pub fn generate_csr(common_name: &str, hostnames: &[String]) -> Result<GeneratedRequest> {
+    let key = KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256)
+        .map_err(|e| anyhow!("Could not generate a private key: {}", e))?;
+
+    let mut params = CertificateParams::new(hostnames.to_vec())
+        .map_err(|e| anyhow!("Invalid hostname: {}", e))?;
+    let mut subject = DistinguishedName::new();
+    subject.push(DnType::CommonName, common_name);
+    params.distinguished_name = subject;
+
+    let csr = params
+        .serialize_request(&key)
+        .and_then(|csr| csr.pem())
+        .map_err(|e| anyhow!("Could not sign the CSR: {}", e))?;
+
+    Ok(GeneratedRequest {
+        key_pem: key.serialize_pem(),
+        csr_pem: csr,
+    })
+}
+
+/// Write a file readable only by the owner (0600 on Unix)
+///
+/// Refuses to replace an existing file unless `overwrite` is set.
+pub fn write_private_file(path: &Path, contents: &str, overwrite: bool) -> Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AlreadyExists => anyhow!(
+            "{} already exists (use --force to overwrite)",
+            path.display()
+        ),
+        _ => anyhow!("Cannot write {}: {}", path.display(), e),
+    })?;
+
+    // An existing file keeps its mode on open; tighten it explicitly
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x509_parser::certification_request::X509CertificationRequest;
+
+    const RSA_CERT: &str = include_str!("testdata/rsa.crt");
+    const RSA_KEY: &str = include_str!("testdata/rsa.key");
+    const RSA_PKCS8_KEY: &str = include_str!("testdata/rsa-pkcs8.key");
+    const EC_CERT: &str = include_str!("testdata/ec.crt");
+    const EC_KEY: &str = include_str!("testdata/ec.key");
+    const PKCS8_CERT: &str = include_str!("testdata/pkcs8.crt");
+    const PKCS8_KEY: &str = include_str!("testdata/pkcs8.key");
+
+    fn leaf(pem: &str) -> Certificate {
+        parse_certificates(pem).unwrap().remove(0)
+    }
+
+    #[test]
+    fn keys_match_their_certificates() {
+        for (cert, key) in [
+            (RSA_CERT, RSA_KEY),
+            (RSA_CERT, RSA_PKCS8_KEY),
+            (EC_CERT, EC_KEY),
+            (PKCS8_CERT, PKCS8_KEY),
+        ] {
+            let key = parse_private_key(key).unwrap();
+            assert_eq!(leaf(cert).public_key, key.public_key);
+        }
+    }
+
+    #[test]
+    fn keys_do_not_match_other_certificates() {
+        let rsa = parse_private_key(RSA_KEY).unwrap();
+        let ec = parse_private_key(EC_KEY).unwrap();
+        assert!(matches!(rsa.public_key, PublicKey::Rsa { .. }));
+        assert!(matches!(ec.public_key, PublicKey::Ec(_)));
+        assert_ne!(leaf(EC_CERT).public_key, rsa.public_key);
+        assert_ne!(leaf(PKCS8_CERT).public_key, ec.public_key);
+    }
+
+    #[test]
+    fn certificate_names_and_validity() {
+        let rsa = leaf(RSA_CERT);
+        assert_eq!(rsa.subject.as_deref(), Some("example.com"));
+        assert_eq!(rsa.issuer.as_deref(), Some("example.com"));
+        assert_eq!(rsa.hostnames(), ["example.com", "www.example.com"]);
+        assert!(rsa.covers_zone("Example.com"));
+        assert!(!rsa.covers_zone("ample.com"));
+        assert!(rsa.not_before < rsa.not_after);
+        assert!(rsa.days_left() > 0);
+
+        // Without SANs the common name counts
+        let ec = leaf(EC_CERT);
+        assert!(ec.dns_names.is_empty());
+        assert_eq!(ec.hostnames(), ["api.example.org"]);
+        assert!(ec.covers_zone("example.org"));
+
+        assert!(leaf(PKCS8_CERT).covers_zone("example.net"));
+    }
+
+    #[test]
+    fn chains_keep_their_order() {
+        let chain = format!("{}{}", RSA_CERT, EC_CERT);
+        let certs = parse_certificates(&chain).unwrap();
+        assert_eq!(certs.len(), 2);
+        assert_eq!(certs[1].subject.as_deref(), Some("api.example.org"));
+        // Re-encoding gives back the same certificate
+        assert_eq!(
+            parse_certificates(&to_pem("CERTIFICATE", &certs[0].der)).unwrap()[0].der,
+            certs[0].der
+        );
+    }
+
+    #[test]
+    fn rejects_missing_or_encrypted_material() {
+        assert!(parse_certificates(RSA_KEY).is_err());
+        assert!(parse_private_key(RSA_CERT).is_err());
+        let encrypted = to_pem("ENCRYPTED PRIVATE KEY", b"\x30\x00");
+        let error = parse_private_key(&encrypted).err().unwrap().to_string();
+        assert!(error.contains("encrypted"), "{}", error);
+    }
+
+    #[test]
+    fn generated_csr_matches_its_key() {
+        let hostnames = vec!["example.com".to_string(), "*.example.com".to_string()];
+        let request = generate_csr("example.com", &hostnames).unwrap();
+        let key = parse_private_key(&request.key_pem).unwrap();
+
+        let csr = pem::parse(&request.csr_pem).unwrap();
+        assert_eq!(csr.tag(), "CERTIFICATE REQUEST");
+        let (_, csr) = X509CertificationRequest::from_der(csr.contents()).unwrap();
+        let info = &csr.certification_request_info;
+        assert_eq!(
+            info.subject
+                .iter_common_name()
+                .next()
+                .and_then(|cn| cn.as_str().ok()),
+            Some("example.com")
+        );
+        assert_eq!(
+            PublicKey::from_spki(&info.subject_pki).unwrap(),
+            key.public_key
+        );
+        let names: Vec<String> = csr
+            .requested_extensions()
+            .into_iter()
+            .flatten()
+            .filter_map(|ext| match ext {
+                x509_parser::extensions::ParsedExtension::SubjectAlternativeName(san) => Some(
+                    san.general_names
+                        .iter()
+                        .map(|n| n.to_string())
+                        .collect::<Vec<_>>(),
+                ),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        assert_eq!(names, ["DNSName(example.com)", "DNSName(*.example.com)"]);
+    }
+}